@@ -0,0 +1,48 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use rusty_chain::core::crypto::{generate_keypair, sign_bytes, verifying_key_to_hex};
+use rusty_chain::core::sigcache::{SignatureCache, verify_signatures_batch};
+use rusty_chain::core::types::Transaction;
+
+const BLOCK_TXS: usize = 1_000;
+
+fn signed_block_txs(n: usize) -> Vec<Transaction> {
+    (0..n)
+        .map(|i| {
+            let (sk, vk) = generate_keypair();
+            let from = verifying_key_to_hex(&vk);
+            let mut tx = Transaction::new(from.clone(), "bob", 1 + i as u64, 0);
+            tx.signature_b64 = Some(sign_bytes(&sk, &tx.signing_bytes()));
+            tx.pubkey_hex = Some(from);
+            tx
+        })
+        .collect()
+}
+
+fn bench_block_signatures(c: &mut Criterion) {
+    let txs = signed_block_txs(BLOCK_TXS);
+
+    let mut warm = SignatureCache::new();
+    for tx in &txs {
+        warm.insert(tx);
+    }
+
+    let mut group = c.benchmark_group("block_1k_signed_txs");
+    group.sample_size(10);
+    group.bench_function("individual", |b| {
+        b.iter(|| {
+            for tx in &txs {
+                tx.verify_signature_if_present().unwrap();
+            }
+        })
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| verify_signatures_batch(&txs, None).unwrap())
+    });
+    group.bench_function("batch_warm_cache", |b| {
+        b.iter(|| verify_signatures_batch(&txs, Some(&warm)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_block_signatures);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use crate::core::hash::sha256_hex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Recipient of every asset-creation tx. Its account's `storage_root` commits to the registry
+/// (`State::assets`).
+pub const ASSET_REGISTRY_ADDRESS: &str = "assets";
+
+/// Longest asset id.
+pub const MAX_ASSET_ID_LEN: usize = 16;
+
+/// A new asset, carried in `Transaction::create_asset`. The whole supply goes to the sender,
+/// who is recorded as its issuer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AssetDefinition {
+    pub asset_id: String,
+    pub supply: u64,
+}
+
+/// A registered asset, kept in `State::assets`. The supply is fixed at creation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AssetInfo {
+    pub issuer: String,
+    pub supply: u64,
+}
+
+/// Asset ids are 1 to `MAX_ASSET_ID_LEN` ASCII uppercase letters, digits and `-`, starting
+/// with a letter (e.g. `PROJ`, `USD-1`).
+pub fn check_asset_id(asset_id: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        (1..=MAX_ASSET_ID_LEN).contains(&asset_id.len()),
+        "asset id must be 1..={} chars (got {:?})",
+        MAX_ASSET_ID_LEN,
+        asset_id
+    );
+    anyhow::ensure!(
+        asset_id.starts_with(|c: char| c.is_ascii_uppercase())
+            && asset_id
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-'),
+        "asset id must be uppercase letters, digits and '-', starting with a letter (got {:?})",
+        asset_id
+    );
+    Ok(())
+}
+
+/// SHA-256 (hex) of the registry's canonical JSON; stored as the registry account's
+/// `storage_root`.
+pub fn registry_hash(assets: &BTreeMap<String, AssetInfo>) -> String {
+    sha256_hex(&serde_json::to_vec(assets).expect("asset registry serialization"))
+}
@@ -1,6 +1,12 @@
+use crate::core::chain_id::{DEVNET_CHAIN_ID, Network, data_dir_for};
 use crate::core::hash::sha256_hex;
-use crate::core::state::State;
+use crate::core::mempool::Mempool;
+use crate::core::params::ChainParams;
+use crate::core::sigcache::SignatureCache;
+use crate::core::state::{State, SupplyChange};
+use crate::core::state_tree::{AccountProof, state_root};
 use crate::core::time::now_ms;
+use crate::core::tx_index::{TxIndex, TxLocation};
 use crate::core::types::{Block, BlockHeader, Transaction};
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
@@ -9,12 +15,24 @@ use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chain {
+    /// Network name this chain belongs to (set from the genesis config).
+    #[serde(default = "default_network")]
+    pub network: String,
+
+    /// Chain id committed into tx signing payloads (replay protection) and the P2P handshake.
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u32,
+
     /// Chain-wide PoW difficulty (leading '0' hex chars).
     ///
     /// Stored in the chain file so `validate` can check PoW without CLI flags.
     #[serde(default = "default_pow_difficulty")]
     pub pow_difficulty: usize,
 
+    /// Consensus parameters (subsidy schedule, supply cap, block limits, timestamp drift).
+    #[serde(default)]
+    pub params: ChainParams,
+
     pub blocks: Vec<Block>,
 
     /// Checkpoints for pruning and fast synchronization.
@@ -26,25 +44,103 @@ pub struct Chain {
     /// Used primarily in P2P sync (GetData handling).
     #[serde(skip, default)]
     pub block_index: std::collections::HashMap<String, usize>,
+
+    /// Network time offset (ms) applied to the local clock, from peer handshakes.
+    #[serde(skip, default)]
+    pub time_offset_ms: i64,
+
+    /// Optional confirmed-tx index, kept up to date as blocks are appended or disconnected.
+    /// Off by default; see `enable_tx_index`.
+    #[serde(skip, default)]
+    pub tx_index: Option<TxIndex>,
+
+    /// Set when the chain was bootstrapped from a snapshot (see `snapshot`) or pruned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_base: Option<SnapshotBase>,
+
+    /// Pruning mode: keep tx bodies for only this many blocks below the tip (see `prune`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prune_keep_blocks: Option<usize>,
 }
 
+/// Starting point of a chain imported from a snapshot or pruned: blocks `1..=height` are
+/// header-only and the ledger is replayed from `state` (the state after block `height`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotBase {
+    pub height: usize,
+    pub state: State,
+}
+
+/// Fewest recent blocks a pruned chain keeps in full. Matches the auto-checkpoint interval, so
+/// every block a reorg could still disconnect keeps its body.
+pub const MIN_PRUNE_KEEP_BLOCKS: usize = 10;
+
+/// Number of recent blocks whose median timestamp a new block must exceed.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// A block stamped too far ahead of network-adjusted time.
+///
+/// Not a permanent failure: the block may become acceptable later, so callers can hold it and
+/// retry. Returned inside `anyhow::Error`; use `downcast_ref` to tell it apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FutureBlockError {
+    pub timestamp_ms: u64,
+    pub max_allowed_ms: u64,
+}
+
+impl std::fmt::Display for FutureBlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "block timestamp {} too far in the future (max {})",
+            self.timestamp_ms, self.max_allowed_ms
+        )
+    }
+}
+
+impl std::error::Error for FutureBlockError {}
+
 fn default_pow_difficulty() -> usize {
     3
 }
 
+fn default_network() -> String {
+    "devnet".to_string()
+}
+
+fn default_chain_id() -> u32 {
+    DEVNET_CHAIN_ID
+}
+
 impl Chain {
+    /// Ad-hoc genesis: empty block stamped with the current time.
+    ///
+    /// Use `GenesisConfig::build_chain` when nodes need to agree on the genesis hash.
     pub fn new_genesis() -> Self {
         let header = BlockHeader {
             prev_hash: "0".repeat(64),
             timestamp_ms: now_ms(),
             nonce: 0,
             merkle_root: merkle_root(&[]),
+            state_root: state_root(&State::new()),
         };
-        let genesis = Block {
-            header: header.clone(),
+        Self::from_genesis_block(Block {
+            header,
             txs: vec![],
-        };
-        let genesis_hash = header.hash();
+        })
+    }
+
+    /// Empty genesis chain for one of the well-known networks.
+    pub fn new_genesis_for(network: Network) -> Self {
+        let mut chain = Self::new_genesis();
+        chain.network = network.name().to_string();
+        chain.chain_id = network.chain_id();
+        chain
+    }
+
+    /// Start a chain from an already-built genesis block, using default settings.
+    pub fn from_genesis_block(genesis: Block) -> Self {
+        let genesis_hash = genesis.header.hash();
         let mut checkpoints = std::collections::HashMap::new();
         checkpoints.insert(0, genesis_hash.clone());
 
@@ -52,11 +148,64 @@ impl Chain {
         block_index.insert(genesis_hash, 0);
 
         Self {
+            network: default_network(),
+            chain_id: default_chain_id(),
             pow_difficulty: default_pow_difficulty(),
+            params: ChainParams::default(),
             blocks: vec![genesis],
             checkpoints,
             block_index,
+            time_offset_ms: 0,
+            tx_index: None,
+            snapshot_base: None,
+            prune_keep_blocks: None,
+        }
+    }
+
+    pub fn genesis_hash(&self) -> String {
+        self.blocks.first().expect("genesis exists").header.hash()
+    }
+
+    /// Supply minted and burned over the whole chain, checked against the ledger: the
+    /// circulating supply must equal minted minus burned. On a pruned or imported chain the
+    /// base state's supply counts as minted.
+    pub fn supply_audit(&self) -> anyhow::Result<SupplyChange> {
+        let (mut total, start) = match &self.snapshot_base {
+            Some(base) => (
+                SupplyChange {
+                    minted: base.state.total_supply(),
+                    burned: 0,
+                },
+                base.height + 1,
+            ),
+            None => (SupplyChange::default(), 0),
+        };
+        for (height, block) in self.blocks.iter().enumerate().skip(start) {
+            total = total
+                .checked_add(SupplyChange::of_txs(&block.txs)?)
+                .with_context(|| format!("block {}", height))?;
         }
+        let circulating = self.compute_state()?.total_supply();
+        anyhow::ensure!(
+            total.net() == Some(circulating),
+            "supply invariant broken: minted {} - burned {} != circulating {}",
+            total.minted,
+            total.burned,
+            circulating
+        );
+        Ok(total)
+    }
+
+    /// Total coins allocated by the genesis block.
+    pub fn genesis_allocated(&self) -> u64 {
+        self.blocks
+            .first()
+            .map(|g| {
+                g.txs
+                    .iter()
+                    .fold(0_u64, |acc, tx| acc.saturating_add(tx.amount))
+            })
+            .unwrap_or(0)
     }
 
     pub fn height(&self) -> usize {
@@ -78,6 +227,10 @@ impl Chain {
     /// - Per-sender monotonically increasing u64 starting at 0.
     /// - This does NOT check balances or signatures (yet).
     pub fn next_nonce_for(&self, sender: &str) -> u64 {
+        let base_nonce = self
+            .snapshot_base
+            .as_ref()
+            .map_or(0, |b| b.state.get_nonce(sender));
         let mut max_nonce: Option<u64> = None;
         for b in &self.blocks {
             for tx in &b.txs {
@@ -86,17 +239,30 @@ impl Chain {
                 }
             }
         }
-        max_nonce.map_or(0, |m| m.saturating_add(1))
+        max_nonce.map_or(0, |m| m.saturating_add(1)).max(base_nonce)
     }
 
     pub fn default_path() -> PathBuf {
-        PathBuf::from("data/chain.json")
+        Self::default_path_for(&default_network())
+    }
+
+    /// Default chain file for a network (`data/<network>/chain.json`).
+    pub fn default_path_for(network: &str) -> PathBuf {
+        data_dir_for(network).join("chain.json")
     }
 
     pub fn load(path: &Path) -> anyhow::Result<Self> {
         let s = fs::read_to_string(path)?;
         let mut c: Self = serde_json::from_str(&s)?;
 
+        // Chains from before state roots were mined without the coinbase, maturity and
+        // governance rules.
+        if !c.commits_state() {
+            c.params.strict_coinbase = false;
+            c.params.coinbase_maturity = 0;
+            c.params.governance_voting_period = 0;
+        }
+
         // Rebuild block index
         c.rebuild_block_index();
 
@@ -116,9 +282,64 @@ impl Chain {
         &self.blocks.last().expect("genesis exists").header
     }
 
+    /// Pick mempool txs for the next block: highest fee-per-byte first, within the consensus
+    /// size, tx count and gas limits. Space for the header and the coinbase is reserved. A
+    /// contract tx that would overrun the gas limit is left out with the sender's later txs.
+    pub fn select_block_txs(
+        &self,
+        mempool: &Mempool,
+        miner_address: Option<&str>,
+    ) -> Vec<Transaction> {
+        let block_height = self.height() as u64 + 1;
+        let mut skeleton = Block {
+            header: BlockHeader {
+                prev_hash: self.tip_hash(),
+                timestamp_ms: u64::MAX,
+                nonce: u64::MAX,
+                merkle_root: self.tip_hash(),
+                state_root: self.tip_hash(),
+            },
+            txs: vec![],
+        };
+        if let Some(miner) = miner_address {
+            skeleton
+                .txs
+                .push(Transaction::coinbase(miner, block_height, u64::MAX));
+        }
+
+        let params = self
+            .params_at(block_height as usize)
+            .unwrap_or_else(|_| self.params.clone());
+        let max_bytes = params.max_block_size.saturating_sub(skeleton.size());
+        let max_txs = params.max_block_txs.saturating_sub(skeleton.txs.len());
+        let picked =
+            mempool.select_for_block(max_bytes, max_txs, |sender| self.next_nonce_for(sender));
+        let mut gas_left = params.max_block_gas;
+        let mut cut_off = std::collections::HashSet::new();
+        picked
+            .into_iter()
+            .filter(|tx| {
+                if cut_off.contains(&tx.from) {
+                    return false;
+                }
+                let gas = if tx.contract.is_some() {
+                    tx.gas_limit
+                } else {
+                    0
+                };
+                if gas > gas_left {
+                    cut_off.insert(tx.from.clone());
+                    return false;
+                }
+                gas_left -= gas;
+                true
+            })
+            .collect()
+    }
+
     /// Mine and append a block with provided transactions.
     ///
-    /// If `miner_address` is provided, a coinbase transaction (50 coins + fees) is prepended.
+    /// If `miner_address` is provided, a coinbase transaction (block subsidy + fees) is prepended.
     pub fn mine_block(
         &mut self,
         mut txs: Vec<Transaction>,
@@ -131,34 +352,55 @@ impl Chain {
         self.pow_difficulty = new_difficulty;
         let difficulty = self.pow_difficulty;
 
+        for (i, tx) in txs.iter().enumerate() {
+            self.check_tx_chain_id(tx)
+                .with_context(|| format!("tx index={i}"))?;
+        }
+
         // Prepend coinbase if miner specified
+        if miner_address.is_none() && !txs.first().is_some_and(|tx| tx.is_coinbase()) {
+            anyhow::ensure!(
+                !self.params.strict_coinbase,
+                "block {} needs a coinbase: pass a miner address",
+                block_height
+            );
+        }
+        // Parameters in force for this block, governance changes included.
+        let mut state = self.compute_state()?;
+        let params = state.params_at(&self.params, block_height);
         if let Some(miner) = miner_address {
-            let total_fees: u64 = txs.iter().map(|tx| tx.fee).sum();
-            let coinbase = Transaction {
-                from: "SYSTEM".to_string(),
-                to: miner.to_string(),
-                amount: 50 + total_fees,
-                nonce: block_height,
-                memo: Some(format!("Block {block_height} Reward")),
-                priority: 255, // Max priority for coinbase
-                nonce_id: Some(format!("coinbase-{}", block_height)),
-                ..Default::default()
-            };
-            txs.insert(0, coinbase);
+            let total_fees = SupplyChange::of_txs(&txs)?.burned;
+            let amount = params
+                .block_subsidy(block_height)
+                .checked_add(total_fees)
+                .context("block reward + fees overflow u64")?;
+            txs.insert(0, Transaction::coinbase(miner, block_height, amount));
         }
 
+        // Check limits up front with a worst-case header so we don't mine an invalid block.
+        let prev_hash = self.tip_hash();
+        params.check_block_limits(&Block {
+            header: BlockHeader {
+                prev_hash: prev_hash.clone(),
+                timestamp_ms: u64::MAX,
+                nonce: u64::MAX,
+                merkle_root: prev_hash.clone(),
+                state_root: prev_hash.clone(),
+            },
+            txs: txs.clone(),
+        })?;
+
         // Validate state transitions (balances, nonces) before mining.
-        // We create a temporary state, apply the new transactions, and see if it holds.
-        let mut state = self.compute_state()?;
+        // We apply the new transactions to the temporary state and see if it holds.
         state
-            .apply_block_txs(&txs, block_height as usize)
+            .apply_block_txs(&txs, block_height as usize, &self.params)
             .context("mempool transactions failed state application")?;
 
-        let prev = self.blocks.last().expect("genesis exists");
-        let prev_hash = prev.header.hash();
-
         let merkle_root = merkle_root(&txs);
-        let timestamp_ms = now_ms();
+        let state_root = self.expected_state_root(&state);
+        let timestamp_ms = self
+            .adjusted_time_ms()
+            .max(self.median_time_past().saturating_add(1));
         let mut nonce = 0_u64;
 
         loop {
@@ -167,6 +409,7 @@ impl Chain {
                 timestamp_ms,
                 nonce,
                 merkle_root: merkle_root.clone(),
+                state_root: state_root.clone(),
             };
             let h = header.hash();
             if pow_ok(&h, difficulty) {
@@ -176,6 +419,9 @@ impl Chain {
                 };
                 let hash = candidate.header.hash();
                 let height = self.blocks.len();
+                if let Some(index) = &mut self.tx_index {
+                    index.index_block(height, &candidate);
+                }
                 self.blocks.push(candidate.clone());
                 self.block_index.insert(hash, height);
 
@@ -183,6 +429,7 @@ impl Chain {
                 if height > 0 && height.is_multiple_of(10) {
                     self.checkpoints.insert(height, candidate.header.hash());
                 }
+                self.auto_prune()?;
 
                 return Ok(candidate);
             }
@@ -190,38 +437,260 @@ impl Chain {
         }
     }
 
-    /// Mine and append an empty block (demo PoW).
-    pub fn mine_empty_block(&mut self, new_difficulty: usize) -> anyhow::Result<Block> {
-        self.mine_block(vec![], new_difficulty, None)
+    /// Mine and append a block holding only the coinbase for `miner_address` (demo PoW).
+    pub fn mine_empty_block(
+        &mut self,
+        new_difficulty: usize,
+        miner_address: &str,
+    ) -> anyhow::Result<Block> {
+        self.mine_block(vec![], new_difficulty, Some(miner_address))
+    }
+
+    /// Whether headers commit to the post-block state. Decided by the genesis header, so chains
+    /// created before state roots existed stay valid.
+    pub fn commits_state(&self) -> bool {
+        !self.blocks[0].header.state_root.is_empty()
+    }
+
+    /// The `state_root` a header must carry when its block leaves the ledger at `state`.
+    pub fn expected_state_root(&self, state: &State) -> String {
+        if self.commits_state() {
+            state_root(state)
+        } else {
+            String::new()
+        }
+    }
+
+    fn check_state_root(&self, header: &BlockHeader, state: &State) -> anyhow::Result<()> {
+        let expected = self.expected_state_root(state);
+        anyhow::ensure!(
+            header.state_root == expected,
+            "state root mismatch: expected {} got {}",
+            expected,
+            header.state_root
+        );
+        Ok(())
+    }
+
+    /// Proof of `address`'s account (or of its absence) against the tip's `state_root`.
+    pub fn account_proof(&self, address: &str) -> anyhow::Result<AccountProof> {
+        anyhow::ensure!(self.commits_state(), "chain does not commit to state roots");
+        Ok(AccountProof::build(&self.compute_state()?, address))
+    }
+
+    /// Like `account_proof`, from `state`, which must be the tip's (e.g. one a node keeps
+    /// cached).
+    pub fn account_proof_on(&self, state: &State, address: &str) -> anyhow::Result<AccountProof> {
+        anyhow::ensure!(self.commits_state(), "chain does not commit to state roots");
+        Ok(AccountProof::build(state, address))
+    }
+
+    /// Consensus parameters for the block at `height`: `params` with the governance changes
+    /// active by then, as recorded in the state of the block before.
+    pub fn params_at(&self, height: usize) -> anyhow::Result<ChainParams> {
+        if height == 0 {
+            return Ok(self.params.clone());
+        }
+        Ok(self
+            .compute_state_at(height - 1)?
+            .params_at(&self.params, height as u64))
     }
 
     pub fn compute_state(&self) -> anyhow::Result<State> {
-        let mut state = State::new();
-        for (i, block) in self.blocks.iter().enumerate() {
+        self.compute_state_at(self.height())
+    }
+
+    /// Ledger state after the block at `height`. Below a snapshot base it is not available.
+    pub fn compute_state_at(&self, height: usize) -> anyhow::Result<State> {
+        anyhow::ensure!(
+            height <= self.height(),
+            "height {} beyond tip {}",
+            height,
+            self.height()
+        );
+        let (mut state, start) = match &self.snapshot_base {
+            Some(base) => {
+                anyhow::ensure!(
+                    height >= base.height,
+                    "state below snapshot height {} is not available",
+                    base.height
+                );
+                (base.state.clone(), base.height + 1)
+            }
+            None => (State::new(), 0),
+        };
+        for (i, block) in self.blocks.iter().enumerate().take(height + 1).skip(start) {
             state
-                .apply_block(block, i)
+                .apply_block(block, i, &self.params)
                 .with_context(|| format!("block {}", i))?;
         }
         Ok(state)
     }
 
+    /// Whether the block at `height` is header-only (below a snapshot base).
+    pub fn is_pruned(&self, height: usize) -> bool {
+        self.snapshot_base
+            .as_ref()
+            .is_some_and(|b| height >= 1 && height <= b.height)
+    }
+
+    /// Median timestamp of the last `MEDIAN_TIME_SPAN` blocks ending at `height`.
+    pub fn median_time_past_at(&self, height: usize) -> u64 {
+        let end = height.min(self.blocks.len().saturating_sub(1)) + 1;
+        let start = end.saturating_sub(MEDIAN_TIME_SPAN);
+        let mut times: Vec<u64> = self.blocks[start..end]
+            .iter()
+            .map(|b| b.header.timestamp_ms)
+            .collect();
+        times.sort_unstable();
+        times[times.len() / 2]
+    }
+
+    /// Median time past at the current tip; the next block's timestamp must exceed it.
+    pub fn median_time_past(&self) -> u64 {
+        self.median_time_past_at(self.height())
+    }
+
+    /// Local clock corrected by the network time offset.
+    pub fn adjusted_time_ms(&self) -> u64 {
+        (now_ms() as i128 + self.time_offset_ms as i128).max(0) as u64
+    }
+
+    /// Timestamp rules for a block extending the current tip: strictly after median time past,
+    /// and at most `max_future_drift_ms` ahead of adjusted time (`FutureBlockError` otherwise).
+    /// Chains from before state roots were mined without the median-time-past rule.
+    pub fn check_block_time(&self, block: &Block) -> anyhow::Result<()> {
+        if self.commits_state() {
+            let mtp = self.median_time_past();
+            anyhow::ensure!(
+                block.header.timestamp_ms > mtp,
+                "block timestamp {} not after median time past {}",
+                block.header.timestamp_ms,
+                mtp
+            );
+        }
+        let max_allowed_ms = self
+            .adjusted_time_ms()
+            .saturating_add(self.params.max_future_drift_ms);
+        if block.header.timestamp_ms > max_allowed_ms {
+            return Err(FutureBlockError {
+                timestamp_ms: block.header.timestamp_ms,
+                max_allowed_ms,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Replay protection: txs bound to another chain id are invalid here, and signed txs
+    /// (including witness signatures) must be bound to this chain. Coinbase txs are created
+    /// locally and exempt.
+    pub fn check_tx_chain_id(&self, tx: &Transaction) -> anyhow::Result<()> {
+        if tx.is_coinbase() {
+            return Ok(());
+        }
+        if tx.chain_id == 0 {
+            let witness_signed = tx
+                .witness
+                .as_ref()
+                .is_some_and(|w| !w.signatures.is_empty());
+            anyhow::ensure!(
+                tx.signature_b64.is_none() && !witness_signed,
+                "signed tx must be bound to chain_id={}",
+                self.chain_id
+            );
+            return Ok(());
+        }
+        anyhow::ensure!(
+            tx.chain_id == self.chain_id,
+            "tx chain_id mismatch: expected {} got {}",
+            self.chain_id,
+            tx.chain_id
+        );
+        Ok(())
+    }
+
     /// Validates a single transaction against the current ledger state.
     pub fn validate_transaction(&self, tx: &Transaction) -> anyhow::Result<()> {
+        self.validate_transaction_after(&[], tx)
+    }
+
+    /// Validates a transaction that follows `pending`, its sender's earlier mempool txs in
+    /// nonce order (see `Mempool::ancestors_of`).
+    pub fn validate_transaction_after(
+        &self,
+        pending: &[Transaction],
+        tx: &Transaction,
+    ) -> anyhow::Result<()> {
+        self.validate_transaction_on(&self.compute_state()?, pending, tx)
+    }
+
+    /// Like `validate_transaction_after`, against `state`, which must be the tip's (e.g. one
+    /// a node keeps cached).
+    pub fn validate_transaction_on(
+        &self,
+        state: &State,
+        pending: &[Transaction],
+        tx: &Transaction,
+    ) -> anyhow::Result<()> {
         tx.validate_accept()
             .context("TX baseline validation failed")?;
+        self.check_tx_rules_on(state, pending, tx)
+    }
+
+    /// `validate_transaction_on` without the signature check, for callers that verify it
+    /// through a `SignatureCache` instead (see `Mempool::check_signature`).
+    pub fn validate_unverified_transaction_on(
+        &self,
+        state: &State,
+        pending: &[Transaction],
+        tx: &Transaction,
+    ) -> anyhow::Result<()> {
+        tx.validate_basic()
+            .context("TX baseline validation failed")?;
+        self.check_tx_rules_on(state, pending, tx)
+    }
+
+    fn check_tx_rules_on(
+        &self,
+        state: &State,
+        pending: &[Transaction],
+        tx: &Transaction,
+    ) -> anyhow::Result<()> {
+        self.check_tx_chain_id(tx)?;
 
         // Versioning check (future-proofing)
         anyhow::ensure!(tx.version == 1, "only transaction version 1 is supported");
 
-        let state = self.compute_state()?;
-        state.validate_transaction(tx, self.height() + 1)?;
+        let height = self.height() + 1;
+        state.validate_transaction_after(pending, tx, height)?;
+        state.validate_governed(
+            tx,
+            height as u64,
+            &state.params_at(&self.params, height as u64),
+        )?;
         Ok(())
     }
 
-    /// Validates a block's structure, PoW, and state transitions.
+    /// Validates a block's structure, PoW, signatures, and state transitions.
     pub fn validate_block(&self, block: &Block) -> anyhow::Result<()> {
+        self.validate_block_with_cache(block, None)
+    }
+
+    /// Like `validate_block`, but skips signature checks for txs already in `sig_cache`
+    /// (typically txs verified on mempool admission).
+    pub fn validate_block_with_cache(
+        &self,
+        block: &Block,
+        sig_cache: Option<&SignatureCache>,
+    ) -> anyhow::Result<()> {
         let prev_block = self.blocks.last().expect("genesis exists");
         block.validate_with_prev(&prev_block.header, self.pow_difficulty as u32)?;
+        self.check_block_time(block)?;
+        let mut state = self.compute_state()?;
+        state
+            .params_at(&self.params, self.height() as u64 + 1)
+            .check_block_limits(block)?;
 
         let merkle = merkle_root(&block.txs);
         anyhow::ensure!(
@@ -231,22 +700,43 @@ impl Chain {
             block.header.merkle_root
         );
 
+        for (i, tx) in block.txs.iter().enumerate() {
+            self.check_tx_chain_id(tx)
+                .with_context(|| format!("tx index={i}"))?;
+        }
+
+        block
+            .verify_signatures(sig_cache)
+            .context("block signature verification failed")?;
+
         // 2. State transition
-        let mut state = self.compute_state()?;
         state
-            .apply_block(block, self.height() + 1)
+            .apply_block(block, self.height() + 1, &self.params)
             .context("state transition failed for block")?;
+        self.check_state_root(&block.header, &state)?;
 
         Ok(())
     }
 
     /// Appends a validated block to the chain.
     pub fn append_block(&mut self, block: Block) -> anyhow::Result<()> {
+        self.append_block_with_cache(block, None)
+    }
+
+    /// Appends a block, using `sig_cache` to skip already-verified signatures.
+    pub fn append_block_with_cache(
+        &mut self,
+        block: Block,
+        sig_cache: Option<&SignatureCache>,
+    ) -> anyhow::Result<()> {
         let hash = block.header.hash();
         let height = self.blocks.len();
 
-        self.validate_block(&block)?;
+        self.validate_block_with_cache(&block, sig_cache)?;
 
+        if let Some(index) = &mut self.tx_index {
+            index.index_block(height, &block);
+        }
         self.blocks.push(block);
         self.block_index.insert(hash, height);
 
@@ -255,10 +745,142 @@ impl Chain {
         if current_height > 0 && current_height.is_multiple_of(10) {
             self.add_checkpoint();
         }
+        self.auto_prune()?;
 
         Ok(())
     }
 
+    /// Removes the tip block (e.g. to switch to a competing branch) and returns it.
+    ///
+    /// Genesis and checkpointed blocks cannot be disconnected.
+    pub fn disconnect_tip(&mut self) -> anyhow::Result<Block> {
+        let height = self.height();
+        anyhow::ensure!(height > 0, "cannot disconnect the genesis block");
+        anyhow::ensure!(
+            !self.is_pruned(height),
+            "cannot disconnect block {} below the snapshot base",
+            height
+        );
+        anyhow::ensure!(
+            !self.checkpoints.contains_key(&height),
+            "cannot disconnect checkpointed block at height {}",
+            height
+        );
+        let block = self.blocks.pop().expect("height > 0");
+        self.block_index.remove(&block.header.hash());
+        if let Some(index) = &mut self.tx_index {
+            index.unindex_block(height, &block);
+        }
+        Ok(block)
+    }
+
+    /// Height up to which blocks are header-only (0 when every body is kept).
+    pub fn pruned_height(&self) -> usize {
+        self.snapshot_base.as_ref().map_or(0, |b| b.height)
+    }
+
+    /// Turn on pruning mode and prune right away. Returns the number of blocks pruned.
+    pub fn set_prune_keep_blocks(&mut self, keep_blocks: usize) -> anyhow::Result<usize> {
+        let pruned = self.prune(keep_blocks)?;
+        self.prune_keep_blocks = Some(keep_blocks);
+        Ok(pruned)
+    }
+
+    /// Drop the tx bodies of blocks more than `keep_blocks` below the tip, moving the
+    /// snapshot base up to the last pruned block. Headers and checkpoints are kept, and the
+    /// unpruned blocks are enough to undo any reorg that doesn't cross a checkpoint.
+    ///
+    /// Returns the number of blocks pruned.
+    pub fn prune(&mut self, keep_blocks: usize) -> anyhow::Result<usize> {
+        anyhow::ensure!(
+            keep_blocks >= MIN_PRUNE_KEEP_BLOCKS,
+            "must keep at least {} blocks, got {}",
+            MIN_PRUNE_KEEP_BLOCKS,
+            keep_blocks
+        );
+        anyhow::ensure!(
+            self.tx_index.is_none(),
+            "pruning is not supported with the tx index"
+        );
+        let old_base = self.pruned_height();
+        let new_base = self.height().saturating_sub(keep_blocks);
+        if new_base <= old_base {
+            return Ok(0);
+        }
+        let state = self.compute_state_at(new_base)?;
+        for block in &mut self.blocks[old_base + 1..=new_base] {
+            block.txs.clear();
+        }
+        self.snapshot_base = Some(SnapshotBase {
+            height: new_base,
+            state,
+        });
+        Ok(new_base - old_base)
+    }
+
+    fn auto_prune(&mut self) -> anyhow::Result<()> {
+        if let Some(keep_blocks) = self.prune_keep_blocks {
+            self.prune(keep_blocks)?;
+        }
+        Ok(())
+    }
+
+    /// Build the tx index (if not already on) and keep it updated from now on.
+    pub fn enable_tx_index(&mut self) {
+        if self.tx_index.is_none() {
+            self.tx_index = Some(TxIndex::build(self));
+        }
+    }
+
+    /// Look up a confirmed tx via the tx index. Errors if the index is disabled.
+    pub fn find_tx(&self, tx_id: &str) -> anyhow::Result<Option<(TxLocation, &Transaction)>> {
+        let index = self.tx_index.as_ref().context("tx index is disabled")?;
+        Ok(index.get(tx_id).and_then(|loc| {
+            let tx = self.blocks.get(loc.height)?.txs.get(loc.index)?;
+            Some((loc, tx))
+        }))
+    }
+
+    /// Inclusion proof for a confirmed tx, via the tx index.
+    pub fn tx_proof(&self, tx_id: &str) -> anyhow::Result<Option<TxInclusionProof>> {
+        Ok(self.find_tx(tx_id)?.and_then(|(loc, tx)| {
+            Some(TxInclusionProof {
+                tx: tx.clone(),
+                height: loc.height as u64,
+                merkle: MerkleProof::for_block(&self.blocks[loc.height], loc.index)?,
+            })
+        }))
+    }
+
+    /// Confirmed txs touching `address`, oldest first, via the tx index. Errors rather than
+    /// return a partial history once blocks are pruned.
+    pub fn address_history(
+        &self,
+        address: &str,
+    ) -> anyhow::Result<Vec<(TxLocation, &Transaction)>> {
+        let index = self.tx_index.as_ref().context("tx index is disabled")?;
+        anyhow::ensure!(
+            self.pruned_height() == 0,
+            "history unavailable: blocks up to height {} are pruned",
+            self.pruned_height()
+        );
+        index
+            .history(address)
+            .iter()
+            .map(|id| {
+                let loc = index
+                    .get(id)
+                    .with_context(|| format!("tx {id} is not indexed"))?;
+                let tx = self
+                    .blocks
+                    .get(loc.height)
+                    .and_then(|b| b.txs.get(loc.index))
+                    .with_context(|| format!("tx {id} at height {} is missing", loc.height))?;
+                Ok((loc, tx))
+            })
+            .collect()
+    }
+
     /// Adds a checkpoint at the current height.
     pub fn add_checkpoint(&mut self) {
         let height = self.height();
@@ -353,21 +975,67 @@ impl Chain {
         // Checkpoints validation
         self.validate_checkpoints()?;
 
-        // Validate state transitions (balances, nonces)
+        // Validate state transitions (balances, nonces) and each header's state commitment.
         // This ensures every block in the chain is valid according to the state rules.
-        self.compute_state().context("state validation failed")?;
+        let mut state = State::new();
+        let mut start = 0;
+        if let Some(base) = &self.snapshot_base {
+            let header = &self
+                .blocks
+                .get(base.height)
+                .context("snapshot base beyond tip")?
+                .header;
+            self.check_state_root(header, &base.state)
+                .context("snapshot state does not match its header")?;
+            state = base.state.clone();
+            start = base.height + 1;
+        }
+        for (i, block) in self.blocks.iter().enumerate().skip(start) {
+            // Limits are those in force for the block, so they're checked along with the state.
+            if i > 0 {
+                state
+                    .params_at(&self.params, i as u64)
+                    .check_block_limits(block)
+                    .with_context(|| format!("block {i} exceeds limits"))?;
+            }
+            state
+                .apply_block(block, i, &self.params)
+                .and_then(|_| self.check_state_root(&block.header, &state))
+                .with_context(|| format!("block {}", i))
+                .context("state validation failed")?;
+        }
 
         for i in 1..self.blocks.len() {
             let prev = &self.blocks[i - 1];
             let cur = &self.blocks[i];
 
+            cur.validate_with_prev(&prev.header, self.pow_difficulty as u32)
+                .with_context(|| format!("block {} linkage/PoW fail", i))?;
+
+            // Future drift is only checked on arrival; stored blocks only need to follow MTP,
+            // unless they predate it along with state roots.
+            if self.commits_state() {
+                let mtp = self.median_time_past_at(i - 1);
+                anyhow::ensure!(
+                    cur.header.timestamp_ms > mtp,
+                    "block {i} timestamp {} not after median time past {}",
+                    cur.header.timestamp_ms,
+                    mtp
+                );
+            }
+
+            // Header-only below a snapshot base.
+            if self.is_pruned(i) {
+                continue;
+            }
+
             for (j, tx) in cur.txs.iter().enumerate() {
-                tx.validate_accept()
+                tx.validate_basic()
+                    .and_then(|_| self.check_tx_chain_id(tx))
                     .with_context(|| format!("invalid tx in block={i} index={j}"))?;
             }
-
-            cur.validate_with_prev(&prev.header, self.pow_difficulty as u32)
-                .with_context(|| format!("block {} linkage/PoW fail", i))?;
+            cur.verify_signatures(None)
+                .with_context(|| format!("invalid tx in block={i}"))?;
 
             let expected_merkle = merkle_root(&cur.txs);
             anyhow::ensure!(
@@ -396,14 +1064,64 @@ pub fn hash_block(block: &Block) -> String {
 }
 
 pub fn merkle_root(txs: &[Transaction]) -> String {
+    merkle_root_from_ids(&txs.iter().map(|t| t.id()).collect::<Vec<_>>())
+}
+
+/// `merkle_root` over already-computed tx ids.
+pub fn merkle_root_from_ids(tx_ids: &[String]) -> String {
     // Simple demo merkle: hash of concatenated tx hashes.
-    if txs.is_empty() {
+    if tx_ids.is_empty() {
         return sha256_hex(&[]);
     }
 
-    let joined = txs.iter().map(|t| t.id()).collect::<Vec<_>>().join("");
+    sha256_hex(tx_ids.concat().as_bytes())
+}
+
+/// Proof that a tx is committed to by a block header's `merkle_root`.
+///
+/// The root is a flat hash over all of the block's tx ids, so the proof carries every id
+/// rather than a logarithmic branch.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// Position of the proven tx in the block.
+    pub index: usize,
+    pub tx_ids: Vec<String>,
+}
+
+/// A confirmed tx with the proof tying it to the header at `height`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TxInclusionProof {
+    pub tx: Transaction,
+    pub height: u64,
+    pub merkle: MerkleProof,
+}
+
+impl MerkleProof {
+    /// Proof for the tx at `index` of `block`.
+    pub fn for_block(block: &Block, index: usize) -> Option<Self> {
+        (index < block.txs.len()).then(|| Self {
+            index,
+            tx_ids: block.txs.iter().map(|t| t.id()).collect(),
+        })
+    }
 
-    sha256_hex(joined.as_bytes())
+    /// Check that `tx_id` sits at `index` under `merkle_root`.
+    pub fn verify(&self, tx_id: &str, merkle_root: &str) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.tx_ids.get(self.index).is_some_and(|id| id == tx_id),
+            "merkle proof does not contain tx {} at index {}",
+            tx_id,
+            self.index
+        );
+        let root = merkle_root_from_ids(&self.tx_ids);
+        anyhow::ensure!(
+            root == merkle_root,
+            "merkle proof root mismatch: {} (header {})",
+            root,
+            merkle_root
+        );
+        Ok(())
+    }
 }
 
 /// Very small PoW: block hash must start with N '0' hex chars.
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Well-known networks. Each has a fixed chain id and its own default data directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Devnet,
+}
+
+pub const MAINNET_CHAIN_ID: u32 = 1;
+pub const TESTNET_CHAIN_ID: u32 = 2;
+pub const DEVNET_CHAIN_ID: u32 = 3;
+
+impl Network {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Devnet => "devnet",
+        }
+    }
+
+    pub fn chain_id(&self) -> u32 {
+        match self {
+            Network::Mainnet => MAINNET_CHAIN_ID,
+            Network::Testnet => TESTNET_CHAIN_ID,
+            Network::Devnet => DEVNET_CHAIN_ID,
+        }
+    }
+
+    /// Default data directory for this network (`data/<name>`).
+    pub fn data_dir(&self) -> PathBuf {
+        data_dir_for(self.name())
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Network {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            "devnet" => Ok(Network::Devnet),
+            other => anyhow::bail!(
+                "unknown network: {} (expected mainnet|testnet|devnet)",
+                other
+            ),
+        }
+    }
+}
+
+/// Chain id for a network name, if it is one of the well-known networks.
+pub fn known_chain_id(network: &str) -> Option<u32> {
+    network.parse::<Network>().ok().map(|n| n.chain_id())
+}
+
+/// Default data directory for any network name (`data/<name>`).
+pub fn data_dir_for(network: &str) -> PathBuf {
+    PathBuf::from("data").join(network)
+}
@@ -1,6 +1,12 @@
 use base64::{Engine as _, engine::general_purpose::STANDARD as B64};
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
 use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::Rng;
 use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
 
 /// Generate a fresh ed25519 keypair.
 pub fn generate_keypair() -> (SigningKey, VerifyingKey) {
@@ -39,8 +45,96 @@ pub fn sign_bytes(sk: &SigningKey, msg: &[u8]) -> String {
 }
 
 pub fn verify_bytes(vk: &VerifyingKey, msg: &[u8], sig_b64: &str) -> anyhow::Result<()> {
+    let sig = signature_from_base64(sig_b64)?;
+    verify_signature(vk, msg, &sig)
+}
+
+pub fn signature_from_base64(sig_b64: &str) -> anyhow::Result<Signature> {
     let sig_bytes = B64.decode(sig_b64)?;
-    let sig = Signature::from_slice(&sig_bytes)?;
-    vk.verify_strict(msg, &sig)?;
+    Ok(Signature::from_slice(&sig_bytes)?)
+}
+
+/// Decode `R` and `s` and apply the encoding rules every signature must meet: canonical `R`
+/// and `s`, and neither `R` nor the key of small order.
+fn signature_points(vk: &VerifyingKey, sig: &Signature) -> anyhow::Result<(EdwardsPoint, Scalar)> {
+    let r = CompressedEdwardsY(*sig.r_bytes());
+    let big_r = r
+        .decompress()
+        .filter(|p| p.compress() == r)
+        .ok_or_else(|| anyhow::anyhow!("signature R is not a canonical point encoding"))?;
+    anyhow::ensure!(!big_r.is_small_order(), "signature R has small order");
+    anyhow::ensure!(!vk.is_weak(), "verifying key has small order");
+    let s = Option::<Scalar>::from(Scalar::from_canonical_bytes(*sig.s_bytes()))
+        .ok_or_else(|| anyhow::anyhow!("signature s is not canonical"))?;
+    Ok((big_r, s))
+}
+
+/// `H(R || A || msg)`, the challenge scalar of an ed25519 signature.
+fn challenge(vk: &VerifyingKey, sig: &Signature, msg: &[u8]) -> Scalar {
+    let digest = Sha512::new()
+        .chain_update(sig.r_bytes())
+        .chain_update(vk.as_bytes())
+        .chain_update(msg)
+        .finalize();
+    Scalar::from_bytes_mod_order_wide(&digest.into())
+}
+
+/// Verify one signature with the cofactored equation `[8]([s]B - R - [k]A) = 0`.
+///
+/// This is the consensus rule for tx signatures, and it is a rule change: signatures used to
+/// be checked with `VerifyingKey::verify_strict`, whose cofactorless equation rejects an `R`
+/// carrying a torsion component. Both rules reject non-canonical encodings and small-order
+/// `R` or keys and agree on every honestly made signature; the only signatures whose verdict
+/// changed are ones with a torsioned `R`, which no standard signer produces and which are now
+/// accepted. Multiplying by the cofactor is what lets `verify_batch` decide exactly the same
+/// thing, so the verdict never depends on which signatures share a batch.
+pub fn verify_signature(vk: &VerifyingKey, msg: &[u8], sig: &Signature) -> anyhow::Result<()> {
+    let (big_r, s) = signature_points(vk, sig)?;
+    let k = challenge(vk, sig, msg);
+    let minus_a = -vk.to_edwards();
+    let check = EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &minus_a, &s) - big_r;
+    anyhow::ensure!(
+        check.mul_by_cofactor().is_identity(),
+        "signature verification failed"
+    );
+    Ok(())
+}
+
+/// Verify many (message, signature, key) triples at once against the `verify_signature` rule.
+///
+/// Checks `[8](sum z_i ([s_i]B - R_i - [k_i]A_i)) = 0` for random 128-bit `z_i`, so it agrees
+/// with `verify_signature` on every item except with probability 2^-128. A batch failure only
+/// says that *some* signature is bad; callers that need to know which one should fall back to
+/// `verify_signature` per item.
+pub fn verify_batch(
+    messages: &[&[u8]],
+    signatures: &[Signature],
+    keys: &[VerifyingKey],
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        messages.len() == signatures.len() && signatures.len() == keys.len(),
+        "batch lengths differ"
+    );
+    let mut rng = rand::thread_rng();
+    let mut b_coefficient = Scalar::ZERO;
+    let mut scalars = Vec::with_capacity(2 * keys.len() + 1);
+    let mut points = Vec::with_capacity(2 * keys.len() + 1);
+    for ((msg, sig), vk) in messages.iter().zip(signatures).zip(keys) {
+        let (big_r, s) = signature_points(vk, sig)?;
+        let z = Scalar::from(rng.r#gen::<u128>());
+        b_coefficient += z * s;
+        scalars.push(z);
+        points.push(big_r);
+        scalars.push(z * challenge(vk, sig, msg));
+        points.push(vk.to_edwards());
+    }
+    scalars.push(-b_coefficient);
+    points.push(ED25519_BASEPOINT_POINT);
+
+    let sum = EdwardsPoint::vartime_multiscalar_mul(&scalars, &points);
+    anyhow::ensure!(
+        sum.mul_by_cofactor().is_identity(),
+        "batch signature verification failed"
+    );
     Ok(())
 }
@@ -0,0 +1,242 @@
+use crate::core::chain::Chain;
+use crate::core::mempool::Mempool;
+use crate::core::policy::fee_per_kb;
+use crate::core::types::{Block, Transaction};
+use std::collections::HashMap;
+
+/// Longest confirmation target (in blocks) the estimator answers for.
+pub const MAX_CONFIRM_TARGET: usize = 25;
+
+/// Target used when a request doesn't give one.
+pub const DEFAULT_CONFIRM_TARGET: usize = 2;
+
+/// Share of txs in a fee-rate range that must confirm within the target.
+pub const SUCCESS_THRESHOLD: f64 = 0.85;
+
+/// Minimum (decayed) number of txs a fee-rate range needs before it is trusted.
+pub const MIN_BUCKET_SAMPLES: f64 = 2.0;
+
+/// Per-block decay of past observations, so recent blocks dominate.
+pub const DECAY: f64 = 0.998;
+
+/// Blocks replayed by `FeeEstimator::from_chain`.
+pub const HISTORY_BLOCKS: usize = 1_000;
+
+/// Bucket 0 holds zero-fee txs; bucket `b > 0` holds rates in `[2^(b-1), 2^b)` per kB.
+const NUM_BUCKETS: usize = 65;
+
+/// Bucket index for a fee rate (per kB).
+pub fn bucket_for(fee_per_kb: u64) -> usize {
+    if fee_per_kb == 0 {
+        0
+    } else {
+        fee_per_kb.ilog2() as usize + 1
+    }
+}
+
+/// Lowest fee rate (per kB) that falls into `bucket`.
+pub fn bucket_floor(bucket: usize) -> u64 {
+    if bucket == 0 { 0 } else { 1 << (bucket - 1) }
+}
+
+#[derive(Debug, Clone)]
+struct BucketStats {
+    /// `confirmed_within[t - 1]`: txs that confirmed within `t` blocks of entering the pool.
+    confirmed_within: [f64; MAX_CONFIRM_TARGET],
+    /// All confirmed txs.
+    total: f64,
+}
+
+impl Default for BucketStats {
+    fn default() -> Self {
+        Self {
+            confirmed_within: [0.0; MAX_CONFIRM_TARGET],
+            total: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TrackedTx {
+    bucket: usize,
+    entry_height: u64,
+}
+
+/// Fee estimate for a confirmation target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub target_blocks: usize,
+    pub fee_per_kb: u64,
+}
+
+impl FeeEstimate {
+    /// Total fee for a tx of `size` bytes at this rate, rounded up.
+    pub fn fee_for_size(&self, size: usize) -> u64 {
+        let fee = (self.fee_per_kb as u128 * size as u128).div_ceil(1000);
+        fee.min(u64::MAX as u128) as u64
+    }
+
+    /// The rate in fee units per byte, rounded up.
+    pub fn fee_per_byte(&self) -> u64 {
+        self.fee_per_kb.div_ceil(1000)
+    }
+}
+
+/// Tracks how many blocks txs in each fee-rate bucket take to confirm.
+///
+/// Txs are tracked from the height at which they entered the mempool until they are mined
+/// or dropped. Estimates combine that history with the current mempool backlog.
+#[derive(Debug, Clone)]
+pub struct FeeEstimator {
+    buckets: Vec<BucketStats>,
+    tracked: HashMap<String, TrackedTx>,
+    height: u64,
+}
+
+impl Default for FeeEstimator {
+    fn default() -> Self {
+        Self {
+            buckets: vec![BucketStats::default(); NUM_BUCKETS],
+            tracked: HashMap::new(),
+            height: 0,
+        }
+    }
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed the estimator from the last `HISTORY_BLOCKS` blocks of `chain`.
+    ///
+    /// A confirmed tx is assumed to have entered the mempool at the last block whose timestamp
+    /// is not after the tx's `timestamp_ms`.
+    pub fn from_chain(chain: &Chain) -> Self {
+        let mut est = Self::new();
+        let tip = chain.height();
+        let start = tip.saturating_sub(HISTORY_BLOCKS).max(1);
+        let times: Vec<u64> = chain.blocks.iter().map(|b| b.header.timestamp_ms).collect();
+        for h in start..=tip {
+            est.decay();
+            for tx in &chain.blocks[h].txs {
+                if tx.is_coinbase() {
+                    continue;
+                }
+                let entry = times[..h]
+                    .partition_point(|&t| t <= tx.timestamp_ms)
+                    .saturating_sub(1);
+                est.record(bucket_for(fee_per_kb(tx.fee, tx.size())), h - entry);
+            }
+        }
+        est.height = tip as u64;
+        est
+    }
+
+    /// Number of mempool txs being tracked.
+    pub fn tracked_len(&self) -> usize {
+        self.tracked.len()
+    }
+
+    /// Start tracking a tx that just entered the mempool at the current height.
+    pub fn track_tx(&mut self, tx: &Transaction) {
+        self.tracked.insert(
+            tx.id(),
+            TrackedTx {
+                bucket: bucket_for(fee_per_kb(tx.fee, tx.size())),
+                entry_height: self.height,
+            },
+        );
+    }
+
+    /// Stop tracking a tx without recording an outcome (e.g. it was dropped on revalidation).
+    pub fn untrack(&mut self, tx_id: &str) {
+        self.tracked.remove(tx_id);
+    }
+
+    /// Record confirmations from a block connected at `height`.
+    ///
+    /// Tracked txs that are neither in `block` nor still in `mempool` are forgotten.
+    pub fn on_block_connected(&mut self, height: u64, block: &Block, mempool: &Mempool) {
+        self.decay();
+        self.height = height;
+        for tx in &block.txs {
+            if let Some(t) = self.tracked.remove(&tx.id()) {
+                let blocks = height.saturating_sub(t.entry_height).max(1) as usize;
+                self.record(t.bucket, blocks);
+            }
+        }
+        self.untrack_missing(mempool);
+    }
+
+    /// Stop tracking txs that left `mempool` without being mined (expired, replaced or
+    /// evicted for space).
+    pub fn untrack_missing(&mut self, mempool: &Mempool) {
+        self.tracked.retain(|id, _| mempool.contains_tx(id));
+    }
+
+    fn decay(&mut self) {
+        for b in &mut self.buckets {
+            b.total *= DECAY;
+            for c in &mut b.confirmed_within {
+                *c *= DECAY;
+            }
+        }
+    }
+
+    fn record(&mut self, bucket: usize, blocks: usize) {
+        let stats = &mut self.buckets[bucket];
+        stats.total += 1.0;
+        for c in stats.confirmed_within.iter_mut().skip(blocks.max(1) - 1) {
+            *c += 1.0;
+        }
+    }
+
+    /// Lowest fee rate (per kB) at which past txs confirmed within `target` blocks at least
+    /// `SUCCESS_THRESHOLD` of the time, or `None` without enough data.
+    ///
+    /// Buckets are scanned from the highest rate down, merged until they hold
+    /// `MIN_BUCKET_SAMPLES`, and the scan stops at the first range that falls short. Tracked
+    /// txs that have already waited `target` blocks count as failures.
+    pub fn history_fee_per_kb(&self, target: usize) -> Option<u64> {
+        let target = target.clamp(1, MAX_CONFIRM_TARGET);
+        let mut waiting = vec![0.0; NUM_BUCKETS];
+        for t in self.tracked.values() {
+            if self.height.saturating_sub(t.entry_height) >= target as u64 {
+                waiting[t.bucket] += 1.0;
+            }
+        }
+
+        let mut best = None;
+        let (mut confirmed, mut samples) = (0.0, 0.0);
+        for b in (0..NUM_BUCKETS).rev() {
+            confirmed += self.buckets[b].confirmed_within[target - 1];
+            samples += self.buckets[b].total + waiting[b];
+            if samples < MIN_BUCKET_SAMPLES {
+                continue;
+            }
+            if confirmed / samples < SUCCESS_THRESHOLD {
+                break;
+            }
+            best = Some(bucket_floor(b));
+            (confirmed, samples) = (0.0, 0.0);
+        }
+        best
+    }
+
+    /// Fee rate for confirmation within `target` blocks of up to `block_bytes` each.
+    ///
+    /// The highest of the history-based rate, the rate needed to outbid the mempool backlog
+    /// that fills `target` blocks, and the mempool's current minimum relay fee.
+    pub fn estimate(&self, target: usize, mempool: &Mempool, block_bytes: usize) -> FeeEstimate {
+        let target = target.clamp(1, MAX_CONFIRM_TARGET);
+        let history = self.history_fee_per_kb(target).unwrap_or(0);
+        let depth = mempool
+            .fee_per_kb_at_depth(block_bytes.saturating_mul(target))
+            .unwrap_or(0);
+        FeeEstimate {
+            target_blocks: target,
+            fee_per_kb: history.max(depth).max(mempool.min_fee_per_kb()),
+        }
+    }
+}
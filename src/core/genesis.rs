@@ -0,0 +1,169 @@
+use crate::core::chain::{Chain, merkle_root};
+use crate::core::chain_id::known_chain_id;
+use crate::core::hash::sha256_hex;
+use crate::core::params::ChainParams;
+use crate::core::state::State;
+use crate::core::state_tree::state_root;
+use crate::core::types::{Block, BlockHeader, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Initial balance for one account, applied by `State` at height 0.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GenesisAllocation {
+    pub address: String,
+    pub balance: u64,
+}
+
+/// Genesis configuration file (`init --genesis genesis.json`).
+///
+/// Everything that affects the genesis block comes from this file, so nodes initialized from
+/// the same file end up with the same genesis hash.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GenesisConfig {
+    /// Human-readable network name (e.g. "devnet").
+    pub network: String,
+
+    /// Chain id for replay protection. Optional for mainnet/testnet/devnet, which have fixed ids.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u32>,
+
+    /// Genesis block timestamp (Unix epoch ms).
+    pub timestamp_ms: u64,
+
+    /// Initial PoW difficulty (leading '0' hex chars).
+    #[serde(default = "default_genesis_difficulty")]
+    pub difficulty: usize,
+
+    #[serde(default)]
+    pub params: ChainParams,
+
+    #[serde(default)]
+    pub allocations: Vec<GenesisAllocation>,
+}
+
+fn default_genesis_difficulty() -> usize {
+    3
+}
+
+impl GenesisConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let s = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+
+    /// Explicit `chain_id`, or the fixed id of a well-known network.
+    pub fn resolved_chain_id(&self) -> anyhow::Result<u32> {
+        let id = self
+            .chain_id
+            .or_else(|| known_chain_id(&self.network))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "network {} has no well-known chain id; set chain_id in the genesis config",
+                    self.network
+                )
+            })?;
+        anyhow::ensure!(id != 0, "chain_id must be non-zero");
+        Ok(id)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !self.network.trim().is_empty(),
+            "genesis network name must be non-empty"
+        );
+        self.resolved_chain_id()?;
+
+        let mut seen = HashSet::new();
+        let mut total: u64 = 0;
+        for a in &self.allocations {
+            anyhow::ensure!(
+                !a.address.trim().is_empty(),
+                "genesis allocation address must be non-empty"
+            );
+            anyhow::ensure!(
+                a.address != "SYSTEM",
+                "genesis allocation cannot target SYSTEM"
+            );
+            anyhow::ensure!(
+                a.balance > 0,
+                "genesis allocation for {} must be > 0",
+                a.address
+            );
+            anyhow::ensure!(
+                seen.insert(a.address.as_str()),
+                "duplicate genesis allocation for {}",
+                a.address
+            );
+            total = total
+                .checked_add(a.balance)
+                .ok_or_else(|| anyhow::anyhow!("genesis allocations overflow u64"))?;
+        }
+        Ok(())
+    }
+
+    /// Hash of the non-allocation settings (network, chain id, difficulty, params).
+    pub fn config_hash(&self) -> String {
+        let committed = serde_json::json!({
+            "network": self.network,
+            "chain_id": self.resolved_chain_id().unwrap_or(0),
+            "difficulty": self.difficulty,
+            "params": self.params,
+        });
+        sha256_hex(committed.to_string().as_bytes())
+    }
+
+    /// Allocation txs in file order. Timestamps are pinned to the genesis timestamp so the
+    /// resulting merkle root is deterministic.
+    pub fn allocation_txs(&self) -> Vec<Transaction> {
+        self.allocations
+            .iter()
+            .map(|a| Transaction {
+                from: "SYSTEM".to_string(),
+                to: a.address.clone(),
+                amount: a.balance,
+                nonce: 0,
+                timestamp_ms: self.timestamp_ms,
+                memo: Some("genesis allocation".to_string()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Build the genesis block.
+    ///
+    /// The genesis block needs no PoW, so its header nonce is used to commit to
+    /// `config_hash` (first 8 bytes).
+    pub fn genesis_block(&self) -> Block {
+        let txs = self.allocation_txs();
+        let config_hash = self.config_hash();
+        let nonce = u64::from_str_radix(&config_hash[..16], 16).unwrap_or(0);
+        // Allocations that don't apply are rejected by `validate` before the block is used.
+        let mut state = State::new();
+        let state_root = match state.apply_block_txs(&txs, 0, &self.params) {
+            Ok(()) => state_root(&state),
+            Err(_) => String::new(),
+        };
+        let header = BlockHeader {
+            prev_hash: "0".repeat(64),
+            timestamp_ms: self.timestamp_ms,
+            nonce,
+            merkle_root: merkle_root(&txs),
+            state_root,
+        };
+        Block { header, txs }
+    }
+
+    /// Build a fresh chain from this configuration.
+    pub fn build_chain(&self) -> anyhow::Result<Chain> {
+        self.validate()?;
+        let mut chain = Chain::from_genesis_block(self.genesis_block());
+        chain.network = self.network.clone();
+        chain.chain_id = self.resolved_chain_id()?;
+        chain.pow_difficulty = self.difficulty;
+        chain.params = self.params.clone();
+        Ok(chain)
+    }
+}
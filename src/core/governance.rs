@@ -0,0 +1,244 @@
+use crate::core::hash::sha256_hex;
+use crate::core::params::ChainParams;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Recipient of every governance tx. Its account's `storage_root` commits to `Governance`.
+pub const GOVERNANCE_ADDRESS: &str = "governance";
+
+/// Most proposals one sender can have open at once.
+pub const MAX_OPEN_PROPOSALS_PER_SENDER: usize = 2;
+
+/// Smallest `max_block_size` a proposal may set.
+pub const MIN_GOVERNED_BLOCK_SIZE: usize = 10_000;
+
+/// Smallest `max_block_txs` a proposal may set (a coinbase plus one tx).
+pub const MIN_GOVERNED_BLOCK_TXS: usize = 2;
+
+/// What a governance tx does, carried in `Transaction::governance`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GovernanceOp {
+    /// Propose `change`, to take effect from `activation_height` if the vote passes.
+    Propose {
+        change: ParamChange,
+        activation_height: u64,
+    },
+    /// Vote on the proposal whose tx id is `proposal_id`. A later vote replaces an earlier one.
+    Vote { proposal_id: String, approve: bool },
+}
+
+/// A chain parameter a proposal can change.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "param", content = "value", rename_all = "snake_case")]
+pub enum ParamChange {
+    /// Flat block subsidy, replacing the halving schedule.
+    BlockReward(u64),
+    MaxBlockSize(usize),
+    MaxBlockTxs(usize),
+    /// Minimum fee of every non-coinbase tx.
+    MinTxFee(u64),
+}
+
+impl ParamChange {
+    /// Parameter names accepted by `from_name`, as shown by `Display`.
+    pub const NAMES: [&'static str; 4] = [
+        "block_reward",
+        "max_block_size",
+        "max_block_txs",
+        "min_tx_fee",
+    ];
+
+    /// The change setting parameter `name` to `value`.
+    pub fn from_name(name: &str, value: u64) -> anyhow::Result<Self> {
+        let size = || usize::try_from(value).map_err(|_| anyhow::anyhow!("{} is too large", value));
+        Ok(match name {
+            "block_reward" => ParamChange::BlockReward(value),
+            "max_block_size" => ParamChange::MaxBlockSize(size()?),
+            "max_block_txs" => ParamChange::MaxBlockTxs(size()?),
+            "min_tx_fee" => ParamChange::MinTxFee(value),
+            other => anyhow::bail!(
+                "unknown parameter {:?} (expected one of {})",
+                other,
+                Self::NAMES.join(", ")
+            ),
+        })
+    }
+
+    pub fn apply(&self, params: &mut ChainParams) {
+        match *self {
+            ParamChange::BlockReward(reward) => {
+                params.initial_subsidy = reward;
+                params.halving_interval = 0;
+                params.tail_emission = 0;
+            }
+            ParamChange::MaxBlockSize(size) => params.max_block_size = size,
+            ParamChange::MaxBlockTxs(txs) => params.max_block_txs = txs,
+            ParamChange::MinTxFee(fee) => params.min_tx_fee = fee,
+        }
+    }
+
+    /// Whether the change can be made to a chain with `params`.
+    pub fn check(&self, params: &ChainParams) -> anyhow::Result<()> {
+        match *self {
+            ParamChange::BlockReward(_) => anyhow::ensure!(
+                params.max_supply == 0,
+                "block reward can't be governed on a chain with max_supply"
+            ),
+            ParamChange::MaxBlockSize(size) => anyhow::ensure!(
+                size >= MIN_GOVERNED_BLOCK_SIZE,
+                "max_block_size must be >= {}",
+                MIN_GOVERNED_BLOCK_SIZE
+            ),
+            ParamChange::MaxBlockTxs(txs) => anyhow::ensure!(
+                txs >= MIN_GOVERNED_BLOCK_TXS,
+                "max_block_txs must be >= {}",
+                MIN_GOVERNED_BLOCK_TXS
+            ),
+            ParamChange::MinTxFee(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ParamChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamChange::BlockReward(v) => write!(f, "block_reward={v}"),
+            ParamChange::MaxBlockSize(v) => write!(f, "max_block_size={v}"),
+            ParamChange::MaxBlockTxs(v) => write!(f, "max_block_txs={v}"),
+            ParamChange::MinTxFee(v) => write!(f, "min_tx_fee={v}"),
+        }
+    }
+}
+
+/// A proposal still being voted on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Proposal {
+    pub proposer: String,
+    pub change: ParamChange,
+    pub activation_height: u64,
+    /// Last height a vote can be included at; the tally happens at the end of that block.
+    pub voting_ends: u64,
+    /// Height of the proposal's block. An account's voting weight is its balance after it.
+    pub snapshot_height: u64,
+    /// Voting weight of all accounts together at `snapshot_height`.
+    pub total_weight: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub votes: BTreeMap<String, Ballot>,
+}
+
+/// A vote cast on a proposal, with the voter's snapshot weight.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Ballot {
+    pub approve: bool,
+    pub weight: u64,
+}
+
+impl Proposal {
+    /// Weight voted so far with `approve`.
+    pub fn tally(&self, approve: bool) -> u128 {
+        self.votes
+            .values()
+            .filter(|ballot| ballot.approve == approve)
+            .map(|ballot| ballot.weight as u128)
+            .sum()
+    }
+
+    /// Passed if approving weight is more than half of all snapshot weight.
+    pub fn passed(&self) -> bool {
+        self.tally(true) * 2 > self.total_weight as u128
+    }
+}
+
+/// A passed proposal's change, in force from `activation_height` on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScheduledChange {
+    pub proposal_id: String,
+    pub activation_height: u64,
+    pub change: ParamChange,
+}
+
+/// Open proposals and the changes that passed, kept in `State::governance`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Governance {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub proposals: BTreeMap<String, Proposal>,
+    /// In the order they passed; kept after activation so parameters can be recomputed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scheduled: Vec<ScheduledChange>,
+    /// `(height, balance before that block)` for each block that changed an account's balance
+    /// while proposals were open, oldest first. Only what open snapshots still need is kept.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub balance_history: BTreeMap<String, Vec<(u64, u64)>>,
+}
+
+impl Governance {
+    pub fn is_empty(&self) -> bool {
+        self.proposals.is_empty() && self.scheduled.is_empty() && self.balance_history.is_empty()
+    }
+
+    /// Balance of `address` after the block at `snapshot_height`, given its current `balance`:
+    /// the balance before the first later block that changed it, if any.
+    pub fn balance_at(&self, address: &str, snapshot_height: u64, balance: u64) -> u64 {
+        self.balance_history
+            .get(address)
+            .and_then(|changes| changes.iter().find(|(height, _)| *height > snapshot_height))
+            .map_or(balance, |&(_, before)| before)
+    }
+
+    /// Number of open proposals made by `proposer`.
+    pub fn open_proposals_by(&self, proposer: &str) -> usize {
+        self.proposals
+            .values()
+            .filter(|p| p.proposer == proposer)
+            .count()
+    }
+
+    /// SHA-256 (hex) of the canonical JSON; stored as the governance account's `storage_root`.
+    pub fn hash(&self) -> String {
+        sha256_hex(&serde_json::to_vec(self).expect("governance serialization"))
+    }
+
+    /// `base` with every change active at `height` applied, in the order they passed.
+    pub fn params_at(&self, base: &ChainParams, height: u64) -> ChainParams {
+        let mut params = base.clone();
+        for scheduled in &self.scheduled {
+            if scheduled.activation_height <= height {
+                scheduled.change.apply(&mut params);
+            }
+        }
+        params
+    }
+
+    /// Tally proposals whose voting ended at or before `height`; passed ones are scheduled.
+    /// Balance history no open proposal needs any more is dropped.
+    pub fn close_voting(&mut self, height: u64) {
+        let ended: Vec<String> = self
+            .proposals
+            .iter()
+            .filter(|(_, p)| p.voting_ends <= height)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ended {
+            let proposal = self.proposals.remove(&id).expect("listed above");
+            if proposal.passed() {
+                self.scheduled.push(ScheduledChange {
+                    proposal_id: id,
+                    activation_height: proposal.activation_height,
+                    change: proposal.change,
+                });
+            }
+        }
+        match self.proposals.values().map(|p| p.snapshot_height).min() {
+            Some(oldest) => {
+                for changes in self.balance_history.values_mut() {
+                    changes.retain(|(height, _)| *height > oldest);
+                }
+                self.balance_history
+                    .retain(|_, changes| !changes.is_empty());
+            }
+            None => self.balance_history.clear(),
+        }
+    }
+}
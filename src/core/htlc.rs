@@ -0,0 +1,189 @@
+use crate::core::chain::Chain;
+use crate::core::crypto::{sign_bytes, verifying_key_to_hex};
+use crate::core::hash::sha256_hex;
+use crate::core::script::{Condition, Witness, WitnessSignature};
+use crate::core::types::Transaction;
+use ed25519_dalek::SigningKey;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// A hash time-locked contract: coins sent to `address()` can be claimed by `recipient` with
+/// the preimage of `hash`, or taken back by `refund_to` from block `timeout_height` on.
+///
+/// An atomic swap is two contracts with the same hash on two chains. The party that made the
+/// secret claims on the other chain first, which reveals the preimage for the counterparty to
+/// claim with; the contract that party locked needs the later timeout.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Htlc {
+    /// Chain the contract lives on; claims and refunds are bound to it.
+    pub chain_id: u32,
+    /// The other chain of the swap, if any (recorded in `target_chain` of the lock tx).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterparty_chain_id: Option<u32>,
+    /// SHA-256 (hex) of the secret.
+    pub hash: String,
+    /// Pubkey hex that may claim with the preimage.
+    pub recipient: String,
+    /// Pubkey hex that locked the coins and may refund them.
+    pub refund_to: String,
+    pub timeout_height: u64,
+    /// Amount the lock tx sends.
+    pub amount: u64,
+}
+
+/// A fresh 32-byte secret and its hash, both hex.
+pub fn new_secret() -> (String, String) {
+    let mut secret = [0_u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    (hex::encode(secret), sha256_hex(&secret))
+}
+
+/// SHA-256 (hex) of a hex secret.
+pub fn hash_secret(secret_hex: &str) -> anyhow::Result<String> {
+    let bytes = hex::decode(secret_hex).map_err(|e| anyhow::anyhow!("secret is not hex: {}", e))?;
+    Ok(sha256_hex(&bytes))
+}
+
+impl Htlc {
+    /// The spending condition: hash lock and recipient signature, or the timeout and the
+    /// refund key's signature.
+    pub fn condition(&self) -> Condition {
+        let signed_by = |pk: &str| Condition::Multisig {
+            threshold: 1,
+            pubkeys: vec![pk.to_string()],
+        };
+        Condition::Any {
+            conditions: vec![
+                Condition::All {
+                    conditions: vec![
+                        Condition::HashLock {
+                            sha256: self.hash.clone(),
+                        },
+                        signed_by(&self.recipient),
+                    ],
+                },
+                Condition::All {
+                    conditions: vec![
+                        Condition::After {
+                            height: self.timeout_height,
+                        },
+                        signed_by(&self.refund_to),
+                    ],
+                },
+            ],
+        }
+    }
+
+    /// Address the contract's coins are held at.
+    pub fn address(&self) -> String {
+        self.condition().address()
+    }
+
+    pub fn check(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.recipient != self.refund_to,
+            "htlc recipient and refund key must differ"
+        );
+        anyhow::ensure!(self.amount > 0, "htlc amount must be > 0");
+        self.condition().check()
+    }
+
+    /// Unsigned transfer of `amount` from `refund_to` into the contract.
+    pub fn lock_tx(&self, nonce: u64, fee: u64) -> Transaction {
+        let mut tx =
+            Transaction::new_with_fee(&self.refund_to, self.address(), self.amount, fee, nonce, 0);
+        tx.chain_id = self.chain_id;
+        tx.bridge_id = Some(self.hash.clone());
+        tx.origin_chain = Some(self.chain_id.to_string());
+        tx.target_chain = self.counterparty_chain_id.map(|id| id.to_string());
+        tx
+    }
+
+    /// Claim `balance` (less `fee`) for the recipient with the secret.
+    pub fn claim_tx(
+        &self,
+        balance: u64,
+        nonce: u64,
+        fee: u64,
+        secret_hex: &str,
+        recipient_key: &SigningKey,
+    ) -> anyhow::Result<Transaction> {
+        anyhow::ensure!(
+            hash_secret(secret_hex)? == self.hash,
+            "secret does not match the contract hash {}",
+            self.hash
+        );
+        self.spend_tx(
+            &self.recipient,
+            balance,
+            nonce,
+            fee,
+            vec![secret_hex.to_string()],
+            recipient_key,
+        )
+    }
+
+    /// Return `balance` (less `fee`) to the refund key. Valid from `timeout_height` on.
+    pub fn refund_tx(
+        &self,
+        balance: u64,
+        nonce: u64,
+        fee: u64,
+        refund_key: &SigningKey,
+    ) -> anyhow::Result<Transaction> {
+        self.spend_tx(&self.refund_to, balance, nonce, fee, vec![], refund_key)
+    }
+
+    fn spend_tx(
+        &self,
+        to: &str,
+        balance: u64,
+        nonce: u64,
+        fee: u64,
+        preimages: Vec<String>,
+        key: &SigningKey,
+    ) -> anyhow::Result<Transaction> {
+        let pubkey_hex = verifying_key_to_hex(&key.verifying_key());
+        anyhow::ensure!(
+            pubkey_hex == to,
+            "key {} is not the htlc party {}",
+            pubkey_hex,
+            to
+        );
+        let amount = balance.checked_sub(fee).filter(|&a| a > 0).ok_or_else(|| {
+            anyhow::anyhow!("htlc balance {} does not cover fee {}", balance, fee)
+        })?;
+
+        let mut tx = Transaction::new_with_fee(self.address(), to, amount, fee, nonce, 0);
+        tx.chain_id = self.chain_id;
+        tx.bridge_id = Some(self.hash.clone());
+        tx.script = Some(self.condition().to_script());
+        // A refund also carries the timeout as its locktime, so it waits in the mempool.
+        if preimages.is_empty() {
+            tx.locktime = Some(self.timeout_height);
+        }
+        let signature_b64 = sign_bytes(key, &tx.signing_bytes());
+        tx.witness = Some(Witness {
+            preimages,
+            signatures: vec![WitnessSignature {
+                pubkey_hex,
+                signature_b64,
+            }],
+        });
+        Ok(tx)
+    }
+
+    /// The secret revealed by a claim of this contract on `chain`, if any.
+    pub fn find_secret(&self, chain: &Chain) -> Option<String> {
+        let address = self.address();
+        chain
+            .blocks
+            .iter()
+            .flat_map(|b| &b.txs)
+            .filter(|tx| tx.from == address)
+            .filter_map(|tx| tx.witness.as_ref())
+            .flat_map(|w| &w.preimages)
+            .find(|p| hash_secret(p).is_ok_and(|h| h == self.hash))
+            .cloned()
+    }
+}
@@ -0,0 +1,443 @@
+use crate::core::chain::{Chain, MEDIAN_TIME_SPAN, TxInclusionProof, pow_ok};
+use crate::core::chain_id::data_dir_for;
+use crate::core::network::Message;
+use crate::core::params::ChainParams;
+use crate::core::state::Account;
+use crate::core::state_tree::AccountProof;
+use crate::core::time::now_ms;
+use crate::core::types::{BlockHeader, Transaction};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Headers requested per `GetHeaders` round trip.
+pub const HEADERS_BATCH: u32 = 500;
+
+/// How far back the light client looks for a common ancestor when the node's chain no longer
+/// extends its tip.
+pub const MAX_REORG_DEPTH: usize = 100;
+
+/// A tx of a tracked address whose inclusion was verified against a stored header.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProvenTx {
+    pub height: u64,
+    pub index: usize,
+    pub tx: Transaction,
+}
+
+/// A tracked address's account (`None`: no account), proven against the `state_root` of the
+/// header at `height`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProvenAccount {
+    pub height: u64,
+    pub account: Option<Account>,
+}
+
+/// What a `LightClient::sync` changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub new_headers: usize,
+    pub reorged: bool,
+    pub new_txs: usize,
+    pub proven_accounts: usize,
+}
+
+/// Headers-only client.
+///
+/// It PoW-validates the header chain (linkage, difficulty, median-time-past, future drift) and
+/// keeps only the txs of tracked addresses, each checked against its header's `merkle_root`.
+/// On chains whose headers commit to a state root, each tracked account is also proven directly
+/// against the tip's `state_root`. Otherwise balances are summed from the proven txs, so they
+/// are only as complete as the history the full node reports: a node can withhold txs, but not
+/// invent or alter them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightClient {
+    pub network: String,
+    pub chain_id: u32,
+    pub pow_difficulty: usize,
+    #[serde(default)]
+    pub params: ChainParams,
+    pub headers: Vec<BlockHeader>,
+    #[serde(default)]
+    pub addresses: BTreeSet<String>,
+    #[serde(default)]
+    pub txs: Vec<ProvenTx>,
+    #[serde(default)]
+    pub accounts: BTreeMap<String, ProvenAccount>,
+}
+
+impl LightClient {
+    /// Default state file for a network (`data/<network>/light.json`).
+    pub fn default_path_for(network: &str) -> PathBuf {
+        data_dir_for(network).join("light.json")
+    }
+
+    /// Start from `chain`'s genesis header and consensus settings.
+    pub fn from_genesis(chain: &Chain) -> Self {
+        Self {
+            network: chain.network.clone(),
+            chain_id: chain.chain_id,
+            pow_difficulty: chain.pow_difficulty,
+            params: chain.params.clone(),
+            headers: vec![chain.blocks[0].header.clone()],
+            addresses: BTreeSet::new(),
+            txs: Vec::new(),
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let s = fs::read_to_string(path)?;
+        let client: Self = serde_json::from_str(&s)?;
+        anyhow::ensure!(
+            !client.headers.is_empty(),
+            "light client state has no genesis"
+        );
+        Ok(client)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn height(&self) -> usize {
+        self.headers.len() - 1
+    }
+
+    pub fn tip_hash(&self) -> String {
+        self.headers[self.height()].hash()
+    }
+
+    pub fn genesis_hash(&self) -> String {
+        self.headers[0].hash()
+    }
+
+    /// Check `header` as the successor of `prev` (a header chain from genesis).
+    fn check_header(&self, prev: &[BlockHeader], header: &BlockHeader) -> anyhow::Result<()> {
+        let tip = prev.last().context("no parent header")?;
+        let height = prev.len();
+        anyhow::ensure!(
+            header.prev_hash == tip.hash(),
+            "header at height {} does not connect (prev_hash={})",
+            height,
+            header.prev_hash
+        );
+        let hash = header.hash();
+        anyhow::ensure!(
+            pow_ok(&hash, self.pow_difficulty),
+            "header at height {} fails PoW: hash={} difficulty={}",
+            height,
+            hash,
+            self.pow_difficulty
+        );
+
+        // Chains from before state roots were mined without the median-time-past rule.
+        if !prev[0].state_root.is_empty() {
+            let mut recent: Vec<u64> = prev
+                .iter()
+                .rev()
+                .take(MEDIAN_TIME_SPAN)
+                .map(|h| h.timestamp_ms)
+                .collect();
+            recent.sort_unstable();
+            let mtp = recent[recent.len() / 2];
+            anyhow::ensure!(
+                header.timestamp_ms > mtp,
+                "header at height {} timestamp {} not after median time past {}",
+                height,
+                header.timestamp_ms,
+                mtp
+            );
+        }
+        let max_allowed = now_ms().saturating_add(self.params.max_future_drift_ms);
+        anyhow::ensure!(
+            header.timestamp_ms <= max_allowed,
+            "header at height {} timestamp {} too far in the future (max {})",
+            height,
+            header.timestamp_ms,
+            max_allowed
+        );
+        Ok(())
+    }
+
+    /// Apply `headers` for heights `start_height..`. Returns the number of headers added.
+    ///
+    /// Headers already stored are skipped. If they diverge from the stored chain, the new
+    /// branch replaces it only if it ends higher (all blocks have the same work), and proofs
+    /// above the fork point are dropped. Nothing changes if any header is invalid.
+    pub fn apply_headers(
+        &mut self,
+        start_height: usize,
+        headers: Vec<BlockHeader>,
+    ) -> anyhow::Result<usize> {
+        anyhow::ensure!(start_height >= 1, "the genesis header cannot be replaced");
+        anyhow::ensure!(
+            start_height <= self.headers.len(),
+            "headers start at height {} beyond tip {}",
+            start_height,
+            self.height()
+        );
+        let known = headers
+            .iter()
+            .zip(&self.headers[start_height..])
+            .take_while(|(new, old)| new == old)
+            .count();
+        let fork = start_height + known;
+        let new = &headers[known..];
+        if new.is_empty() {
+            return Ok(0);
+        }
+        if fork < self.headers.len() {
+            anyhow::ensure!(
+                fork + new.len() > self.headers.len(),
+                "competing branch from height {} is not longer than the local chain (tip {})",
+                fork,
+                self.height()
+            );
+        }
+
+        let mut candidate = self.headers[..fork].to_vec();
+        for header in new {
+            self.check_header(&candidate, header)?;
+            candidate.push(header.clone());
+        }
+        self.headers = candidate;
+        self.txs.retain(|t| (t.height as usize) < fork);
+        self.accounts.retain(|_, a| (a.height as usize) < fork);
+        Ok(new.len())
+    }
+
+    /// Track `address`. Returns false if it was already tracked.
+    pub fn track(&mut self, address: &str) -> bool {
+        self.addresses.insert(address.to_string())
+    }
+
+    /// Verify and store a tx of a tracked address. Returns false if it was already stored.
+    pub fn add_proof(&mut self, proof: &TxInclusionProof) -> anyhow::Result<bool> {
+        let height = proof.height as usize;
+        let header = self
+            .headers
+            .get(height)
+            .with_context(|| format!("no header at height {height}; sync headers first"))?;
+        let tx_id = proof.tx.id();
+        proof.merkle.verify(&tx_id, &header.merkle_root)?;
+        anyhow::ensure!(
+            self.addresses.contains(&proof.tx.from) || self.addresses.contains(&proof.tx.to),
+            "tx {} does not touch a tracked address",
+            tx_id
+        );
+        if self.txs.iter().any(|t| t.tx.id() == tx_id) {
+            return Ok(false);
+        }
+        self.txs.push(ProvenTx {
+            height: proof.height,
+            index: proof.merkle.index,
+            tx: proof.tx.clone(),
+        });
+        self.txs.sort_by_key(|t| (t.height, t.index));
+        Ok(true)
+    }
+
+    /// Verify and store a tracked account's state proof against the header at `height`, which
+    /// must be the synced tip: an older proof, though valid, may hide later spends.
+    pub fn add_account_proof(&mut self, height: u64, proof: &AccountProof) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            height as usize == self.height(),
+            "account proof at height {} is not for the synced tip at height {}",
+            height,
+            self.height()
+        );
+        let header = self
+            .headers
+            .get(height as usize)
+            .with_context(|| format!("no header at height {height}; sync headers first"))?;
+        anyhow::ensure!(
+            !header.state_root.is_empty(),
+            "header at height {} has no state root",
+            height
+        );
+        anyhow::ensure!(
+            self.addresses.contains(&proof.address),
+            "{} is not a tracked address",
+            proof.address
+        );
+        proof.verify(&header.state_root)?;
+        self.accounts.insert(
+            proof.address.clone(),
+            ProvenAccount {
+                height,
+                account: proof.account.clone(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Confirmed balance of `address`: from its state proof if there is one, otherwise summed
+    /// from its proven txs.
+    pub fn balance(&self, address: &str) -> u64 {
+        if let Some(proven) = self.accounts.get(address) {
+            return proven.account.as_ref().map_or(0, |a| a.balance);
+        }
+        let balance = self.txs.iter().fold(0_i128, |acc, t| {
+            let mut acc = acc;
+            // Asset transfers move no native coin; the sender still pays the fee.
+            let native = if t.tx.asset.is_some() { 0 } else { t.tx.amount };
+            if t.tx.to == address {
+                acc += native as i128;
+            }
+            if t.tx.from == address && !t.tx.is_coinbase() {
+                acc -= native as i128 + t.tx.fee as i128;
+            }
+            acc
+        });
+        balance.clamp(0, u64::MAX as i128) as u64
+    }
+
+    /// Proven txs touching `address`, oldest first.
+    pub fn history(&self, address: &str) -> Vec<&ProvenTx> {
+        self.txs
+            .iter()
+            .filter(|t| t.tx.from == address || t.tx.to == address)
+            .collect()
+    }
+
+    /// Sync headers from the full node at `node`, then fetch and verify proofs for new txs of
+    /// the tracked addresses. The node must run with a tx index to serve history and proofs.
+    pub async fn sync(&mut self, node: SocketAddr) -> anyhow::Result<SyncReport> {
+        let mut stream = tokio::net::TcpStream::connect(node)
+            .await
+            .with_context(|| format!("failed to connect to {node}"))?;
+
+        // The node greets every connection with its handshake: check it serves our chain.
+        match Message::decode_async(&mut stream).await? {
+            Message::Handshake {
+                genesis_hash,
+                chain_id,
+                ..
+            } => {
+                anyhow::ensure!(
+                    genesis_hash == self.genesis_hash(),
+                    "node {} is on a different chain (genesis {})",
+                    node,
+                    genesis_hash
+                );
+                anyhow::ensure!(
+                    chain_id == self.chain_id,
+                    "node {} is on chain id {} (expected {})",
+                    node,
+                    chain_id,
+                    self.chain_id
+                );
+            }
+            other => anyhow::bail!("expected a handshake from {}, got {:?}", node, other),
+        }
+
+        let mut report = SyncReport::default();
+        loop {
+            let start = self.height() + 1;
+            let headers = fetch_headers(&mut stream, start, HEADERS_BATCH).await?;
+            let Some(first) = headers.first() else {
+                break;
+            };
+            if first.prev_hash == self.tip_hash() {
+                report.new_headers += self.apply_headers(start, headers)?;
+                continue;
+            }
+
+            // Our tip is not on the node's chain: refetch from before the fork.
+            anyhow::ensure!(
+                !report.reorged,
+                "node {} diverges more than {} blocks back",
+                node,
+                MAX_REORG_DEPTH
+            );
+            report.reorged = true;
+            let from = self.height().saturating_sub(MAX_REORG_DEPTH).max(1);
+            let limit = (start - from) as u32 + HEADERS_BATCH;
+            let headers = fetch_headers(&mut stream, from, limit).await?;
+            report.new_headers += self.apply_headers(from, headers)?;
+        }
+
+        let commits_state = !self.headers[self.height()].state_root.is_empty();
+        for address in self.addresses.clone() {
+            if commits_state {
+                let reply = Message::GetAccountProof {
+                    address: address.clone(),
+                }
+                .request_async(&mut stream, "AccountProof")
+                .await?;
+                let Message::AccountProof { height, proof } = reply else {
+                    unreachable!("request_async returns the requested type");
+                };
+                anyhow::ensure!(
+                    proof.address == address,
+                    "node returned a proof for {} instead of {}",
+                    proof.address,
+                    address
+                );
+                // The node may have moved past the synced headers; prove it next time.
+                if height as usize <= self.height() {
+                    self.add_account_proof(height, &proof)?;
+                    report.proven_accounts += 1;
+                }
+            }
+
+            let reply = Message::GetHistory {
+                address: address.clone(),
+            }
+            .request_async(&mut stream, "History")
+            .await?;
+            let Message::History { confirmed, .. } = reply else {
+                unreachable!("request_async returns the requested type");
+            };
+            for (tx_id, loc) in confirmed {
+                if loc.height > self.height() || self.txs.iter().any(|t| t.tx.id() == tx_id) {
+                    continue;
+                }
+                let reply = Message::GetTxProof {
+                    tx_id: tx_id.clone(),
+                }
+                .request_async(&mut stream, "TxProof")
+                .await?;
+                let Message::TxProof { proof, .. } = reply else {
+                    unreachable!("request_async returns the requested type");
+                };
+                let proof = proof.with_context(|| format!("node has no proof for tx {tx_id}"))?;
+                anyhow::ensure!(
+                    proof.tx.id() == tx_id,
+                    "node returned a proof for the wrong tx ({} instead of {})",
+                    proof.tx.id(),
+                    tx_id
+                );
+                if self.add_proof(&proof)? {
+                    report.new_txs += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+async fn fetch_headers(
+    stream: &mut tokio::net::TcpStream,
+    start_height: usize,
+    limit: u32,
+) -> anyhow::Result<Vec<BlockHeader>> {
+    let reply = Message::GetHeaders {
+        start_height: start_height as u64,
+        limit,
+    }
+    .request_async(stream, "Headers")
+    .await?;
+    let Message::Headers(headers) = reply else {
+        unreachable!("request_async returns the requested type");
+    };
+    Ok(headers)
+}
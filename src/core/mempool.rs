@@ -1,21 +1,86 @@
-use crate::core::types::Transaction;
+use crate::core::policy::{MempoolPolicy, fee_per_kb};
+use crate::core::sigcache::SignatureCache;
+use crate::core::state::State;
+use crate::core::types::{Block, Transaction};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Why a tx left the mempool during revalidation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemovalReason {
+    /// Included in a connected block.
+    Included,
+    /// Its nonce was already used on chain (e.g. by a competing tx), or another pending tx
+    /// from the same sender holds the same nonce.
+    NonceConflict,
+    /// An earlier nonce from the same sender is missing from chain and mempool.
+    NonceGap,
+    /// The sender can no longer cover amount + fee (including its earlier pending txs).
+    InsufficientBalance,
+    /// Past `expiry` (height) or `expiration_ms`.
+    Expired,
+}
+
+impl std::fmt::Display for RemovalReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            RemovalReason::Included => "included",
+            RemovalReason::NonceConflict => "nonce conflict",
+            RemovalReason::NonceGap => "nonce gap",
+            RemovalReason::InsufficientBalance => "insufficient balance",
+            RemovalReason::Expired => "expired",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Mempool change produced by connecting or disconnecting a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MempoolEvent {
+    /// Returned to the pool from a disconnected block.
+    Added { tx_id: String },
+    Removed {
+        tx_id: String,
+        reason: RemovalReason,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Mempool {
     pub txs: Vec<Transaction>,
 
     /// Transaction ID to index mapping for fast O(1) lookups.
     #[serde(skip, default)]
-    pub tx_index: std::collections::HashMap<String, usize>,
+    pub tx_index: HashMap<String, usize>,
+
+    /// Encoded size of each pending tx by ID, so fill checks never re-serialize the pool.
+    #[serde(skip, default)]
+    tx_sizes: HashMap<String, usize>,
+
+    /// Sum of `tx_sizes`.
+    #[serde(skip, default)]
+    total_bytes: usize,
+
+    /// Signatures verified on admission, so block validation can skip them. An entry is
+    /// dropped when its tx leaves the pool, whatever the reason.
+    #[serde(skip, default)]
+    pub sig_cache: SignatureCache,
+
+    /// Admission limits; set by the node/CLI after loading.
+    #[serde(skip, default)]
+    pub policy: MempoolPolicy,
 }
 
 impl Mempool {
     pub fn default_path() -> PathBuf {
-        PathBuf::from("data/mempool.json")
+        Self::default_path_for("devnet")
+    }
+
+    /// Default mempool file for a network (`data/<network>/mempool.json`).
+    pub fn default_path_for(network: &str) -> PathBuf {
+        crate::core::chain_id::data_dir_for(network).join("mempool.json")
     }
 
     pub fn new() -> Self {
@@ -26,10 +91,8 @@ impl Mempool {
         let s = fs::read_to_string(path)?;
         let mut m: Self = serde_json::from_str(&s)?;
 
-        // Rebuild tx index
-        for (i, tx) in m.txs.iter().enumerate() {
-            m.tx_index.insert(tx.id(), i);
-        }
+        // Rebuild tx index and size totals
+        m.rebuild_index();
 
         Ok(m)
     }
@@ -43,6 +106,31 @@ impl Mempool {
         Ok(())
     }
 
+    /// `validate_accept` that records verified signatures in `sig_cache`.
+    fn validate_accept_cached(&mut self, tx: &Transaction) -> anyhow::Result<()> {
+        tx.validate_basic()?;
+        self.check_signature(tx)
+    }
+
+    /// Verify `tx`'s signature (when it is verifiable) unless `sig_cache` already holds it,
+    /// and record it there, so admitting the tx right after doesn't verify it again. If the
+    /// tx is then turned away, the admission drops the entry.
+    pub fn check_signature(&mut self, tx: &Transaction) -> anyhow::Result<()> {
+        if tx.is_verifiable && !self.sig_cache.contains(tx) {
+            tx.verify_signature_if_present()?;
+            self.sig_cache.insert(tx);
+        }
+        Ok(())
+    }
+
+    /// Drop the cached signature of a tx that failed admission, unless a tx with its id is
+    /// pooled already.
+    fn forget_rejected(&mut self, tx_id: &str) {
+        if !self.tx_index.contains_key(tx_id) {
+            self.sig_cache.remove(tx_id);
+        }
+    }
+
     fn ensure_unique_hash(&self, tx: &Transaction) -> anyhow::Result<()> {
         let h = tx.id();
         anyhow::ensure!(!self.tx_index.contains_key(&h), "duplicate tx (hash={h})");
@@ -57,12 +145,33 @@ impl Mempool {
         base_nonce.saturating_add(pending)
     }
 
+    /// `tx`'s pending ancestors: its sender's txs with lower nonces, in nonce order.
+    pub fn ancestors_of(&self, tx: &Transaction) -> Vec<Transaction> {
+        let mut ancestors: Vec<Transaction> = self
+            .txs
+            .iter()
+            .filter(|t| t.from == tx.from && t.nonce < tx.nonce)
+            .cloned()
+            .collect();
+        ancestors.sort_by_key(|t| t.nonce);
+        ancestors
+    }
+
     /// Add a tx enforcing a simple per-sender nonce rule.
     ///
     /// This is intentionally minimal (Week 2 demo): it prevents gaps and duplicates for a sender
     /// within the mempool, using the caller-provided `base_nonce` (from chain).
     pub fn add_tx_checked(&mut self, tx: Transaction, base_nonce: u64) -> anyhow::Result<()> {
-        tx.validate_accept()?;
+        let id = tx.id();
+        let added = self.admit_checked(tx, base_nonce);
+        if added.is_err() {
+            self.forget_rejected(&id);
+        }
+        added
+    }
+
+    fn admit_checked(&mut self, tx: Transaction, base_nonce: u64) -> anyhow::Result<()> {
+        self.validate_accept_cached(&tx)?;
 
         // If nonce_id is present, ensure it is unique within the mempool.
         if let Some(nonce_id) = &tx.nonce_id
@@ -71,82 +180,180 @@ impl Mempool {
             anyhow::bail!("duplicate nonce_id detected in mempool: {}", nonce_id);
         }
 
-        // Mempool size limit check: refuse new transactions if mempool is at absolute capacity
-        // and the new transaction has a very low fee.
-        // (Hardcoded 10MB limit for demo purposes)
-        const MAX_MEMPOOL_BYTES: usize = 10 * 1024 * 1024;
-        let current_size: usize = self.txs.iter().map(|t| t.size()).sum();
-        if current_size > MAX_MEMPOOL_BYTES {
-            let min_fee = self.txs.iter().map(|t| t.fee).min().unwrap_or(0);
-            anyhow::ensure!(
-                tx.fee > min_fee,
-                "mempool is full; transaction fee (={}) too low to displace others (min_fee={})",
-                tx.fee,
-                min_fee
-            );
-        }
+        // Relay fee policy: the minimum rises as the pool fills.
+        let rate = fee_per_kb(tx.fee, tx.size());
+        let min_rate = self.min_fee_per_kb();
+        anyhow::ensure!(
+            rate >= min_rate,
+            "fee rate {} per kB below minimum relay fee {} per kB",
+            rate,
+            min_rate
+        );
+
+        let id = tx.id();
 
-        // If a transaction with the same sender and nonce exists, we check if the new tx
-        // is an RBF (Replace-By-Fee) candidate.
-        // Rule: same sender, same nonce, higher fee OR higher sequence.
+        // Same sender and nonce as a pending tx: an RBF candidate (rules in `MempoolPolicy`).
         if let Some(pos) = self
             .txs
             .iter()
             .position(|t| t.from == tx.from && t.nonce == tx.nonce)
         {
-            let existing = &self.txs[pos];
-
-            // RBF Rule:
-            // 1. Fee must be strictly higher (to prevent spam).
-            // 2. Sequence must be higher than the existing transaction's sequence.
+            let descendants: Vec<&Transaction> = self
+                .txs
+                .iter()
+                .filter(|t| t.from == tx.from && t.nonce > tx.nonce)
+                .collect();
+            self.policy
+                .check_replacement(&tx, &self.txs[pos], &descendants)?;
+
+            // Replace in place and evict the descendants.
+            let mut evicted: HashSet<String> = descendants.iter().map(|t| t.id()).collect();
+            evicted.insert(self.txs[pos].id());
+            self.txs[pos] = tx;
+            self.txs.retain(|t| !evicted.contains(&t.id()));
+            for old in &evicted {
+                self.sig_cache.remove(old);
+            }
+            self.rebuild_index();
+        } else {
+            let pending = self.txs.iter().filter(|t| t.from == tx.from).count();
             anyhow::ensure!(
-                tx.fee > existing.fee,
-                "replacement tx must have a strictly higher fee (existing={} new={})",
-                existing.fee,
-                tx.fee
+                pending < self.policy.max_txs_per_sender,
+                "too many pending txs from sender={} (max={})",
+                tx.from,
+                self.policy.max_txs_per_sender
             );
+
+            let expected = self.next_nonce_for(&tx.from, base_nonce);
             anyhow::ensure!(
-                tx.sequence > existing.sequence,
-                "replacement tx must have a higher sequence number (existing={} new={})",
-                existing.sequence,
-                tx.sequence
+                tx.nonce == expected,
+                "invalid nonce for sender={} (expected={} got={})",
+                tx.from,
+                expected,
+                tx.nonce
             );
 
-            // Replace the existing transaction
-            self.tx_index.remove(&existing.id());
-            let id = tx.id();
-            self.txs[pos] = tx;
-            self.tx_index.insert(id, pos);
-            return Ok(());
+            self.ensure_unique_hash(&tx)?;
+            self.push(id.clone(), tx);
         }
 
-        let expected = self.next_nonce_for(&tx.from, base_nonce);
+        // Over capacity: evict the lowest fee-rate packages, possibly the new tx itself.
+        self.limit_size(self.policy.max_bytes);
         anyhow::ensure!(
-            tx.nonce == expected,
-            "invalid nonce for sender={} (expected={} got={})",
-            tx.from,
-            expected,
-            tx.nonce
+            self.tx_index.contains_key(&id),
+            "mempool full: tx fee rate too low to displace others"
         );
+        Ok(())
+    }
 
-        self.ensure_unique_hash(&tx)?;
+    /// Total encoded size of all pending txs.
+    pub fn total_size(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Current minimum relay fee rate (per kB) under `policy` for the pool's fill level.
+    pub fn min_fee_per_kb(&self) -> u64 {
+        let lowest = self
+            .tx_index
+            .iter()
+            .map(|(id, &pos)| fee_per_kb(self.txs[pos].fee, self.tx_sizes[id]))
+            .min()
+            .unwrap_or(0);
+        self.policy
+            .dynamic_min_fee_per_kb(self.total_size(), lowest)
+    }
 
+    /// Fee rate (per kB) a new tx must beat to be mined ahead of the first `depth_bytes` of
+    /// the pool in block-template order, or `None` if the whole pool fits in `depth_bytes`.
+    pub fn fee_per_kb_at_depth(&self, depth_bytes: usize) -> Option<u64> {
+        let lowest = self.lowest_nonces();
+        let ordered = package_order(
+            &self.txs,
+            |sender| lowest.get(sender).copied().unwrap_or(0),
+            usize::MAX,
+            usize::MAX,
+        );
+        let mut used = 0_usize;
+        for tx in ordered {
+            let size = tx.size();
+            used = used.saturating_add(size + 1);
+            if used > depth_bytes {
+                return Some(fee_per_kb(tx.fee, size).saturating_add(1));
+            }
+        }
+        None
+    }
+
+    /// Total native cost (amount + fee, or just the fee of asset transfers) of all pending txs
+    /// from `sender`.
+    pub fn pending_spend(&self, sender: &str) -> u64 {
+        self.txs
+            .iter()
+            .filter(|t| t.from == sender)
+            .fold(0_u64, |acc, t| {
+                acc.saturating_add(t.native_cost().unwrap_or(u64::MAX))
+            })
+    }
+
+    /// `add_tx_checked` that also requires the sender's confirmed `balance` to cover its whole
+    /// pending chain including `tx`. An RBF replacement is counted instead of the txs it evicts.
+    pub fn add_tx_with_balance(
+        &mut self,
+        tx: Transaction,
+        base_nonce: u64,
+        balance: u64,
+    ) -> anyhow::Result<()> {
         let id = tx.id();
-        let pos = self.txs.len();
-        self.txs.push(tx);
-        self.tx_index.insert(id, pos);
-        Ok(())
+        let added = self.admit_with_balance(tx, base_nonce, balance);
+        if added.is_err() {
+            self.forget_rejected(&id);
+        }
+        added
+    }
+
+    fn admit_with_balance(
+        &mut self,
+        tx: Transaction,
+        base_nonce: u64,
+        balance: u64,
+    ) -> anyhow::Result<()> {
+        let is_replacement = self
+            .txs
+            .iter()
+            .any(|t| t.from == tx.from && t.nonce == tx.nonce);
+        let replaced = if is_replacement {
+            self.txs
+                .iter()
+                .filter(|t| t.from == tx.from && t.nonce >= tx.nonce)
+                .fold(0_u64, |acc, t| {
+                    acc.saturating_add(t.native_cost().unwrap_or(u64::MAX))
+                })
+        } else {
+            0
+        };
+        let cost = tx
+            .native_cost()
+            .ok_or_else(|| anyhow::anyhow!("amount + fee overflow for {}", tx.from))?;
+        let pending = self.pending_spend(&tx.from) - replaced;
+        let needed = pending.saturating_add(cost);
+        anyhow::ensure!(
+            needed <= balance,
+            "insufficient balance for pending txs: sender={} balance={} pending={} needs={}",
+            tx.from,
+            balance,
+            pending,
+            needed
+        );
+        self.admit_checked(tx, base_nonce)
     }
 
     pub fn add_tx(&mut self, tx: Transaction) -> anyhow::Result<()> {
-        tx.validate_accept()?;
+        self.validate_accept_cached(&tx)?;
 
         self.ensure_unique_hash(&tx)?;
 
         let id = tx.id();
-        let pos = self.txs.len();
-        self.txs.push(tx);
-        self.tx_index.insert(id, pos);
+        self.push(id, tx);
         Ok(())
     }
 
@@ -154,12 +361,80 @@ impl Mempool {
         let mut out = Vec::new();
         std::mem::swap(&mut self.txs, &mut out);
         self.tx_index.clear();
+        self.tx_sizes.clear();
+        self.total_bytes = 0;
         out
     }
 
-    /// Optimized drain that clears mempool and returns transactions sorted by fee, priority, and timestamp.
+    /// Choose txs for a block template without removing them from the pool.
+    ///
+    /// Txs are taken as ancestor packages in fee-per-byte order (see `package_order`), starting
+    /// at `next_nonce(sender)` for each sender, so the template is always nonce-consistent.
+    /// Each tx costs its encoded size plus one byte (the list separator) against `max_bytes`.
+    /// Txs that don't fit, or whose nonce chain has a gap, are left out.
+    pub fn select_for_block(
+        &self,
+        max_bytes: usize,
+        max_txs: usize,
+        next_nonce: impl Fn(&str) -> u64,
+    ) -> Vec<Transaction> {
+        package_order(&self.txs, next_nonce, max_bytes, max_txs)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Reorder the pool best-first by ancestor package fee rate.
+    ///
+    /// Each sender's chain starts at its lowest pending nonce, so a tx never precedes its
+    /// ancestors. Txs after a nonce gap (or duplicate nonces) go last, in nonce order.
+    pub fn sort_by_fee_rate(&mut self) {
+        let lowest = self.lowest_nonces();
+        let ordered: Vec<String> = package_order(
+            &self.txs,
+            |sender| lowest.get(sender).copied().unwrap_or(0),
+            usize::MAX,
+            usize::MAX,
+        )
+        .into_iter()
+        .map(|t| t.id())
+        .collect();
+
+        let rank: HashMap<&str, usize> = ordered
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let mut keyed: Vec<(usize, u64, Transaction)> = std::mem::take(&mut self.txs)
+            .into_iter()
+            .map(|t| {
+                (
+                    rank.get(t.id().as_str()).copied().unwrap_or(usize::MAX),
+                    t.nonce,
+                    t,
+                )
+            })
+            .collect();
+        keyed.sort_by_key(|(rank, nonce, _)| (*rank, *nonce));
+        self.txs = keyed.into_iter().map(|(_, _, t)| t).collect();
+        self.rebuild_index();
+    }
+
+    /// Lowest pending nonce per sender.
+    fn lowest_nonces(&self) -> HashMap<&str, u64> {
+        let mut lowest: HashMap<&str, u64> = HashMap::new();
+        for tx in &self.txs {
+            lowest
+                .entry(tx.from.as_str())
+                .and_modify(|n| *n = (*n).min(tx.nonce))
+                .or_insert(tx.nonce);
+        }
+        lowest
+    }
+
+    /// Drain the mempool in `sort_by_fee_rate` order.
     pub fn drain_sorted(&mut self) -> Vec<Transaction> {
-        self.sort_by_fee_priority_and_timestamp();
+        self.sort_by_fee_rate();
         self.drain()
     }
 
@@ -183,14 +458,16 @@ impl Mempool {
         self.txs.is_empty()
     }
 
-    /// Truncates the mempool to a maximum count, removing lowest fee/priority transactions.
+    /// Truncates the mempool to a maximum count, removing the lowest fee-rate transactions.
     pub fn truncate(&mut self, max_count: usize) -> usize {
         if self.txs.len() <= max_count {
             return 0;
         }
-        self.sort_by_fee_priority_and_timestamp();
+        self.sort_by_fee_rate();
         let evicted = self.txs.len() - max_count;
-        self.txs.truncate(max_count);
+        for tx in self.txs.drain(max_count..) {
+            self.sig_cache.remove(&tx.id());
+        }
         self.rebuild_index();
         evicted
     }
@@ -246,44 +523,29 @@ impl Mempool {
     pub fn remove_tx(&mut self, tx_id: &str) {
         if let Some(pos) = self.tx_index.remove(tx_id) {
             self.txs.remove(pos);
+            self.sig_cache.remove(tx_id);
             // Rebuild index after removal because positions shifted
             self.rebuild_index();
         }
     }
 
-    /// Sorts transactions in the mempool by fee (descending), then by priority (descending).
-    pub fn sort_by_fee_priority_and_timestamp(&mut self) {
-        self.txs.sort_by(|a, b| {
-            // 1. Fee descending
-            let fee_cmp = b.fee.cmp(&a.fee);
-            if fee_cmp != std::cmp::Ordering::Equal {
-                return fee_cmp;
-            }
-            // 2. Priority descending
-            let prio_cmp = b.priority.cmp(&a.priority);
-            if prio_cmp != std::cmp::Ordering::Equal {
-                return prio_cmp;
-            }
-            // 3. Timestamp ascending (first in first out for equal fee/prio)
-            a.timestamp_ms.cmp(&b.timestamp_ms)
-        });
-        self.rebuild_index();
-    }
-
-    /// Limits the mempool to a maximum size (in bytes), removing lowest fee/priority transactions.
+    /// Limits the mempool to a maximum size (in bytes), evicting from the end of the fee-rate
+    /// order. Descendants sort after their ancestors, so they go first.
     pub fn limit_size(&mut self, max_bytes: usize) -> usize {
-        let current_size: usize = self.txs.iter().map(|t| t.size()).sum();
+        let current_size = self.total_bytes;
         if current_size <= max_bytes {
             return 0;
         }
 
-        self.sort_by_fee_priority_and_timestamp();
+        self.sort_by_fee_rate();
         let mut new_size = current_size;
         let mut evicted = 0;
 
         while new_size > max_bytes && !self.txs.is_empty() {
             if let Some(tx) = self.txs.pop() {
-                new_size -= tx.size();
+                let id = tx.id();
+                new_size -= self.tx_sizes[&id];
+                self.sig_cache.remove(&id);
                 evicted += 1;
             }
         }
@@ -298,35 +560,164 @@ impl Mempool {
     pub fn remove_included(&mut self, txs: &[Transaction]) {
         let ids: HashSet<String> = txs.iter().map(|t| t.id()).collect();
         self.txs.retain(|t| !ids.contains(&t.id()));
+        for id in &ids {
+            self.sig_cache.remove(id);
+        }
+        self.rebuild_index();
+    }
+
+    /// Update the pool after `block` was connected; `state` is the state after the block.
+    pub fn on_block_connected(
+        &mut self,
+        block: &Block,
+        state: &State,
+        next_height: u64,
+        now_ms: u64,
+    ) -> Vec<MempoolEvent> {
+        let mut events: Vec<MempoolEvent> = block
+            .txs
+            .iter()
+            .filter(|t| !t.is_coinbase())
+            .map(|t| t.id())
+            .filter(|id| self.tx_index.contains_key(id))
+            .map(|tx_id| MempoolEvent::Removed {
+                tx_id,
+                reason: RemovalReason::Included,
+            })
+            .collect();
+        self.remove_included(&block.txs);
+        events.extend(self.revalidate(state, next_height, now_ms));
+        events
+    }
+
+    /// Update the pool after `block` was disconnected; `state` is the state without it.
+    ///
+    /// The block's non-coinbase txs return to the pool, then everything is revalidated.
+    pub fn on_block_disconnected(
+        &mut self,
+        block: &Block,
+        state: &State,
+        next_height: u64,
+        now_ms: u64,
+    ) -> Vec<MempoolEvent> {
+        let mut events = Vec::new();
+        for tx in block.txs.iter().filter(|t| !t.is_coinbase()) {
+            let tx_id = tx.id();
+            if self.tx_index.contains_key(&tx_id) {
+                continue;
+            }
+            if self.add_tx(tx.clone()).is_ok() {
+                events.push(MempoolEvent::Added { tx_id });
+            }
+        }
+        events.extend(self.revalidate(state, next_height, now_ms));
+        events
+    }
+
+    /// Drop txs that can no longer be mined on top of `state`.
+    ///
+    /// Each sender's txs are replayed in nonce order against its confirmed nonce, native balance
+    /// and asset balances, as a block template would include them; `next_height` is the height
+    /// of the next block. Txs with a future `locktime` are kept.
+    pub fn revalidate(
+        &mut self,
+        state: &State,
+        next_height: u64,
+        now_ms: u64,
+    ) -> Vec<MempoolEvent> {
+        let mut by_sender: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, tx) in self.txs.iter().enumerate() {
+            by_sender.entry(tx.from.as_str()).or_default().push(i);
+        }
+
+        let mut removed: Vec<(usize, RemovalReason)> = Vec::new();
+        for (sender, mut idxs) in by_sender {
+            idxs.sort_by(|&a, &b| {
+                let (a, b) = (&self.txs[a], &self.txs[b]);
+                a.nonce.cmp(&b.nonce).then(b.fee.cmp(&a.fee))
+            });
+            let mut nonce = state.get_nonce(sender);
+            let mut balance = state.get_spendable(sender, next_height);
+            let mut asset_balances: HashMap<&str, u64> = HashMap::new();
+            for i in idxs {
+                let tx = &self.txs[i];
+                let expired = (tx.expiration_ms > 0 && now_ms >= tx.expiration_ms)
+                    || tx.expiry.is_some_and(|exp| next_height > exp);
+                let cost = tx.native_cost();
+                let asset_balance = tx.asset.as_deref().map(|asset_id| {
+                    *asset_balances
+                        .entry(asset_id)
+                        .or_insert_with(|| state.get_asset_balance(sender, asset_id))
+                });
+                let reason = if expired {
+                    Some(RemovalReason::Expired)
+                } else if tx.nonce < nonce {
+                    Some(RemovalReason::NonceConflict)
+                } else if tx.nonce > nonce {
+                    Some(RemovalReason::NonceGap)
+                } else if cost.is_none_or(|c| c > balance)
+                    || asset_balance.is_some_and(|b| tx.amount > b)
+                {
+                    Some(RemovalReason::InsufficientBalance)
+                } else {
+                    None
+                };
+                match reason {
+                    Some(reason) => removed.push((i, reason)),
+                    None => {
+                        nonce += 1;
+                        balance -= cost.unwrap_or(0);
+                        if let Some(asset_id) = tx.asset.as_deref() {
+                            *asset_balances.entry(asset_id).or_default() -= tx.amount;
+                        }
+                        // A pending create hands its whole supply to the sender.
+                        if let Some(def) = &tx.create_asset {
+                            asset_balances.insert(def.asset_id.as_str(), def.supply);
+                        }
+                    }
+                }
+            }
+        }
+
+        if removed.is_empty() {
+            return Vec::new();
+        }
+        removed.sort_by_key(|(i, _)| *i);
+        let events: Vec<MempoolEvent> = removed
+            .iter()
+            .map(|(i, reason)| MempoolEvent::Removed {
+                tx_id: self.txs[*i].id(),
+                reason: reason.clone(),
+            })
+            .collect();
+        for (i, _) in removed.iter().rev() {
+            let tx = self.txs.remove(*i);
+            self.sig_cache.remove(&tx.id());
+        }
         self.rebuild_index();
+        events
     }
 
     /// Clear all transactions from the mempool.
     pub fn clear(&mut self) {
         self.txs.clear();
         self.tx_index.clear();
+        self.tx_sizes.clear();
+        self.total_bytes = 0;
+        self.sig_cache.clear();
     }
 
     /// Evicts transactions from the mempool that have exceeded the time-to-live (TTL) or expiration_ms.
     /// Returns the number of evicted transactions.
     pub fn evict_expired(&mut self, now_ms: u64) -> usize {
         let count_before = self.txs.len();
+        let sig_cache = &mut self.sig_cache;
         self.txs.retain(|t| {
-            // Check explicit expiration_ms
-            if t.expiration_ms > 0 && now_ms >= t.expiration_ms {
-                return false;
-            }
-
-            if t.timestamp_ms == 0 {
-                return true;
+            let keep = Self::is_live(t, now_ms);
+            if !keep {
+                sig_cache.remove(&t.id());
             }
-            // Use transaction-specific TTL if set, otherwise default to a high value (e.g., 2 weeks)
-            let ttl = if t.ttl_ms > 0 {
-                t.ttl_ms
-            } else {
-                14 * 24 * 60 * 60 * 1000 // 14 days default
-            };
-            now_ms < t.timestamp_ms.saturating_add(ttl)
+            keep
         });
         let evicted = count_before - self.txs.len();
         if evicted > 0 {
@@ -335,11 +726,188 @@ impl Mempool {
         evicted
     }
 
+    /// Whether `t` is still within its `expiration_ms` and TTL at `now_ms`.
+    fn is_live(t: &Transaction, now_ms: u64) -> bool {
+        // Check explicit expiration_ms
+        if t.expiration_ms > 0 && now_ms >= t.expiration_ms {
+            return false;
+        }
+
+        if t.timestamp_ms == 0 {
+            return true;
+        }
+        // Use transaction-specific TTL if set, otherwise default to a high value (e.g., 2 weeks)
+        let ttl = if t.ttl_ms > 0 {
+            t.ttl_ms
+        } else {
+            14 * 24 * 60 * 60 * 1000 // 14 days default
+        };
+        now_ms < t.timestamp_ms.saturating_add(ttl)
+    }
+
+    /// Append a tx that passed admission, indexing it and counting its bytes.
+    fn push(&mut self, id: String, tx: Transaction) {
+        let size = tx.size();
+        self.tx_index.insert(id.clone(), self.txs.len());
+        self.tx_sizes.insert(id, size);
+        self.total_bytes += size;
+        self.txs.push(tx);
+    }
+
+    /// Re-derive `tx_index` after positions changed, and bring the size cache in line with the
+    /// remaining txs. Only txs not seen before (e.g. RBF replacements) are measured.
     fn rebuild_index(&mut self) {
         self.tx_index.clear();
         for (i, tx) in self.txs.iter().enumerate() {
-            self.tx_index.insert(tx.id(), i);
+            let id = tx.id();
+            if !self.tx_sizes.contains_key(&id) {
+                self.tx_sizes.insert(id.clone(), tx.size());
+            }
+            self.tx_index.insert(id, i);
+        }
+        self.tx_sizes.retain(|id, _| self.tx_index.contains_key(id));
+        self.total_bytes = self.tx_sizes.values().sum();
+    }
+}
+
+/// Order txs into ancestor packages, best fee-per-byte first, within a byte and tx budget.
+///
+/// Each sender's txs form a chain starting at `next_nonce(sender)`. A package is a prefix of the
+/// not-yet-taken part of a chain, so its rate counts the tx plus all of its pending ancestors:
+/// a high-fee child lifts a low-fee parent. Repeatedly the best-rated package that still fits is
+/// taken whole. Txs beyond a nonce gap are never returned.
+fn package_order(
+    txs: &[Transaction],
+    next_nonce: impl Fn(&str) -> u64,
+    max_bytes: usize,
+    max_txs: usize,
+) -> Vec<&Transaction> {
+    // Per-sender contiguous nonce chains; for duplicate nonces the highest fee wins.
+    let mut by_sender: HashMap<&str, Vec<&Transaction>> = HashMap::new();
+    for tx in txs {
+        by_sender.entry(tx.from.as_str()).or_default().push(tx);
+    }
+    let mut chains: Vec<Vec<(&Transaction, usize)>> = Vec::new();
+    for (sender, mut pending) in by_sender {
+        pending.sort_by(|a, b| a.nonce.cmp(&b.nonce).then(b.fee.cmp(&a.fee)));
+        let mut expected = next_nonce(sender);
+        let mut chain = Vec::new();
+        for tx in pending {
+            if tx.nonce == expected {
+                chain.push((tx, tx.size().max(1) + 1));
+                expected = expected.saturating_add(1);
+            }
         }
+        if !chain.is_empty() {
+            chains.push(chain);
+        }
+    }
+
+    let mut cursors = vec![0_usize; chains.len()];
+    let mut heap = BinaryHeap::new();
+    for (i, chain) in chains.iter().enumerate() {
+        if let Some(p) = Package::best(i, chain, 0, max_bytes, max_txs) {
+            heap.push(p);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut bytes_left = max_bytes;
+    while let Some(top) = heap.pop() {
+        let txs_left = max_txs - out.len();
+        if txs_left == 0 {
+            break;
+        }
+        let chain = &chains[top.chain];
+        // Budgets only shrink, so a stale entry can only get worse: re-rate and retry.
+        let Some(best) = Package::best(top.chain, chain, cursors[top.chain], bytes_left, txs_left)
+        else {
+            continue;
+        };
+        if best != top {
+            heap.push(best);
+            continue;
+        }
+
+        let start = cursors[top.chain];
+        for (tx, _) in &chain[start..start + top.len] {
+            out.push(*tx);
+        }
+        bytes_left -= top.cost;
+        cursors[top.chain] += top.len;
+        if let Some(next) = Package::best(
+            top.chain,
+            chain,
+            cursors[top.chain],
+            bytes_left,
+            max_txs - out.len(),
+        ) {
+            heap.push(next);
+        }
+    }
+    out
+}
+
+/// Best-rated prefix of a sender chain (from its cursor) that fits the remaining budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Package {
+    chain: usize,
+    len: usize,
+    fee: u128,
+    cost: usize,
+    first_timestamp_ms: u64,
+}
+
+impl Package {
+    fn best(
+        chain_idx: usize,
+        chain: &[(&Transaction, usize)],
+        start: usize,
+        bytes_left: usize,
+        txs_left: usize,
+    ) -> Option<Self> {
+        let first_timestamp_ms = chain.get(start)?.0.timestamp_ms;
+        let mut best: Option<Self> = None;
+        let (mut fee, mut cost) = (0_u128, 0_usize);
+        for (len, (tx, tx_cost)) in chain[start..].iter().enumerate().take(txs_left) {
+            fee += tx.fee as u128;
+            cost = cost.saturating_add(*tx_cost);
+            if cost > bytes_left {
+                break;
+            }
+            let candidate = Self {
+                chain: chain_idx,
+                len: len + 1,
+                fee,
+                cost,
+                first_timestamp_ms,
+            };
+            // Strictly better only: on ties the shorter package goes first.
+            if best.is_none_or(|b| candidate.rate_cmp(&b).is_gt()) {
+                best = Some(candidate);
+            }
+        }
+        best
+    }
+
+    /// Compare fee / cost without division.
+    fn rate_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.fee * other.cost as u128).cmp(&(other.fee * self.cost as u128))
+    }
+}
+
+impl Ord for Package {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rate_cmp(other)
+            .then_with(|| other.first_timestamp_ms.cmp(&self.first_timestamp_ms))
+            .then_with(|| other.chain.cmp(&self.chain))
+            .then_with(|| other.len.cmp(&self.len))
+    }
+}
+
+impl PartialOrd for Package {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -369,43 +937,37 @@ mod mempool_index_tests {
     }
 
     #[test]
-    fn test_mempool_sort_by_fee_priority_and_timestamp() {
+    fn test_mempool_sort_by_fee_rate() {
         let mut mempool = Mempool::new();
-        let mut tx_low = Transaction::new("A", "B", 10, 0);
-        tx_low.fee = 1;
-        tx_low.priority = 100;
-
-        let mut tx_mid_prio1 = Transaction::new("A", "C", 20, 1);
-        tx_mid_prio1.fee = 5;
-        tx_mid_prio1.priority = 10;
-        tx_mid_prio1.timestamp_ms = 2000;
-
-        let mut tx_mid_prio2 = Transaction::new("A", "D", 30, 2);
-        tx_mid_prio2.fee = 5;
-        tx_mid_prio2.priority = 10;
-        tx_mid_prio2.timestamp_ms = 1000; // Older should come first if fee/prio same
-
-        let mut tx_high = Transaction::new("A", "E", 40, 3);
-        tx_high.fee = 10;
-        tx_high.priority = 0;
-
-        mempool.add_tx(tx_low).unwrap();
-        mempool.add_tx(tx_mid_prio1).unwrap();
-        mempool.add_tx(tx_mid_prio2).unwrap();
-        mempool.add_tx(tx_high).unwrap();
-
-        mempool.sort_by_fee_priority_and_timestamp();
-
-        // 1. Fee 10
-        assert_eq!(mempool.txs[0].fee, 10);
-        // 2. Fee 5, Priority 10, Older timestamp (1000)
-        assert_eq!(mempool.txs[1].fee, 5);
-        assert_eq!(mempool.txs[1].timestamp_ms, 1000);
-        // 3. Fee 5, Priority 10, Newer timestamp (2000)
-        assert_eq!(mempool.txs[2].fee, 5);
-        assert_eq!(mempool.txs[2].timestamp_ms, 2000);
-        // 4. Fee 1
-        assert_eq!(mempool.txs[3].fee, 1);
+        // Same size txs, so fee order is fee-rate order.
+        let mut a0 = Transaction::new("A", "B", 10, 0);
+        a0.fee = 1;
+        a0.priority = 255; // Ignored
+        let mut a1 = Transaction::new("A", "B", 10, 1);
+        a1.fee = 9; // Lifts its parent: package rate (1 + 9) / 2
+        let mut b0 = Transaction::new("C", "B", 10, 0);
+        b0.fee = 4;
+        b0.timestamp_ms = 2000;
+        let mut d0 = Transaction::new("D", "B", 10, 0);
+        d0.fee = 4;
+        d0.timestamp_ms = 1000; // Older first on equal rate
+
+        mempool.add_tx(b0).unwrap();
+        mempool.add_tx(a1).unwrap();
+        mempool.add_tx(d0).unwrap();
+        mempool.add_tx(a0).unwrap();
+
+        mempool.sort_by_fee_rate();
+
+        let order: Vec<(&str, u64)> = mempool
+            .txs
+            .iter()
+            .map(|t| (t.from.as_str(), t.nonce))
+            .collect();
+        assert_eq!(order, vec![("A", 0), ("A", 1), ("D", 0), ("C", 0)]);
+        for (i, tx) in mempool.txs.iter().enumerate() {
+            assert_eq!(mempool.tx_index.get(&tx.id()), Some(&i));
+        }
     }
 
     #[test]
@@ -471,6 +1033,31 @@ mod mempool_index_tests {
         assert_eq!(mempool.tx_index.get(&id2), Some(&0));
     }
 
+    #[test]
+    fn test_mempool_total_size_tracks_changes() {
+        let mut mempool = Mempool::new();
+        let tx1 = Transaction::new("A", "B", 10, 0);
+        let tx2 = Transaction::new("A", "C", 20, 1);
+        let tx3 = Transaction::new("D", "E", 30, 0);
+        let id1 = tx1.id();
+        let (s1, s2, s3) = (tx1.size(), tx2.size(), tx3.size());
+
+        mempool.add_tx(tx1).unwrap();
+        mempool.add_tx(tx2).unwrap();
+        mempool.add_tx(tx3).unwrap();
+        assert_eq!(mempool.total_size(), s1 + s2 + s3);
+
+        mempool.remove_tx(&id1);
+        assert_eq!(mempool.total_size(), s2 + s3);
+
+        mempool.limit_size(s2.max(s3));
+        let left: usize = mempool.txs.iter().map(|t| t.size()).sum();
+        assert_eq!(mempool.total_size(), left);
+
+        mempool.clear();
+        assert_eq!(mempool.total_size(), 0);
+    }
+
     #[test]
     fn test_mempool_nonce_id_uniqueness() {
         let mut mempool = Mempool::new();
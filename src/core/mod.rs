@@ -1,10 +1,25 @@
+pub mod asset;
 pub mod chain;
+pub mod chain_id;
 pub mod crypto;
+pub mod fee_estimator;
+pub mod genesis;
+pub mod governance;
 pub mod hash;
+pub mod htlc;
 pub mod keys;
+pub mod light;
 pub mod mempool;
 pub mod network;
 pub mod p2p;
+pub mod params;
+pub mod policy;
+pub mod script;
+pub mod sigcache;
+pub mod snapshot;
 pub mod state;
+pub mod state_tree;
 pub mod time;
+pub mod tx_index;
 pub mod types;
+pub mod vm;
@@ -1,8 +1,18 @@
+use crate::core::chain::TxInclusionProof;
+use crate::core::mempool::Mempool;
+use crate::core::state::State;
+use crate::core::state_tree::AccountProof;
+use crate::core::tx_index::TxLocation;
 use crate::core::types::{Block, BlockHeader, Transaction};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::{Read, Write};
 use std::net::{SocketAddr, TcpStream};
 
+fn default_target_blocks() -> usize {
+    crate::core::fee_estimator::DEFAULT_CONFIRM_TARGET
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Message {
     Ping,
@@ -27,6 +37,19 @@ pub enum Message {
         version: u32,
         best_height: u64,
         agent: String,
+        /// Hash of the sender's genesis block; peers on a different chain are refused.
+        #[serde(default)]
+        genesis_hash: String,
+        /// Sender's chain id (network magic); peers on a different network are refused.
+        #[serde(default)]
+        chain_id: u32,
+        /// Sender's clock (Unix epoch ms) when the handshake was sent; feeds adjusted time.
+        #[serde(default)]
+        timestamp_ms: u64,
+        /// Height up to which the sender has pruned block bodies (0 for a full node); peers
+        /// should not `GetData` blocks at or below it.
+        #[serde(default)]
+        pruned_height: u64,
     },
     GetHeaders {
         start_height: u64,
@@ -77,11 +100,20 @@ pub enum Message {
     /// Request the fee estimation for a transaction
     GetFeeEstimate {
         tx_size: usize,
+        /// Blocks within which the tx should confirm.
+        #[serde(default = "default_target_blocks")]
+        target_blocks: usize,
     },
     /// Fee estimation response
     FeeEstimate {
         fee_per_byte: u64,
         estimated_total: u64,
+        /// Confirmation target the estimate is for (clamped by the node).
+        #[serde(default = "default_target_blocks")]
+        target_blocks: usize,
+        /// Estimated fee rate per kB; `fee_per_byte` is this rounded up.
+        #[serde(default)]
+        fee_per_kb: u64,
     },
     /// Request checkpoints from a peer
     GetCheckpoints,
@@ -101,6 +133,55 @@ pub enum Message {
         total_size: usize,
         min_fee: u64,
         max_fee: u64,
+        /// Current minimum relay fee rate (per kB) under the node's mempool policy.
+        #[serde(default)]
+        min_relay_fee_per_kb: u64,
+    },
+    /// Look up a tx by id (confirmed, via the node's tx index, or pending in its mempool)
+    GetTx {
+        tx_id: String,
+    },
+    /// `GetTx` response. `tx` is `None` if the node doesn't know the id; `location` is `None`
+    /// for a pending tx.
+    TxInfo {
+        tx_id: String,
+        tx: Option<Transaction>,
+        location: Option<TxLocation>,
+        confirmations: u64,
+    },
+    /// Request the tx history of an address
+    GetHistory {
+        address: String,
+    },
+    /// Confirmed txs of an address (oldest first), plus ids of its pending mempool txs
+    History {
+        address: String,
+        confirmed: Vec<(String, TxLocation)>,
+        pending: Vec<String>,
+    },
+    /// Request an account's confirmed and pending state
+    GetAccount {
+        address: String,
+    },
+    /// Account state response
+    Account(AccountInfo),
+    /// Request a Merkle inclusion proof for a confirmed tx (served from the tx index)
+    GetTxProof {
+        tx_id: String,
+    },
+    /// `GetTxProof` response; `None` if the tx is not confirmed on the node's chain
+    TxProof {
+        tx_id: String,
+        proof: Option<TxInclusionProof>,
+    },
+    /// Request a proof of an account (or its absence) against the tip's state root
+    GetAccountProof {
+        address: String,
+    },
+    /// `GetAccountProof` response, proven against the header at `height`
+    AccountProof {
+        height: u64,
+        proof: AccountProof,
     },
 }
 
@@ -111,6 +192,61 @@ pub struct PeerInfo {
     pub is_banned: bool,
 }
 
+/// Unrelated messages `Message::request_async` skips before giving up on a reply.
+pub const MAX_SKIPPED_REPLIES: usize = 64;
+
+/// An account as seen by a node: confirmed state at `height` plus its pending mempool txs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountInfo {
+    pub address: String,
+    pub height: u64,
+    pub balance: u64,
+    /// Part of `balance` held in coinbase rewards that the next block can't spend yet.
+    #[serde(default)]
+    pub immature: u64,
+    /// Next nonce expected on chain.
+    pub nonce: u64,
+    /// Next nonce for a new tx, after the pending ones.
+    pub pending_nonce: u64,
+    /// Amount + fee of the pending txs sent from this address.
+    pub pending_spend: u64,
+    pub pending_txs: usize,
+    /// Balances of user-defined assets, by asset id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, u64>,
+}
+
+impl AccountInfo {
+    pub fn new(address: &str, state: &State, mempool: &Mempool, height: u64) -> Self {
+        let nonce = state.get_nonce(address);
+        Self {
+            address: address.to_string(),
+            height,
+            balance: state.get_balance(address),
+            immature: state
+                .accounts
+                .get(address)
+                .map_or(0, |a| a.locked_at(height + 1)),
+            nonce,
+            pending_nonce: mempool.next_nonce_for(address, nonce),
+            pending_spend: mempool.pending_spend(address),
+            pending_txs: mempool.txs.iter().filter(|t| t.from == address).count(),
+            assets: state
+                .accounts
+                .get(address)
+                .map(|a| a.assets.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Spendable balance left once the pending txs confirm.
+    pub fn available(&self) -> u64 {
+        self.balance
+            .saturating_sub(self.immature)
+            .saturating_sub(self.pending_spend)
+    }
+}
+
 impl Message {
     pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
         let json = serde_json::to_vec(self)?;
@@ -226,9 +362,39 @@ impl Message {
             Message::BroadcastTransaction(_) => "BroadcastTransaction",
             Message::GetMempoolInfo => "GetMempoolInfo",
             Message::MempoolInfo { .. } => "MempoolInfo",
+            Message::GetTx { .. } => "GetTx",
+            Message::TxInfo { .. } => "TxInfo",
+            Message::GetHistory { .. } => "GetHistory",
+            Message::History { .. } => "History",
+            Message::GetAccount { .. } => "GetAccount",
+            Message::Account(_) => "Account",
+            Message::GetTxProof { .. } => "GetTxProof",
+            Message::TxProof { .. } => "TxProof",
+            Message::GetAccountProof { .. } => "GetAccountProof",
+            Message::AccountProof { .. } => "AccountProof",
         }
     }
 
+    /// Send `self` and wait for a reply named `reply_type` (see `get_type_name`), skipping
+    /// unrelated messages such as the peer's handshake or gossip. A `Reject` becomes an error.
+    pub async fn request_async(
+        self,
+        stream: &mut tokio::net::TcpStream,
+        reply_type: &str,
+    ) -> anyhow::Result<Message> {
+        self.send_async(stream).await?;
+        for _ in 0..MAX_SKIPPED_REPLIES {
+            let reply = Message::decode_async(&mut *stream).await?;
+            if let Message::Reject { code, reason, .. } = &reply {
+                anyhow::bail!("request rejected ({}): {}", code, reason);
+            }
+            if reply.get_type_name() == reply_type {
+                return Ok(reply);
+            }
+        }
+        anyhow::bail!("no {} reply received", reply_type)
+    }
+
     pub async fn decode_async<R: tokio::io::AsyncRead + Unpin>(
         mut reader: R,
     ) -> anyhow::Result<Self> {
@@ -283,7 +449,10 @@ mod tests {
 
     #[test]
     fn test_message_fee_estimate_roundtrip() {
-        let msg = Message::GetFeeEstimate { tx_size: 250 };
+        let msg = Message::GetFeeEstimate {
+            tx_size: 250,
+            target_blocks: 3,
+        };
         let encoded = msg.encode().unwrap();
         let decoded = Message::decode(Cursor::new(encoded)).unwrap();
         assert_eq!(msg, decoded);
@@ -291,6 +460,8 @@ mod tests {
         let msg2 = Message::FeeEstimate {
             fee_per_byte: 10,
             estimated_total: 2500,
+            target_blocks: 3,
+            fee_per_kb: 10_000,
         };
         let encoded2 = msg2.encode().unwrap();
         let decoded2 = Message::decode(Cursor::new(encoded2)).unwrap();
@@ -314,6 +485,10 @@ mod tests {
             version: 1,
             best_height: 123,
             agent: "rusty-chain/0.1.0".to_string(),
+            genesis_hash: "ab".repeat(32),
+            chain_id: 3,
+            timestamp_ms: 1_700_000_000_000,
+            pruned_height: 40,
         };
         let encoded = msg.encode().unwrap();
         let decoded = Message::decode(Cursor::new(encoded)).unwrap();
@@ -330,6 +505,7 @@ mod tests {
                     merkle_root: "".to_string(),
                     timestamp_ms: 0,
                     nonce: 0,
+                    state_root: "".to_string(),
                 },
                 txs: vec![],
             })
@@ -1,6 +1,9 @@
-use crate::core::chain::Chain;
-use crate::core::mempool::Mempool;
-use crate::core::network::{Message, PeerInfo};
+use crate::core::chain::{Chain, FutureBlockError, pow_ok};
+use crate::core::fee_estimator::FeeEstimator;
+use crate::core::mempool::{Mempool, MempoolEvent, RemovalReason};
+use crate::core::network::{AccountInfo, Message, PeerInfo};
+use crate::core::state::State;
+use crate::core::time::{PeerTimeOffsets, now_ms};
 use crate::core::types::{Block, BlockHeader, Transaction};
 use anyhow::Context;
 use std::collections::{HashMap, HashSet};
@@ -29,6 +32,235 @@ pub struct NodeState {
     pub whitelist_path: Option<String>,
     pub banned_peers: HashSet<SocketAddr>,
     pub whitelisted_peers: HashSet<SocketAddr>,
+    /// Peer clock offsets from handshakes; their median drives `chain.time_offset_ms`.
+    pub time_offsets: PeerTimeOffsets,
+    /// Blocks too far in the future to accept yet, with the peer that sent them.
+    pub held_blocks: Vec<(Block, SocketAddr)>,
+    /// Confirmation-time statistics behind `GetFeeEstimate`.
+    pub fee_estimator: FeeEstimator,
+    /// Pruned height each peer advertised in its handshake; absent means a full node.
+    pub peer_pruned_heights: HashMap<SocketAddr, u64>,
+    /// Blocks off the main chain by hash, kept until their branch outgrows it (see `reorg_to`).
+    pub side_blocks: HashMap<String, SideBlock>,
+    /// Tip hash and the state after it, behind `tip_state`.
+    pub tip_state_cache: Option<(String, Arc<State>)>,
+}
+
+/// Maximum number of future blocks held for retry.
+pub const MAX_HELD_BLOCKS: usize = 64;
+
+/// Maximum number of future blocks held for retry from any one peer.
+pub const MAX_HELD_BLOCKS_PER_PEER: usize = 8;
+
+/// Maximum number of side-branch blocks kept from peers.
+pub const MAX_SIDE_BLOCKS: usize = 256;
+
+/// Maximum number of side-branch blocks kept from any one peer.
+pub const MAX_SIDE_BLOCKS_PER_PEER: usize = 32;
+
+/// A block off the main chain, with its height and the peer that sent it (`None` for blocks
+/// disconnected by a reorg).
+#[derive(Debug, Clone)]
+pub struct SideBlock {
+    pub block: Block,
+    pub height: usize,
+    pub from: Option<SocketAddr>,
+}
+
+impl NodeState {
+    /// Record a peer clock sample and refresh the chain's adjusted-time offset.
+    pub fn add_time_sample(&mut self, peer: SocketAddr, peer_ms: u64) {
+        self.time_offsets.add(peer, peer_ms, now_ms());
+        self.chain.time_offset_ms = self.time_offsets.offset_ms();
+    }
+
+    pub fn remove_time_sample(&mut self, peer: &SocketAddr) {
+        self.time_offsets.remove(peer);
+        self.chain.time_offset_ms = self.time_offsets.offset_ms();
+    }
+
+    /// State at the chain tip, recomputed only when the tip has changed since the last call.
+    pub fn tip_state(&mut self) -> anyhow::Result<Arc<State>> {
+        let tip = self.chain.tip_hash();
+        match &self.tip_state_cache {
+            Some((hash, state)) if *hash == tip => Ok(Arc::clone(state)),
+            _ => {
+                let state = Arc::new(self.chain.compute_state()?);
+                self.tip_state_cache = Some((tip, Arc::clone(&state)));
+                Ok(state)
+            }
+        }
+    }
+
+    /// Whether `peer` can still serve the full block at `height`.
+    pub fn peer_has_block(&self, peer: &SocketAddr, height: u64) -> bool {
+        self.peer_pruned_heights
+            .get(peer)
+            .is_none_or(|&pruned| height > pruned)
+    }
+
+    /// Keep `block` as a side block. It must build on a known block above the last checkpoint,
+    /// and a peer may have at most `MAX_SIDE_BLOCKS_PER_PEER` of them. When the store is full,
+    /// the lowest side block makes room.
+    pub fn add_side_block(&mut self, block: Block, from: Option<SocketAddr>) -> anyhow::Result<()> {
+        let prev = &block.header.prev_hash;
+        let parent = match self.chain.block_index.get(prev) {
+            Some(&height) => height,
+            None => self
+                .side_blocks
+                .get(prev)
+                .map(|s| s.height)
+                .ok_or_else(|| anyhow::anyhow!("unknown parent {}", prev))?,
+        };
+        let height = parent + 1;
+        let checkpoint = self.last_checkpoint_height();
+        anyhow::ensure!(
+            height > checkpoint,
+            "forks at height {} at or below checkpoint {}",
+            height,
+            checkpoint
+        );
+        if let Some(peer) = from {
+            let held = self
+                .side_blocks
+                .values()
+                .filter(|s| s.from == Some(peer))
+                .count();
+            anyhow::ensure!(
+                held < MAX_SIDE_BLOCKS_PER_PEER,
+                "too many side blocks from {}",
+                peer
+            );
+        }
+
+        self.prune_side_blocks();
+        while self.side_blocks.len() >= MAX_SIDE_BLOCKS {
+            let Some(lowest) = self
+                .side_blocks
+                .iter()
+                .min_by_key(|(hash, s)| (s.height, (*hash).clone()))
+                .map(|(hash, _)| hash.clone())
+            else {
+                break;
+            };
+            self.side_blocks.remove(&lowest);
+        }
+        self.side_blocks.insert(
+            block.header.hash(),
+            SideBlock {
+                block,
+                height,
+                from,
+            },
+        );
+        Ok(())
+    }
+
+    /// Drop side blocks at or below the last checkpoint; no reorg can reach them.
+    pub fn prune_side_blocks(&mut self) {
+        let checkpoint = self.last_checkpoint_height();
+        self.side_blocks.retain(|_, s| s.height > checkpoint);
+    }
+
+    fn last_checkpoint_height(&self) -> usize {
+        self.chain
+            .get_last_checkpoint()
+            .map_or(0, |(height, _)| height)
+    }
+
+    /// Drop the side block `hash` and every side block built on it.
+    fn remove_side_branch(&mut self, hash: &str) {
+        let mut removed = HashSet::from([hash.to_string()]);
+        self.side_blocks.remove(hash);
+        loop {
+            let children: Vec<String> = self
+                .side_blocks
+                .iter()
+                .filter(|(_, s)| removed.contains(&s.block.header.prev_hash))
+                .map(|(hash, _)| hash.clone())
+                .collect();
+            if children.is_empty() {
+                break;
+            }
+            for child in children {
+                self.side_blocks.remove(&child);
+                removed.insert(child);
+            }
+        }
+    }
+
+    /// Switch to the side branch ending at `tip` if it is longer than the main chain.
+    ///
+    /// Blocks above the fork point are disconnected, their txs going back to the mempool, and
+    /// become side blocks; the branch is then connected. If a branch block is invalid, it and
+    /// its descendants are dropped and nothing else changes. Returns whether the chain switched.
+    pub fn reorg_to(&mut self, tip: &str) -> anyhow::Result<bool> {
+        let mut branch = Vec::new();
+        let mut hash = tip.to_string();
+        let fork_height = loop {
+            if let Some(&height) = self.chain.block_index.get(&hash) {
+                break height;
+            }
+            let Some(side) = self.side_blocks.get(&hash) else {
+                return Ok(false);
+            };
+            hash = side.block.header.prev_hash.clone();
+            branch.push(side.block.clone());
+        };
+        branch.reverse();
+        if fork_height + branch.len() <= self.chain.height() {
+            return Ok(false);
+        }
+
+        let mut chain = self.chain.clone();
+        let mut disconnected = Vec::new();
+        while chain.height() > fork_height {
+            disconnected.push(chain.disconnect_tip()?);
+        }
+        let fork_state = chain.compute_state()?;
+        for block in &branch {
+            let hash = block.header.hash();
+            if let Err(e) =
+                chain.append_block_with_cache(block.clone(), Some(&self.mempool.sig_cache))
+            {
+                self.remove_side_branch(&hash);
+                return Err(e.context(format!("side block {}", hash)));
+            }
+        }
+        let tip_state = chain.compute_state()?;
+        self.chain = chain;
+        self.tip_state_cache = Some((self.chain.tip_hash(), Arc::new(tip_state.clone())));
+
+        let now = now_ms();
+        let mut events = Vec::new();
+        for block in &disconnected {
+            events.extend(self.mempool.on_block_disconnected(
+                block,
+                &fork_state,
+                fork_height as u64 + 1,
+                now,
+            ));
+        }
+        let next_height = self.chain.height() as u64 + 1;
+        for (height, block) in (fork_height + 1..).zip(&branch) {
+            events.extend(
+                self.mempool
+                    .on_block_connected(block, &tip_state, next_height, now),
+            );
+            self.fee_estimator
+                .on_block_connected(height as u64, block, &self.mempool);
+            self.side_blocks.remove(&block.header.hash());
+        }
+        log_mempool_events(&events, &mut self.fee_estimator);
+        self.prune_side_blocks();
+        for block in disconnected.into_iter().rev() {
+            let hash = block.header.hash();
+            if let Err(e) = self.add_side_block(block, None) {
+                println!("Dropping disconnected block {}: {}", hash, e);
+            }
+        }
+        Ok(true)
+    }
 }
 
 pub struct P2PNode {
@@ -69,6 +301,8 @@ impl P2PNode {
             }
         }
 
+        let fee_estimator = FeeEstimator::from_chain(&chain);
+
         Self {
             addr,
             state: Arc::new(Mutex::new(NodeState {
@@ -83,6 +317,12 @@ impl P2PNode {
                 whitelist_path,
                 banned_peers: HashSet::new(),
                 whitelisted_peers,
+                time_offsets: PeerTimeOffsets::new(),
+                held_blocks: Vec::new(),
+                fee_estimator,
+                peer_pruned_heights: HashMap::new(),
+                side_blocks: HashMap::new(),
+                tip_state_cache: None,
             })),
         }
     }
@@ -159,6 +399,19 @@ impl P2PNode {
             }
         });
 
+        // Background retry of held future blocks
+        let held_handle = P2PNodeHandle {
+            state: Arc::clone(&self.state),
+        };
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                if let Err(e) = held_handle.retry_held_blocks().await {
+                    eprintln!("Held block retry failed: {}", e);
+                }
+            }
+        });
+
         // Background mempool evictor
         tokio::spawn(async move {
             loop {
@@ -172,6 +425,12 @@ impl P2PNode {
                 // TTL is now transaction-specific or defaults in Mempool
                 let evicted = state.mempool.evict_expired(now);
                 if evicted > 0 {
+                    let NodeState {
+                        mempool,
+                        fee_estimator,
+                        ..
+                    } = &mut *state;
+                    fee_estimator.untrack_missing(mempool);
                     println!(
                         "Background evictor: removed {} expired transactions",
                         evicted
@@ -249,11 +508,16 @@ impl P2PNode {
         println!("Connected to outbound peer {}", target);
 
         // Add to known addrs
-        {
+        let (genesis_hash, chain_id, pruned_height) = {
             let mut s = self.state.lock().await;
             s.known_addrs.insert(target);
             s.outgoing_conns.insert(target);
-        }
+            (
+                s.chain.genesis_hash(),
+                s.chain.chain_id,
+                s.chain.pruned_height() as u64,
+            )
+        };
 
         let mut stream = stream;
 
@@ -262,6 +526,10 @@ impl P2PNode {
             version: 1,
             best_height,
             agent: agent.clone(),
+            genesis_hash,
+            chain_id,
+            timestamp_ms: now_ms(),
+            pruned_height,
         }
         .send_async(&mut stream)
         .await?;
@@ -281,6 +549,23 @@ impl P2PNode {
     }
 }
 
+/// Log mempool changes and stop fee-tracking txs dropped without being mined.
+fn log_mempool_events(events: &[MempoolEvent], fee_estimator: &mut FeeEstimator) {
+    for event in events {
+        match event {
+            MempoolEvent::Added { tx_id } => println!("Mempool: re-added {}", tx_id),
+            MempoolEvent::Removed {
+                reason: RemovalReason::Included,
+                ..
+            } => {}
+            MempoolEvent::Removed { tx_id, reason } => {
+                println!("Mempool: dropped {} ({})", tx_id, reason);
+                fee_estimator.untrack(tx_id);
+            }
+        }
+    }
+}
+
 /// A lightweight handle to the P2PNode to avoid circular Arc or complex lifetimes in handlers
 #[derive(Clone)]
 pub struct P2PNodeHandle {
@@ -304,6 +589,24 @@ impl P2PNodeHandle {
         state.seen_messages.insert(id)
     }
 
+    /// Whether the block with `hash` is on the main chain, a side branch or held for later.
+    async fn has_block(&self, hash: &str) -> bool {
+        let state = self.state.lock().await;
+        state.chain.block_index.contains_key(hash)
+            || state.side_blocks.contains_key(hash)
+            || state
+                .held_blocks
+                .iter()
+                .any(|(b, _)| b.header.hash() == hash)
+    }
+
+    /// Forget `id` so a later copy is processed again. Tx ids and block hashes don't commit
+    /// to witnesses, so a copy that failed validation may have carried a mangled witness.
+    pub async fn unmark_seen(&self, id: &str) {
+        let mut state = self.state.lock().await;
+        state.seen_messages.remove(id);
+    }
+
     pub async fn is_seen(&self, id: &str) -> bool {
         let state = self.state.lock().await;
         state.seen_messages.contains(id)
@@ -400,13 +703,15 @@ impl P2PNodeHandle {
         state.banned_peers.clone()
     }
 
-    pub async fn get_mempool_info(&self) -> (usize, usize, u64, u64) {
+    /// (count, total_size, min_fee, max_fee, min_relay_fee_per_kb)
+    pub async fn get_mempool_info(&self) -> (usize, usize, u64, u64, u64) {
         let state = self.state.lock().await;
         let count = state.mempool.txs.len();
-        let total_size = state.mempool.txs.iter().map(|tx| tx.size()).sum();
+        let total_size = state.mempool.total_size();
         let min_fee = state.mempool.txs.iter().map(|tx| tx.fee).min().unwrap_or(0);
         let max_fee = state.mempool.txs.iter().map(|tx| tx.fee).max().unwrap_or(0);
-        (count, total_size, min_fee, max_fee)
+        let min_relay_fee_per_kb = state.mempool.min_fee_per_kb();
+        (count, total_size, min_fee, max_fee, min_relay_fee_per_kb)
     }
 
     pub async fn get_reputation_snapshot(&self) -> HashMap<SocketAddr, i32> {
@@ -455,6 +760,22 @@ impl P2PNodeHandle {
         Ok(())
     }
 
+    /// Send `reply`, or a `Reject` (code 501) carrying its error, e.g. a disabled tx index or
+    /// a state that failed to compute.
+    async fn reply_or_reject(
+        &self,
+        to: SocketAddr,
+        reply: anyhow::Result<Message>,
+        message_type: &str,
+    ) -> anyhow::Result<()> {
+        let msg = reply.unwrap_or_else(|e| Message::Reject {
+            code: 501,
+            reason: e.to_string(),
+            message_type: message_type.to_string(),
+        });
+        self.send_to(to, msg).await
+    }
+
     pub async fn broadcast(&self, msg: Message) -> anyhow::Result<()> {
         let state = self.state.lock().await;
         for tx in state.peer_senders.values() {
@@ -480,6 +801,7 @@ impl P2PNodeHandle {
         let mut results = Vec::new();
         for hash in hashes {
             if let Some(&height) = state.chain.block_index.get(&hash)
+                && !state.chain.is_pruned(height)
                 && let Some(block) = state.chain.blocks.get(height)
             {
                 results.push(block.clone());
@@ -492,51 +814,166 @@ impl P2PNodeHandle {
         let blk_id = block.header.hash();
         if self.mark_seen(blk_id.clone()).await {
             println!("Gossip: New Block {} from {}", blk_id, from);
+            self.accept_block(block, from).await?;
+            if !self.has_block(&blk_id).await {
+                self.unmark_seen(&blk_id).await;
+            }
+        }
+        Ok(())
+    }
 
-            // 1. Initial validation against local chain (lightweight clone)
-            let chain_copy = {
-                let state = self.state.lock().await;
-                // Basic duplicate check against local chain
-                if state.chain.block_index.contains_key(&blk_id) {
-                    return Ok(());
-                }
-                state.chain.clone()
-            };
+    /// Validate and append a gossiped block, then relay it.
+    ///
+    /// Blocks too far ahead of adjusted time are held (see `retry_held_blocks`) instead of
+    /// being rejected, and the sender is not penalized for them.
+    async fn accept_block(&self, block: Block, from: SocketAddr) -> anyhow::Result<()> {
+        let blk_id = block.header.hash();
 
-            if let Err(e) = chain_copy.validate_block(&block) {
-                println!("Invalid block {} from {}: {}", blk_id, from, e);
-                self.update_reputation(from, -50).await;
+        // 1. Initial validation against local chain (lightweight clone)
+        let (chain_copy, sig_cache) = {
+            let state = self.state.lock().await;
+            // Basic duplicate check against local chain
+            if state.chain.block_index.contains_key(&blk_id)
+                || state.side_blocks.contains_key(&blk_id)
+            {
                 return Ok(());
             }
-
-            // 2. Final append under lock
+            let prev = &block.header.prev_hash;
+            if *prev != state.chain.tip_hash()
+                && (state.chain.block_index.contains_key(prev)
+                    || state.side_blocks.contains_key(prev))
             {
+                drop(state);
+                return self.accept_side_block(block, from).await;
+            }
+            (state.chain.clone(), state.mempool.sig_cache.clone())
+        };
+
+        if let Err(e) = chain_copy.validate_block_with_cache(&block, Some(&sig_cache)) {
+            if e.downcast_ref::<FutureBlockError>().is_some() {
                 let mut state = self.state.lock().await;
-                // Re-verify linkage in case tip changed during validation
-                if block.header.prev_hash != state.chain.tip_hash() {
-                    println!(
-                        "Gossip block {} from {} rejected: prev_hash mismatch during lock",
-                        blk_id, from
-                    );
-                    return Ok(());
+                let from_peer = state.held_blocks.iter().filter(|(_, p)| *p == from).count();
+                if state.held_blocks.len() < MAX_HELD_BLOCKS
+                    && from_peer < MAX_HELD_BLOCKS_PER_PEER
+                    && !state
+                        .held_blocks
+                        .iter()
+                        .any(|(b, _)| b.header == block.header)
+                {
+                    println!("Holding future block {} from {}: {}", blk_id, from, e);
+                    state.held_blocks.push((block, from));
                 }
+                return Ok(());
+            }
+            println!("Invalid block {} from {}: {}", blk_id, from, e);
+            self.update_reputation(from, -50).await;
+            return Ok(());
+        }
 
-                if let Err(e) = state.chain.append_block(block.clone()) {
-                    println!(
-                        "Failed to append validated block {} to chain: {}",
-                        blk_id, e
+        // 2. Final append under lock
+        {
+            let mut guard = self.state.lock().await;
+            let state = &mut *guard;
+            // Re-verify linkage in case tip changed during validation
+            if block.header.prev_hash != state.chain.tip_hash() {
+                println!(
+                    "Gossip block {} from {} rejected: prev_hash mismatch during lock",
+                    blk_id, from
+                );
+                return Ok(());
+            }
+
+            if let Err(e) = state
+                .chain
+                .append_block_with_cache(block.clone(), Some(&state.mempool.sig_cache))
+            {
+                println!(
+                    "Failed to append validated block {} to chain: {}",
+                    blk_id, e
+                );
+                return Ok(());
+            }
+            state.prune_side_blocks();
+            // 3. Drop included txs and revalidate the rest against the new tip
+            match state.tip_state() {
+                Ok(tip_state) => {
+                    let next_height = state.chain.height() as u64 + 1;
+                    let events =
+                        state
+                            .mempool
+                            .on_block_connected(&block, &tip_state, next_height, now_ms());
+                    log_mempool_events(&events, &mut state.fee_estimator);
+                    state.fee_estimator.on_block_connected(
+                        state.chain.height() as u64,
+                        &block,
+                        &state.mempool,
                     );
-                    return Ok(());
                 }
-                // 3. Clear mempool txs
-                state.mempool.remove_included(&block.txs);
+                Err(e) => {
+                    eprintln!("Failed to compute state after block {}: {}", blk_id, e);
+                    state.mempool.remove_included(&block.txs);
+                }
             }
+        }
 
-            // 4. Update reputation
-            self.update_reputation(from, 10).await;
-            // 5. Re-gossip
-            self.broadcast_except(Message::NewBlock(block), from)
-                .await?;
+        // 4. Update reputation
+        self.update_reputation(from, 10).await;
+        // 5. Re-gossip
+        self.broadcast_except(Message::NewBlock(block), from)
+            .await?;
+        Ok(())
+    }
+
+    /// Keep a block that builds on a known block other than the tip, and reorganize onto its
+    /// branch once that is longer (see `NodeState::reorg_to`).
+    async fn accept_side_block(&self, block: Block, from: SocketAddr) -> anyhow::Result<()> {
+        let blk_id = block.header.hash();
+        let result = {
+            let mut state = self.state.lock().await;
+            if !pow_ok(&blk_id, state.chain.pow_difficulty) {
+                Err(anyhow::anyhow!("insufficient proof of work"))
+            } else if let Err(e) = state.add_side_block(block.clone(), Some(from)) {
+                println!("Dropping side block {} from {}: {}", blk_id, from, e);
+                return Ok(());
+            } else {
+                state.reorg_to(&blk_id)
+            }
+        };
+        match result {
+            Ok(true) => {
+                println!("Reorganized onto block {} from {}", blk_id, from);
+                self.update_reputation(from, 10).await;
+                self.broadcast_except(Message::NewBlock(block), from)
+                    .await?;
+            }
+            Ok(false) => println!("Holding side block {} from {}", blk_id, from),
+            Err(e) => {
+                println!("Invalid side block {} from {}: {:#}", blk_id, from, e);
+                self.update_reputation(from, -50).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Retry held future blocks whose timestamp is now within the allowed drift.
+    pub async fn retry_held_blocks(&self) -> anyhow::Result<()> {
+        let ready = {
+            let mut state = self.state.lock().await;
+            let max_allowed = state
+                .chain
+                .adjusted_time_ms()
+                .saturating_add(state.chain.params.max_future_drift_ms);
+            let (ready, held): (Vec<_>, Vec<_>) = std::mem::take(&mut state.held_blocks)
+                .into_iter()
+                .partition(|(b, _)| b.header.timestamp_ms <= max_allowed);
+            state.held_blocks = held;
+            ready
+        };
+        // Oldest first so parents connect before children.
+        let mut ready = ready;
+        ready.sort_by_key(|(b, _)| b.header.timestamp_ms);
+        for (block, from) in ready {
+            self.accept_block(block, from).await?;
         }
         Ok(())
     }
@@ -548,24 +985,45 @@ impl P2PNodeHandle {
     ) -> anyhow::Result<()> {
         let tx_id = tx.id();
         let gossip_id = format!("{}_{}", tx_id, tx.fee);
-        if self.mark_seen(gossip_id).await {
+        if self.mark_seen(gossip_id.clone()).await {
             println!(
                 "Gossip: New Transaction {} (fee={}) from {}",
                 tx_id, tx.fee, from
             );
-            // 1. Validate tx
+            // 1. Validate tx against the cached tip state, after its pending ancestors. The
+            // signature is checked last and once, through the mempool's signature cache.
             let mut state = self.state.lock().await;
-            if let Err(e) = state.chain.validate_transaction(&tx) {
+            let tip_state = match state.tip_state() {
+                Ok(tip_state) => tip_state,
+                Err(e) => {
+                    eprintln!("Failed to compute tip state for tx {}: {}", tx_id, e);
+                    return Ok(());
+                }
+            };
+            let pending = state.mempool.ancestors_of(&tx);
+            let valid = state
+                .chain
+                .validate_unverified_transaction_on(&tip_state, &pending, &tx)
+                .and_then(|()| state.mempool.check_signature(&tx));
+            if let Err(e) = valid {
                 println!("Invalid transaction {} from {}: {}", tx_id, from, e);
+                // A copy with an intact witness may still come.
+                state.seen_messages.remove(&gossip_id);
                 drop(state);
                 self.update_reputation(from, -10).await;
                 return Ok(());
             }
             // 2. Add to mempool
-            let base_nonce = state.chain.next_nonce_for(&tx.from);
-            if let Err(e) = state.mempool.add_tx_checked(tx.clone(), base_nonce) {
+            let base_nonce = tip_state.get_nonce(&tx.from);
+            let next_height = state.chain.height() as u64 + 1;
+            let balance = tip_state.get_spendable(&tx.from, next_height);
+            let added = state
+                .mempool
+                .add_tx_with_balance(tx.clone(), base_nonce, balance);
+            if let Err(e) = added {
                 println!("Failed to add tx {} from {} to mempool: {}", tx_id, from, e);
-                // Send rejection message for invalid RBF attempt or nonce gap
+                drop(state);
+                // Send rejection message for invalid RBF attempt, nonce gap or overdraft
                 let _ = self
                     .send_to(
                         from,
@@ -578,6 +1036,14 @@ impl P2PNodeHandle {
                     .await;
                 return Ok(());
             }
+            // The new tx may have replaced or evicted others.
+            let NodeState {
+                mempool,
+                fee_estimator,
+                ..
+            } = &mut *state;
+            fee_estimator.untrack_missing(mempool);
+            fee_estimator.track_tx(&tx);
             drop(state);
             self.update_reputation(from, 1).await;
 
@@ -598,10 +1064,14 @@ impl P2PNodeHandle {
                 version,
                 best_height,
                 agent,
+                genesis_hash,
+                chain_id,
+                timestamp_ms,
+                pruned_height,
             } => {
                 println!(
-                    "Handshake from {}: version={}, height={}, agent={}",
-                    from, version, best_height, agent
+                    "Handshake from {}: version={}, height={}, agent={}, pruned={}",
+                    from, version, best_height, agent, pruned_height
                 );
                 // Version check: simple exact match for this demo
                 if version != 1 {
@@ -627,6 +1097,49 @@ impl P2PNodeHandle {
                     return Ok(());
                 }
 
+                // Network and genesis checks: such peers are on another chain.
+                let (our_chain_id, our_genesis) = {
+                    let state = self.state.lock().await;
+                    (state.chain.chain_id, state.chain.genesis_hash())
+                };
+                let mismatch = if chain_id != our_chain_id {
+                    Some(format!(
+                        "Chain id mismatch: expected {} got {}",
+                        our_chain_id, chain_id
+                    ))
+                } else if genesis_hash != our_genesis {
+                    Some(format!("Genesis mismatch: expected {}", our_genesis))
+                } else {
+                    None
+                };
+                if let Some(reason) = mismatch {
+                    println!("Refusing peer {}: {}", from, reason);
+                    self.send_to(
+                        from,
+                        Message::Reject {
+                            code: 400,
+                            reason,
+                            message_type: "Handshake".to_string(),
+                        },
+                    )
+                    .await?;
+                    let state = self.state.lock().await;
+                    if let Some(tx) = state.peer_senders.get(&from) {
+                        let _ = tx.send(PeerCmd::Disconnect);
+                    }
+                    return Ok(());
+                }
+
+                {
+                    let mut state = self.state.lock().await;
+                    if timestamp_ms > 0 {
+                        state.add_time_sample(from, timestamp_ms);
+                    }
+                    if pruned_height > 0 {
+                        state.peer_pruned_heights.insert(from, pruned_height);
+                    }
+                }
+
                 // Request mempool transactions upon connection
                 self.send_to(from, Message::GetMempoolTxs).await?;
 
@@ -636,7 +1149,12 @@ impl P2PNodeHandle {
                     state.chain.blocks.len() as u64
                 };
 
-                if best_height > our_height {
+                if best_height > our_height && our_height <= pruned_height {
+                    println!(
+                        "Peer {} is ahead but pruned up to {}; not syncing blocks from it",
+                        from, pruned_height
+                    );
+                } else if best_height > our_height {
                     println!(
                         "Peer {} is ahead ({} > {}), requesting checkpoints...",
                         from, best_height, our_height
@@ -663,6 +1181,14 @@ impl P2PNodeHandle {
             Message::Headers(headers) => {
                 if !headers.is_empty() {
                     println!("Received {} headers from {}", headers.len(), from);
+                    let pruned = {
+                        let state = self.state.lock().await;
+                        !state.peer_has_block(&from, state.chain.height() as u64 + 1)
+                    };
+                    if pruned {
+                        println!("Peer {} has pruned the blocks we need", from);
+                        return Ok(());
+                    }
                     // Request blocks for these headers
                     let hashes = headers.iter().map(|h| h.hash()).collect();
                     self.send_to(
@@ -704,21 +1230,59 @@ impl P2PNodeHandle {
                 }
             }
             Message::GetData { block_hashes } => {
+                let pruned = {
+                    let state = self.state.lock().await;
+                    block_hashes
+                        .iter()
+                        .filter_map(|hash| state.chain.block_index.get(hash))
+                        .copied()
+                        .filter(|&height| state.chain.is_pruned(height))
+                        .collect::<Vec<_>>()
+                };
+                if let Some(height) = pruned.first() {
+                    self.send_to(
+                        from,
+                        Message::Reject {
+                            code: 404,
+                            reason: format!(
+                                "{} requested block(s) are pruned (first at height {})",
+                                pruned.len(),
+                                height
+                            ),
+                            message_type: "GetData".to_string(),
+                        },
+                    )
+                    .await?;
+                }
                 let blocks = self.get_blocks_by_hash(block_hashes).await;
-                self.send_to(from, Message::Blocks(blocks)).await?;
+                if pruned.is_empty() || !blocks.is_empty() {
+                    self.send_to(from, Message::Blocks(blocks)).await?;
+                }
             }
-            Message::GetFeeEstimate { tx_size } => {
-                let rate = {
-                    let state = self.state.lock().await;
-                    state.chain.estimate_fee_rate(10) // Window of 10 blocks
+            Message::GetFeeEstimate {
+                tx_size,
+                target_blocks,
+            } => {
+                let estimate = {
+                    let mut state = self.state.lock().await;
+                    // Size the estimate by the next block's limit, governance changes included.
+                    let next_height = state.chain.height() as u64 + 1;
+                    let params = state
+                        .tip_state()?
+                        .params_at(&state.chain.params, next_height);
+                    state.fee_estimator.estimate(
+                        target_blocks,
+                        &state.mempool,
+                        params.max_block_size,
+                    )
                 };
-                let fee_per_byte = rate.ceil() as u64;
-                let estimated_total = (tx_size as f64 * rate).ceil() as u64;
                 self.send_to(
                     from,
                     Message::FeeEstimate {
-                        fee_per_byte,
-                        estimated_total,
+                        fee_per_byte: estimate.fee_per_byte(),
+                        estimated_total: estimate.fee_for_size(tx_size),
+                        target_blocks: estimate.target_blocks,
+                        fee_per_kb: estimate.fee_per_kb,
                     },
                 )
                 .await?;
@@ -726,10 +1290,12 @@ impl P2PNodeHandle {
             Message::FeeEstimate {
                 fee_per_byte,
                 estimated_total,
+                target_blocks,
+                ..
             } => {
                 println!(
-                    "Received fee estimate from {}: {} units (rate: {}/byte)",
-                    from, estimated_total, fee_per_byte
+                    "Received fee estimate from {}: {} units within {} blocks (rate: {}/byte)",
+                    from, estimated_total, target_blocks, fee_per_byte
                 );
             }
             Message::GetAddr => {
@@ -909,7 +1475,8 @@ impl P2PNodeHandle {
                 self.process_new_transaction(tx, from).await?;
             }
             Message::GetMempoolInfo => {
-                let (count, total_size, min_fee, max_fee) = self.get_mempool_info().await;
+                let (count, total_size, min_fee, max_fee, min_relay_fee_per_kb) =
+                    self.get_mempool_info().await;
                 self.send_to(
                     from,
                     Message::MempoolInfo {
@@ -917,6 +1484,7 @@ impl P2PNodeHandle {
                         total_size,
                         min_fee,
                         max_fee,
+                        min_relay_fee_per_kb,
                     },
                 )
                 .await?;
@@ -926,10 +1494,141 @@ impl P2PNodeHandle {
                 total_size,
                 min_fee,
                 max_fee,
+                min_relay_fee_per_kb,
             } => {
                 println!(
-                    "Received mempool info from {}: count={}, size={} bytes, fees={}-{}",
-                    from, count, total_size, min_fee, max_fee
+                    "Received mempool info from {}: count={}, size={} bytes, fees={}-{}, min_relay={}/kB",
+                    from, count, total_size, min_fee, max_fee, min_relay_fee_per_kb
+                );
+            }
+            Message::GetTx { tx_id } => {
+                let reply = {
+                    let state = self.state.lock().await;
+                    if let Some(tx) = state.mempool.get_tx_by_id(&tx_id) {
+                        Ok(Message::TxInfo {
+                            tx: Some(tx.clone()),
+                            tx_id,
+                            location: None,
+                            confirmations: 0,
+                        })
+                    } else {
+                        let tip = state.chain.height();
+                        state.chain.find_tx(&tx_id).map(|found| Message::TxInfo {
+                            tx: found.map(|(_, tx)| tx.clone()),
+                            location: found.map(|(loc, _)| loc),
+                            confirmations: found
+                                .map_or(0, |(loc, _)| (tip - loc.height) as u64 + 1),
+                            tx_id,
+                        })
+                    }
+                };
+                self.reply_or_reject(from, reply, "GetTx").await?;
+            }
+            Message::TxInfo {
+                tx_id,
+                location,
+                confirmations,
+                ..
+            } => {
+                println!(
+                    "Received tx info from {}: {} at {:?} ({} confirmations)",
+                    from, tx_id, location, confirmations
+                );
+            }
+            Message::GetHistory { address } => {
+                let reply = {
+                    let state = self.state.lock().await;
+                    state.chain.address_history(&address).map(|confirmed| {
+                        let pending = state
+                            .mempool
+                            .txs
+                            .iter()
+                            .filter(|t| t.from == address || t.to == address)
+                            .map(|t| t.id())
+                            .collect();
+                        Message::History {
+                            confirmed: confirmed
+                                .into_iter()
+                                .map(|(loc, tx)| (tx.id(), loc))
+                                .collect(),
+                            pending,
+                            address,
+                        }
+                    })
+                };
+                self.reply_or_reject(from, reply, "GetHistory").await?;
+            }
+            Message::GetAccount { address } => {
+                let reply = {
+                    let mut state = self.state.lock().await;
+                    state.tip_state().map(|chain_state| {
+                        Message::Account(AccountInfo::new(
+                            &address,
+                            &chain_state,
+                            &state.mempool,
+                            state.chain.height() as u64,
+                        ))
+                    })
+                };
+                self.reply_or_reject(from, reply, "GetAccount").await?;
+            }
+            Message::GetTxProof { tx_id } => {
+                let reply = {
+                    let state = self.state.lock().await;
+                    state
+                        .chain
+                        .tx_proof(&tx_id)
+                        .map(|proof| Message::TxProof { tx_id, proof })
+                };
+                self.reply_or_reject(from, reply, "GetTxProof").await?;
+            }
+            Message::GetAccountProof { address } => {
+                let reply = {
+                    let mut state = self.state.lock().await;
+                    state
+                        .tip_state()
+                        .and_then(|tip_state| state.chain.account_proof_on(&tip_state, &address))
+                        .map(|proof| Message::AccountProof {
+                            height: state.chain.height() as u64,
+                            proof,
+                        })
+                };
+                self.reply_or_reject(from, reply, "GetAccountProof").await?;
+            }
+            Message::AccountProof { height, proof } => {
+                println!(
+                    "Received account proof from {}: {} at height {} (exists: {})",
+                    from,
+                    proof.address,
+                    height,
+                    proof.account.is_some()
+                );
+            }
+            Message::TxProof { tx_id, proof } => {
+                println!(
+                    "Received tx proof from {}: {} (found: {})",
+                    from,
+                    tx_id,
+                    proof.is_some()
+                );
+            }
+            Message::Account(info) => {
+                println!(
+                    "Received account {} from {}: balance={} nonce={} pending_txs={}",
+                    info.address, from, info.balance, info.nonce, info.pending_txs
+                );
+            }
+            Message::History {
+                address,
+                confirmed,
+                pending,
+            } => {
+                println!(
+                    "Received history of {} from {}: {} confirmed, {} pending",
+                    address,
+                    from,
+                    confirmed.len(),
+                    pending.len()
                 );
             }
             _ => {
@@ -966,15 +1665,24 @@ async fn handle_peer(
     let peer_reader = async move {
         // Send initial Handshake upon connection (for both inbound and outbound)
         {
-            let best_height = {
+            let (best_height, genesis_hash, chain_id, pruned_height) = {
                 let s = state_for_reader.lock().await;
-                s.chain.height() as u64
+                (
+                    s.chain.height() as u64,
+                    s.chain.genesis_hash(),
+                    s.chain.chain_id,
+                    s.chain.pruned_height() as u64,
+                )
             };
             let mut w = writer_clone.lock().await;
             Message::Handshake {
                 version: 1,
                 best_height,
                 agent,
+                genesis_hash,
+                chain_id,
+                timestamp_ms: now_ms(),
+                pruned_height,
             }
             .send_async(&mut *w)
             .await?;
@@ -1022,6 +1730,8 @@ async fn handle_peer(
         let mut s = state.lock().await;
         s.peer_senders.remove(&addr);
         s.outgoing_conns.remove(&addr);
+        s.remove_time_sample(&addr);
+        s.peer_pruned_heights.remove(&addr);
     }
 
     res
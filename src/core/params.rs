@@ -0,0 +1,214 @@
+use crate::core::types::Block;
+use serde::{Deserialize, Serialize};
+
+/// Consensus parameters carried by the chain (monetary policy, block limits).
+///
+/// Stored in the chain file and read field by field from genesis files; every field has a
+/// serde default matching `Default`, so older chain files keep the original fixed 50-coin
+/// reward for their first `halving_interval` blocks. Chains from before state roots also
+/// predate the coinbase and governance rules; `Chain::load` turns those off for them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChainParams {
+    /// Block subsidy paid by the coinbase at height 1 (before any halving).
+    #[serde(default = "default_initial_subsidy")]
+    pub initial_subsidy: u64,
+
+    /// Number of blocks between subsidy halvings. 0 disables halving.
+    #[serde(default = "default_halving_interval")]
+    pub halving_interval: u64,
+
+    /// Minimum subsidy per block once halvings would drop below it. 0 disables tail emission.
+    #[serde(default)]
+    pub tail_emission: u64,
+
+    /// Hard cap on total issued subsidy. 0 means no explicit cap (the schedule decides).
+    #[serde(default)]
+    pub max_supply: u64,
+
+    /// Maximum block size in bytes, measured on the canonical (JSON) encoding.
+    #[serde(default = "default_max_block_size")]
+    pub max_block_size: usize,
+
+    /// Maximum number of txs per block, coinbase included.
+    #[serde(default = "default_max_block_txs")]
+    pub max_block_txs: usize,
+
+    /// Maximum total `gas_limit` of a block's contract txs.
+    #[serde(default = "default_max_block_gas")]
+    pub max_block_gas: u64,
+
+    /// How far (ms) a block timestamp may run ahead of the node's network-adjusted time.
+    #[serde(default = "default_max_future_drift_ms")]
+    pub max_future_drift_ms: u64,
+
+    /// Every non-genesis block must start with exactly one coinbase paying a valid address.
+    /// Off only for legacy chain files.
+    #[serde(default = "default_strict_coinbase")]
+    pub strict_coinbase: bool,
+
+    /// Blocks a coinbase reward stays unspendable: the reward of block `h` can be spent from
+    /// block `h + coinbase_maturity`. 0 disables maturity.
+    #[serde(default = "default_coinbase_maturity")]
+    pub coinbase_maturity: u64,
+
+    /// Smallest fee a non-coinbase tx may pay.
+    #[serde(default)]
+    pub min_tx_fee: u64,
+
+    /// Blocks after a governance proposal's own during which it can be voted on.
+    /// 0 disables governance proposals.
+    #[serde(default = "default_governance_voting_period")]
+    pub governance_voting_period: u64,
+}
+
+/// Coinbase maturity of new chains. By then the reward's block is behind a checkpoint (one
+/// every 10 blocks), so it can no longer be reorged away.
+pub const DEFAULT_COINBASE_MATURITY: u64 = 10;
+
+/// Governance voting period of new chains.
+pub const DEFAULT_GOVERNANCE_VOTING_PERIOD: u64 = 100;
+
+fn default_initial_subsidy() -> u64 {
+    50
+}
+
+fn default_halving_interval() -> u64 {
+    210_000
+}
+
+fn default_max_block_size() -> usize {
+    1_000_000
+}
+
+fn default_max_block_txs() -> usize {
+    5_000
+}
+
+fn default_max_block_gas() -> u64 {
+    10 * crate::core::vm::MAX_TX_GAS
+}
+
+fn default_max_future_drift_ms() -> u64 {
+    2 * 60 * 60 * 1000
+}
+
+fn default_strict_coinbase() -> bool {
+    true
+}
+
+fn default_coinbase_maturity() -> u64 {
+    DEFAULT_COINBASE_MATURITY
+}
+
+fn default_governance_voting_period() -> u64 {
+    DEFAULT_GOVERNANCE_VOTING_PERIOD
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self {
+            initial_subsidy: default_initial_subsidy(),
+            halving_interval: default_halving_interval(),
+            tail_emission: 0,
+            max_supply: 0,
+            max_block_size: default_max_block_size(),
+            max_block_txs: default_max_block_txs(),
+            max_block_gas: default_max_block_gas(),
+            max_future_drift_ms: default_max_future_drift_ms(),
+            strict_coinbase: default_strict_coinbase(),
+            coinbase_maturity: default_coinbase_maturity(),
+            min_tx_fee: 0,
+            governance_voting_period: default_governance_voting_period(),
+        }
+    }
+}
+
+impl ChainParams {
+    /// Subsidy from the schedule alone (halvings + tail emission), ignoring `max_supply`.
+    fn scheduled_subsidy(&self, height: u64) -> u64 {
+        if height == 0 {
+            return 0;
+        }
+        let era = match self.halving_interval {
+            0 => 0,
+            interval => (height - 1) / interval,
+        };
+        let halved = if era >= 64 {
+            0
+        } else {
+            self.initial_subsidy >> era
+        };
+        halved.max(self.tail_emission)
+    }
+
+    /// Block subsidy (excluding fees) for the block at `height`.
+    ///
+    /// The genesis block (height 0) has no subsidy.
+    pub fn block_subsidy(&self, height: u64) -> u64 {
+        let scheduled = self.scheduled_subsidy(height);
+        if self.max_supply == 0 || height == 0 {
+            return scheduled;
+        }
+        let issued = self.issued_through(height - 1);
+        scheduled.min(self.max_supply.saturating_sub(issued))
+    }
+
+    /// Enforce the consensus block size, tx count and gas limits.
+    pub fn check_block_limits(&self, block: &Block) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            block.txs.len() <= self.max_block_txs,
+            "block has too many txs ({} > max {})",
+            block.txs.len(),
+            self.max_block_txs
+        );
+        let size = block.size();
+        anyhow::ensure!(
+            size <= self.max_block_size,
+            "block too large ({} bytes > max {})",
+            size,
+            self.max_block_size
+        );
+        let gas = block.gas_limit();
+        anyhow::ensure!(
+            gas <= self.max_block_gas,
+            "block asks for too much gas ({} > max {})",
+            gas,
+            self.max_block_gas
+        );
+        Ok(())
+    }
+
+    /// Total subsidy issued by blocks `1..=height`.
+    pub fn issued_through(&self, height: u64) -> u64 {
+        let mut total: u128 = 0;
+        let mut start = 1_u64;
+
+        while start <= height {
+            let reward = self.scheduled_subsidy(start);
+            // Last height of the current era (or `height` if the reward no longer changes).
+            let era_end = if self.halving_interval == 0 || reward == self.tail_emission {
+                height
+            } else {
+                let era = (start - 1) / self.halving_interval;
+                era.saturating_add(1)
+                    .saturating_mul(self.halving_interval)
+                    .min(height)
+            };
+            total += reward as u128 * (era_end - start + 1) as u128;
+            if reward == 0 && self.tail_emission == 0 {
+                break;
+            }
+            start = era_end.saturating_add(1);
+            if era_end == u64::MAX {
+                break;
+            }
+        }
+
+        let total = total.min(u64::MAX as u128) as u64;
+        if self.max_supply == 0 {
+            total
+        } else {
+            total.min(self.max_supply)
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::core::types::Transaction;
+use serde::{Deserialize, Serialize};
+
+/// Local mempool admission policy (not consensus).
+///
+/// Fee rates are in fee units per 1000 bytes of encoded tx (`fee * 1000 / size`).
+///
+/// # Replacement (RBF)
+///
+/// A tx with the same sender and nonce as a pending tx replaces it. Because later nonces from
+/// that sender may depend on the replaced tx (e.g. its amount), they are evicted with it. The
+/// replacement is accepted only if:
+///
+/// 1. its `sequence` is higher than the replaced tx's (opt-in signal);
+/// 2. its fee is strictly higher than the replaced tx's;
+/// 3. its fee rate is at least `min_rbf_bump_pct` percent above the replaced tx's;
+/// 4. its fee covers the fees of every evicted tx plus `incremental_relay_fee_per_kb` for its
+///    own size, so replacements can't be used to relay for free.
+///
+/// # Child pays for parent
+///
+/// Block templates and eviction rate each tx together with its pending ancestors (the sender's
+/// lower nonces), so a high-fee nonce N+1 can get a stuck low-fee nonce N mined.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MempoolPolicy {
+    /// Total encoded size the pool may hold before it evicts.
+    pub max_bytes: usize,
+
+    /// Maximum pending txs per sender. RBF replacements don't count as new txs.
+    pub max_txs_per_sender: usize,
+
+    /// Minimum relay fee rate while the pool is below `fill_threshold_pct`.
+    pub min_relay_fee_per_kb: u64,
+
+    /// Pool usage (percent of `max_bytes`) above which the minimum relay fee starts rising.
+    pub fill_threshold_pct: u8,
+
+    /// Minimum fee-rate increase (percent) of a replacement over the tx it replaces.
+    #[serde(default = "default_min_rbf_bump_pct")]
+    pub min_rbf_bump_pct: u64,
+
+    /// Fee rate a replacement pays for its own relay, on top of the fees it evicts.
+    #[serde(default = "default_incremental_relay_fee_per_kb")]
+    pub incremental_relay_fee_per_kb: u64,
+}
+
+pub const DEFAULT_MAX_MEMPOOL_BYTES: usize = 10 * 1024 * 1024;
+pub const DEFAULT_MAX_TXS_PER_SENDER: usize = 100;
+pub const DEFAULT_MIN_RBF_BUMP_PCT: u64 = 10;
+pub const DEFAULT_INCREMENTAL_RELAY_FEE_PER_KB: u64 = 1;
+
+fn default_min_rbf_bump_pct() -> u64 {
+    DEFAULT_MIN_RBF_BUMP_PCT
+}
+
+fn default_incremental_relay_fee_per_kb() -> u64 {
+    DEFAULT_INCREMENTAL_RELAY_FEE_PER_KB
+}
+
+impl Default for MempoolPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_MEMPOOL_BYTES,
+            max_txs_per_sender: DEFAULT_MAX_TXS_PER_SENDER,
+            min_relay_fee_per_kb: 0,
+            fill_threshold_pct: 50,
+            min_rbf_bump_pct: DEFAULT_MIN_RBF_BUMP_PCT,
+            incremental_relay_fee_per_kb: DEFAULT_INCREMENTAL_RELAY_FEE_PER_KB,
+        }
+    }
+}
+
+/// Fee rate of a tx in fee units per 1000 bytes.
+pub fn fee_per_kb(fee: u64, size: usize) -> u64 {
+    let rate = fee as u128 * 1000 / size.max(1) as u128;
+    rate.min(u64::MAX as u128) as u64
+}
+
+impl MempoolPolicy {
+    /// Minimum relay fee rate for a pool holding `used_bytes`, whose cheapest tx pays
+    /// `lowest_fee_per_kb`.
+    ///
+    /// Below the fill threshold this is `min_relay_fee_per_kb`. Above it, the minimum rises
+    /// linearly until, at a full pool, a new tx must outbid the cheapest one already in it.
+    pub fn dynamic_min_fee_per_kb(&self, used_bytes: usize, lowest_fee_per_kb: u64) -> u64 {
+        let base = self.min_relay_fee_per_kb;
+        let capacity = self.max_bytes.max(1) as u128;
+        let threshold = capacity * self.fill_threshold_pct.min(100) as u128 / 100;
+        let used = (used_bytes as u128).min(capacity);
+        if used <= threshold || capacity == threshold {
+            return base;
+        }
+        let target = lowest_fee_per_kb.max(base) as u128 + 1;
+        let extra = (target - base as u128) * (used - threshold) / (capacity - threshold);
+        (base as u128 + extra).min(u64::MAX as u128) as u64
+    }
+
+    /// Check the replacement rules (see the type docs) for `new` replacing `replaced`, which
+    /// also evicts the sender's later-nonce `descendants`.
+    pub fn check_replacement(
+        &self,
+        new: &Transaction,
+        replaced: &Transaction,
+        descendants: &[&Transaction],
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            new.sequence > replaced.sequence,
+            "replacement tx must have a higher sequence number (existing={} new={})",
+            replaced.sequence,
+            new.sequence
+        );
+        anyhow::ensure!(
+            new.fee > replaced.fee,
+            "replacement tx must have a strictly higher fee (existing={} new={})",
+            replaced.fee,
+            new.fee
+        );
+
+        let old_rate = fee_per_kb(replaced.fee, replaced.size()) as u128;
+        let min_rate = (old_rate * (100 + self.min_rbf_bump_pct as u128)).div_ceil(100);
+        let new_rate = fee_per_kb(new.fee, new.size());
+        anyhow::ensure!(
+            new_rate as u128 >= min_rate,
+            "replacement fee rate {} per kB must be at least {} per kB ({}% bump)",
+            new_rate,
+            min_rate,
+            self.min_rbf_bump_pct
+        );
+
+        let evicted_fees = descendants
+            .iter()
+            .fold(replaced.fee as u128, |acc, t| acc + t.fee as u128);
+        let relay_fee =
+            (self.incremental_relay_fee_per_kb as u128 * new.size() as u128).div_ceil(1000);
+        anyhow::ensure!(
+            new.fee as u128 >= evicted_fees + relay_fee,
+            "replacement fee {} must cover evicted fees {} plus relay fee {} ({} descendants)",
+            new.fee,
+            evicted_fees,
+            relay_fee,
+            descendants.len()
+        );
+        Ok(())
+    }
+}
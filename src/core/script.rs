@@ -0,0 +1,209 @@
+use crate::core::hash::sha256_hex;
+use crate::core::types::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Prefix of addresses that are the hash of a spending condition.
+pub const CONDITION_ADDRESS_PREFIX: &str = "cond-";
+
+/// Deepest nesting of `all`/`any` a condition may use.
+pub const MAX_CONDITION_DEPTH: usize = 8;
+
+/// Most nodes (leaves and combinators) a single condition may contain.
+pub const MAX_CONDITION_NODES: usize = 64;
+
+/// Most keys a `multisig` leaf may list.
+pub const MAX_MULTISIG_KEYS: usize = 16;
+
+/// A spending condition. Funds sent to `Condition::address()` can only be spent by a tx that
+/// reveals the condition in `tx.script` and carries a `tx.witness` satisfying it.
+///
+/// Evaluation only looks at the block height and the witness, and every leaf stays satisfied
+/// once it is, so a tx that is valid at some height is valid at every later one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// Spendable in blocks at `height` or above.
+    After { height: u64 },
+    /// Spendable by revealing a preimage whose SHA-256 (hex) is `sha256`.
+    HashLock { sha256: String },
+    /// Spendable with signatures from at least `threshold` of `pubkeys` (ed25519, hex).
+    Multisig {
+        threshold: usize,
+        pubkeys: Vec<String>,
+    },
+    /// Every sub-condition must hold.
+    All { conditions: Vec<Condition> },
+    /// At least one sub-condition must hold.
+    Any { conditions: Vec<Condition> },
+}
+
+/// Data a spender supplies to satisfy a condition. Not part of the signing payload, so the
+/// signatures can cover the tx itself.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Witness {
+    /// Hash-lock preimages (hex).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preimages: Vec<String>,
+    /// Signatures over `Transaction::signing_bytes`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<WitnessSignature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WitnessSignature {
+    pub pubkey_hex: String,
+    pub signature_b64: String,
+}
+
+/// Whether `address` is a condition hash rather than a plain account.
+pub fn is_condition_address(address: &str) -> bool {
+    address.starts_with(CONDITION_ADDRESS_PREFIX)
+}
+
+impl Condition {
+    /// Parse and check a condition from its JSON form (as carried in `tx.script`).
+    pub fn from_script(script: &str) -> anyhow::Result<Self> {
+        let condition: Self = serde_json::from_str(script)
+            .map_err(|e| anyhow::anyhow!("invalid condition script: {}", e))?;
+        condition.check()?;
+        Ok(condition)
+    }
+
+    /// JSON form for `tx.script`.
+    pub fn to_script(&self) -> String {
+        serde_json::to_string(self).expect("condition serialization")
+    }
+
+    /// Address funds are locked to: `cond-` followed by the SHA-256 of the script.
+    pub fn address(&self) -> String {
+        format!(
+            "{}{}",
+            CONDITION_ADDRESS_PREFIX,
+            sha256_hex(self.to_script().as_bytes())
+        )
+    }
+
+    /// Structural checks: size limits, well-formed hashes and keys, sane thresholds.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let mut nodes = 0;
+        self.check_at(1, &mut nodes)
+    }
+
+    fn check_at(&self, depth: usize, nodes: &mut usize) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            depth <= MAX_CONDITION_DEPTH,
+            "condition nested deeper than {} levels",
+            MAX_CONDITION_DEPTH
+        );
+        *nodes += 1;
+        anyhow::ensure!(
+            *nodes <= MAX_CONDITION_NODES,
+            "condition has more than {} nodes",
+            MAX_CONDITION_NODES
+        );
+        match self {
+            Self::After { .. } => {}
+            Self::HashLock { sha256 } => {
+                anyhow::ensure!(
+                    sha256.len() == 64 && hex::decode(sha256).is_ok(),
+                    "hash_lock needs a 32-byte hex sha256, got {:?}",
+                    sha256
+                );
+            }
+            Self::Multisig { threshold, pubkeys } => {
+                anyhow::ensure!(
+                    !pubkeys.is_empty() && pubkeys.len() <= MAX_MULTISIG_KEYS,
+                    "multisig needs 1..={} keys, got {}",
+                    MAX_MULTISIG_KEYS,
+                    pubkeys.len()
+                );
+                anyhow::ensure!(
+                    (1..=pubkeys.len()).contains(threshold),
+                    "multisig threshold {} out of range 1..={}",
+                    threshold,
+                    pubkeys.len()
+                );
+                let mut seen = HashSet::new();
+                for pk in pubkeys {
+                    crate::core::crypto::verifying_key_from_hex(pk)?;
+                    anyhow::ensure!(seen.insert(pk), "multisig lists key {} twice", pk);
+                }
+            }
+            Self::All { conditions } | Self::Any { conditions } => {
+                anyhow::ensure!(!conditions.is_empty(), "all/any needs sub-conditions");
+                for c in conditions {
+                    c.check_at(depth + 1, nodes)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `witness` satisfies the condition for `tx` in the block at `height`.
+    ///
+    /// Every signature in the witness must verify against the tx; a bad one is an error
+    /// rather than being skipped.
+    pub fn evaluate(
+        &self,
+        tx: &Transaction,
+        witness: &Witness,
+        height: u64,
+    ) -> anyhow::Result<bool> {
+        let msg = tx.signing_bytes();
+        let mut signers = HashSet::new();
+        for ws in &witness.signatures {
+            let vk = crate::core::crypto::verifying_key_from_hex(&ws.pubkey_hex)?;
+            crate::core::crypto::verify_bytes(&vk, &msg, &ws.signature_b64)
+                .map_err(|e| anyhow::anyhow!("witness signature by {}: {}", ws.pubkey_hex, e))?;
+            signers.insert(ws.pubkey_hex.as_str());
+        }
+        let mut hashes = HashSet::new();
+        for preimage in &witness.preimages {
+            let bytes = hex::decode(preimage)
+                .map_err(|e| anyhow::anyhow!("witness preimage is not hex: {}", e))?;
+            hashes.insert(sha256_hex(&bytes));
+        }
+        Ok(self.holds(height, &signers, &hashes))
+    }
+
+    fn holds(&self, height: u64, signers: &HashSet<&str>, hashes: &HashSet<String>) -> bool {
+        match self {
+            Self::After { height: h } => height >= *h,
+            Self::HashLock { sha256 } => hashes.contains(&sha256.to_ascii_lowercase()),
+            Self::Multisig { threshold, pubkeys } => {
+                pubkeys
+                    .iter()
+                    .filter(|pk| signers.contains(pk.as_str()))
+                    .count()
+                    >= *threshold
+            }
+            Self::All { conditions } => conditions.iter().all(|c| c.holds(height, signers, hashes)),
+            Self::Any { conditions } => conditions.iter().any(|c| c.holds(height, signers, hashes)),
+        }
+    }
+}
+
+/// Check that `tx` may spend from its condition-hash sender at `height`: the script must hash
+/// to `tx.from` and the witness must satisfy it.
+pub fn verify_spend(tx: &Transaction, height: u64) -> anyhow::Result<()> {
+    let script = tx
+        .script
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("spend from {} needs the condition script", tx.from))?;
+    let condition = Condition::from_script(script)?;
+    anyhow::ensure!(
+        condition.address() == tx.from,
+        "script hashes to {}, not the sender {}",
+        condition.address(),
+        tx.from
+    );
+    let witness = tx.witness.clone().unwrap_or_default();
+    anyhow::ensure!(
+        condition.evaluate(tx, &witness, height)?,
+        "witness does not satisfy the condition of {} at height {}",
+        tx.from,
+        height
+    );
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use crate::core::crypto::verify_batch;
+use crate::core::types::Transaction;
+use std::collections::{HashMap, VecDeque};
+
+/// Default number of verified signatures remembered by a `SignatureCache`.
+pub const DEFAULT_SIG_CACHE_CAPACITY: usize = 50_000;
+
+/// Remembers signatures that already passed verification, keyed by tx id.
+///
+/// The tx id is computed over the signing payload only, so the key and signature are stored
+/// too and a lookup only hits when both match exactly.
+#[derive(Debug, Clone)]
+pub struct SignatureCache {
+    entries: HashMap<String, (String, String)>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_SIG_CACHE_CAPACITY)
+    }
+}
+
+impl SignatureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Returns true if this exact (tx id, key, signature) triple was verified before.
+    pub fn contains(&self, tx: &Transaction) -> bool {
+        match (&tx.pubkey_hex, &tx.signature_b64) {
+            (Some(pk), Some(sig)) => self
+                .entries
+                .get(&tx.id())
+                .is_some_and(|(cached_pk, cached_sig)| cached_pk == pk && cached_sig == sig),
+            _ => false,
+        }
+    }
+
+    /// Records a tx whose signature has been verified. Unsigned txs are ignored.
+    ///
+    /// When the cache is full the oldest entries are dropped first.
+    pub fn insert(&mut self, tx: &Transaction) {
+        let (Some(pk), Some(sig)) = (&tx.pubkey_hex, &tx.signature_b64) else {
+            return;
+        };
+        let id = tx.id();
+        if self
+            .entries
+            .insert(id.clone(), (pk.clone(), sig.clone()))
+            .is_some()
+        {
+            return;
+        }
+        self.order.push_back(id);
+        while self.entries.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(old) => {
+                    self.entries.remove(&old);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn remove(&mut self, tx_id: &str) {
+        if self.entries.remove(tx_id).is_some() {
+            self.order.retain(|id| id != tx_id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Verify the signatures of a list of transactions (usually a block body) in one batch.
+///
+/// - Unsigned txs and txs with `is_verifiable=false` are skipped, matching `validate_accept`.
+/// - Txs found in `cache` are skipped.
+/// - If the batch fails, each remaining tx is verified on its own so the error names the bad one.
+pub fn verify_signatures_batch(
+    txs: &[Transaction],
+    cache: Option<&SignatureCache>,
+) -> anyhow::Result<()> {
+    let mut indices = Vec::new();
+    let mut messages = Vec::new();
+    let mut signatures = Vec::new();
+    let mut keys = Vec::new();
+
+    for (i, tx) in txs.iter().enumerate() {
+        if !tx.is_verifiable || cache.is_some_and(|c| c.contains(tx)) {
+            continue;
+        }
+        let parts = tx
+            .signature_parts()
+            .map_err(|e| anyhow::anyhow!("invalid signature in tx index={i}: {e}"))?;
+        if let Some((vk, sig)) = parts {
+            indices.push(i);
+            messages.push(tx.signing_bytes());
+            signatures.push(sig);
+            keys.push(vk);
+        }
+    }
+
+    if indices.is_empty() {
+        return Ok(());
+    }
+
+    let msg_refs: Vec<&[u8]> = messages.iter().map(|m| m.as_slice()).collect();
+    if verify_batch(&msg_refs, &signatures, &keys).is_ok() {
+        return Ok(());
+    }
+
+    // Batch failed: find the offending tx.
+    for i in indices {
+        txs[i]
+            .verify_signature_if_present()
+            .map_err(|e| anyhow::anyhow!("invalid signature in tx index={i}: {e}"))?;
+    }
+    Ok(())
+}
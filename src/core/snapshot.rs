@@ -0,0 +1,207 @@
+use crate::core::chain::{Chain, SnapshotBase};
+use crate::core::hash::sha256_hex;
+use crate::core::params::ChainParams;
+use crate::core::state::State;
+use crate::core::state_tree::state_root;
+use crate::core::types::{Block, BlockHeader};
+use anyhow::Context;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Snapshot format version written by `Snapshot::save`. Version 2 hashes the parameters and
+/// difficulty too.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Ledger state at a checkpointed height plus the header chain leading to it, so a node can
+/// start there instead of replaying every block from genesis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub network: String,
+    pub chain_id: u32,
+    pub pow_difficulty: usize,
+    pub params: ChainParams,
+    /// Full genesis block (its allocations are needed for supply accounting).
+    pub genesis: Block,
+    /// Headers of blocks `1..=height`.
+    pub headers: Vec<BlockHeader>,
+    /// State after block `height`.
+    pub state: State,
+}
+
+/// On-disk layout: gzip-compressed JSON of the snapshot and its hash.
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    hash: String,
+    snapshot: Snapshot,
+}
+
+impl Snapshot {
+    /// Snapshot of `chain` at `height`, which must be a checkpoint.
+    pub fn from_chain(chain: &Chain, height: usize) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            height <= chain.height(),
+            "height {} beyond tip {}",
+            height,
+            chain.height()
+        );
+        let block_hash = chain.blocks[height].header.hash();
+        anyhow::ensure!(
+            chain.checkpoints.get(&height) == Some(&block_hash),
+            "height {} is not a checkpoint",
+            height
+        );
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            network: chain.network.clone(),
+            chain_id: chain.chain_id,
+            pow_difficulty: chain.pow_difficulty,
+            params: chain.params.clone(),
+            genesis: chain.blocks[0].clone(),
+            headers: chain.blocks[1..=height]
+                .iter()
+                .map(|b| b.header.clone())
+                .collect(),
+            state: chain.compute_state_at(height)?,
+        })
+    }
+
+    pub fn height(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Hash of the block the snapshot ends at.
+    pub fn block_hash(&self) -> String {
+        self.headers.last().unwrap_or(&self.genesis.header).hash()
+    }
+
+    /// Snapshot hash: commits to the network, the consensus parameters and difficulty, the
+    /// block at `height` and the state root. The header chain is covered through the block
+    /// hash.
+    pub fn hash(&self) -> String {
+        let committed = serde_json::json!({
+            "version": self.version,
+            "network": self.network,
+            "chain_id": self.chain_id,
+            "pow_difficulty": self.pow_difficulty,
+            "params": self.params,
+            "genesis_hash": self.genesis.header.hash(),
+            "height": self.height(),
+            "block_hash": self.block_hash(),
+            "state_root": state_root(&self.state),
+        });
+        sha256_hex(committed.to_string().as_bytes())
+    }
+
+    /// Write the compressed snapshot file. Returns the snapshot hash.
+    pub fn save(&self, path: &Path) -> anyhow::Result<String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let hash = self.hash();
+        let file = SnapshotFile {
+            hash: hash.clone(),
+            snapshot: self.clone(),
+        };
+        let mut encoder = GzEncoder::new(fs::File::create(path)?, Compression::default());
+        encoder.write_all(&serde_json::to_vec(&file)?)?;
+        encoder.finish()?;
+        Ok(hash)
+    }
+
+    /// Read a snapshot file and check its stored hash.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut json = Vec::new();
+        GzDecoder::new(fs::File::open(path)?)
+            .read_to_end(&mut json)
+            .context("snapshot is not a gzip file")?;
+        let file: SnapshotFile = serde_json::from_slice(&json)?;
+        anyhow::ensure!(
+            file.snapshot.version == SNAPSHOT_VERSION,
+            "unsupported snapshot version {}",
+            file.snapshot.version
+        );
+        let hash = file.snapshot.hash();
+        anyhow::ensure!(
+            hash == file.hash,
+            "snapshot hash mismatch: file says {} but contents hash to {}",
+            file.hash,
+            hash
+        );
+        Ok(file.snapshot)
+    }
+
+    /// Check the snapshot is for `local`'s chain: same genesis, consensus parameters and
+    /// difficulty. Headers don't commit to the latter two, so a local chain is the only
+    /// thing they can be checked against.
+    pub fn check_local(&self, local: &Chain) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            local.genesis_hash() == self.genesis.header.hash(),
+            "snapshot is for a different chain (genesis {})",
+            self.genesis.header.hash()
+        );
+        anyhow::ensure!(
+            local.params == self.params,
+            "snapshot parameters differ from the local chain's"
+        );
+        anyhow::ensure!(
+            local.pow_difficulty == self.pow_difficulty,
+            "snapshot difficulty {} differs from the local chain's {}",
+            self.pow_difficulty,
+            local.pow_difficulty
+        );
+        Ok(())
+    }
+
+    /// Build a chain from the snapshot after checking it against `trusted_block_hash`, the
+    /// checkpointed hash of the block at the snapshot height.
+    ///
+    /// Headers are checked for linkage, PoW and timestamps, and the state must match the
+    /// `state_root` of the last header, so chains without state roots cannot be imported.
+    pub fn into_chain(self, trusted_block_hash: &str) -> anyhow::Result<Chain> {
+        let height = self.height();
+        let block_hash = self.block_hash();
+        anyhow::ensure!(
+            block_hash == trusted_block_hash,
+            "snapshot block at height {} is {}, not the trusted checkpoint {}",
+            height,
+            block_hash,
+            trusted_block_hash
+        );
+        anyhow::ensure!(
+            !self.genesis.header.state_root.is_empty(),
+            "chain does not commit to state roots; its snapshots cannot be verified"
+        );
+
+        self.state
+            .check_contracts()
+            .and_then(|_| self.state.check_governance())
+            .and_then(|_| self.state.check_assets())
+            .context("invalid snapshot")?;
+
+        let mut chain = Chain::from_genesis_block(self.genesis);
+        chain.network = self.network;
+        chain.chain_id = self.chain_id;
+        chain.pow_difficulty = self.pow_difficulty;
+        chain.params = self.params;
+        for header in self.headers {
+            chain.blocks.push(Block {
+                header,
+                txs: vec![],
+            });
+        }
+        chain.rebuild_block_index();
+        chain.checkpoints.insert(height, block_hash);
+        chain.snapshot_base = Some(SnapshotBase {
+            height,
+            state: self.state,
+        });
+        chain.validate().context("invalid snapshot")?;
+        Ok(chain)
+    }
+}
@@ -1,16 +1,120 @@
-use crate::core::types::{Block, Transaction};
+use crate::core::asset::{ASSET_REGISTRY_ADDRESS, AssetDefinition, AssetInfo, registry_hash};
+use crate::core::governance::{
+    Ballot, GOVERNANCE_ADDRESS, Governance, GovernanceOp, MAX_OPEN_PROPOSALS_PER_SENDER, Proposal,
+};
+use crate::core::params::ChainParams;
+use crate::core::script::{is_condition_address, verify_spend};
+use crate::core::types::{Block, Transaction, is_valid_address};
+use crate::core::vm::{CallContext, Contract, ContractOp, DEPLOY_GAS_PER_OP, contract_address};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct Account {
     pub balance: u64,
     pub nonce: u64,
+    /// Coinbase rewards (included in `balance`) that can't be spent yet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub immature: Vec<ImmatureReward>,
+    /// SHA-256 (hex) of the code, for contract accounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<String>,
+    /// Hash of the contract's storage (see `Contract::storage_root`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_root: Option<String>,
+    /// Balances of user-defined assets, by asset id. Zero balances are removed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, u64>,
+}
+
+/// A coinbase reward locked until block `spendable_at` (see `ChainParams::coinbase_maturity`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImmatureReward {
+    pub spendable_at: u64,
+    pub amount: u64,
+}
+
+impl Account {
+    /// Part of the balance a tx in the block at `height` can't spend yet.
+    pub fn locked_at(&self, height: u64) -> u64 {
+        self.immature
+            .iter()
+            .filter(|r| r.spendable_at > height)
+            .fold(0_u64, |acc, r| acc.saturating_add(r.amount))
+    }
+
+    /// Balance a tx in the block at `height` can spend.
+    pub fn spendable_at(&self, height: u64) -> u64 {
+        self.balance.saturating_sub(self.locked_at(height))
+    }
+
+    pub fn asset_balance(&self, asset_id: &str) -> u64 {
+        self.assets.get(asset_id).copied().unwrap_or(0)
+    }
+}
+
+/// Coins a block creates and destroys. Coinbase (and genesis) amounts are minted; every other
+/// tx's fee is burned from its sender, and the coinbase re-mints it for the miner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SupplyChange {
+    pub minted: u64,
+    pub burned: u64,
+}
+
+impl SupplyChange {
+    pub fn of_txs(txs: &[Transaction]) -> anyhow::Result<Self> {
+        let mut change = Self::default();
+        for tx in txs {
+            if tx.is_coinbase() {
+                change.minted = change
+                    .minted
+                    .checked_add(tx.amount)
+                    .ok_or_else(|| anyhow::anyhow!("minted amount overflows u64"))?;
+            } else {
+                change.burned = change
+                    .burned
+                    .checked_add(tx.fee)
+                    .ok_or_else(|| anyhow::anyhow!("total fees overflow u64"))?;
+            }
+        }
+        Ok(change)
+    }
+
+    /// Combined change of `self` followed by `other`.
+    pub fn checked_add(self, other: Self) -> anyhow::Result<Self> {
+        Ok(Self {
+            minted: self
+                .minted
+                .checked_add(other.minted)
+                .ok_or_else(|| anyhow::anyhow!("minted amount overflows u64"))?,
+            burned: self
+                .burned
+                .checked_add(other.burned)
+                .ok_or_else(|| anyhow::anyhow!("total fees overflow u64"))?,
+        })
+    }
+
+    /// Supply left once the burned coins are taken out of the minted ones.
+    pub fn net(&self) -> Option<u64> {
+        self.minted.checked_sub(self.burned)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct State {
     pub accounts: HashMap<String, Account>,
+    /// Code and storage of deployed contracts, by address. Each has an account whose
+    /// `code_hash` and `storage_root` commit to it.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub contracts: HashMap<String, Contract>,
+    /// Open proposals and passed changes. The `GOVERNANCE_ADDRESS` account's `storage_root`
+    /// commits to it.
+    #[serde(default, skip_serializing_if = "Governance::is_empty")]
+    pub governance: Governance,
+    /// Registered assets, by id. The `ASSET_REGISTRY_ADDRESS` account's `storage_root` commits
+    /// to it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, AssetInfo>,
 }
 
 impl State {
@@ -22,19 +126,154 @@ impl State {
         self.accounts.get(address).map(|a| a.balance).unwrap_or(0)
     }
 
+    /// Balance of `address` that a tx in the block at `height` can spend.
+    pub fn get_spendable(&self, address: &str, height: u64) -> u64 {
+        self.accounts
+            .get(address)
+            .map_or(0, |a| a.spendable_at(height))
+    }
+
+    /// Balance of `address` in the asset `asset_id`.
+    pub fn get_asset_balance(&self, address: &str, asset_id: &str) -> u64 {
+        self.accounts
+            .get(address)
+            .map_or(0, |a| a.asset_balance(asset_id))
+    }
+
     pub fn get_nonce(&self, address: &str) -> u64 {
         self.accounts.get(address).map(|a| a.nonce).unwrap_or(0)
     }
 
+    /// Circulating supply: the sum of all account balances.
+    pub fn total_supply(&self) -> u64 {
+        self.accounts
+            .values()
+            .fold(0_u64, |acc, a| acc.saturating_add(a.balance))
+    }
+
+    /// Exact sum of all balances; unlike `total_supply` it cannot saturate.
+    fn exact_supply(&self) -> u128 {
+        self.accounts.values().map(|a| a.balance as u128).sum()
+    }
+
+    /// Check that `contracts` matches what the accounts commit to: each contract's code and
+    /// storage hash to its account's `code_hash` and `storage_root`, and every account with
+    /// code has a contract. The state root only covers the hashes.
+    pub fn check_contracts(&self) -> anyhow::Result<()> {
+        for (address, contract) in &self.contracts {
+            let account = self.accounts.get(address);
+            anyhow::ensure!(
+                account.and_then(|a| a.code_hash.as_deref()) == Some(&contract.code_hash()),
+                "contract {} code does not match its account",
+                address
+            );
+            anyhow::ensure!(
+                account.and_then(|a| a.storage_root.as_deref()) == Some(&contract.storage_root()),
+                "contract {} storage does not match its account",
+                address
+            );
+        }
+        for (address, account) in &self.accounts {
+            anyhow::ensure!(
+                account.code_hash.is_none() || self.contracts.contains_key(address),
+                "account {} has code but no contract",
+                address
+            );
+        }
+        Ok(())
+    }
+
+    /// Check that `governance` matches the governance account's `storage_root`. That account
+    /// exists once the first governance tx is applied; before then there is nothing to commit.
+    pub fn check_governance(&self) -> anyhow::Result<()> {
+        match self.accounts.get(GOVERNANCE_ADDRESS) {
+            Some(account) => {
+                anyhow::ensure!(
+                    account.code_hash.is_none()
+                        && account.storage_root.as_deref() == Some(&self.governance.hash()),
+                    "governance state does not match its account"
+                );
+            }
+            None => anyhow::ensure!(
+                self.governance.is_empty(),
+                "governance state without a governance account"
+            ),
+        }
+        Ok(())
+    }
+
+    /// Check that `assets` matches the registry account's `storage_root`, that accounts only
+    /// hold registered assets, and that each asset's balances add up to its supply.
+    pub fn check_assets(&self) -> anyhow::Result<()> {
+        match self.accounts.get(ASSET_REGISTRY_ADDRESS) {
+            Some(account) => anyhow::ensure!(
+                account.code_hash.is_none()
+                    && account.storage_root.as_deref() == Some(&registry_hash(&self.assets)),
+                "asset registry does not match its account"
+            ),
+            None => anyhow::ensure!(
+                self.assets.is_empty(),
+                "asset registry without a registry account"
+            ),
+        }
+        let mut held: BTreeMap<&str, u128> = BTreeMap::new();
+        for (address, account) in &self.accounts {
+            for (asset_id, amount) in &account.assets {
+                anyhow::ensure!(
+                    self.assets.contains_key(asset_id),
+                    "account {} holds unregistered asset {}",
+                    address,
+                    asset_id
+                );
+                *held.entry(asset_id).or_default() += *amount as u128;
+            }
+        }
+        for (asset_id, info) in &self.assets {
+            self.check_asset_supply(asset_id, info, held.get(asset_id.as_str()).copied())?;
+        }
+        Ok(())
+    }
+
+    /// `held` (the sum of all balances of `asset_id`) must equal its supply.
+    fn check_asset_supply(
+        &self,
+        asset_id: &str,
+        info: &AssetInfo,
+        held: Option<u128>,
+    ) -> anyhow::Result<()> {
+        let held = held.unwrap_or_else(|| {
+            self.accounts
+                .values()
+                .map(|a| a.asset_balance(asset_id) as u128)
+                .sum()
+        });
+        anyhow::ensure!(
+            held == info.supply as u128,
+            "asset {} supply not conserved: balances add up to {} (supply {})",
+            asset_id,
+            held,
+            info.supply
+        );
+        Ok(())
+    }
+
+    /// Consensus parameters for the block at `height`: `base` with the governance changes
+    /// active by then.
+    pub fn params_at(&self, base: &ChainParams, height: u64) -> ChainParams {
+        self.governance.params_at(base, height)
+    }
+
     /// Apply a block to the state.
     ///
-    /// If any transaction is invalid (e.g. insufficient balance), returns an error
-    /// and the state remains unchanged (atomic application is the caller's responsibility
-    /// if they modify `self` directly, but here we clone inside or assume sequential checks).
-    ///
-    /// For this simple implementation, we'll check everything before mutating.
-    pub fn apply_block(&mut self, block: &Block, height: usize) -> anyhow::Result<()> {
-        self.apply_block_txs(&block.txs, height)
+    /// If any transaction is invalid (e.g. insufficient balance or a balance overflow),
+    /// returns an error and the state remains unchanged.
+    pub fn apply_block(
+        &mut self,
+        block: &Block,
+        height: usize,
+        params: &ChainParams,
+    ) -> anyhow::Result<()> {
+        self.apply_block_txs(&block.txs, height, params)
     }
 
     pub fn validate_transaction(&self, tx: &Transaction, height: usize) -> anyhow::Result<()> {
@@ -43,38 +282,270 @@ impl State {
         self.validate_tx(tx, height, 0, 0)
     }
 
-    pub fn apply_block_txs(&mut self, txs: &[Transaction], height: usize) -> anyhow::Result<()> {
+    /// Like `validate_transaction`, for a tx that follows `pending` (its sender's earlier
+    /// mempool txs, in nonce order). Each is applied to a copy first, so `tx` sees their
+    /// nonces and spends.
+    pub fn validate_transaction_after(
+        &self,
+        pending: &[Transaction],
+        tx: &Transaction,
+        height: usize,
+    ) -> anyhow::Result<()> {
         use anyhow::Context;
 
-        let block_reward = 50;
-        let total_fees: u64 = txs.iter().map(|tx| tx.fee).sum();
+        if pending.is_empty() {
+            return self.validate_transaction(tx, height);
+        }
+        let mut state = self.clone();
+        for p in pending {
+            state
+                .validate_transaction(p, height)
+                .and_then(|_| state.apply_tx(p, height as u64, None))
+                .with_context(|| format!("pending tx nonce={}", p.nonce))?;
+        }
+        state.validate_transaction(tx, height)
+    }
 
-        // 1. Check if the block has a coinbase transaction at index 0
-        if let Some(first_tx) = txs.first() {
-            if !first_tx.is_coinbase() && height > 0 {
-                println!("Warning: Block {} has no coinbase at index 0", height);
+    pub fn apply_block_txs(
+        &mut self,
+        txs: &[Transaction],
+        height: usize,
+        params: &ChainParams,
+    ) -> anyhow::Result<()> {
+        if height == 0 {
+            return self.apply_genesis_txs(txs);
+        }
+        let params = &self.params_at(params, height as u64);
+
+        let block_reward = params.block_subsidy(height as u64);
+        let total_fees = txs.iter().try_fold(0_u64, |acc, tx| {
+            acc.checked_add(tx.fee)
+                .ok_or_else(|| anyhow::anyhow!("total fees overflow u64"))
+        })?;
+
+        // 1. Check the block has a coinbase transaction at index 0
+        match txs.first() {
+            Some(first_tx) if first_tx.is_coinbase() => {
+                anyhow::ensure!(
+                    !params.strict_coinbase || is_valid_address(&first_tx.to),
+                    "Block {} coinbase pays an invalid address: {:?}",
+                    height,
+                    first_tx.to
+                );
             }
-        } else if height > 0 {
-            println!("Warning: Block {} has no transactions", height);
+            // Legacy chains (see `Chain::load`) may hold coinbase-less blocks.
+            _ => anyhow::ensure!(
+                !params.strict_coinbase,
+                "Block {} has no coinbase at index 0",
+                height
+            ),
         }
 
-        // 2. Verify all transactions against current state (read-only check)
-        for (i, tx) in txs.iter().enumerate() {
-            if i > 0 && tx.is_coinbase() {
+        // 2. Validate each tx against the state left by the ones before it, so a sender's
+        //    later txs see its earlier nonces and spends, and apply them to a copy, so a
+        //    failure leaves `self` untouched
+        for (i, tx) in txs.iter().enumerate().skip(1) {
+            if tx.is_coinbase() {
                 anyhow::bail!("Coinbase tx at index {} invalid (only index 0 allowed)", i);
             }
-            self.validate_tx(tx, height, block_reward, total_fees)
-                .with_context(|| format!("tx index={}", i))?;
         }
+        self.apply_txs_checked(
+            txs,
+            height as u64,
+            params.coinbase_maturity,
+            params.governance_voting_period,
+            |state, tx| {
+                state
+                    .validate_tx(tx, height, block_reward, total_fees)
+                    .and_then(|_| state.validate_governed(tx, height as u64, params))
+            },
+        )
+    }
 
-        // 3. Apply transactions (mutate)
-        for tx in txs {
-            self.apply_tx(tx);
+    /// Rules that depend on the parameters in force at `height` (see `params_at`): the minimum
+    /// fee, and governance ops checked against the open proposals.
+    pub fn validate_governed(
+        &self,
+        tx: &Transaction,
+        height: u64,
+        params: &ChainParams,
+    ) -> anyhow::Result<()> {
+        if tx.is_coinbase() {
+            return Ok(());
+        }
+        anyhow::ensure!(
+            tx.fee >= params.min_tx_fee,
+            "fee {} is below the minimum {}",
+            tx.fee,
+            params.min_tx_fee
+        );
+        // Votes weigh balances, so only the holder can cast them. The signature itself is
+        // verified with the block's, which skips txs with `is_verifiable` off.
+        anyhow::ensure!(
+            tx.governance.is_none()
+                || (tx.is_verifiable
+                    && tx.signature_b64.is_some()
+                    && tx.pubkey_hex.as_deref() == Some(&tx.from)),
+            "governance txs must be signed by their sender ({})",
+            tx.from
+        );
+        match &tx.governance {
+            Some(GovernanceOp::Propose {
+                change,
+                activation_height,
+            }) => {
+                anyhow::ensure!(
+                    params.governance_voting_period > 0,
+                    "governance is disabled on this chain"
+                );
+                anyhow::ensure!(
+                    self.governance.open_proposals_by(&tx.from) < MAX_OPEN_PROPOSALS_PER_SENDER,
+                    "{} already has {} open proposals",
+                    tx.from,
+                    MAX_OPEN_PROPOSALS_PER_SENDER
+                );
+                change.check(params)?;
+                let voting_ends = height
+                    .checked_add(params.governance_voting_period)
+                    .ok_or_else(|| anyhow::anyhow!("voting period overflows u64"))?;
+                anyhow::ensure!(
+                    *activation_height > voting_ends,
+                    "proposal must activate after voting ends at height {} (got {})",
+                    voting_ends,
+                    activation_height
+                );
+            }
+            Some(GovernanceOp::Vote { proposal_id, .. }) => {
+                let proposal = self
+                    .governance
+                    .proposals
+                    .get(proposal_id)
+                    .ok_or_else(|| anyhow::anyhow!("no open proposal {}", proposal_id))?;
+                let weight = self.governance.balance_at(
+                    &tx.from,
+                    proposal.snapshot_height,
+                    self.voting_balance(&tx.from),
+                );
+                anyhow::ensure!(
+                    weight > 0,
+                    "{} has no voting weight on proposal {}",
+                    tx.from,
+                    proposal_id
+                );
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Apply txs of the block at `height` atomically, each checked by `validate` against the
+    /// state left by the txs before it, then check the block conserved supply.
+    ///
+    /// Rewards that mature at `height` are unlocked first, and a coinbase is locked for
+    /// `maturity` blocks. Governance ops are applied once balances have settled, so a
+    /// proposal's weights are the balances after its block, and it stays open for
+    /// `voting_period` more blocks. Proposals whose voting ends at `height` are then tallied.
+    fn apply_txs_checked(
+        &mut self,
+        txs: &[Transaction],
+        height: u64,
+        maturity: u64,
+        voting_period: u64,
+        validate: impl Fn(&State, &Transaction) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        use anyhow::Context;
+
+        let change = SupplyChange::of_txs(txs)?;
+        let mut next = self.clone();
+        for account in next.accounts.values_mut() {
+            account.immature.retain(|r| r.spendable_at > height);
+        }
+        let lock_until = match maturity {
+            0 => None,
+            m => Some(
+                height
+                    .checked_add(m)
+                    .ok_or_else(|| anyhow::anyhow!("coinbase maturity overflows u64"))?,
+            ),
+        };
+        for (i, tx) in txs.iter().enumerate() {
+            validate(&next, tx)
+                .and_then(|_| next.apply_tx(tx, height, lock_until))
+                .with_context(|| format!("tx index={}", i))?;
+        }
+        if !next.governance.proposals.is_empty() {
+            next.record_balance_changes(self, height);
+        }
+        for (i, tx) in txs.iter().enumerate() {
+            if let Some(op) = &tx.governance {
+                next.apply_governance(tx, op, height, voting_period)
+                    .with_context(|| format!("tx index={}", i))?;
+            }
+        }
+        // Each asset the block touched must still add up to its supply.
+        let touched: std::collections::BTreeSet<&str> = txs
+            .iter()
+            .filter_map(|tx| {
+                tx.asset
+                    .as_deref()
+                    .or(tx.create_asset.as_ref().map(|d| d.asset_id.as_str()))
+            })
+            .collect();
+        for asset_id in &touched {
+            let info = next
+                .assets
+                .get(*asset_id)
+                .ok_or_else(|| anyhow::anyhow!("unknown asset {}", asset_id))?;
+            next.check_asset_supply(asset_id, info, None)?;
+        }
+        if txs.iter().any(|tx| tx.create_asset.is_some())
+            && let Some(account) = next.accounts.get_mut(ASSET_REGISTRY_ADDRESS)
+        {
+            account.storage_root = Some(registry_hash(&next.assets));
+        }
+        next.governance.close_voting(height);
+        if let Some(account) = next.accounts.get_mut(GOVERNANCE_ADDRESS) {
+            account.storage_root = Some(next.governance.hash());
         }
 
+        let before = self.exact_supply();
+        let after = next.exact_supply();
+        let expected = (before + change.minted as u128).checked_sub(change.burned as u128);
+        anyhow::ensure!(
+            expected == Some(after),
+            "supply not conserved: {} + {} minted - {} burned != {}",
+            before,
+            change.minted,
+            change.burned,
+            after
+        );
+        anyhow::ensure!(
+            after <= u64::MAX as u128,
+            "total supply {} overflows u64",
+            after
+        );
+        *self = next;
         Ok(())
     }
 
+    /// Genesis allocations: every genesis tx must be a SYSTEM transfer with nonce 0.
+    /// There is no block reward to check them against.
+    fn apply_genesis_txs(&mut self, txs: &[Transaction]) -> anyhow::Result<()> {
+        let mut total: u64 = 0;
+        for (i, tx) in txs.iter().enumerate() {
+            anyhow::ensure!(
+                tx.is_coinbase() && tx.nonce == 0,
+                "genesis tx index={} must be a SYSTEM allocation with nonce 0",
+                i
+            );
+            total = total
+                .checked_add(tx.amount)
+                .ok_or_else(|| anyhow::anyhow!("genesis allocations overflow u64"))?;
+        }
+
+        self.apply_txs_checked(txs, 0, 0, 0, |_, _| Ok(()))
+    }
+
     fn validate_tx(
         &self,
         tx: &Transaction,
@@ -85,6 +556,17 @@ impl State {
         if tx.is_coinbase() {
             // Coinbase validation rules:
             // - Must be the first tx in block (checked by apply_block loop index)
+            // - Can't deploy or call a contract, or take part in governance
+            anyhow::ensure!(tx.contract.is_none(), "coinbase can't carry a contract op");
+            anyhow::ensure!(
+                tx.governance.is_none(),
+                "coinbase can't carry a governance op"
+            );
+            // - Only pays native coin
+            anyhow::ensure!(
+                tx.asset.is_none() && tx.create_asset.is_none(),
+                "coinbase can't transfer or create an asset"
+            );
             // - Nonce must match block height
             if tx.nonce != height as u64 {
                 anyhow::bail!(
@@ -94,7 +576,9 @@ impl State {
                 );
             }
             // - Reward must match block_reward + fees
-            let expected_reward = block_reward + total_fees;
+            let expected_reward = block_reward
+                .checked_add(total_fees)
+                .ok_or_else(|| anyhow::anyhow!("Block reward + fees overflow"))?;
             if tx.amount != expected_reward {
                 anyhow::bail!(
                     "Invalid coinbase reward: expected {}, got {}",
@@ -107,6 +591,13 @@ impl State {
 
         let sender = self.accounts.get(&tx.from).cloned().unwrap_or_default();
 
+        // Contracts hold no key, so nothing can sign for their balance
+        anyhow::ensure!(
+            sender.code_hash.is_none(),
+            "{} is a contract and can't send txs",
+            tx.from
+        );
+
         // Nonce check
         if tx.nonce != sender.nonce {
             anyhow::bail!(
@@ -117,23 +608,79 @@ impl State {
             );
         }
 
-        // Balance check
+        // Balance check (fees are always native coin)
         let total_needed = tx
-            .amount
-            .checked_add(tx.fee)
+            .native_cost()
             .ok_or_else(|| anyhow::anyhow!("Amount + Fee overflow for {}", tx.from))?;
 
-        if sender.balance < total_needed {
+        let spendable = sender.spendable_at(height as u64);
+        if spendable < total_needed {
             anyhow::bail!(
                 "Insufficient balance for {}: has {}, needs {} (amount={} fee={})",
                 tx.from,
-                sender.balance,
+                spendable,
                 total_needed,
                 tx.amount,
                 tx.fee
             );
         }
 
+        // Asset checks
+        if let Some(asset_id) = &tx.asset {
+            anyhow::ensure!(
+                self.assets.contains_key(asset_id),
+                "unknown asset {}",
+                asset_id
+            );
+            let held = sender.asset_balance(asset_id);
+            anyhow::ensure!(
+                held >= tx.amount,
+                "Insufficient {} balance for {}: has {}, needs {}",
+                asset_id,
+                tx.from,
+                held,
+                tx.amount
+            );
+        }
+        if let Some(definition) = &tx.create_asset {
+            anyhow::ensure!(
+                !self.assets.contains_key(&definition.asset_id),
+                "asset {} already exists",
+                definition.asset_id
+            );
+        }
+
+        // Spending condition check
+        if is_condition_address(&tx.from) {
+            verify_spend(tx, height as u64)?;
+        }
+
+        // Contract target check
+        match &tx.contract {
+            Some(ContractOp::Deploy { .. }) => {
+                let expected = contract_address(&tx.from, tx.nonce);
+                anyhow::ensure!(
+                    tx.to == expected,
+                    "deploy must target {} (got {})",
+                    expected,
+                    tx.to
+                );
+                anyhow::ensure!(
+                    !self.contracts.contains_key(&tx.to),
+                    "contract {} already exists",
+                    tx.to
+                );
+            }
+            Some(ContractOp::Call { .. }) => {
+                anyhow::ensure!(
+                    self.contracts.contains_key(&tx.to),
+                    "no contract at {}",
+                    tx.to
+                );
+            }
+            None => {}
+        }
+
         // Locktime check
         if let Some(lock) = tx.locktime
             && (height as u64) < lock
@@ -159,16 +706,240 @@ impl State {
         Ok(())
     }
 
-    fn apply_tx(&mut self, tx: &Transaction) {
+    /// Apply one tx in the block at `height`; a coinbase reward is locked until `lock_until`
+    /// when given.
+    fn apply_tx(
+        &mut self,
+        tx: &Transaction,
+        height: u64,
+        lock_until: Option<u64>,
+    ) -> anyhow::Result<()> {
         if !tx.is_coinbase() {
-            // Deduct from sender (amount + fee)
+            // Deduct from sender (amount + fee, or only the fee for an asset transfer)
+            let cost = tx
+                .native_cost()
+                .ok_or_else(|| anyhow::anyhow!("Amount + Fee overflow for {}", tx.from))?;
             let sender = self.accounts.entry(tx.from.clone()).or_default();
-            sender.balance = sender.balance.saturating_sub(tx.amount + tx.fee);
-            sender.nonce = sender.nonce.saturating_add(1);
+            sender.balance = sender.balance.checked_sub(cost).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Insufficient balance for {}: has {}, needs {}",
+                    tx.from,
+                    sender.balance,
+                    cost
+                )
+            })?;
+            sender.nonce = sender
+                .nonce
+                .checked_add(1)
+                .ok_or_else(|| anyhow::anyhow!("Nonce overflow for {}", tx.from))?;
+            if let Some(asset_id) = &tx.asset {
+                let held = sender.asset_balance(asset_id);
+                let left = held.checked_sub(tx.amount).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Insufficient {} balance for {}: has {}, needs {}",
+                        asset_id,
+                        tx.from,
+                        held,
+                        tx.amount
+                    )
+                })?;
+                if left == 0 {
+                    sender.assets.remove(asset_id);
+                } else {
+                    sender.assets.insert(asset_id.clone(), left);
+                }
+            }
+        }
+        if let Some(definition) = &tx.create_asset {
+            self.create_asset(&tx.from, definition)?;
         }
 
+        // A failed contract tx keeps its fee (gas) but the amount goes back to the sender
+        let recipient = match &tx.contract {
+            Some(op) if !self.run_contract(tx, op, height) => &tx.from,
+            _ => &tx.to,
+        };
+
         // Add to receiver (amount only; fees are already collected by the miner via coinbase)
-        let receiver = self.accounts.entry(tx.to.clone()).or_default();
-        receiver.balance = receiver.balance.saturating_add(tx.amount);
+        let receiver = self.accounts.entry(recipient.clone()).or_default();
+        let balance = match &tx.asset {
+            Some(asset_id) => receiver.assets.entry(asset_id.clone()).or_default(),
+            None => &mut receiver.balance,
+        };
+        *balance = balance
+            .checked_add(tx.amount)
+            .ok_or_else(|| anyhow::anyhow!("Balance overflow for {}", recipient))?;
+        if let Some(spendable_at) = lock_until
+            && tx.is_coinbase()
+            && tx.amount > 0
+        {
+            receiver.immature.push(ImmatureReward {
+                spendable_at,
+                amount: tx.amount,
+            });
+        }
+        Ok(())
+    }
+
+    /// Register `definition` with `issuer` and credit the issuer with its whole supply.
+    fn create_asset(&mut self, issuer: &str, definition: &AssetDefinition) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !self.assets.contains_key(&definition.asset_id),
+            "asset {} already exists",
+            definition.asset_id
+        );
+        self.assets.insert(
+            definition.asset_id.clone(),
+            AssetInfo {
+                issuer: issuer.to_string(),
+                supply: definition.supply,
+            },
+        );
+        self.accounts
+            .entry(issuer.to_string())
+            .or_default()
+            .assets
+            .insert(definition.asset_id.clone(), definition.supply);
+        Ok(())
+    }
+
+    /// Voting weight `address` has now: its balance, or 0 for contract accounts and the
+    /// governance account itself.
+    fn voting_balance(&self, address: &str) -> u64 {
+        match self.accounts.get(address) {
+            Some(account) if account.code_hash.is_none() && address != GOVERNANCE_ADDRESS => {
+                account.balance
+            }
+            _ => 0,
+        }
+    }
+
+    /// Note in `governance.balance_history` the balance, before the block at `height`, of each
+    /// account the block changed, so votes can still weigh open proposals' snapshots.
+    fn record_balance_changes(&mut self, before: &State, height: u64) {
+        for (address, account) in &self.accounts {
+            let old = before.get_balance(address);
+            if account.balance != old {
+                self.governance
+                    .balance_history
+                    .entry(address.clone())
+                    .or_default()
+                    .push((height, old));
+            }
+        }
+    }
+
+    /// Record a proposal (with its snapshot height and total weight) or a vote, weighed by the
+    /// voter's balance at the proposal's snapshot.
+    fn apply_governance(
+        &mut self,
+        tx: &Transaction,
+        op: &GovernanceOp,
+        height: u64,
+        period: u64,
+    ) -> anyhow::Result<()> {
+        match op {
+            GovernanceOp::Propose {
+                change,
+                activation_height,
+            } => {
+                // Validation saw the proposals from before the block; the block may add more.
+                anyhow::ensure!(
+                    self.governance.open_proposals_by(&tx.from) < MAX_OPEN_PROPOSALS_PER_SENDER,
+                    "{} already has {} open proposals",
+                    tx.from,
+                    MAX_OPEN_PROPOSALS_PER_SENDER
+                );
+                let total_weight = self.accounts.keys().fold(0_u64, |acc, address| {
+                    acc.saturating_add(self.voting_balance(address))
+                });
+                self.governance.proposals.insert(
+                    tx.id(),
+                    Proposal {
+                        proposer: tx.from.clone(),
+                        change: *change,
+                        activation_height: *activation_height,
+                        voting_ends: height.saturating_add(period),
+                        snapshot_height: height,
+                        total_weight,
+                        votes: Default::default(),
+                    },
+                );
+            }
+            GovernanceOp::Vote {
+                proposal_id,
+                approve,
+            } => {
+                let Some(snapshot_height) = self
+                    .governance
+                    .proposals
+                    .get(proposal_id)
+                    .map(|p| p.snapshot_height)
+                else {
+                    return Ok(());
+                };
+                let weight = self.governance.balance_at(
+                    &tx.from,
+                    snapshot_height,
+                    self.voting_balance(&tx.from),
+                );
+                let proposal = self.governance.proposals.get_mut(proposal_id);
+                proposal.expect("looked up above").votes.insert(
+                    tx.from.clone(),
+                    Ballot {
+                        approve: *approve,
+                        weight,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Deploy or call a contract for `tx`, whose fee already paid for `tx.gas_limit`.
+    /// Returns whether it succeeded; a failure leaves contracts and storage untouched.
+    /// Gas is a flat charge: the whole fee goes to the miner however much gas the run used,
+    /// so nothing is refunded and the coinbase doesn't depend on execution.
+    fn run_contract(&mut self, tx: &Transaction, op: &ContractOp, height: u64) -> bool {
+        let contract = match op {
+            ContractOp::Deploy { code } => {
+                let fits = crate::core::vm::parse(code).is_ok_and(|ops| {
+                    (ops.len() as u64).saturating_mul(DEPLOY_GAS_PER_OP) <= tx.gas_limit
+                });
+                if !fits {
+                    return false;
+                }
+                self.contracts.entry(tx.to.clone()).or_insert(Contract {
+                    code: code.clone(),
+                    storage: Default::default(),
+                })
+            }
+            ContractOp::Call { args } => {
+                let Some(contract) = self.contracts.get_mut(&tx.to) else {
+                    return false;
+                };
+                let Ok(code) = crate::core::vm::parse(&contract.code) else {
+                    return false;
+                };
+                let ctx = CallContext {
+                    args,
+                    value: tx.amount,
+                    height,
+                    gas_limit: tx.gas_limit,
+                };
+                if crate::core::vm::execute(&code, &mut contract.storage, ctx)
+                    .result
+                    .is_err()
+                {
+                    return false;
+                }
+                contract
+            }
+        };
+        let (code_hash, storage_root) = (contract.code_hash(), contract.storage_root());
+        let account = self.accounts.entry(tx.to.clone()).or_default();
+        account.code_hash = Some(code_hash);
+        account.storage_root = Some(storage_root);
+        true
     }
 }
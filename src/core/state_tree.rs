@@ -0,0 +1,215 @@
+use crate::core::state::{Account, State};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+type Hash = [u8; 32];
+
+const EMPTY: Hash = [0; 32];
+
+fn sha256(parts: &[&[u8]]) -> Hash {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn key_for(address: &str) -> Hash {
+    sha256(&[address.as_bytes()])
+}
+
+fn bit(key: &Hash, depth: usize) -> bool {
+    (key[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+/// Leaf encoding: every section starts with a tag byte and an item count, and every string
+/// is length-prefixed, so no two distinct accounts share an encoding.
+fn leaf_hash(key: &Hash, account: &Account) -> Hash {
+    fn update_str(hasher: &mut Sha256, s: &str) {
+        hasher.update((s.len() as u64).to_be_bytes());
+        hasher.update(s.as_bytes());
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(key);
+    hasher.update(account.balance.to_be_bytes());
+    hasher.update(account.nonce.to_be_bytes());
+
+    hasher.update([b'i']);
+    hasher.update((account.immature.len() as u64).to_be_bytes());
+    for reward in &account.immature {
+        hasher.update(reward.spendable_at.to_be_bytes());
+        hasher.update(reward.amount.to_be_bytes());
+    }
+
+    let code = [&account.code_hash, &account.storage_root];
+    hasher.update([b'c']);
+    hasher.update((code.iter().filter(|f| f.is_some()).count() as u64).to_be_bytes());
+    for (field, value) in code.iter().enumerate() {
+        if let Some(value) = value {
+            hasher.update([field as u8]);
+            update_str(&mut hasher, value);
+        }
+    }
+
+    hasher.update([b'a']);
+    hasher.update((account.assets.len() as u64).to_be_bytes());
+    for (asset_id, amount) in &account.assets {
+        update_str(&mut hasher, asset_id);
+        hasher.update(amount.to_be_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    sha256(&[&[0x01], left, right])
+}
+
+fn decode_hash(s: &str) -> anyhow::Result<Hash> {
+    let bytes = hex::decode(s).with_context(|| format!("invalid hash hex: {s}"))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("hash must be 32 bytes: {s}"))
+}
+
+/// Leaves sorted by key, so each subtree is a contiguous slice.
+fn sorted_leaves(state: &State) -> Vec<(Hash, Hash)> {
+    let mut leaves: Vec<(Hash, Hash)> = state
+        .accounts
+        .iter()
+        .map(|(address, account)| {
+            let key = key_for(address);
+            (key, leaf_hash(&key, account))
+        })
+        .collect();
+    leaves.sort_unstable_by_key(|(key, _)| *key);
+    leaves
+}
+
+fn subtree_hash(leaves: &[(Hash, Hash)], depth: usize) -> Hash {
+    match leaves {
+        [] => EMPTY,
+        [(_, leaf)] => *leaf,
+        _ => {
+            let split = leaves.partition_point(|(key, _)| !bit(key, depth));
+            node_hash(
+                &subtree_hash(&leaves[..split], depth + 1),
+                &subtree_hash(&leaves[split..], depth + 1),
+            )
+        }
+    }
+}
+
+/// Root hash (hex) of a sparse Merkle tree over `state.accounts`, keyed by `sha256(address)`.
+///
+/// - A leaf commits to its key and account: `H(0x00 || key || balance || nonce)` (big-endian),
+///   followed by `spendable_at || amount` for each immature coinbase reward and, for a
+///   contract (or the governance or asset registry account), its `code_hash || storage_root`
+///   (hex text, empty when unset), then `len || asset_id || amount` for each asset balance
+///   in id order.
+/// - An inner node is `H(0x01 || left || right)`; bit `d` of the key (MSB first) picks the side
+///   at depth `d`.
+/// - An empty subtree hashes to 32 zero bytes, and a subtree holding a single account is just
+///   that account's leaf, so the tree is only as deep as needed to tell keys apart.
+pub fn state_root(state: &State) -> String {
+    hex::encode(subtree_hash(&sorted_leaves(state), 0))
+}
+
+/// Proof that `address` has `account` under a state root, or has no account
+/// (`account: None`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountProof {
+    pub address: String,
+    pub account: Option<Account>,
+    /// Sibling hashes (hex) from the root down to where the address's path ends.
+    pub siblings: Vec<String>,
+    /// For a missing account whose path ends at another account's leaf: that leaf's key and
+    /// account, showing the slot is taken by a different address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other_leaf: Option<(String, Account)>,
+}
+
+impl AccountProof {
+    /// Build the proof for `address` against `state_root(state)`.
+    pub fn build(state: &State, address: &str) -> Self {
+        let key = key_for(address);
+        let leaves = sorted_leaves(state);
+        let mut slice = leaves.as_slice();
+        let mut siblings = Vec::new();
+        let mut depth = 0;
+        while slice.len() > 1 {
+            let split = slice.partition_point(|(k, _)| !bit(k, depth));
+            let (left, right) = slice.split_at(split);
+            let (own, other) = if bit(&key, depth) {
+                (right, left)
+            } else {
+                (left, right)
+            };
+            siblings.push(hex::encode(subtree_hash(other, depth + 1)));
+            slice = own;
+            depth += 1;
+        }
+
+        let account = state.accounts.get(address).cloned();
+        let other_leaf = match slice {
+            [(k, _)] if *k != key => {
+                let other = state
+                    .accounts
+                    .iter()
+                    .find(|(a, _)| key_for(a) == *k)
+                    .map(|(_, acc)| acc.clone())
+                    .expect("leaf comes from state");
+                Some((hex::encode(k), other))
+            }
+            _ => None,
+        };
+        Self {
+            address: address.to_string(),
+            account,
+            siblings,
+            other_leaf,
+        }
+    }
+
+    /// Check the proof against `root` (hex).
+    pub fn verify(&self, root: &str) -> anyhow::Result<()> {
+        let key = key_for(&self.address);
+        anyhow::ensure!(
+            self.siblings.len() < 256,
+            "account proof has too many siblings"
+        );
+        let mut cur = match (&self.account, &self.other_leaf) {
+            (Some(account), None) => leaf_hash(&key, account),
+            (Some(_), Some(_)) => anyhow::bail!("inclusion proof must not carry another leaf"),
+            (None, None) => EMPTY,
+            (None, Some((other_key, other))) => {
+                let other_key = decode_hash(other_key)?;
+                anyhow::ensure!(other_key != key, "other leaf has the proven key");
+                anyhow::ensure!(
+                    (0..self.siblings.len()).all(|d| bit(&other_key, d) == bit(&key, d)),
+                    "other leaf is not on the proven key's path"
+                );
+                leaf_hash(&other_key, other)
+            }
+        };
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            let sibling = decode_hash(sibling)?;
+            cur = if bit(&key, depth) {
+                node_hash(&sibling, &cur)
+            } else {
+                node_hash(&cur, &sibling)
+            };
+        }
+        let computed = hex::encode(cur);
+        anyhow::ensure!(
+            computed == root,
+            "account proof for {} does not match state root (expected={} got={})",
+            self.address,
+            root,
+            computed
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Current UNIX timestamp in milliseconds.
@@ -7,3 +9,57 @@ pub fn now_ms() -> u64 {
         .unwrap_or_else(|_| std::time::Duration::from_secs(0))
         .as_millis() as u64
 }
+
+/// Minimum number of peer samples before the network offset is applied.
+pub const MIN_TIME_SAMPLES: usize = 3;
+
+/// Offsets larger than this (70 minutes) are treated as a broken clock and ignored.
+pub const MAX_TIME_OFFSET_MS: i64 = 70 * 60 * 1000;
+
+/// Clock offsets reported by peers in their handshakes (peer time - local time).
+///
+/// The network-adjusted time is local time plus the median offset.
+#[derive(Debug, Clone, Default)]
+pub struct PeerTimeOffsets {
+    samples: HashMap<SocketAddr, i64>,
+}
+
+impl PeerTimeOffsets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a peer's clock reading, taken when its handshake arrived at `local_ms`.
+    pub fn add(&mut self, peer: SocketAddr, peer_ms: u64, local_ms: u64) {
+        let offset = peer_ms as i128 - local_ms as i128;
+        let offset = offset.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        self.samples.insert(peer, offset);
+    }
+
+    pub fn remove(&mut self, peer: &SocketAddr) {
+        self.samples.remove(peer);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Median peer offset, or 0 with too few samples or an implausible median.
+    pub fn offset_ms(&self) -> i64 {
+        if self.samples.len() < MIN_TIME_SAMPLES {
+            return 0;
+        }
+        let mut offsets: Vec<i64> = self.samples.values().copied().collect();
+        offsets.sort_unstable();
+        let median = offsets[offsets.len() / 2];
+        if median.abs() > MAX_TIME_OFFSET_MS {
+            0
+        } else {
+            median
+        }
+    }
+}
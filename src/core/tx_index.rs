@@ -0,0 +1,121 @@
+use crate::core::chain::Chain;
+use crate::core::types::Block;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a confirmed tx sits in the chain.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxLocation {
+    pub height: usize,
+    /// Position within the block's tx list.
+    pub index: usize,
+}
+
+/// Optional index of confirmed txs: tx id -> location, and address -> tx ids (chain order).
+///
+/// Coinbase txs are indexed under their recipient only (`SYSTEM` is not an address).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TxIndex {
+    /// Height and hash of the last indexed block, to detect a stale index file.
+    pub height: usize,
+    pub tip_hash: String,
+    pub txs: HashMap<String, TxLocation>,
+    pub addresses: HashMap<String, Vec<String>>,
+}
+
+impl TxIndex {
+    /// Index file kept next to a chain file (`<dir>/txindex.json`).
+    pub fn path_for_chain(chain_path: &Path) -> PathBuf {
+        chain_path.with_file_name("txindex.json")
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let s = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&s)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Index every block of `chain`.
+    pub fn build(chain: &Chain) -> Self {
+        let mut index = Self::default();
+        for (height, block) in chain.blocks.iter().enumerate() {
+            index.index_block(height, block);
+        }
+        index
+    }
+
+    /// Bring the index up to `chain`'s tip: index the missing blocks if the indexed tip is
+    /// still on `chain`, otherwise rebuild. Returns the number of blocks indexed.
+    pub fn sync(&mut self, chain: &Chain) -> usize {
+        let on_chain = chain
+            .blocks
+            .get(self.height)
+            .is_some_and(|b| b.header.hash() == self.tip_hash);
+        if !on_chain {
+            *self = Self::build(chain);
+            return chain.blocks.len();
+        }
+        let start = self.height + 1;
+        for (height, block) in chain.blocks.iter().enumerate().skip(start) {
+            self.index_block(height, block);
+        }
+        chain.blocks.len() - start
+    }
+
+    /// Add a block appended at `height`.
+    pub fn index_block(&mut self, height: usize, block: &Block) {
+        for (index, tx) in block.txs.iter().enumerate() {
+            let id = tx.id();
+            self.txs.insert(id.clone(), TxLocation { height, index });
+            if !tx.is_coinbase() {
+                self.addresses
+                    .entry(tx.from.clone())
+                    .or_default()
+                    .push(id.clone());
+            }
+            if tx.to != tx.from || tx.is_coinbase() {
+                self.addresses.entry(tx.to.clone()).or_default().push(id);
+            }
+        }
+        self.height = height;
+        self.tip_hash = block.header.hash();
+    }
+
+    /// Remove the tip block at `height`; `prev_hash` becomes the indexed tip.
+    pub fn unindex_block(&mut self, height: usize, block: &Block) {
+        for tx in block.txs.iter().rev() {
+            let id = tx.id();
+            self.txs.remove(&id);
+            for addr in [&tx.from, &tx.to] {
+                if let Some(ids) = self.addresses.get_mut(addr) {
+                    ids.retain(|i| *i != id);
+                    if ids.is_empty() {
+                        self.addresses.remove(addr);
+                    }
+                }
+            }
+        }
+        self.height = height.saturating_sub(1);
+        self.tip_hash = block.header.prev_hash.clone();
+    }
+
+    pub fn get(&self, tx_id: &str) -> Option<TxLocation> {
+        self.txs.get(tx_id).copied()
+    }
+
+    /// Confirmed tx ids touching `address`, oldest first.
+    pub fn history(&self, address: &str) -> &[String] {
+        self.addresses
+            .get(address)
+            .map_or(&[], |ids| ids.as_slice())
+    }
+}
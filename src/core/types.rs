@@ -6,6 +6,11 @@ pub struct BlockHeader {
     pub timestamp_ms: u64,
     pub nonce: u64,
     pub merkle_root: String,
+
+    /// State root (see `state_tree::state_root`) after applying this block.
+    /// Empty on chains whose genesis predates state commitments, keeping their header hashes.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub state_root: String,
 }
 
 impl BlockHeader {
@@ -29,6 +34,20 @@ impl BlockHeader {
     }
 }
 
+/// Longest address accepted as a coinbase recipient.
+pub const MAX_ADDRESS_LEN: usize = 128;
+
+/// Whether `address` can receive a coinbase: 1..=`MAX_ADDRESS_LEN` ASCII letters, digits,
+/// `-` or `_` (names and pubkey hex both qualify), and not the `SYSTEM` sender.
+pub fn is_valid_address(address: &str) -> bool {
+    !address.is_empty()
+        && address.len() <= MAX_ADDRESS_LEN
+        && address != "SYSTEM"
+        && address
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
 /// A minimal transaction (Week 2: add optional signatures).
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Transaction {
@@ -39,6 +58,11 @@ pub struct Transaction {
     pub fee: u64,
     pub nonce: u64,
 
+    /// Chain id this tx is bound to (replay protection across networks).
+    /// 0 means unbound; signed txs must carry the chain's id.
+    #[serde(default, skip_serializing_if = "is_zero_u32")]
+    pub chain_id: u32,
+
     /// Optional ed25519 public key (hex) used to verify `signature_b64`.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pubkey_hex: Option<String>,
@@ -47,6 +71,11 @@ pub struct Transaction {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature_b64: Option<String>,
 
+    /// Data satisfying the spending condition of a `cond-` sender (see `core::script`).
+    /// Like the signature, it is not part of the signing payload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness: Option<crate::core::script::Witness>,
+
     /// Optional comment/metadata for the transaction (limit: 128 chars)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub memo: Option<String>,
@@ -265,11 +294,28 @@ pub struct Transaction {
     #[serde(default)]
     pub gas_limit: u64,
 
+    /// Contract deploy or call (see `core::vm`); `gas_limit` bounds its execution and is
+    /// charged in full through the fee, whatever the run uses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<crate::core::vm::ContractOp>,
+
+    /// Governance proposal or vote (see `core::governance`); sent to `GOVERNANCE_ADDRESS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub governance: Option<crate::core::governance::GovernanceOp>,
+
+    /// Asset id of a token transfer: `amount` is in this asset, the fee still in native coin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+
+    /// New asset to create (see `core::asset`); sent to `ASSET_REGISTRY_ADDRESS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub create_asset: Option<crate::core::asset::AssetDefinition>,
+
     /// Optional reference to an external system for validation.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub external_system: Option<String>,
 
-    /// Transaction-specific logic or script for advanced validation.
+    /// Spending condition (JSON) revealed when spending from a `cond-` address.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub script: Option<String>,
 
@@ -411,6 +457,18 @@ pub struct Transaction {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub frequency_id: Option<String>,
 
+    /// Transaction lifecycle flags.
+    #[serde(default)]
+    pub is_reverting: bool,
+    #[serde(default)]
+    pub is_conditional: bool,
+    #[serde(default)]
+    pub is_delegated: bool,
+    #[serde(default)]
+    pub is_validated: bool,
+    #[serde(default)]
+    pub is_audited: bool,
+
     /// New field for Day 16: is the transaction part of a system core update?
     #[serde(default)]
     pub is_system_update: bool,
@@ -540,8 +598,10 @@ impl Default for Transaction {
             amount: 0,
             fee: 0,
             nonce: 0,
+            chain_id: 0,
             pubkey_hex: None,
             signature_b64: None,
+            witness: None,
             memo: None,
             sequence: 0,
             timestamp_ms: crate::core::time::now_ms(),
@@ -602,6 +662,10 @@ impl Default for Transaction {
             is_expired: false,
             is_replayable: false,
             gas_limit: 0,
+            contract: None,
+            governance: None,
+            asset: None,
+            create_asset: None,
             external_system: None,
             script: None,
             bridge_id: None,
@@ -704,6 +768,10 @@ fn default_tx_version() -> u32 {
     1
 }
 
+fn is_zero_u32(v: &u32) -> bool {
+    *v == 0
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TxSignPayload {
     pub from: String,
@@ -711,6 +779,8 @@ pub struct TxSignPayload {
     pub amount: u64,
     pub fee: u64,
     pub nonce: u64,
+    #[serde(default, skip_serializing_if = "is_zero_u32")]
+    pub chain_id: u32,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub memo: Option<String>,
     #[serde(default)]
@@ -836,6 +906,14 @@ pub struct TxSignPayload {
     #[serde(default)]
     pub gas_limit: u64,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<crate::core::vm::ContractOp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub governance: Option<crate::core::governance::GovernanceOp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub create_asset: Option<crate::core::asset::AssetDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub external_system: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub script: Option<String>,
@@ -1069,6 +1147,20 @@ impl Transaction {
         }
     }
 
+    /// Coinbase paying `amount` (block subsidy + fees) to `miner` in the block at `height`.
+    pub fn coinbase(miner: &str, height: u64, amount: u64) -> Self {
+        Self {
+            from: "SYSTEM".to_string(),
+            to: miner.to_string(),
+            amount,
+            nonce: height,
+            memo: Some(format!("Block {height} Reward")),
+            priority: 255, // Max priority for coinbase
+            nonce_id: Some(format!("coinbase-{}", height)),
+            ..Default::default()
+        }
+    }
+
     pub fn new_with_fee(
         from: impl Into<String>,
         to: impl Into<String>,
@@ -1095,6 +1187,7 @@ impl Transaction {
             amount: self.amount,
             fee: self.fee,
             nonce: self.nonce,
+            chain_id: self.chain_id,
             memo: self.memo.clone(),
             sequence: self.sequence,
             timestamp_ms: self.timestamp_ms,
@@ -1153,6 +1246,10 @@ impl Transaction {
             is_expired: self.is_expired,
             is_replayable: self.is_replayable,
             gas_limit: self.gas_limit,
+            contract: self.contract.clone(),
+            governance: self.governance.clone(),
+            asset: self.asset.clone(),
+            create_asset: self.create_asset.clone(),
             external_system: self.external_system.clone(),
             script: self.script.clone(),
             bridge_id: self.bridge_id.clone(),
@@ -1281,8 +1378,27 @@ impl Transaction {
         anyhow::ensure!(!self.from.trim().is_empty(), "tx.from must be non-empty");
         anyhow::ensure!(!self.to.trim().is_empty(), "tx.to must be non-empty");
         anyhow::ensure!(self.from != self.to, "tx.from and tx.to must differ");
-        // Minimum amount of 1 unit (prevents dust/negative amounts)
-        anyhow::ensure!(self.amount > 0, "tx.amount must be > 0");
+        // Minimum amount of 1 unit (prevents dust/negative amounts); contract, governance and
+        // asset-creation txs may send 0
+        anyhow::ensure!(
+            self.amount > 0
+                || self.contract.is_some()
+                || self.governance.is_some()
+                || self.create_asset.is_some(),
+            "tx.amount must be > 0"
+        );
+        if let Some(op) = &self.contract {
+            self.validate_contract_op(op)?;
+        }
+        if self.governance.is_some() || self.to == crate::core::governance::GOVERNANCE_ADDRESS {
+            self.validate_governance_op()?;
+        }
+        if self.asset.is_some()
+            || self.create_asset.is_some()
+            || self.to == crate::core::asset::ASSET_REGISTRY_ADDRESS
+        {
+            self.validate_asset_op()?;
+        }
 
         // Enhanced amount check for large transactions
         if self.amount > 1_000_000_000 {
@@ -1390,6 +1506,108 @@ impl Transaction {
         Ok(())
     }
 
+    /// Stateless rules for contract txs: a gas limit the fee covers at one unit per gas,
+    /// bounded arguments, and code that parses.
+    fn validate_contract_op(&self, op: &crate::core::vm::ContractOp) -> anyhow::Result<()> {
+        use crate::core::vm::{ContractOp, MAX_CALL_ARGS, MAX_TX_GAS};
+
+        anyhow::ensure!(
+            (1..=MAX_TX_GAS).contains(&self.gas_limit),
+            "contract tx gas_limit must be 1..={} (got {})",
+            MAX_TX_GAS,
+            self.gas_limit
+        );
+        anyhow::ensure!(
+            self.fee >= self.gas_limit,
+            "contract tx fee {} does not cover gas_limit {}",
+            self.fee,
+            self.gas_limit
+        );
+        match op {
+            ContractOp::Deploy { code } => {
+                crate::core::vm::parse(code)?;
+            }
+            ContractOp::Call { args } => {
+                anyhow::ensure!(
+                    args.len() <= MAX_CALL_ARGS,
+                    "contract call has {} args (max {})",
+                    args.len(),
+                    MAX_CALL_ARGS
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Stateless rules for governance txs: a zero-amount tx to `GOVERNANCE_ADDRESS` that
+    /// carries nothing else to execute. Nothing else may be sent there.
+    fn validate_governance_op(&self) -> anyhow::Result<()> {
+        use crate::core::governance::GOVERNANCE_ADDRESS;
+
+        anyhow::ensure!(
+            self.governance.is_some(),
+            "txs to {:?} must carry a governance op",
+            GOVERNANCE_ADDRESS
+        );
+        anyhow::ensure!(
+            self.to == GOVERNANCE_ADDRESS,
+            "governance tx must be sent to {:?}",
+            GOVERNANCE_ADDRESS
+        );
+        anyhow::ensure!(self.amount == 0, "governance tx must not carry an amount");
+        anyhow::ensure!(
+            self.contract.is_none(),
+            "governance tx can't carry a contract op"
+        );
+        Ok(())
+    }
+
+    /// Stateless rules for asset txs. A transfer names a well-formed asset and does nothing
+    /// else; a creation is a zero-amount tx to `ASSET_REGISTRY_ADDRESS` defining a well-formed
+    /// id and a non-zero supply. Nothing else may be sent to the registry.
+    fn validate_asset_op(&self) -> anyhow::Result<()> {
+        use crate::core::asset::{ASSET_REGISTRY_ADDRESS, check_asset_id};
+
+        anyhow::ensure!(
+            self.contract.is_none() && self.governance.is_none(),
+            "asset tx can't carry a contract or governance op"
+        );
+        match (&self.asset, &self.create_asset) {
+            (Some(asset), None) => {
+                check_asset_id(asset)?;
+                anyhow::ensure!(
+                    self.to != ASSET_REGISTRY_ADDRESS,
+                    "assets can't be sent to {:?}",
+                    ASSET_REGISTRY_ADDRESS
+                );
+            }
+            (None, Some(definition)) => {
+                check_asset_id(&definition.asset_id)?;
+                anyhow::ensure!(definition.supply > 0, "asset supply must be > 0");
+                anyhow::ensure!(
+                    self.to == ASSET_REGISTRY_ADDRESS,
+                    "asset creation must be sent to {:?}",
+                    ASSET_REGISTRY_ADDRESS
+                );
+                anyhow::ensure!(self.amount == 0, "asset creation must not carry an amount");
+            }
+            (Some(_), Some(_)) => anyhow::bail!("asset creation can't also transfer an asset"),
+            (None, None) => {
+                anyhow::bail!("txs to {:?} must create an asset", ASSET_REGISTRY_ADDRESS)
+            }
+        }
+        Ok(())
+    }
+
+    /// Native coins the sender pays: the fee, plus the amount unless it's an asset transfer.
+    /// `None` on overflow.
+    pub fn native_cost(&self) -> Option<u64> {
+        match self.asset {
+            Some(_) => Some(self.fee),
+            None => self.amount.checked_add(self.fee),
+        }
+    }
+
     /// Basic tx validation for accepting into the mempool or a block.
     pub fn validate_accept(&self) -> anyhow::Result<()> {
         self.validate_basic()?;
@@ -1402,12 +1620,25 @@ impl Transaction {
     /// Verify signature if present.
     ///
     /// Rules (for now):
-    /// - If both `pubkey_hex` and `signature_b64` are present, verify strictly.
+    /// - If both `pubkey_hex` and `signature_b64` are present, verify with `crypto::verify_signature`.
     /// - If neither is present, treat as unsigned and accept.
     /// - If only one is present, reject.
     pub fn verify_signature_if_present(&self) -> anyhow::Result<()> {
+        if let Some((vk, sig)) = self.signature_parts()? {
+            crate::core::crypto::verify_signature(&vk, &self.signing_bytes(), &sig)?;
+        }
+        Ok(())
+    }
+
+    /// Decode the key and signature of a signed tx without verifying it.
+    ///
+    /// Applies the same field rules as `verify_signature_if_present` and returns `None` for
+    /// unsigned txs. Used by batch verification.
+    pub fn signature_parts(
+        &self,
+    ) -> anyhow::Result<Option<(ed25519_dalek::VerifyingKey, ed25519_dalek::Signature)>> {
         match (&self.pubkey_hex, &self.signature_b64) {
-            (None, None) => Ok(()),
+            (None, None) => Ok(None),
             (Some(_), None) | (None, Some(_)) => {
                 anyhow::bail!("tx signature fields must be both present or both absent")
             }
@@ -1420,8 +1651,8 @@ impl Transaction {
                 );
 
                 let vk = crate::core::crypto::verifying_key_from_hex(pk_hex)?;
-                crate::core::crypto::verify_bytes(&vk, &self.signing_bytes(), sig_b64)?;
-                Ok(())
+                let sig = crate::core::crypto::signature_from_base64(sig_b64)?;
+                Ok(Some((vk, sig)))
             }
         }
     }
@@ -1438,8 +1669,17 @@ impl Block {
         self.txs.first().is_some_and(|tx| tx.is_coinbase())
     }
 
-    pub fn total_reward(&self) -> u64 {
-        let block_reward = 50;
+    /// Gas the block's contract txs may burn together: the sum of their `gas_limit`s.
+    pub fn gas_limit(&self) -> u64 {
+        self.txs
+            .iter()
+            .filter(|tx| tx.contract.is_some())
+            .fold(0, |acc, tx| acc.saturating_add(tx.gas_limit))
+    }
+
+    /// Coinbase amount for this block at `height`: subsidy plus all non-coinbase fees.
+    pub fn total_reward(&self, params: &crate::core::params::ChainParams, height: u64) -> u64 {
+        let block_reward = params.block_subsidy(height);
         let fees: u64 = self
             .txs
             .iter()
@@ -1449,6 +1689,14 @@ impl Block {
         block_reward + fees
     }
 
+    /// Batch-verify all tx signatures in this block, skipping txs already in `cache`.
+    pub fn verify_signatures(
+        &self,
+        cache: Option<&crate::core::sigcache::SignatureCache>,
+    ) -> anyhow::Result<()> {
+        crate::core::sigcache::verify_signatures_batch(&self.txs, cache)
+    }
+
     /// Calculate the size of the block in bytes when serialized.
     pub fn size(&self) -> usize {
         serde_json::to_vec(self).unwrap_or_default().len()
@@ -1459,7 +1707,9 @@ impl Block {
         self.header.verify_pow(difficulty).is_ok()
     }
 
-    /// Basic block validation against a previous header.
+    /// Basic block validation against a previous header (linkage and PoW).
+    ///
+    /// Timestamp rules need more history than one header; see `Chain::check_block_time`.
     pub fn validate_with_prev(
         &self,
         prev_header: &BlockHeader,
@@ -1471,12 +1721,6 @@ impl Block {
             self.header.prev_hash,
             prev_header.hash()
         );
-        anyhow::ensure!(
-            self.header.timestamp_ms >= prev_header.timestamp_ms,
-            "timestamp cannot go backward: {} (prev: {})",
-            self.header.timestamp_ms,
-            prev_header.timestamp_ms
-        );
         self.header.verify_pow(difficulty)?;
         Ok(())
     }
@@ -0,0 +1,306 @@
+use crate::core::hash::sha256_hex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Most instructions a contract's code may hold.
+pub const MAX_CODE_OPS: usize = 1024;
+
+/// Deepest the VM stack may grow.
+pub const MAX_STACK: usize = 256;
+
+/// Most call arguments a tx may pass.
+pub const MAX_CALL_ARGS: usize = 16;
+
+/// Highest `gas_limit` a single contract tx may ask for.
+pub const MAX_TX_GAS: u64 = 1_000_000;
+
+/// Gas a deploy pays per instruction of code stored.
+pub const DEPLOY_GAS_PER_OP: u64 = 10;
+
+/// What a contract tx does, carried in `Transaction::contract`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContractOp {
+    /// Store `code` (assembly, see `parse`) at `tx.to`, which must be
+    /// `contract_address(tx.from, tx.nonce)`.
+    Deploy { code: String },
+    /// Run the contract at `tx.to` with `args`; `tx.amount` is added to its balance.
+    Call {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<u64>,
+    },
+}
+
+/// A deployed contract's code and storage, kept in `State::contracts`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Contract {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<u64, u64>,
+}
+
+impl Contract {
+    /// SHA-256 (hex) of the code text; stored in the account as `code_hash`.
+    pub fn code_hash(&self) -> String {
+        sha256_hex(self.code.as_bytes())
+    }
+
+    /// SHA-256 (hex) over the storage entries in key order, `key || value` big-endian.
+    pub fn storage_root(&self) -> String {
+        let mut bytes = Vec::with_capacity(self.storage.len() * 16);
+        for (key, value) in &self.storage {
+            bytes.extend_from_slice(&key.to_be_bytes());
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        sha256_hex(&bytes)
+    }
+}
+
+/// Address of the contract `deployer` creates with the tx at `nonce`.
+pub fn contract_address(deployer: &str, nonce: u64) -> String {
+    let hash = sha256_hex(format!("{deployer}:{nonce}").as_bytes());
+    format!("contract-{}", &hash[..40])
+}
+
+/// One VM instruction. Values are `u64`; arithmetic is checked and any fault reverts the call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Push(u64),
+    Pop,
+    Dup,
+    Swap,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Lt,
+    Gt,
+    IsZero,
+    /// Jump to the instruction index on top of the stack.
+    Jump,
+    /// Pop a target and a condition; jump if the condition is non-zero.
+    JumpI,
+    /// Replace the key on top of the stack with its stored value (0 if unset).
+    SLoad,
+    /// Pop a value and a key; store the value (storing 0 deletes the key).
+    SStore,
+    /// Replace the index on top of the stack with that call argument.
+    Arg,
+    /// Push `tx.amount`.
+    Value,
+    /// Push the height of the block being applied.
+    Height,
+    Stop,
+    Revert,
+}
+
+impl Op {
+    pub fn gas(&self) -> u64 {
+        match self {
+            Op::Push(_) | Op::Pop | Op::Dup | Op::Swap | Op::Stop | Op::Revert => 1,
+            Op::Arg | Op::Value | Op::Height => 2,
+            Op::Add | Op::Sub | Op::Eq | Op::Lt | Op::Gt | Op::IsZero => 3,
+            Op::Mul | Op::Div | Op::Mod => 5,
+            Op::Jump | Op::JumpI => 8,
+            Op::SLoad => 50,
+            Op::SStore => 200,
+        }
+    }
+}
+
+/// Parse assembly: whitespace-separated mnemonics (case-insensitive), `PUSH` followed by a
+/// decimal value. `#` starts a comment that runs to the end of the line.
+pub fn parse(code: &str) -> anyhow::Result<Vec<Op>> {
+    let mut ops = Vec::new();
+    for line in code.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let op = match token.to_ascii_uppercase().as_str() {
+                "PUSH" => {
+                    let value = tokens
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("PUSH at op {} needs a value", ops.len()))?;
+                    Op::Push(value.parse().map_err(|_| {
+                        anyhow::anyhow!("PUSH at op {}: bad value {:?}", ops.len(), value)
+                    })?)
+                }
+                "POP" => Op::Pop,
+                "DUP" => Op::Dup,
+                "SWAP" => Op::Swap,
+                "ADD" => Op::Add,
+                "SUB" => Op::Sub,
+                "MUL" => Op::Mul,
+                "DIV" => Op::Div,
+                "MOD" => Op::Mod,
+                "EQ" => Op::Eq,
+                "LT" => Op::Lt,
+                "GT" => Op::Gt,
+                "ISZERO" => Op::IsZero,
+                "JUMP" => Op::Jump,
+                "JUMPI" => Op::JumpI,
+                "SLOAD" => Op::SLoad,
+                "SSTORE" => Op::SStore,
+                "ARG" => Op::Arg,
+                "VALUE" => Op::Value,
+                "HEIGHT" => Op::Height,
+                "STOP" => Op::Stop,
+                "REVERT" => Op::Revert,
+                other => anyhow::bail!("unknown instruction {:?} at op {}", other, ops.len()),
+            };
+            ops.push(op);
+        }
+    }
+    anyhow::ensure!(!ops.is_empty(), "contract code is empty");
+    anyhow::ensure!(
+        ops.len() <= MAX_CODE_OPS,
+        "contract code has {} ops (max {})",
+        ops.len(),
+        MAX_CODE_OPS
+    );
+    Ok(ops)
+}
+
+/// Inputs of one call.
+#[derive(Debug, Clone, Copy)]
+pub struct CallContext<'a> {
+    pub args: &'a [u64],
+    pub value: u64,
+    pub height: u64,
+    pub gas_limit: u64,
+}
+
+/// Result of running a call: the gas it burned, and the error if it reverted.
+#[derive(Debug)]
+pub struct Execution {
+    pub gas_used: u64,
+    pub result: anyhow::Result<()>,
+}
+
+/// Run `code` against `storage`. Storage is only written when the call succeeds: writes go
+/// straight to `storage`, and the previous value of each written key is journaled so a failed
+/// call can put it back.
+pub fn execute(code: &[Op], storage: &mut BTreeMap<u64, u64>, ctx: CallContext) -> Execution {
+    let mut journal = HashMap::new();
+    let mut gas_used = 0;
+    let result = run(code, storage, &mut journal, ctx, &mut gas_used);
+    if result.is_err() {
+        for (key, old) in journal {
+            match old {
+                Some(value) => storage.insert(key, value),
+                None => storage.remove(&key),
+            };
+        }
+    }
+    Execution { gas_used, result }
+}
+
+fn run(
+    code: &[Op],
+    storage: &mut BTreeMap<u64, u64>,
+    journal: &mut HashMap<u64, Option<u64>>,
+    ctx: CallContext,
+    gas_used: &mut u64,
+) -> anyhow::Result<()> {
+    let mut stack: Vec<u64> = Vec::new();
+    let mut pc = 0;
+    let pop = |stack: &mut Vec<u64>, pc: usize| {
+        stack
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("stack underflow at op {}", pc))
+    };
+    let arith = |r: Option<u64>, pc: usize| {
+        r.ok_or_else(|| anyhow::anyhow!("arithmetic fault at op {}", pc))
+    };
+
+    while let Some(&op) = code.get(pc) {
+        let cost = op.gas();
+        if *gas_used + cost > ctx.gas_limit {
+            *gas_used = ctx.gas_limit;
+            anyhow::bail!("out of gas at op {} (limit {})", pc, ctx.gas_limit);
+        }
+        *gas_used += cost;
+
+        let mut next = pc + 1;
+        match op {
+            Op::Push(v) => stack.push(v),
+            Op::Pop => {
+                pop(&mut stack, pc)?;
+            }
+            Op::Dup => {
+                let v = *stack
+                    .last()
+                    .ok_or_else(|| anyhow::anyhow!("stack underflow at op {}", pc))?;
+                stack.push(v);
+            }
+            Op::Swap => {
+                let (a, b) = (pop(&mut stack, pc)?, pop(&mut stack, pc)?);
+                stack.push(a);
+                stack.push(b);
+            }
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod | Op::Eq | Op::Lt | Op::Gt => {
+                let (b, a) = (pop(&mut stack, pc)?, pop(&mut stack, pc)?);
+                stack.push(match op {
+                    Op::Add => arith(a.checked_add(b), pc)?,
+                    Op::Sub => arith(a.checked_sub(b), pc)?,
+                    Op::Mul => arith(a.checked_mul(b), pc)?,
+                    Op::Div => arith(a.checked_div(b), pc)?,
+                    Op::Mod => arith(a.checked_rem(b), pc)?,
+                    Op::Eq => (a == b) as u64,
+                    Op::Lt => (a < b) as u64,
+                    _ => (a > b) as u64,
+                });
+            }
+            Op::IsZero => {
+                let v = pop(&mut stack, pc)?;
+                stack.push((v == 0) as u64);
+            }
+            Op::Jump => next = jump_target(pop(&mut stack, pc)?, code.len(), pc)?,
+            Op::JumpI => {
+                let (target, cond) = (pop(&mut stack, pc)?, pop(&mut stack, pc)?);
+                if cond != 0 {
+                    next = jump_target(target, code.len(), pc)?;
+                }
+            }
+            Op::SLoad => {
+                let key = pop(&mut stack, pc)?;
+                stack.push(storage.get(&key).copied().unwrap_or(0));
+            }
+            Op::SStore => {
+                let (value, key) = (pop(&mut stack, pc)?, pop(&mut stack, pc)?);
+                journal
+                    .entry(key)
+                    .or_insert_with(|| storage.get(&key).copied());
+                if value == 0 {
+                    storage.remove(&key);
+                } else {
+                    storage.insert(key, value);
+                }
+            }
+            Op::Arg => {
+                let i = pop(&mut stack, pc)?;
+                let arg = usize::try_from(i)
+                    .ok()
+                    .and_then(|i| ctx.args.get(i))
+                    .ok_or_else(|| anyhow::anyhow!("no call argument {} at op {}", i, pc))?;
+                stack.push(*arg);
+            }
+            Op::Value => stack.push(ctx.value),
+            Op::Height => stack.push(ctx.height),
+            Op::Stop => return Ok(()),
+            Op::Revert => anyhow::bail!("reverted at op {}", pc),
+        }
+        anyhow::ensure!(stack.len() <= MAX_STACK, "stack overflow at op {}", pc);
+        pc = next;
+    }
+    Ok(())
+}
+
+fn jump_target(target: u64, len: usize, pc: usize) -> anyhow::Result<usize> {
+    usize::try_from(target)
+        .ok()
+        .filter(|&t| t < len)
+        .ok_or_else(|| anyhow::anyhow!("jump to {} out of range at op {}", target, pc))
+}
@@ -1,10 +1,23 @@
 use anyhow::Context;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
+use rusty_chain::core::asset::{ASSET_REGISTRY_ADDRESS, AssetDefinition};
 use rusty_chain::core::chain::Chain;
+use rusty_chain::core::chain_id::{Network, data_dir_for};
+use rusty_chain::core::fee_estimator::{DEFAULT_CONFIRM_TARGET, FeeEstimator};
+use rusty_chain::core::genesis::GenesisConfig;
+use rusty_chain::core::governance::{GOVERNANCE_ADDRESS, GovernanceOp, ParamChange};
+use rusty_chain::core::htlc::Htlc;
 use rusty_chain::core::keys::KeyFile;
-use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::light::LightClient;
+use rusty_chain::core::mempool::{Mempool, MempoolEvent, RemovalReason};
+use rusty_chain::core::network::AccountInfo;
+use rusty_chain::core::policy::MempoolPolicy;
+use rusty_chain::core::script::{Condition, Witness, WitnessSignature};
+use rusty_chain::core::snapshot::Snapshot;
+use rusty_chain::core::tx_index::TxIndex;
 use rusty_chain::core::types::Transaction;
+use rusty_chain::core::vm::{ContractOp, contract_address};
 
 use std::collections::HashMap;
 
@@ -12,10 +25,418 @@ use std::collections::HashMap;
 #[command(name = "rusty-chain")]
 #[command(about = "A mini blockchain built in Rust (30-day build).", long_about = None)]
 struct Cli {
+    /// Network to operate on (mainnet|testnet|devnet, or a custom genesis network).
+    /// Selects the default data directory data/<network>. Default: devnet.
+    #[arg(long, global = true)]
+    network: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Subcommand, Debug)]
+enum SnapshotAction {
+    /// Write the state at a checkpointed height plus the header chain to a compressed file
+    Export {
+        /// Checkpointed height to snapshot (default: the latest checkpoint)
+        #[arg(long)]
+        height: Option<usize>,
+
+        /// Output file (default: data/<network>/snapshot-<height>.json.gz)
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Input path for chain JSON
+        #[arg(long)]
+        path: Option<String>,
+    },
+
+    /// Start a chain file from a snapshot; `node` then syncs the blocks after it over P2P
+    Import {
+        /// Snapshot file
+        file: String,
+
+        /// Trusted hash of the block at the snapshot height (default: the local chain's
+        /// checkpoint at that height)
+        #[arg(long)]
+        checkpoint: Option<String>,
+
+        /// Output path for chain JSON
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Replace a chain file that already has blocks
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SwapAction {
+    /// Generate a swap secret and print it with its hash
+    Secret,
+
+    /// Lock coins in a hash time-locked contract and write the contract file
+    Lock {
+        /// Local key name that pays and may refund after the timeout
+        #[arg(long)]
+        signer: String,
+
+        /// Pubkey hex (or local key name) that may claim with the secret
+        #[arg(long)]
+        recipient: String,
+
+        /// SHA-256 (hex) of the swap secret
+        #[arg(long)]
+        hash: String,
+
+        /// Height from which the signer can refund
+        #[arg(long)]
+        timeout: u64,
+
+        #[arg(long)]
+        amount: u64,
+
+        #[arg(long, default_value_t = 0)]
+        fee: u64,
+
+        /// Chain id of the other side of the swap
+        #[arg(long)]
+        counterparty_chain_id: Option<u32>,
+
+        /// Where to write the contract JSON for the counterparty
+        #[arg(long)]
+        out: String,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// Claim a contract's coins with the secret
+    Claim {
+        /// Contract JSON written by `swap lock`
+        contract: String,
+
+        /// Local key name of the recipient
+        #[arg(long)]
+        signer: String,
+
+        /// The swap secret (hex); see `swap inspect` on the other chain
+        #[arg(long)]
+        secret: String,
+
+        #[arg(long, default_value_t = 0)]
+        fee: u64,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// Take a contract's coins back after its timeout
+    Refund {
+        /// Contract JSON written by `swap lock`
+        contract: String,
+
+        /// Local key name that locked the coins
+        #[arg(long)]
+        signer: String,
+
+        #[arg(long, default_value_t = 0)]
+        fee: u64,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// Print a contract's balance, timeout and any secret its claim revealed
+    Inspect {
+        /// Contract JSON written by `swap lock`
+        contract: String,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ContractAction {
+    /// Deploy contract code (VM assembly)
+    Deploy {
+        /// Code as assembly text, or a path to a file holding it
+        code: String,
+
+        /// Sender address (ignored with --signer)
+        #[arg(long, required_unless_present = "signer")]
+        from: Option<String>,
+
+        /// Local key name to sign with
+        #[arg(long)]
+        signer: Option<String>,
+
+        /// Gas the deploy may use; the fee must cover it and is charged in full
+        #[arg(long)]
+        gas_limit: u64,
+
+        /// Fee (default: the gas limit)
+        #[arg(long)]
+        fee: Option<u64>,
+
+        /// Coins to send to the new contract
+        #[arg(long, default_value_t = 0)]
+        amount: u64,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// Call a deployed contract
+    Call {
+        /// Contract address
+        address: String,
+
+        /// Call argument (repeatable)
+        #[arg(long)]
+        arg: Vec<u64>,
+
+        /// Sender address (ignored with --signer)
+        #[arg(long, required_unless_present = "signer")]
+        from: Option<String>,
+
+        /// Local key name to sign with
+        #[arg(long)]
+        signer: Option<String>,
+
+        /// Gas the call may use; the fee must cover it and is charged in full
+        #[arg(long)]
+        gas_limit: u64,
+
+        /// Fee (default: the gas limit)
+        #[arg(long)]
+        fee: Option<u64>,
+
+        /// Coins to send with the call
+        #[arg(long, default_value_t = 0)]
+        amount: u64,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// Print a contract's code hash, balance and storage
+    Inspect {
+        /// Contract address
+        address: String,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AssetAction {
+    /// Create an asset; its whole supply goes to the sender, who becomes its issuer
+    Create {
+        /// Asset id: uppercase letters, digits and '-', starting with a letter
+        asset_id: String,
+
+        /// Total supply, fixed at creation
+        #[arg(long)]
+        supply: u64,
+
+        /// Sender address (ignored with --signer)
+        #[arg(long, required_unless_present = "signer")]
+        from: Option<String>,
+
+        /// Local key name to sign with
+        #[arg(long)]
+        signer: Option<String>,
+
+        #[arg(long, default_value_t = 1)]
+        fee: u64,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// List registered assets with their issuer and supply
+    List {
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GovAction {
+    /// Propose a chain parameter change
+    Propose {
+        /// Parameter: block_reward, max_block_size, max_block_txs or min_tx_fee
+        #[arg(long)]
+        param: String,
+
+        /// New value
+        #[arg(long)]
+        value: u64,
+
+        /// Height the change takes effect from if the vote passes
+        #[arg(long)]
+        activation_height: u64,
+
+        /// Local key name to sign with; governance txs must be signed by their sender
+        #[arg(long)]
+        signer: String,
+
+        #[arg(long, default_value_t = 1)]
+        fee: u64,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// Vote on an open proposal; a later vote replaces an earlier one
+    Vote {
+        /// Proposal id (the proposing tx's hash)
+        proposal_id: String,
+
+        /// Vote against the proposal (default: for it)
+        #[arg(long)]
+        reject: bool,
+
+        /// Local key name to sign with; governance txs must be signed by their sender
+        #[arg(long)]
+        signer: String,
+
+        #[arg(long, default_value_t = 1)]
+        fee: u64,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// List open proposals with their tally, and the changes that passed
+    List {
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+    },
+}
+
+/// Mempool admission policy flags (shared by commands that add to a mempool).
+#[derive(Args, Debug, Clone)]
+struct PolicyArgs {
+    /// Maximum mempool size in bytes before the lowest fee-rate txs are evicted
+    #[arg(long, default_value_t = rusty_chain::core::policy::DEFAULT_MAX_MEMPOOL_BYTES)]
+    max_mempool_bytes: usize,
+
+    /// Maximum pending txs per sender
+    #[arg(long, default_value_t = rusty_chain::core::policy::DEFAULT_MAX_TXS_PER_SENDER)]
+    max_txs_per_sender: usize,
+
+    /// Minimum relay fee rate (fee per 1000 bytes) while the mempool is not filling up
+    #[arg(long, default_value_t = 0)]
+    min_relay_fee: u64,
+
+    /// Minimum fee-rate bump (percent) for a replacement (RBF) tx
+    #[arg(long, default_value_t = rusty_chain::core::policy::DEFAULT_MIN_RBF_BUMP_PCT)]
+    min_rbf_bump: u64,
+
+    /// Fee rate (per 1000 bytes) a replacement pays on top of the fees it evicts
+    #[arg(long, default_value_t = rusty_chain::core::policy::DEFAULT_INCREMENTAL_RELAY_FEE_PER_KB)]
+    incremental_relay_fee: u64,
+}
+
+impl PolicyArgs {
+    fn policy(&self) -> MempoolPolicy {
+        MempoolPolicy {
+            max_bytes: self.max_mempool_bytes,
+            max_txs_per_sender: self.max_txs_per_sender,
+            min_relay_fee_per_kb: self.min_relay_fee,
+            min_rbf_bump_pct: self.min_rbf_bump,
+            incremental_relay_fee_per_kb: self.incremental_relay_fee,
+            ..MempoolPolicy::default()
+        }
+    }
+}
+
+/// `--fee` value: a fixed amount, or `auto` to use the fee estimator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FeeArg {
+    Fixed(u64),
+    Auto,
+}
+
+impl std::str::FromStr for FeeArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(FeeArg::Auto);
+        }
+        s.parse()
+            .map(FeeArg::Fixed)
+            .map_err(|_| format!("invalid fee '{s}': expected an amount or 'auto'"))
+    }
+}
+
+/// Spending from a condition address (see `core::script`).
+#[derive(Args, Debug)]
+struct ScriptSpendArgs {
+    /// Spend from a condition address: the condition as JSON, or a path to a JSON file.
+    /// Replaces `--from` with the condition's address.
+    #[arg(long, conflicts_with = "signer")]
+    script: Option<String>,
+
+    /// Hash-lock preimage (hex) for the witness (repeatable).
+    #[arg(long, requires = "script")]
+    preimage: Vec<String>,
+
+    /// Local key name whose signature goes into the witness (repeatable).
+    #[arg(long, requires = "script")]
+    witness_signer: Vec<String>,
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Generate a local keypair for signing transactions
@@ -36,11 +457,22 @@ enum Commands {
         name: String,
     },
 
+    /// Print the address of a spending condition (see `TxAdd --script`)
+    ScriptAddr {
+        /// Condition as JSON, or a path to a JSON file
+        condition: String,
+    },
+
     /// Initialize a new chain (writes genesis to disk)
     Init {
         /// Output path for chain JSON
         #[arg(long)]
         path: Option<String>,
+
+        /// Optional genesis config JSON (network, timestamp, difficulty, params, allocations).
+        /// Without it, an empty genesis stamped with the current time is created.
+        #[arg(long)]
+        genesis: Option<String>,
     },
 
     /// Print current chain status
@@ -54,6 +486,101 @@ enum Commands {
         mempool: Option<String>,
     },
 
+    /// Print the balance of an address or local key name
+    Balance {
+        /// Address, or the name of a local key (data/keys/<name>.json)
+        who: String,
+
+        /// Print the balance of this asset instead of the native coin
+        #[arg(long)]
+        asset: Option<String>,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+
+        /// Query this node (e.g. 127.0.0.1:9000) instead of the local files
+        #[arg(long)]
+        node: Option<String>,
+    },
+
+    /// Print an account's balance, nonces and pending spend
+    Account {
+        address: String,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+
+        /// Query this node (e.g. 127.0.0.1:9000) instead of the local files
+        #[arg(long)]
+        node: Option<String>,
+    },
+
+    /// Light client: sync PoW-checked headers from a node and print proven balances
+    Light {
+        /// Full node to sync from (needs --txindex to serve history and proofs)
+        #[arg(long)]
+        node: String,
+
+        /// Address or local key name to start tracking (repeatable)
+        #[arg(long)]
+        track: Vec<String>,
+
+        /// Light client state file (default: data/<network>/light.json)
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Chain JSON to take the genesis header from on first use
+        #[arg(long)]
+        chain: Option<String>,
+    },
+
+    /// Report circulating coin supply computed from the ledger state
+    Supply {
+        /// Input path for chain JSON
+        #[arg(long)]
+        path: Option<String>,
+    },
+
+    /// Export or import a state snapshot for fast bootstrap
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Atomic swaps between two chains with hash time-locked contracts
+    Swap {
+        #[command(subcommand)]
+        action: SwapAction,
+    },
+
+    /// Deploy, call and inspect VM contracts
+    Contract {
+        #[command(subcommand)]
+        action: ContractAction,
+    },
+
+    /// Create and list user-defined assets (send them with `tx-add --asset`)
+    Asset {
+        #[command(subcommand)]
+        action: AssetAction,
+    },
+
+    /// Propose and vote on chain parameter changes
+    Gov {
+        #[command(subcommand)]
+        action: GovAction,
+    },
+
     /// Validate chain invariants (genesis + linkage)
     Validate {
         /// Input path for chain JSON
@@ -75,7 +602,7 @@ enum Commands {
         #[arg(long, default_value_t = 3)]
         difficulty: usize,
 
-        /// Address to receive block reward (coinbase)
+        /// Address to receive block reward (coinbase); required unless the chain has lax coinbase rules
         #[arg(long)]
         miner: Option<String>,
     },
@@ -86,8 +613,9 @@ enum Commands {
         #[arg(long)]
         chain: Option<String>,
 
-        #[arg(long)]
-        from: String,
+        /// Sender address; not needed with `--signer` or `--script`, which set it.
+        #[arg(long, conflicts_with = "script", required_unless_present_any = ["signer", "script"])]
+        from: Option<String>,
 
         #[arg(long)]
         to: String,
@@ -95,8 +623,17 @@ enum Commands {
         #[arg(long)]
         amount: u64,
 
-        #[arg(long, default_value_t = 0)]
-        fee: u64,
+        /// Send this asset instead of the native coin (the fee is still native)
+        #[arg(long)]
+        asset: Option<String>,
+
+        /// Fee amount, or `auto` to estimate one for `--target` from chain history and mempool.
+        #[arg(long, default_value = "0")]
+        fee: FeeArg,
+
+        /// Confirmation target in blocks for `--fee auto`.
+        #[arg(long, default_value_t = DEFAULT_CONFIRM_TARGET)]
+        target: usize,
 
         /// Optional local key name (data/keys/<name>.json) to sign this tx.
         #[arg(long)]
@@ -126,32 +663,80 @@ enum Commands {
         #[arg(long)]
         locktime: Option<u64>,
 
-        /// Optional expiry (block height). If set, tx is invalid after chain reaches this height.
+        /// Optional expiry (block height). If set, tx is invalid after chain reaches this height.
+        #[arg(long)]
+        expiry: Option<u64>,
+
+        /// Optional priority (0-255). Used for mempool ordering.
+        #[arg(long, default_value_t = 0)]
+        priority: u8,
+
+        /// Optional node address to broadcast the transaction to (e.g. 127.0.0.1:9000)
+        #[arg(long)]
+        broadcast_to: Option<String>,
+
+        /// Optional explicit expiration timestamp (ms).
+        #[arg(long)]
+        expiration: Option<u64>,
+
+        /// Optional tag for the transaction.
+        #[arg(long)]
+        tag: Option<String>,
+
+        #[command(flatten)]
+        spend: Box<ScriptSpendArgs>,
+
+        #[command(flatten)]
+        policy: Box<PolicyArgs>,
+    },
+
+    /// List mempool transactions
+    TxList {
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+    },
+
+    /// Look up a confirmed or pending transaction by id (uses the tx index)
+    TxGet {
+        /// Transaction id (hash)
+        id: String,
+
+        /// Optional path for chain JSON
+        #[arg(long)]
+        chain: Option<String>,
+
+        /// Optional path for mempool JSON
+        #[arg(long)]
+        mempool: Option<String>,
+
+        /// Query this node (e.g. 127.0.0.1:9000) instead of the local files
         #[arg(long)]
-        expiry: Option<u64>,
+        node: Option<String>,
+    },
 
-        /// Optional priority (0-255). Used for mempool ordering.
-        #[arg(long, default_value_t = 0)]
-        priority: u8,
+    /// List the confirmed and pending transactions of an address (uses the tx index)
+    History {
+        address: String,
 
-        /// Optional node address to broadcast the transaction to (e.g. 127.0.0.1:9000)
+        /// Optional path for chain JSON
         #[arg(long)]
-        broadcast_to: Option<String>,
+        chain: Option<String>,
 
-        /// Optional explicit expiration timestamp (ms).
+        /// Optional path for mempool JSON
         #[arg(long)]
-        expiration: Option<u64>,
+        mempool: Option<String>,
 
-        /// Optional tag for the transaction.
+        /// Query this node (e.g. 127.0.0.1:9000) instead of the local files
         #[arg(long)]
-        tag: Option<String>,
+        node: Option<String>,
     },
 
-    /// List mempool transactions
-    TxList {
-        /// Optional path for mempool JSON
+    /// Rebuild the tx index (txindex.json next to the chain file) from scratch
+    TxIndexRebuild {
+        /// Optional path for chain JSON
         #[arg(long)]
-        mempool: Option<String>,
+        chain: Option<String>,
     },
 
     /// Evict expired transactions from the mempool
@@ -190,6 +775,17 @@ enum Commands {
         /// Path for peer list JSON
         #[arg(long)]
         peers_file: Option<String>,
+
+        /// Maintain a tx/address index to answer GetTx and GetHistory queries
+        #[arg(long)]
+        txindex: bool,
+
+        /// Pruning mode: keep tx bodies for only this many blocks below the tip (min 10)
+        #[arg(long, value_name = "KEEP_BLOCKS", conflicts_with = "txindex")]
+        prune: Option<usize>,
+
+        #[command(flatten)]
+        policy: PolicyArgs,
     },
 
     /// Query reputation of connected peers
@@ -269,6 +865,10 @@ enum Commands {
         /// Transaction size in bytes to estimate for
         #[arg(long, default_value_t = 250)]
         size: u32,
+
+        /// Confirmation target in blocks
+        #[arg(long, default_value_t = DEFAULT_CONFIRM_TARGET)]
+        target: usize,
     },
 
     /// Request mempool statistics from a node
@@ -279,30 +879,280 @@ enum Commands {
     },
 }
 
-fn chain_path(path: Option<String>) -> std::path::PathBuf {
+fn chain_path(path: Option<String>, network: &str) -> std::path::PathBuf {
     path.map(std::path::PathBuf::from)
-        .unwrap_or_else(Chain::default_path)
+        .unwrap_or_else(|| Chain::default_path_for(network))
+}
+
+/// Refuse to operate on a chain file from another network when `--network` was given.
+fn check_network(chain: &Chain, expected: Option<&str>) -> anyhow::Result<()> {
+    if let Some(expected) = expected {
+        anyhow::ensure!(
+            chain.network == expected,
+            "chain belongs to network {} (requested {})",
+            chain.network,
+            expected
+        );
+    }
+    Ok(())
 }
 
-fn load_chain(path: &std::path::Path) -> anyhow::Result<Chain> {
+fn load_chain(path: &std::path::Path, expected: Option<&str>) -> anyhow::Result<Chain> {
     anyhow::ensure!(
         path.exists(),
         "chain file does not exist: {}",
         path.display()
     );
-    Chain::load(path)
+    let chain = Chain::load(path)?;
+    check_network(&chain, expected)?;
+    Ok(chain)
 }
 
-fn mempool_path(path: Option<String>) -> std::path::PathBuf {
+fn mempool_path(path: Option<String>, network: &str) -> std::path::PathBuf {
     path.map(std::path::PathBuf::from)
-        .unwrap_or_else(Mempool::default_path)
+        .unwrap_or_else(|| Mempool::default_path_for(network))
+}
+
+fn load_or_genesis(path: &std::path::Path, expected: Option<&str>) -> anyhow::Result<Chain> {
+    if path.exists() {
+        load_chain(path, expected)
+    } else {
+        let network: Network = expected.unwrap_or("devnet").parse()?;
+        Ok(Chain::new_genesis_for(network))
+    }
+}
+
+/// Load (or build) the tx index next to `chain_path`, catch it up with `chain`, save it and
+/// attach it to `chain`.
+fn open_tx_index(chain: &mut Chain, chain_path: &std::path::Path) -> anyhow::Result<()> {
+    let path = TxIndex::path_for_chain(chain_path);
+    let mut index = if path.exists() {
+        TxIndex::load(&path)?
+    } else {
+        TxIndex::default()
+    };
+    if index.sync(chain) > 0 {
+        index.save(&path)?;
+    }
+    chain.tx_index = Some(index);
+    Ok(())
+}
+
+/// A local key name resolves to its address (pubkey hex); anything else is an address.
+fn resolve_address(who: String) -> anyhow::Result<String> {
+    let key_path = KeyFile::path_for(&who);
+    if key_path.exists() {
+        Ok(KeyFile::load(&key_path)?.verifying_key_hex)
+    } else {
+        Ok(who)
+    }
 }
 
-fn load_or_genesis(path: &std::path::Path) -> anyhow::Result<Chain> {
+/// A spending condition given inline as JSON, or as the path of a JSON file.
+fn read_condition(arg: &str) -> anyhow::Result<Condition> {
+    let path = std::path::Path::new(arg);
     if path.exists() {
-        Chain::load(path)
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("read condition {}", path.display()))?;
+        Condition::from_script(&json)
+    } else {
+        Condition::from_script(arg)
+    }
+}
+
+fn load_key(name: &str) -> anyhow::Result<KeyFile> {
+    let path = KeyFile::path_for(name);
+    anyhow::ensure!(path.exists(), "key not found: {}", path.display());
+    KeyFile::load(&path)
+}
+
+fn load_contract(path: &str) -> anyhow::Result<Htlc> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("read contract {path}"))?;
+    let htlc: Htlc =
+        serde_json::from_str(&json).with_context(|| format!("parse contract {path}"))?;
+    htlc.check()?;
+    Ok(htlc)
+}
+
+/// Sign `tx` with `key`; `tx.from` must already be the key's address.
+fn sign_tx(tx: &mut Transaction, key: &KeyFile) -> anyhow::Result<()> {
+    tx.pubkey_hex = Some(key.verifying_key_hex.clone());
+    tx.signature_b64 = Some(rusty_chain::core::crypto::sign_bytes(
+        &key.signing_key()?,
+        &tx.signing_bytes(),
+    ));
+    Ok(())
+}
+
+/// Build a contract tx from `from` (or the `signer` key's address) with the next free nonce,
+/// sign it if a key is given, check it against the chain and add it to the mempool. Returns
+/// the tx.
+#[allow(clippy::too_many_arguments)]
+fn submit_contract_tx(
+    chain: &Chain,
+    mp_path: &std::path::Path,
+    from: Option<String>,
+    signer: Option<String>,
+    to: Option<String>,
+    op: ContractOp,
+    amount: u64,
+    gas_limit: u64,
+    fee: Option<u64>,
+) -> anyhow::Result<Transaction> {
+    let (key, from, nonce) = next_sender(chain, mp_path, from, signer)?;
+    let to = to.unwrap_or_else(|| contract_address(&from, nonce));
+    let mut tx = Transaction::new_with_fee(from, to, amount, fee.unwrap_or(gas_limit), nonce, 0);
+    tx.chain_id = chain.chain_id;
+    tx.gas_limit = gas_limit;
+    tx.contract = Some(op);
+    if let Some(key) = &key {
+        sign_tx(&mut tx, key)?;
+    }
+    validate_after_pending(chain, mp_path, &tx)?;
+    submit_to_mempool(chain, mp_path, tx.clone())?;
+    Ok(tx)
+}
+
+/// Build a zero-amount tx to `to` like `submit_contract_tx`, with `set_op` filling in what it
+/// does, check it against the chain and add it to the mempool. Returns the tx.
+fn submit_op_tx(
+    chain: &Chain,
+    mp_path: &std::path::Path,
+    from: Option<String>,
+    signer: Option<String>,
+    to: &str,
+    fee: u64,
+    set_op: impl FnOnce(&mut Transaction),
+) -> anyhow::Result<Transaction> {
+    let (key, from, nonce) = next_sender(chain, mp_path, from, signer)?;
+    let mut tx = Transaction::new_with_fee(from, to, 0, fee, nonce, 0);
+    tx.chain_id = chain.chain_id;
+    set_op(&mut tx);
+    if let Some(key) = &key {
+        sign_tx(&mut tx, key)?;
+    }
+    validate_after_pending(chain, mp_path, &tx)?;
+    submit_to_mempool(chain, mp_path, tx.clone())?;
+    Ok(tx)
+}
+
+/// Fully validate `tx` against the chain after its pending ancestors in the mempool file.
+fn validate_after_pending(
+    chain: &Chain,
+    mp_path: &std::path::Path,
+    tx: &Transaction,
+) -> anyhow::Result<()> {
+    let pending = if mp_path.exists() {
+        Mempool::load(mp_path)?.ancestors_of(tx)
+    } else {
+        Vec::new()
+    };
+    chain.validate_transaction_after(&pending, tx)
+}
+
+/// The key (if `signer` is given), sender address and next free nonce (counting the mempool)
+/// for a new tx.
+fn next_sender(
+    chain: &Chain,
+    mp_path: &std::path::Path,
+    from: Option<String>,
+    signer: Option<String>,
+) -> anyhow::Result<(Option<KeyFile>, String, u64)> {
+    let key = signer.as_deref().map(load_key).transpose()?;
+    let from = match &key {
+        Some(k) => k.verifying_key_hex.clone(),
+        None => from.context("--from or --signer is required")?,
+    };
+    let base_nonce = chain.next_nonce_for(&from);
+    let nonce = if mp_path.exists() {
+        Mempool::load(mp_path)?.next_nonce_for(&from, base_nonce)
+    } else {
+        base_nonce
+    };
+    Ok((key, from, nonce))
+}
+
+/// Add `tx` to the mempool file at `mp_path`, checked against the sender's spendable balance.
+fn submit_to_mempool(
+    chain: &Chain,
+    mp_path: &std::path::Path,
+    tx: Transaction,
+) -> anyhow::Result<()> {
+    let mut mp = if mp_path.exists() {
+        Mempool::load(mp_path)?
+    } else {
+        Mempool::default()
+    };
+    let balance = chain
+        .compute_state()?
+        .get_spendable(&tx.from, chain.height() as u64 + 1);
+    let id = tx.id();
+    mp.add_tx_with_balance(tx.clone(), chain.next_nonce_for(&tx.from), balance)?;
+    mp.save(mp_path)?;
+    println!("Added tx to mempool: {}", mp_path.display());
+    println!(
+        "tx_hash={} from={} to={} amount={}",
+        id, tx.from, tx.to, tx.amount
+    );
+    Ok(())
+}
+
+/// Account state from a node, or from the local chain and mempool files.
+async fn fetch_account(
+    address: &str,
+    chain: Option<String>,
+    mempool: Option<String>,
+    node: Option<String>,
+    network: &str,
+    explicit_network: Option<&str>,
+) -> anyhow::Result<AccountInfo> {
+    use rusty_chain::core::network::Message;
+
+    if let Some(node) = node {
+        let msg = Message::GetAccount {
+            address: address.to_string(),
+        };
+        return match query_node(&node, msg, "Account").await? {
+            Message::Account(info) => Ok(info),
+            other => anyhow::bail!("Unexpected response: {:?}", other),
+        };
+    }
+    let p = chain_path(chain, network);
+    let chain = load_chain(&p, explicit_network)?;
+    let mp_path = mempool_path(mempool, network);
+    let mp = if mp_path.exists() {
+        Mempool::load(&mp_path)?
     } else {
-        Ok(Chain::new_genesis())
+        Mempool::default()
+    };
+    Ok(AccountInfo::new(
+        address,
+        &chain.compute_state()?,
+        &mp,
+        chain.height() as u64,
+    ))
+}
+
+/// Send one request to a node and wait for its `reply_type` reply.
+async fn query_node(
+    node: &str,
+    msg: rusty_chain::core::network::Message,
+    reply_type: &str,
+) -> anyhow::Result<rusty_chain::core::network::Message> {
+    let target: std::net::SocketAddr = node.parse().context("Invalid node address")?;
+    let mut stream = tokio::net::TcpStream::connect(target).await?;
+    msg.request_async(&mut stream, reply_type)
+        .await
+        .with_context(|| format!("query to {target} failed"))
+}
+
+fn print_mempool_events(events: &[MempoolEvent]) {
+    for event in events {
+        if let MempoolEvent::Removed { tx_id, reason } = event
+            && *reason != RemovalReason::Included
+        {
+            println!("Dropped tx {} from mempool: {}", tx_id, reason);
+        }
     }
 }
 
@@ -329,6 +1179,8 @@ fn validate_nonce_sequence(chain: &Chain, txs: &[Transaction]) -> anyhow::Result
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    let explicit_network = cli.network.as_deref();
+    let network = explicit_network.unwrap_or("devnet").to_string();
 
     match cli.command {
         Commands::Keygen { name, force } => {
@@ -353,18 +1205,156 @@ async fn main() -> anyhow::Result<()> {
             println!("path={}", path.display());
             println!("pubkey_hex={}", file.verifying_key_hex);
         }
-        Commands::Init { path } => {
-            let p = chain_path(path);
-            let chain = Chain::new_genesis();
+        Commands::ScriptAddr { condition } => {
+            let condition = read_condition(&condition)?;
+            println!("address={}", condition.address());
+            println!("script={}", condition.to_script());
+        }
+        Commands::Init { path, genesis } => {
+            let chain = match genesis {
+                Some(g) => {
+                    let g = std::path::PathBuf::from(g);
+                    anyhow::ensure!(g.exists(), "genesis file not found: {}", g.display());
+                    let config = GenesisConfig::load(&g)?;
+                    if let Some(n) = explicit_network {
+                        anyhow::ensure!(
+                            config.network == n,
+                            "genesis file is for network {} (requested {})",
+                            config.network,
+                            n
+                        );
+                    }
+                    config
+                        .build_chain()
+                        .with_context(|| format!("invalid genesis config: {}", g.display()))?
+                }
+                None => Chain::new_genesis_for(network.parse()?),
+            };
+            let p = chain_path(path, &chain.network);
             chain.save(&p)?;
             println!("Initialized chain at {}", p.display());
-            println!("height={} tip={}", chain.height(), chain.tip_hash());
+            println!(
+                "network={} chain_id={} height={} tip={}",
+                chain.network,
+                chain.chain_id,
+                chain.height(),
+                chain.tip_hash()
+            );
+            println!(
+                "genesis={} allocated={}",
+                chain.genesis_hash(),
+                chain.genesis_allocated()
+            );
+        }
+        Commands::Balance {
+            who,
+            asset,
+            chain,
+            mempool,
+            node,
+        } => {
+            let address = resolve_address(who)?;
+            let info =
+                fetch_account(&address, chain, mempool, node, &network, explicit_network).await?;
+            println!("address={}", info.address);
+            if let Some(asset) = asset {
+                println!(
+                    "asset={} balance={} height={}",
+                    asset,
+                    info.assets.get(&asset).copied().unwrap_or(0),
+                    info.height
+                );
+                return Ok(());
+            }
+            println!(
+                "balance={} available={} height={}",
+                info.balance,
+                info.available(),
+                info.height
+            );
+        }
+        Commands::Account {
+            address,
+            chain,
+            mempool,
+            node,
+        } => {
+            let info =
+                fetch_account(&address, chain, mempool, node, &network, explicit_network).await?;
+            println!("address={}", info.address);
+            println!("height={}", info.height);
+            println!("balance={}", info.balance);
+            println!("immature={}", info.immature);
+            println!("nonce={}", info.nonce);
+            println!("pending_nonce={}", info.pending_nonce);
+            println!("pending_txs={}", info.pending_txs);
+            println!("pending_spend={}", info.pending_spend);
+            println!("available={}", info.available());
+            for (asset, balance) in &info.assets {
+                println!("asset[{}]={}", asset, balance);
+            }
+        }
+        Commands::Light {
+            node,
+            track,
+            path,
+            chain,
+        } => {
+            let p = path
+                .map(std::path::PathBuf::from)
+                .unwrap_or_else(|| LightClient::default_path_for(&network));
+            let mut client = if p.exists() {
+                let client = LightClient::load(&p)?;
+                if let Some(n) = explicit_network {
+                    anyhow::ensure!(
+                        client.network == n,
+                        "light client state is for network {} (requested {})",
+                        client.network,
+                        n
+                    );
+                }
+                client
+            } else {
+                LightClient::from_genesis(&load_or_genesis(
+                    &chain_path(chain, &network),
+                    explicit_network,
+                )?)
+            };
+            for who in track {
+                client.track(&resolve_address(who)?);
+            }
+
+            let target: std::net::SocketAddr = node.parse().context("Invalid node address")?;
+            let report = client.sync(target).await?;
+            client.save(&p)?;
+
+            println!(
+                "height={} tip={} new_headers={} new_txs={}{}",
+                client.height(),
+                client.tip_hash(),
+                report.new_headers,
+                report.new_txs,
+                if report.reorged { " (reorged)" } else { "" }
+            );
+            for address in &client.addresses {
+                let state_proof = client
+                    .accounts
+                    .get(address)
+                    .map_or("none".to_string(), |a| format!("height {}", a.height));
+                println!(
+                    "address={} balance={} proven_txs={} state_proof={}",
+                    address,
+                    client.balance(address),
+                    client.history(address).len(),
+                    state_proof
+                );
+            }
         }
         Commands::Status { path, mempool } => {
-            let p = chain_path(path);
-            let chain = load_chain(&p)?;
+            let p = chain_path(path, &network);
+            let chain = load_chain(&p, explicit_network)?;
 
-            let mp_path = mempool_path(mempool);
+            let mp_path = mempool_path(mempool, &network);
             let mp_count = if mp_path.exists() {
                 Mempool::load(&mp_path)?.txs.len()
             } else {
@@ -380,10 +1370,492 @@ async fn main() -> anyhow::Result<()> {
                 chain.tx_count(),
                 mp_count
             );
+            if chain.pruned_height() > 0 {
+                println!("pruned_height={}", chain.pruned_height());
+            }
+        }
+        Commands::Contract {
+            action:
+                ContractAction::Deploy {
+                    code,
+                    from,
+                    signer,
+                    gas_limit,
+                    fee,
+                    amount,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let code = match std::fs::read_to_string(&code) {
+                Ok(text) => text,
+                Err(_) => code,
+            };
+            let ops = rusty_chain::core::vm::parse(&code)?;
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let tx = submit_contract_tx(
+                &chain,
+                &mempool_path(mempool, &network),
+                from,
+                signer,
+                None,
+                ContractOp::Deploy { code },
+                amount,
+                gas_limit,
+                fee,
+            )?;
+            println!("contract_address={}", tx.to);
+            println!(
+                "ops={} deploy_gas={}",
+                ops.len(),
+                ops.len() as u64 * rusty_chain::core::vm::DEPLOY_GAS_PER_OP
+            );
+        }
+        Commands::Contract {
+            action:
+                ContractAction::Call {
+                    address,
+                    arg,
+                    from,
+                    signer,
+                    gas_limit,
+                    fee,
+                    amount,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            anyhow::ensure!(
+                chain.compute_state()?.contracts.contains_key(&address),
+                "no contract at {}",
+                address
+            );
+            submit_contract_tx(
+                &chain,
+                &mempool_path(mempool, &network),
+                from,
+                signer,
+                Some(address),
+                ContractOp::Call { args: arg },
+                amount,
+                gas_limit,
+                fee,
+            )?;
+        }
+        Commands::Contract {
+            action: ContractAction::Inspect { address, chain },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let state = chain.compute_state()?;
+            let contract = state
+                .contracts
+                .get(&address)
+                .with_context(|| format!("no contract at {address}"))?;
+            println!("contract_address={}", address);
+            println!("code_hash={}", contract.code_hash());
+            println!("storage_root={}", contract.storage_root());
+            println!("balance={}", state.get_balance(&address));
+            for (key, value) in &contract.storage {
+                println!("storage[{}]={}", key, value);
+            }
+        }
+        Commands::Asset {
+            action:
+                AssetAction::Create {
+                    asset_id,
+                    supply,
+                    from,
+                    signer,
+                    fee,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            submit_op_tx(
+                &chain,
+                &mempool_path(mempool, &network),
+                from,
+                signer,
+                ASSET_REGISTRY_ADDRESS,
+                fee,
+                |tx| tx.create_asset = Some(AssetDefinition { asset_id, supply }),
+            )?;
+        }
+        Commands::Asset {
+            action: AssetAction::List { chain },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            for (asset_id, info) in &chain.compute_state()?.assets {
+                println!(
+                    "asset={} issuer={} supply={}",
+                    asset_id, info.issuer, info.supply
+                );
+            }
+        }
+        Commands::Gov {
+            action:
+                GovAction::Propose {
+                    param,
+                    value,
+                    activation_height,
+                    signer,
+                    fee,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let change = ParamChange::from_name(&param, value)?;
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let tx = submit_op_tx(
+                &chain,
+                &mempool_path(mempool, &network),
+                None,
+                Some(signer),
+                GOVERNANCE_ADDRESS,
+                fee,
+                |tx| {
+                    tx.governance = Some(GovernanceOp::Propose {
+                        change,
+                        activation_height,
+                    })
+                },
+            )?;
+            println!("proposal_id={}", tx.id());
+        }
+        Commands::Gov {
+            action:
+                GovAction::Vote {
+                    proposal_id,
+                    reject,
+                    signer,
+                    fee,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            submit_op_tx(
+                &chain,
+                &mempool_path(mempool, &network),
+                None,
+                Some(signer),
+                GOVERNANCE_ADDRESS,
+                fee,
+                |tx| {
+                    tx.governance = Some(GovernanceOp::Vote {
+                        proposal_id,
+                        approve: !reject,
+                    })
+                },
+            )?;
+        }
+        Commands::Gov {
+            action: GovAction::List { chain },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let state = chain.compute_state()?;
+            let next_height = chain.height() as u64 + 1;
+            for (id, proposal) in &state.governance.proposals {
+                println!(
+                    "proposal={} {} proposer={} voting_ends={} activation_height={} \
+                     approve={} reject={} total_weight={} passing={}",
+                    id,
+                    proposal.change,
+                    proposal.proposer,
+                    proposal.voting_ends,
+                    proposal.activation_height,
+                    proposal.tally(true),
+                    proposal.tally(false),
+                    proposal.total_weight,
+                    proposal.passed()
+                );
+            }
+            for scheduled in &state.governance.scheduled {
+                println!(
+                    "passed={} {} activation_height={} active={}",
+                    scheduled.proposal_id,
+                    scheduled.change,
+                    scheduled.activation_height,
+                    scheduled.activation_height <= next_height
+                );
+            }
+            let params = state.params_at(&chain.params, next_height);
+            println!(
+                "next_block_subsidy={} max_block_size={} max_block_txs={} min_tx_fee={}",
+                params.block_subsidy(next_height),
+                params.max_block_size,
+                params.max_block_txs,
+                params.min_tx_fee
+            );
+        }
+        Commands::Swap {
+            action: SwapAction::Secret,
+        } => {
+            let (secret, hash) = rusty_chain::core::htlc::new_secret();
+            println!("secret={}", secret);
+            println!("hash={}", hash);
+        }
+        Commands::Swap {
+            action:
+                SwapAction::Lock {
+                    signer,
+                    recipient,
+                    hash,
+                    timeout,
+                    amount,
+                    fee,
+                    counterparty_chain_id,
+                    out,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let key = load_key(&signer)?;
+            let htlc = Htlc {
+                chain_id: chain.chain_id,
+                counterparty_chain_id,
+                hash: hash.to_ascii_lowercase(),
+                recipient: resolve_address(recipient)?,
+                refund_to: key.verifying_key_hex.clone(),
+                timeout_height: timeout,
+                amount,
+            };
+            htlc.check()?;
+            anyhow::ensure!(
+                timeout > chain.height() as u64 + 1,
+                "timeout {} must be above the next block height {}",
+                timeout,
+                chain.height() + 1
+            );
+
+            let mp_path = mempool_path(mempool, &network);
+            let base_nonce = chain.next_nonce_for(&htlc.refund_to);
+            let nonce = if mp_path.exists() {
+                Mempool::load(&mp_path)?.next_nonce_for(&htlc.refund_to, base_nonce)
+            } else {
+                base_nonce
+            };
+            let mut tx = htlc.lock_tx(nonce, fee);
+            sign_tx(&mut tx, &key)?;
+            submit_to_mempool(&chain, &mp_path, tx)?;
+
+            std::fs::write(&out, serde_json::to_string_pretty(&htlc)?)
+                .with_context(|| format!("write contract {out}"))?;
+            println!("Wrote contract: {}", out);
+            println!("contract_address={}", htlc.address());
+            println!("timeout_height={}", htlc.timeout_height);
+        }
+        Commands::Swap {
+            action:
+                SwapAction::Claim {
+                    contract,
+                    signer,
+                    secret,
+                    fee,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let htlc = load_contract(&contract)?;
+            anyhow::ensure!(
+                htlc.chain_id == chain.chain_id,
+                "contract is on chain_id={}, not this chain ({})",
+                htlc.chain_id,
+                chain.chain_id
+            );
+            let address = htlc.address();
+            let balance = chain.compute_state()?.get_balance(&address);
+            let tx = htlc.claim_tx(
+                balance,
+                chain.next_nonce_for(&address),
+                fee,
+                &secret,
+                &load_key(&signer)?.signing_key()?,
+            )?;
+            chain.validate_transaction(&tx)?;
+            submit_to_mempool(&chain, &mempool_path(mempool, &network), tx)?;
+        }
+        Commands::Swap {
+            action:
+                SwapAction::Refund {
+                    contract,
+                    signer,
+                    fee,
+                    chain,
+                    mempool,
+                },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let htlc = load_contract(&contract)?;
+            anyhow::ensure!(
+                htlc.chain_id == chain.chain_id,
+                "contract is on chain_id={}, not this chain ({})",
+                htlc.chain_id,
+                chain.chain_id
+            );
+            let next_height = chain.height() as u64 + 1;
+            anyhow::ensure!(
+                next_height >= htlc.timeout_height,
+                "contract refunds from height {} (next block is {})",
+                htlc.timeout_height,
+                next_height
+            );
+            let address = htlc.address();
+            let balance = chain.compute_state()?.get_balance(&address);
+            let tx = htlc.refund_tx(
+                balance,
+                chain.next_nonce_for(&address),
+                fee,
+                &load_key(&signer)?.signing_key()?,
+            )?;
+            chain.validate_transaction(&tx)?;
+            submit_to_mempool(&chain, &mempool_path(mempool, &network), tx)?;
+        }
+        Commands::Swap {
+            action: SwapAction::Inspect { contract, chain },
+        } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let htlc = load_contract(&contract)?;
+            let address = htlc.address();
+            println!("contract_address={}", address);
+            println!(
+                "chain_id={} height={} timeout_height={}",
+                htlc.chain_id,
+                chain.height(),
+                htlc.timeout_height
+            );
+            println!(
+                "balance={} expected={}",
+                chain.compute_state()?.get_balance(&address),
+                htlc.amount
+            );
+            match htlc.find_secret(&chain) {
+                Some(secret) => println!("secret={}", secret),
+                None => println!("secret=(not revealed)"),
+            }
+        }
+        Commands::Supply { path } => {
+            let p = chain_path(path, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let state = chain.compute_state()?;
+            let height = chain.height() as u64;
+            let params = &chain.params;
+
+            println!("chain: {}", p.display());
+            println!("height={}", height);
+            println!("circulating_supply={}", state.total_supply());
+            println!("genesis_allocated={}", chain.genesis_allocated());
+            let audit = chain.supply_audit()?;
+            println!("total_minted={}", audit.minted);
+            println!("total_burned={}", audit.burned);
+            println!("scheduled_issuance={}", params.issued_through(height));
+            println!("next_block_subsidy={}", params.block_subsidy(height + 1));
+            if params.max_supply > 0 {
+                println!("max_supply={}", params.max_supply);
+            } else {
+                println!("max_supply=none");
+            }
+        }
+        Commands::Snapshot {
+            action: SnapshotAction::Export { height, out, path },
+        } => {
+            let p = chain_path(path, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let height = match height {
+                Some(h) => h,
+                None => chain.get_last_checkpoint().map(|(h, _)| h).unwrap_or(0),
+            };
+            let snapshot = Snapshot::from_chain(&chain, height)?;
+            let out = out.map(std::path::PathBuf::from).unwrap_or_else(|| {
+                data_dir_for(&chain.network).join(format!("snapshot-{height}.json.gz"))
+            });
+            let hash = snapshot.save(&out)?;
+            println!("Wrote snapshot: {}", out.display());
+            println!(
+                "height={} block_hash={} accounts={}",
+                height,
+                snapshot.block_hash(),
+                snapshot.state.accounts.len()
+            );
+            println!("snapshot_hash={}", hash);
+        }
+        Commands::Snapshot {
+            action:
+                SnapshotAction::Import {
+                    file,
+                    checkpoint,
+                    path,
+                    force,
+                },
+        } => {
+            let snapshot = Snapshot::load(std::path::Path::new(&file))?;
+            if let Some(n) = explicit_network {
+                anyhow::ensure!(
+                    snapshot.network == n,
+                    "snapshot is for network {} (requested {})",
+                    snapshot.network,
+                    n
+                );
+            }
+            let height = snapshot.height();
+            let p = chain_path(path, &snapshot.network);
+            let local = if p.exists() {
+                Some(load_chain(&p, explicit_network)?)
+            } else {
+                None
+            };
+            if let Some(local) = &local {
+                snapshot
+                    .check_local(local)
+                    .with_context(|| format!("can't import into {}", p.display()))?;
+                anyhow::ensure!(
+                    force || local.height() == 0,
+                    "chain file {} already has {} blocks (use --force to replace it)",
+                    p.display(),
+                    local.height()
+                );
+            }
+            let trusted = match checkpoint {
+                Some(hash) => hash,
+                None => local
+                    .as_ref()
+                    .and_then(|c| c.get_checkpoint_at(height))
+                    .with_context(|| {
+                        format!(
+                            "no trusted checkpoint for height {height}; pass --checkpoint <block hash>"
+                        )
+                    })?,
+            };
+            let chain = snapshot.into_chain(&trusted)?;
+            chain.save(&p)?;
+            println!("Imported snapshot into {}", p.display());
+            println!(
+                "network={} height={} tip={} accounts={}",
+                chain.network,
+                chain.height(),
+                chain.tip_hash(),
+                chain.compute_state()?.accounts.len()
+            );
         }
         Commands::Validate { path } => {
-            let p = chain_path(path);
-            let chain = load_chain(&p)?;
+            let p = chain_path(path, &network);
+            let chain = load_chain(&p, explicit_network)?;
             chain.validate()?;
             println!("OK: chain is valid (height={})", chain.height());
         }
@@ -393,39 +1865,57 @@ async fn main() -> anyhow::Result<()> {
             difficulty,
             miner,
         } => {
-            let p = chain_path(path);
-            let mut chain = load_or_genesis(&p)?;
+            let p = chain_path(path, &network);
+            let mut chain = load_or_genesis(&p, explicit_network)?;
 
-            let mp_path = mempool_path(mempool);
+            let mp_path = mempool_path(mempool, &network);
             let mut mp = if mp_path.exists() {
                 Mempool::load(&mp_path)?
             } else {
                 Mempool::default()
             };
 
-            // Validate mempool txs before draining so we don't lose them on failure.
-            for (i, tx) in mp.txs.iter().enumerate() {
+            // Drop txs that became unminable since they were added.
+            let next_height = chain.height() as u64 + 1;
+            let now = rusty_chain::core::time::now_ms();
+            let state = chain.compute_state()?;
+            print_mempool_events(&mp.revalidate(&state, next_height, now));
+
+            // Build the block template: best fee-per-byte txs that fit the block limits.
+            // Everything else stays in the mempool for a later block.
+            let txs = chain.select_block_txs(&mp, miner.as_deref());
+            for (i, tx) in txs.iter().enumerate() {
                 tx.validate_accept()
                     .with_context(|| format!("invalid mempool tx #{i}"))?;
             }
 
-            validate_nonce_sequence(&chain, &mp.txs)?;
+            validate_nonce_sequence(&chain, &txs)?;
 
-            let txs = mp.drain();
             let mined = chain.mine_block(txs, difficulty, miner.as_deref())?;
+            let state = chain.compute_state()?;
+            let next_height = chain.height() as u64 + 1;
+            print_mempool_events(&mp.on_block_connected(&mined, &state, next_height, now));
             chain.save(&p)?;
             mp.save(&mp_path)?;
 
+            // Keep an existing tx index current.
+            let index_path = TxIndex::path_for_chain(&p);
+            if index_path.exists() {
+                open_tx_index(&mut chain, &p)?;
+            }
+
             println!("Mined block at height={}", chain.height());
             if let Some(m) = miner {
                 println!("Miner reward sent to: {}", m);
             }
             println!(
-                "nonce={} tip={} difficulty={} txs={}",
+                "nonce={} tip={} difficulty={} txs={} size={} left_in_mempool={}",
                 mined.header.nonce,
                 chain.tip_hash(),
                 chain.pow_difficulty,
-                mined.txs.len()
+                mined.txs.len(),
+                mined.size(),
+                mp.len()
             );
         }
         Commands::TxAdd {
@@ -433,7 +1923,9 @@ async fn main() -> anyhow::Result<()> {
             from,
             to,
             amount,
+            asset,
             fee,
+            target,
             signer,
             nonce,
             sequence,
@@ -446,9 +1938,25 @@ async fn main() -> anyhow::Result<()> {
             broadcast_to,
             expiration,
             tag,
+            spend,
+            policy,
         } => {
-            let chain_path = chain_path(chain);
-            let chain = load_or_genesis(&chain_path)?;
+            let ScriptSpendArgs {
+                script,
+                preimage,
+                witness_signer,
+            } = *spend;
+            let chain_path = chain_path(chain, &network);
+            let chain = load_or_genesis(&chain_path, explicit_network)?;
+            let condition = script.as_deref().map(read_condition).transpose()?;
+            let witness_keys = witness_signer
+                .iter()
+                .map(|name| {
+                    let kp_path = KeyFile::path_for(name);
+                    anyhow::ensure!(kp_path.exists(), "key not found: {}", kp_path.display());
+                    KeyFile::load(&kp_path)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
 
             // If we're signing, bind `from` to the signer's address (pubkey hex).
             let signer_file: Option<KeyFile> = if let Some(name) = signer {
@@ -459,25 +1967,27 @@ async fn main() -> anyhow::Result<()> {
                 None
             };
 
-            let effective_from = signer_file
-                .as_ref()
-                .map(|f| f.verifying_key_hex.clone())
-                .unwrap_or(from);
+            let effective_from = match (&signer_file, &condition) {
+                (Some(f), _) => f.verifying_key_hex.clone(),
+                (None, Some(c)) => c.address(),
+                (None, None) => from.context("--from, --signer or --script is required")?,
+            };
 
             let base_nonce = chain.next_nonce_for(&effective_from);
 
-            let mp_path = mempool_path(mempool);
+            let mp_path = mempool_path(mempool, &network);
             let mut mp = if mp_path.exists() {
                 Mempool::load(&mp_path)?
             } else {
                 Mempool::default()
             };
+            mp.policy = policy.policy();
 
             let filled_nonce =
                 nonce.unwrap_or_else(|| mp.next_nonce_for(&effective_from, base_nonce));
 
             let mut tx = Transaction::new(effective_from.clone(), to, amount, filled_nonce);
-            tx.fee = fee;
+            tx.asset = asset;
             tx.sequence = sequence;
             tx.memo = memo;
             tx.locktime = locktime;
@@ -485,19 +1995,74 @@ async fn main() -> anyhow::Result<()> {
             tx.priority = priority;
             tx.expiration_ms = expiration.unwrap_or(0);
             tx.tag = tag;
+            tx.script = condition.as_ref().map(|c| c.to_script());
             if let Some(ts) = timestamp {
                 tx.timestamp_ms = ts;
             }
 
-            if let Some(file) = signer_file {
-                tx.pubkey_hex = Some(file.verifying_key_hex.clone());
-                let sk = file.signing_key()?;
-                let sig = rusty_chain::core::crypto::sign_bytes(&sk, &tx.signing_bytes());
-                tx.signature_b64 = Some(sig);
+            let sign = |tx: &mut Transaction| -> anyhow::Result<()> {
+                if let Some(file) = &signer_file {
+                    tx.chain_id = chain.chain_id;
+                    tx.pubkey_hex = Some(file.verifying_key_hex.clone());
+                    let sk = file.signing_key()?;
+                    let sig = rusty_chain::core::crypto::sign_bytes(&sk, &tx.signing_bytes());
+                    tx.signature_b64 = Some(sig);
+                }
+                if condition.is_some() {
+                    tx.chain_id = chain.chain_id;
+                    let mut witness = Witness {
+                        preimages: preimage.clone(),
+                        signatures: Vec::new(),
+                    };
+                    for file in &witness_keys {
+                        let sk = file.signing_key()?;
+                        witness.signatures.push(WitnessSignature {
+                            pubkey_hex: file.verifying_key_hex.clone(),
+                            signature_b64: rusty_chain::core::crypto::sign_bytes(
+                                &sk,
+                                &tx.signing_bytes(),
+                            ),
+                        });
+                    }
+                    tx.witness = Some(witness);
+                }
+                Ok(())
+            };
+
+            match fee {
+                FeeArg::Fixed(fee) => tx.fee = fee,
+                FeeArg::Auto => {
+                    let params = chain.params_at(chain.height() + 1)?;
+                    let estimate = FeeEstimator::from_chain(&chain).estimate(
+                        target,
+                        &mp,
+                        params.max_block_size,
+                    );
+                    // The fee's own digits change the size; settle on a fee that covers it.
+                    for _ in 0..4 {
+                        sign(&mut tx)?;
+                        let needed = estimate.fee_for_size(tx.size());
+                        if needed == tx.fee {
+                            break;
+                        }
+                        tx.fee = needed;
+                    }
+                    println!(
+                        "fee_estimate: {} per kB within {} blocks",
+                        estimate.fee_per_kb, estimate.target_blocks
+                    );
+                }
             }
+            sign(&mut tx)?;
+            // Full checks (witness, asset balance, contract rules) after the sender's
+            // pending txs, as a block would apply them.
+            chain.validate_transaction_after(&mp.ancestors_of(&tx), &tx)?;
 
             let h = tx.id();
-            mp.add_tx_checked(tx.clone(), base_nonce)?;
+            let balance = chain
+                .compute_state()?
+                .get_spendable(&tx.from, chain.height() as u64 + 1);
+            mp.add_tx_with_balance(tx.clone(), base_nonce, balance)?;
             mp.save(&mp_path)?;
             println!("Added tx to mempool: {}", mp_path.display());
             println!("tx_hash={}", h);
@@ -521,8 +2086,121 @@ async fn main() -> anyhow::Result<()> {
                 println!("Broadcast successful.");
             }
         }
+        Commands::TxGet {
+            id,
+            chain,
+            mempool,
+            node,
+        } => {
+            use rusty_chain::core::network::Message;
+
+            let (tx, location, confirmations) = if let Some(node) = node {
+                match query_node(&node, Message::GetTx { tx_id: id.clone() }, "TxInfo").await? {
+                    Message::TxInfo {
+                        tx,
+                        location,
+                        confirmations,
+                        ..
+                    } => (tx, location, confirmations),
+                    other => anyhow::bail!("Unexpected response: {:?}", other),
+                }
+            } else {
+                let p = chain_path(chain, &network);
+                let mut chain = load_chain(&p, explicit_network)?;
+                open_tx_index(&mut chain, &p)?;
+                let mp_path = mempool_path(mempool, &network);
+                let mp = if mp_path.exists() {
+                    Mempool::load(&mp_path)?
+                } else {
+                    Mempool::default()
+                };
+                match chain.find_tx(&id)? {
+                    Some((loc, tx)) => (
+                        Some(tx.clone()),
+                        Some(loc),
+                        (chain.height() - loc.height) as u64 + 1,
+                    ),
+                    None => (mp.get_tx_by_id(&id).cloned(), None, 0),
+                }
+            };
+
+            let tx = tx.with_context(|| format!("tx not found: {id}"))?;
+            match location {
+                Some(loc) => println!(
+                    "status=confirmed height={} index={} confirmations={}",
+                    loc.height, loc.index, confirmations
+                ),
+                None => println!("status=pending"),
+            }
+            println!("{}", serde_json::to_string_pretty(&tx)?);
+        }
+        Commands::History {
+            address,
+            chain,
+            mempool,
+            node,
+        } => {
+            use rusty_chain::core::network::Message;
+
+            let (confirmed, pending) = if let Some(node) = node {
+                let msg = Message::GetHistory {
+                    address: address.clone(),
+                };
+                match query_node(&node, msg, "History").await? {
+                    Message::History {
+                        confirmed, pending, ..
+                    } => (confirmed, pending),
+                    other => anyhow::bail!("Unexpected response: {:?}", other),
+                }
+            } else {
+                let p = chain_path(chain, &network);
+                let mut chain = load_chain(&p, explicit_network)?;
+                open_tx_index(&mut chain, &p)?;
+                let mp_path = mempool_path(mempool, &network);
+                let mp = if mp_path.exists() {
+                    Mempool::load(&mp_path)?
+                } else {
+                    Mempool::default()
+                };
+                let confirmed = chain
+                    .address_history(&address)?
+                    .into_iter()
+                    .map(|(loc, tx)| (tx.id(), loc))
+                    .collect::<Vec<_>>();
+                let pending = mp
+                    .txs
+                    .iter()
+                    .filter(|t| t.from == address || t.to == address)
+                    .map(|t| t.id())
+                    .collect::<Vec<_>>();
+                (confirmed, pending)
+            };
+
+            println!("address={}", address);
+            println!("confirmed={} pending={}", confirmed.len(), pending.len());
+            for (id, loc) in confirmed {
+                println!("  {} height={} index={}", id, loc.height, loc.index);
+            }
+            for id in pending {
+                println!("  {} pending", id);
+            }
+        }
+        Commands::TxIndexRebuild { chain } => {
+            let p = chain_path(chain, &network);
+            let chain = load_chain(&p, explicit_network)?;
+            let index = TxIndex::build(&chain);
+            let path = TxIndex::path_for_chain(&p);
+            index.save(&path)?;
+            println!(
+                "Rebuilt tx index: {} (height={} txs={} addresses={})",
+                path.display(),
+                index.height,
+                index.txs.len(),
+                index.addresses.len()
+            );
+        }
         Commands::TxList { mempool } => {
-            let mp_path = mempool_path(mempool);
+            let mp_path = mempool_path(mempool, &network);
             if !mp_path.exists() {
                 println!("mempool: {} (empty)", mp_path.display());
                 return Ok(());
@@ -561,7 +2239,7 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         Commands::TxEvict { mempool, ttl: _ttl } => {
-            let mp_path = mempool_path(mempool);
+            let mp_path = mempool_path(mempool, &network);
             if !mp_path.exists() {
                 println!("mempool: {} (not found)", mp_path.display());
                 return Ok(());
@@ -582,24 +2260,46 @@ async fn main() -> anyhow::Result<()> {
             path,
             mempool,
             peers_file,
+            txindex,
+            prune,
+            policy,
         } => {
             use std::net::{IpAddr, Ipv4Addr, SocketAddr};
             let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
 
-            let chain_path = chain_path(path);
-            let chain = load_or_genesis(&chain_path)?;
+            let chain_path = chain_path(path, &network);
+            let mut chain = load_or_genesis(&chain_path, explicit_network)?;
+            if txindex {
+                chain.enable_tx_index();
+            }
+            if let Some(keep_blocks) = prune {
+                let pruned = chain.set_prune_keep_blocks(keep_blocks)?;
+                chain.save(&chain_path)?;
+                println!(
+                    "Pruning mode: keeping {} blocks, pruned {} (pruned height={})",
+                    keep_blocks,
+                    pruned,
+                    chain.pruned_height()
+                );
+            }
 
-            let mp_path = mempool_path(mempool);
-            let mp = if mp_path.exists() {
+            let mp_path = mempool_path(mempool, &network);
+            let mut mp = if mp_path.exists() {
                 Mempool::load(&mp_path)?
             } else {
                 Mempool::default()
             };
+            mp.policy = policy.policy();
 
             let height = chain.height() as u64;
 
-            // Optional whitelist path: data/whitelist.json
-            let whitelist_path = Some("data/whitelist.json".to_string());
+            // Optional whitelist path: data/<network>/whitelist.json
+            let whitelist_path = Some(
+                data_dir_for(&chain.network)
+                    .join("whitelist.json")
+                    .to_string_lossy()
+                    .into_owned(),
+            );
 
             let agent_str =
                 agent.unwrap_or_else(|| format!("rusty-chain/{}", env!("CARGO_PKG_VERSION")));
@@ -777,16 +2477,17 @@ async fn main() -> anyhow::Result<()> {
                 peer_to_unwhitelist, target
             );
         }
-        Commands::FeeEstimate { node, size } => {
+        Commands::FeeEstimate { node, size, target } => {
             use rusty_chain::core::network::Message;
             use std::net::SocketAddr;
             use tokio::net::TcpStream;
 
-            let target: SocketAddr = node.parse().context("Invalid node address")?;
-            let mut stream = TcpStream::connect(target).await?;
+            let addr: SocketAddr = node.parse().context("Invalid node address")?;
+            let mut stream = TcpStream::connect(addr).await?;
 
             Message::GetFeeEstimate {
                 tx_size: size as usize,
+                target_blocks: target,
             }
             .send_async(&mut stream)
             .await?;
@@ -795,10 +2496,14 @@ async fn main() -> anyhow::Result<()> {
             if let Message::FeeEstimate {
                 fee_per_byte,
                 estimated_total,
+                target_blocks,
+                fee_per_kb,
             } = response
             {
-                println!("Fee estimate from {}:", target);
+                println!("Fee estimate from {}:", addr);
                 println!("  tx_size: {} bytes", size);
+                println!("  target_blocks: {}", target_blocks);
+                println!("  fee_per_kb: {} units", fee_per_kb);
                 println!("  fee_per_byte: {} units", fee_per_byte);
                 println!("  estimated_total: {} units", estimated_total);
             } else {
@@ -821,6 +2526,7 @@ async fn main() -> anyhow::Result<()> {
                 total_size,
                 min_fee,
                 max_fee,
+                min_relay_fee_per_kb,
             } = response
             {
                 println!("Mempool info from {}:", target);
@@ -828,6 +2534,7 @@ async fn main() -> anyhow::Result<()> {
                 println!("  total_size: {} bytes", total_size);
                 println!("  min_fee: {}", min_fee);
                 println!("  max_fee: {}", max_fee);
+                println!("  min_relay_fee_per_kb: {}", min_relay_fee_per_kb);
             } else {
                 println!("Unexpected response: {:?}", response);
             }
@@ -0,0 +1,92 @@
+mod common;
+
+use common::funded_chain;
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::{AccountInfo, Message};
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle, PeerCmd};
+use rusty_chain::core::types::Transaction;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Alice has one confirmed tx and two pending ones.
+fn chain_and_mempool() -> (Chain, Mempool) {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    chain
+        .mine_block(
+            vec![Transaction::new_with_fee("alice", "bob", 10, 1, 0, 0)],
+            1,
+            Some("miner"),
+        )
+        .unwrap();
+    let mut mp = Mempool::new();
+    mp.add_tx_checked(Transaction::new_with_fee("alice", "bob", 20, 2, 1, 0), 1)
+        .unwrap();
+    mp.add_tx_checked(Transaction::new_with_fee("alice", "carol", 5, 1, 2, 0), 1)
+        .unwrap();
+    (chain, mp)
+}
+
+#[test]
+fn account_info_combines_state_and_mempool() {
+    let (chain, mp) = chain_and_mempool();
+    let state = chain.compute_state().unwrap();
+
+    let alice = AccountInfo::new("alice", &state, &mp, chain.height() as u64);
+    assert_eq!(
+        alice,
+        AccountInfo {
+            address: "alice".to_string(),
+            height: 1,
+            balance: 89,
+            immature: 0,
+            nonce: 1,
+            pending_nonce: 3,
+            pending_spend: 28,
+            pending_txs: 2,
+            assets: Default::default(),
+        }
+    );
+    assert_eq!(alice.available(), 61);
+
+    // Incoming pending txs don't count until confirmed.
+    let bob = AccountInfo::new("bob", &state, &mp, 1);
+    assert_eq!((bob.balance, bob.nonce, bob.pending_nonce), (10, 0, 0));
+
+    let nobody = AccountInfo::new("nobody", &state, &mp, 1);
+    assert_eq!((nobody.balance, nobody.available()), (0, 0));
+}
+
+#[tokio::test]
+async fn node_answers_get_account() {
+    let (chain, mp) = chain_and_mempool();
+    let node = P2PNode::new("127.0.0.1:9000".parse().unwrap(), chain, mp, None, None);
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    let peer: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    node.state.lock().await.peer_senders.insert(peer, tx);
+
+    handle
+        .process_message(
+            Message::GetAccount {
+                address: "alice".to_string(),
+            },
+            peer,
+        )
+        .await
+        .unwrap();
+
+    match rx.recv().await {
+        Some(PeerCmd::SendMessage(msg)) => match *msg {
+            Message::Account(info) => {
+                assert_eq!(info.balance, 89);
+                assert_eq!(info.pending_nonce, 3);
+                assert_eq!(info.pending_spend, 28);
+            }
+            other => panic!("unexpected: {other:?}"),
+        },
+        other => panic!("unexpected: {other:?}"),
+    }
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::{funded_chain, mine};
+use rusty_chain::core::asset::{ASSET_REGISTRY_ADDRESS, AssetDefinition, check_asset_id};
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::mempool::{Mempool, MempoolEvent, RemovalReason};
+use rusty_chain::core::state_tree::state_root;
+use rusty_chain::core::types::Transaction;
+
+fn create(chain: &Chain, issuer: &str, asset_id: &str, supply: u64) -> Transaction {
+    let nonce = chain.next_nonce_for(issuer);
+    let mut tx = Transaction::new_with_fee(issuer, ASSET_REGISTRY_ADDRESS, 0, 1, nonce, 0);
+    tx.create_asset = Some(AssetDefinition {
+        asset_id: asset_id.to_string(),
+        supply,
+    });
+    tx
+}
+
+fn send(chain: &Chain, from: &str, to: &str, asset_id: &str, amount: u64) -> Transaction {
+    let nonce = chain.next_nonce_for(from);
+    let mut tx = Transaction::new_with_fee(from, to, amount, 2, nonce, 0);
+    tx.asset = Some(asset_id.to_string());
+    tx
+}
+
+#[test]
+fn created_asset_moves_between_accounts_with_native_fees() {
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let tx = create(&chain, "alice", "PROJ", 1_000);
+    mine(&mut chain, vec![tx]).unwrap();
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.assets["PROJ"].issuer, "alice");
+    assert_eq!(state.assets["PROJ"].supply, 1_000);
+    assert_eq!(state.get_asset_balance("alice", "PROJ"), 1_000);
+    let root_before = state_root(&state);
+
+    let tx = send(&chain, "alice", "bob", "PROJ", 300);
+    mine(&mut chain, vec![tx]).unwrap();
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_asset_balance("alice", "PROJ"), 700);
+    assert_eq!(state.get_asset_balance("bob", "PROJ"), 300);
+    // The amount was in PROJ; only the fees came out of the native balance.
+    assert_eq!(state.get_balance("alice"), 10_000 - 1 - 2);
+    assert_eq!(state.get_balance("bob"), 0);
+    assert_ne!(state_root(&state), root_before);
+    state.check_assets().unwrap();
+
+    // Sending a whole balance drops the entry.
+    let tx = send(&chain, "alice", "carol", "PROJ", 700);
+    mine(&mut chain, vec![tx]).unwrap();
+    let state = chain.compute_state().unwrap();
+    assert!(!state.accounts["alice"].assets.contains_key("PROJ"));
+    assert_eq!(state.get_asset_balance("carol", "PROJ"), 700);
+    chain.validate().unwrap();
+
+    // Tampering with a balance breaks the supply check.
+    let mut forged = state.clone();
+    forged
+        .accounts
+        .get_mut("carol")
+        .unwrap()
+        .assets
+        .insert("PROJ".to_string(), 900);
+    assert!(forged.check_assets().is_err());
+}
+
+#[test]
+fn asset_txs_are_checked_before_inclusion() {
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let tx = create(&chain, "alice", "PROJ", 1_000);
+    mine(&mut chain, vec![tx]).unwrap();
+
+    // Unknown assets, overdrafts, and senders without native coin for the fee.
+    assert!(
+        chain
+            .validate_transaction(&send(&chain, "alice", "bob", "NOPE", 1))
+            .is_err()
+    );
+    let err = chain
+        .validate_transaction(&send(&chain, "alice", "bob", "PROJ", 1_001))
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("PROJ balance"), "err={err:#}");
+    let tx = send(&chain, "alice", "bob", "PROJ", 10);
+    mine(&mut chain, vec![tx]).unwrap();
+    assert!(
+        chain
+            .validate_transaction(&send(&chain, "bob", "carol", "PROJ", 5))
+            .is_err()
+    );
+
+    // Ids are unique and well-formed, supplies non-zero, and creations carry no amount.
+    let err = chain
+        .validate_transaction(&create(&chain, "carol", "PROJ", 5))
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("already exists"), "err={err:#}");
+    assert!(
+        chain
+            .validate_transaction(&create(&chain, "carol", "proj", 5))
+            .is_err()
+    );
+    assert!(
+        chain
+            .validate_transaction(&create(&chain, "carol", "NEW", 0))
+            .is_err()
+    );
+    let mut paying = create(&chain, "carol", "NEW", 5);
+    paying.amount = 5;
+    assert!(chain.validate_transaction(&paying).is_err());
+
+    // The registry only takes creations.
+    let plain = Transaction::new_with_fee("carol", ASSET_REGISTRY_ADDRESS, 5, 1, 0, 0);
+    assert!(chain.validate_transaction(&plain).is_err());
+    let tokens = send(&chain, "alice", ASSET_REGISTRY_ADDRESS, "PROJ", 5);
+    assert!(chain.validate_transaction(&tokens).is_err());
+
+    // Two creations of one id can't share a block.
+    let (a, b) = (
+        create(&chain, "alice", "TWIN", 5),
+        create(&chain, "carol", "TWIN", 5),
+    );
+    assert!(mine(&mut chain, vec![a, b]).is_err());
+    chain.validate().unwrap();
+}
+
+#[test]
+fn mempool_charges_asset_transfers_only_their_fee() {
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let tx = create(&chain, "alice", "PROJ", 1_000_000);
+    mine(&mut chain, vec![tx]).unwrap();
+
+    let mut mp = Mempool::default();
+    let tx = send(&chain, "alice", "bob", "PROJ", 500_000);
+    let balance = chain
+        .compute_state()
+        .unwrap()
+        .get_spendable("alice", chain.height() as u64 + 1);
+    mp.add_tx_with_balance(tx, chain.next_nonce_for("alice"), balance)
+        .unwrap();
+    assert_eq!(mp.pending_spend("alice"), 2);
+
+    let txs = mp.drain();
+    mine(&mut chain, txs).unwrap();
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_asset_balance("bob", "PROJ"), 500_000);
+}
+
+#[test]
+fn asset_ids_are_checked() {
+    for ok in ["PROJ", "USD-1", "A", "ABCDEFGHIJKLMNOP"] {
+        check_asset_id(ok).unwrap();
+    }
+    for bad in ["", "proj", "1ABC", "-X", "ABCDEFGHIJKLMNOPQ", "A B", "ÄB"] {
+        assert!(check_asset_id(bad).is_err(), "{bad:?}");
+    }
+}
+
+#[test]
+fn block_draining_an_asset_balance_evicts_pooled_transfers() {
+    let mut chain = funded_chain(&[("alice", 10_000)]);
+    let tx = create(&chain, "alice", "PROJ", 1_000);
+    mine(&mut chain, vec![tx]).unwrap();
+
+    let mut mp = Mempool::default();
+    let first = send(&chain, "alice", "bob", "PROJ", 300);
+    let mut dependent = send(&chain, "alice", "carol", "PROJ", 600);
+    dependent.nonce += 1;
+    mp.add_tx(first.clone()).unwrap();
+    mp.add_tx(dependent.clone()).unwrap();
+
+    // A competing tx with the first nonce spends all of alice's PROJ.
+    let drain = send(&chain, "alice", "dave", "PROJ", 1_000);
+    let block = chain.mine_block(vec![drain], 1, Some("miner")).unwrap();
+    let state = chain.compute_state().unwrap();
+    let events = mp.on_block_connected(&block, &state, chain.height() as u64 + 1, 0);
+    assert_eq!(
+        events,
+        vec![
+            MempoolEvent::Removed {
+                tx_id: first.id(),
+                reason: RemovalReason::NonceConflict,
+            },
+            MempoolEvent::Removed {
+                tx_id: dependent.id(),
+                reason: RemovalReason::InsufficientBalance,
+            },
+        ]
+    );
+    assert!(mp.is_empty());
+
+    // A transfer after a pending create is covered by the new supply.
+    let create_tx = create(&chain, "alice", "NEXT", 500);
+    let mut spend = send(&chain, "alice", "bob", "NEXT", 500);
+    spend.nonce += 1;
+    mp.add_tx(create_tx).unwrap();
+    mp.add_tx(spend).unwrap();
+    assert!(
+        mp.revalidate(&state, chain.height() as u64 + 1, 0)
+            .is_empty()
+    );
+    let picked = chain.select_block_txs(&mp, Some("miner"));
+    assert_eq!(picked.len(), 2);
+    mine(&mut chain, picked).unwrap();
+}
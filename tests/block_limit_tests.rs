@@ -0,0 +1,126 @@
+mod common;
+
+use common::funded_chain;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::types::Transaction;
+
+fn tx(from: &str, nonce: u64, fee: u64) -> Transaction {
+    Transaction {
+        from: from.to_string(),
+        to: "bob".to_string(),
+        amount: 1,
+        fee,
+        nonce,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn block_over_tx_limit_is_rejected() {
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let mut strict = chain.clone();
+    strict.params.max_block_txs = 2;
+
+    let block = chain
+        .mine_block(vec![tx("alice", 0, 1), tx("carol", 0, 1)], 1, Some("miner"))
+        .unwrap();
+    let err = strict.validate_block(&block).unwrap_err().to_string();
+    assert!(err.contains("too many txs"), "err={err}");
+
+    // Full-chain validation applies the same limit.
+    chain.params.max_block_txs = 2;
+    assert!(chain.validate().is_err());
+}
+
+#[test]
+fn block_over_size_limit_is_rejected() {
+    let mut chain = funded_chain(&[("alice", 10_000)]);
+    let mut strict = chain.clone();
+
+    let block = chain
+        .mine_block(vec![tx("alice", 0, 1)], 1, Some("miner"))
+        .unwrap();
+    strict.params.max_block_size = block.size() - 1;
+    let err = strict.validate_block(&block).unwrap_err().to_string();
+    assert!(err.contains("too large"), "err={err}");
+}
+
+#[test]
+fn mine_block_refuses_oversized_template() {
+    let mut chain = funded_chain(&[("alice", 10_000)]);
+    chain.params.max_block_txs = 1;
+    let err = chain
+        .mine_block(vec![tx("alice", 0, 1)], 1, Some("miner"))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("too many txs"), "err={err}");
+    assert_eq!(chain.height(), 0);
+}
+
+#[test]
+fn template_prefers_fee_rate_and_respects_nonce_order() {
+    let chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let mut mp = Mempool::new();
+    // alice's nonce 1 pays enough to carry its cheap parent above carol.
+    mp.add_tx(tx("alice", 0, 1)).unwrap();
+    mp.add_tx(tx("alice", 1, 50)).unwrap();
+    mp.add_tx(tx("carol", 0, 10)).unwrap();
+
+    let picked = chain.select_block_txs(&mp, Some("miner"));
+    let order: Vec<(&str, u64)> = picked.iter().map(|t| (t.from.as_str(), t.nonce)).collect();
+    assert_eq!(order, vec![("alice", 0), ("alice", 1), ("carol", 0)]);
+
+    // The template mines as is, with both of alice's txs confirmed.
+    let mut mined = chain.clone();
+    mined.mine_block(picked.clone(), 1, Some("miner")).unwrap();
+    assert_eq!(mined.next_nonce_for("alice"), 2);
+    assert_eq!(mined.next_nonce_for("carol"), 1);
+    assert_eq!(mined.compute_state().unwrap().get_balance("bob"), 3);
+    mined.validate().unwrap();
+
+    // Without the child, alice's parent alone ranks below carol.
+    mp.remove_tx(&picked[1].id());
+    let picked = chain.select_block_txs(&mp, Some("miner"));
+    let order: Vec<(&str, u64)> = picked.iter().map(|t| (t.from.as_str(), t.nonce)).collect();
+    assert_eq!(order, vec![("carol", 0), ("alice", 0)]);
+}
+
+#[test]
+fn template_leaves_overflow_in_mempool() {
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000), ("dave", 10_000)]);
+    chain.params.max_block_txs = 3; // coinbase + 2
+    let mut mp = Mempool::new();
+    mp.add_tx(tx("alice", 0, 5)).unwrap();
+    mp.add_tx(tx("carol", 0, 1)).unwrap();
+    mp.add_tx(tx("dave", 0, 9)).unwrap();
+    // Gap in the nonce chain: never selected.
+    mp.add_tx(tx("dave", 5, 100)).unwrap();
+
+    let picked = chain.select_block_txs(&mp, Some("miner"));
+    let senders: Vec<&str> = picked.iter().map(|t| t.from.as_str()).collect();
+    assert_eq!(senders, vec!["dave", "alice"]);
+
+    let mined = chain.mine_block(picked, 1, Some("miner")).unwrap();
+    mp.remove_included(&mined.txs);
+    assert_eq!(mp.len(), 2);
+    assert!(mp.txs.iter().any(|t| t.from == "carol"));
+}
+
+#[test]
+fn template_respects_byte_budget() {
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let mut mp = Mempool::new();
+    mp.add_tx(tx("alice", 0, 5)).unwrap();
+    mp.add_tx(tx("carol", 0, 1)).unwrap();
+
+    let one = chain.select_block_txs(&mp, Some("miner"));
+    assert_eq!(one.len(), 2);
+    let empty = chain.clone().mine_block(vec![], 1, Some("miner")).unwrap();
+
+    // Room for exactly one tx on top of an empty block.
+    chain.params.max_block_size = empty.size() + mp.txs[0].size() + 60;
+    let picked = chain.select_block_txs(&mp, Some("miner"));
+    assert_eq!(picked.len(), 1);
+    assert_eq!(picked[0].from, "alice");
+    chain.mine_block(picked, 1, Some("miner")).unwrap();
+}
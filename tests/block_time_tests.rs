@@ -0,0 +1,254 @@
+use rusty_chain::core::chain::{Chain, FutureBlockError, MEDIAN_TIME_SPAN, merkle_root, pow_ok};
+use rusty_chain::core::light::LightClient;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{MAX_HELD_BLOCKS_PER_PEER, P2PNode, P2PNodeHandle};
+use rusty_chain::core::time::{MAX_TIME_OFFSET_MS, PeerTimeOffsets, now_ms};
+use rusty_chain::core::types::{Block, BlockHeader, Transaction};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+fn chain() -> Chain {
+    let mut chain = Chain::new_genesis();
+    chain.pow_difficulty = 1;
+    chain
+}
+
+/// Coinbase-only block on top of `chain`'s tip with the given timestamp and valid PoW.
+fn block_at(chain: &Chain, timestamp_ms: u64) -> Block {
+    let height = chain.height() as u64 + 1;
+    let txs = vec![Transaction::coinbase(
+        "miner",
+        height,
+        chain.params.block_subsidy(height),
+    )];
+    let mut state = chain.compute_state().unwrap();
+    state
+        .apply_block_txs(&txs, height as usize, &chain.params)
+        .unwrap();
+    let mut header = BlockHeader {
+        prev_hash: chain.tip_hash(),
+        timestamp_ms,
+        nonce: 0,
+        merkle_root: merkle_root(&txs),
+        state_root: chain.expected_state_root(&state),
+    };
+    while !pow_ok(&header.hash(), chain.pow_difficulty) {
+        header.nonce += 1;
+    }
+    Block { header, txs }
+}
+
+#[test]
+fn median_time_past_uses_last_eleven_blocks() {
+    let mut chain = chain();
+    let base = chain.blocks[0].header.timestamp_ms;
+    for i in 1..=15 {
+        let block = block_at(&chain, base + i * 1_000);
+        chain.append_block(block).unwrap();
+    }
+    // Blocks 5..=15 are the last eleven; their median is block 10.
+    assert_eq!(MEDIAN_TIME_SPAN, 11);
+    assert_eq!(chain.median_time_past(), base + 10_000);
+    assert_eq!(chain.median_time_past_at(2), base + 1_000);
+}
+
+#[test]
+fn block_not_after_median_time_past_is_rejected() {
+    let mut chain = chain();
+    let base = chain.blocks[0].header.timestamp_ms;
+    for i in 1..=3 {
+        let block = block_at(&chain, base + i * 1_000);
+        chain.append_block(block).unwrap();
+    }
+    // MTP is block 2 (base + 2000); equal is not enough.
+    let mtp = chain.median_time_past();
+    assert_eq!(mtp, base + 2_000);
+    let err = chain
+        .validate_block(&block_at(&chain, mtp))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("median time past"), "err={err}");
+}
+
+#[test]
+fn chains_without_state_roots_skip_median_time_past() {
+    // Before state roots a block only had to keep up with its parent.
+    let mut chain = chain();
+    chain.blocks[0].header.state_root.clear();
+    chain.rebuild_block_index();
+    chain.checkpoints.insert(0, chain.genesis_hash());
+    let base = chain.blocks[0].header.timestamp_ms;
+    for _ in 0..3 {
+        let block = block_at(&chain, base);
+        chain.append_block(block).unwrap();
+    }
+    assert_eq!(chain.median_time_past(), base);
+    chain.validate().unwrap();
+
+    let mut client = LightClient::from_genesis(&chain);
+    let headers = chain.blocks[1..].iter().map(|b| b.header.clone()).collect();
+    assert_eq!(client.apply_headers(1, headers).unwrap(), 3);
+}
+
+#[test]
+fn block_too_far_in_future_is_a_future_block_error() {
+    let chain = chain();
+    let drift = chain.params.max_future_drift_ms;
+    let block = block_at(&chain, now_ms() + drift + 60_000);
+    let err = chain.validate_block(&block).unwrap_err();
+    assert!(
+        err.downcast_ref::<FutureBlockError>().is_some(),
+        "err={err}"
+    );
+
+    // The same block is fine once the network clock runs ahead of ours.
+    let mut ahead = chain.clone();
+    ahead.time_offset_ms = 5 * 60_000;
+    ahead.validate_block(&block).unwrap();
+}
+
+#[test]
+fn mined_blocks_follow_median_time_past() {
+    let mut chain = chain();
+    for _ in 0..5 {
+        chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    }
+    chain.validate().unwrap();
+}
+
+#[test]
+fn peer_time_offsets_need_samples_and_ignore_outliers() {
+    let now = 1_700_000_000_000;
+    let peers: Vec<SocketAddr> = (0..3)
+        .map(|i| format!("10.0.0.{i}:9000").parse().unwrap())
+        .collect();
+    let mut offsets = PeerTimeOffsets::new();
+
+    offsets.add(peers[0], now + 30_000, now);
+    offsets.add(peers[1], now + 10_000, now);
+    assert_eq!(offsets.offset_ms(), 0);
+
+    offsets.add(peers[2], now - 5_000, now);
+    assert_eq!(offsets.offset_ms(), 10_000);
+
+    offsets.remove(&peers[2]);
+    assert_eq!(offsets.offset_ms(), 0);
+
+    let far = now + MAX_TIME_OFFSET_MS as u64 + 1;
+    for p in &peers {
+        offsets.add(*p, far, now);
+    }
+    assert_eq!(offsets.offset_ms(), 0);
+}
+
+fn node(chain: Chain) -> (P2PNode, P2PNodeHandle) {
+    let node = P2PNode::new(
+        "127.0.0.1:9000".parse().unwrap(),
+        chain,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    (node, handle)
+}
+
+#[tokio::test]
+async fn handshakes_set_network_adjusted_time() {
+    let chain = chain();
+    let (genesis_hash, chain_id) = (chain.genesis_hash(), chain.chain_id);
+    let (node, handle) = node(chain);
+
+    for i in 0..3 {
+        let peer: SocketAddr = format!("10.0.0.{i}:9000").parse().unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        node.state.lock().await.peer_senders.insert(peer, tx);
+        handle
+            .process_message(
+                Message::Handshake {
+                    version: 1,
+                    best_height: 0,
+                    agent: "test".to_string(),
+                    genesis_hash: genesis_hash.clone(),
+                    chain_id,
+                    timestamp_ms: now_ms() + 600_000,
+                    pruned_height: 0,
+                },
+                peer,
+            )
+            .await
+            .unwrap();
+    }
+
+    let offset = node.state.lock().await.chain.time_offset_ms;
+    assert!((590_000..=600_000).contains(&offset), "offset={offset}");
+}
+
+#[tokio::test]
+async fn future_block_is_held_and_retried() {
+    let chain = chain();
+    let drift = chain.params.max_future_drift_ms;
+    let block = block_at(&chain, now_ms() + drift + 60_000);
+    let (node, handle) = node(chain);
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+
+    handle
+        .process_message(Message::NewBlock(block.clone()), peer)
+        .await
+        .unwrap();
+    {
+        let state = node.state.lock().await;
+        assert_eq!(state.chain.height(), 0);
+        assert_eq!(state.held_blocks.len(), 1);
+        // Holding a future block does not count against the sender.
+        assert!(state.peer_reputation.get(&peer).copied().unwrap_or(0) >= 0);
+    }
+
+    // Still too early: stays held.
+    handle.retry_held_blocks().await.unwrap();
+    assert_eq!(node.state.lock().await.held_blocks.len(), 1);
+
+    // Network time catches up.
+    node.state.lock().await.chain.time_offset_ms = 5 * 60_000;
+    handle.retry_held_blocks().await.unwrap();
+    let state = node.state.lock().await;
+    assert!(state.held_blocks.is_empty());
+    assert_eq!(state.chain.height(), 1);
+    assert_eq!(state.chain.tip_hash(), block.header.hash());
+}
+
+#[tokio::test]
+async fn held_future_blocks_are_capped_per_peer() {
+    let chain = chain();
+    let drift = chain.params.max_future_drift_ms;
+    let blocks: Vec<Block> = (0..MAX_HELD_BLOCKS_PER_PEER as u64 + 2)
+        .map(|i| block_at(&chain, now_ms() + drift + 60_000 + i))
+        .collect();
+    let (node, handle) = node(chain);
+    let flooder: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+    let other: SocketAddr = "5.6.7.8:5678".parse().unwrap();
+
+    let (flood, rest) = blocks.split_at(MAX_HELD_BLOCKS_PER_PEER + 1);
+    for block in flood {
+        handle
+            .process_message(Message::NewBlock(block.clone()), flooder)
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        node.state.lock().await.held_blocks.len(),
+        MAX_HELD_BLOCKS_PER_PEER
+    );
+
+    // Another peer still gets a block held.
+    handle
+        .process_message(Message::NewBlock(rest[0].clone()), other)
+        .await
+        .unwrap();
+    let state = node.state.lock().await;
+    assert_eq!(state.held_blocks.len(), MAX_HELD_BLOCKS_PER_PEER + 1);
+    assert_eq!(state.held_blocks.last().unwrap().1, other);
+}
@@ -32,7 +32,7 @@ fn validate_accepts_genesis() {
 fn validate_rejects_broken_prev_hash_linkage() {
     let mut c = Chain::new_genesis();
     c.pow_difficulty = 3;
-    let _mined = c.mine_empty_block(3).unwrap();
+    let _mined = c.mine_empty_block(3, "miner").unwrap();
 
     // Tamper with linkage.
     c.blocks[1].header.prev_hash = "deadbeef".to_string();
@@ -50,7 +50,7 @@ fn mine_produces_pow_ok_hash() {
     c.pow_difficulty = 2; // Match the difficulty used in mine_block
     let difficulty = c.pow_difficulty;
 
-    let _mined = c.mine_empty_block(difficulty).unwrap();
+    let _mined = c.mine_empty_block(difficulty, "miner").unwrap();
     c.validate().unwrap();
 
     let tip = c.blocks.last().unwrap();
@@ -65,7 +65,7 @@ fn validate_rejects_block_failing_pow() {
     let mut c = Chain::new_genesis();
 
     // Mine with low difficulty so we can more easily force a failure.
-    c.mine_empty_block(1).unwrap();
+    c.mine_empty_block(1, "miner").unwrap();
 
     // Raise chain difficulty after the fact; block[1] will likely not satisfy it.
     c.pow_difficulty = 6;
@@ -86,6 +86,62 @@ fn load_defaults_pow_difficulty_when_missing_in_json() {
     assert_eq!(loaded.pow_difficulty, 3);
 }
 
+#[test]
+fn mined_blocks_need_a_coinbase_to_a_valid_address() {
+    let mut c = Chain::new_genesis();
+    assert!(c.params.strict_coinbase);
+
+    let err = c.mine_block(vec![], 1, None).unwrap_err();
+    assert!(err.to_string().contains("needs a coinbase"), "err={err}");
+    let err = c.mine_block(vec![], 1, Some("two words")).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("invalid address"),
+        "err={err:#}"
+    );
+    assert_eq!(c.height(), 0);
+
+    c.mine_empty_block(1, "miner").unwrap();
+    assert!(c.blocks[1].is_coinbase());
+    c.validate().unwrap();
+}
+
+#[test]
+fn older_chain_files_keep_lax_coinbase_rules() {
+    // Simulate a chain.json written before state roots and the coinbase rules existed.
+    let mut c = Chain::new_genesis();
+    c.blocks[0].header.state_root.clear();
+    c.checkpoints.insert(0, c.blocks[0].header.hash());
+    let mut v = serde_json::to_value(&c).unwrap();
+    let params = v["params"].as_object_mut().unwrap();
+    params.remove("strict_coinbase");
+    params.remove("coinbase_maturity");
+    params.remove("governance_voting_period");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("chain.json");
+    std::fs::write(&path, v.to_string()).unwrap();
+
+    let mut loaded = Chain::load(&path).unwrap();
+    assert!(!loaded.params.strict_coinbase);
+    assert_eq!(loaded.params.coinbase_maturity, 0);
+    assert_eq!(loaded.params.governance_voting_period, 0);
+    loaded.mine_block(vec![], 1, None).unwrap();
+    loaded.validate().unwrap();
+
+    loaded.params.strict_coinbase = true;
+    let err = format!("{:#}", loaded.validate().unwrap_err());
+    assert!(err.contains("no coinbase"), "err={err}");
+
+    // Chains with state roots keep the rules even if the fields are missing.
+    let c = Chain::new_genesis();
+    let mut v = serde_json::to_value(&c).unwrap();
+    v["params"]
+        .as_object_mut()
+        .unwrap()
+        .remove("strict_coinbase");
+    std::fs::write(&path, v.to_string()).unwrap();
+    assert!(Chain::load(&path).unwrap().params.strict_coinbase);
+}
+
 #[test]
 fn merkle_root_changes_with_tx_order() {
     let tx1 = Transaction::new("a", "b", 1, 0);
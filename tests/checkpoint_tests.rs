@@ -14,7 +14,7 @@ fn test_manual_checkpointing() {
 
     // Mine a few blocks
     for _ in 0..5 {
-        chain.mine_empty_block(3).unwrap();
+        chain.mine_empty_block(3, "miner").unwrap();
     }
 
     let height = chain.height();
@@ -45,18 +45,25 @@ fn test_automatic_checkpointing() {
 
     // append_block triggers auto-checkpoint every 10 blocks
     for _i in 1..=21 {
-        let prev = chain.blocks.last().unwrap();
-        let prev_hash = prev.header.hash();
+        let height = chain.height() as u64 + 1;
+        let txs = vec![rusty_chain::core::types::Transaction::coinbase(
+            "miner",
+            height,
+            chain.params.block_subsidy(height),
+        )];
+        let mut state = chain.compute_state().unwrap();
+        state
+            .apply_block_txs(&txs, height as usize, &chain.params)
+            .unwrap();
         let header = rusty_chain::core::types::BlockHeader {
-            prev_hash,
-            timestamp_ms: rusty_chain::core::time::now_ms(),
+            prev_hash: chain.tip_hash(),
+            // Must be strictly after median time past, even within the same millisecond.
+            timestamp_ms: rusty_chain::core::time::now_ms().max(chain.median_time_past() + 1),
             nonce: 0,
-            merkle_root: rusty_chain::core::chain::merkle_root(&[]),
-        };
-        let mut block = rusty_chain::core::types::Block {
-            header,
-            txs: vec![],
+            merkle_root: rusty_chain::core::chain::merkle_root(&txs),
+            state_root: chain.expected_state_root(&state),
         };
+        let mut block = rusty_chain::core::types::Block { header, txs };
 
         // Find valid PoW for difficulty 3
         let mut n = 0;
@@ -90,7 +97,7 @@ fn test_get_checkpoint_helpers() {
 
     // Add checkpoint
     for _ in 0..5 {
-        chain.mine_empty_block(3).unwrap();
+        chain.mine_empty_block(3, "miner").unwrap();
     }
     let height = chain.height();
     let hash = chain.tip_hash();
@@ -107,7 +114,7 @@ fn test_header_stateless_verification() {
     let _header = &chain.blocks[0].header;
     // Genesis header might not have valid PoW for difficulty 3 if created with different difficulty
     // Let's mine one block to be sure
-    let block = chain.mine_empty_block(3).unwrap();
+    let block = chain.mine_empty_block(3, "miner").unwrap();
     assert!(block.header.verify_pow(3).is_ok());
     // Should fail for impossible difficulty
     assert!(block.header.verify_pow(64).is_err());
@@ -117,7 +124,7 @@ fn test_header_stateless_verification() {
 fn test_block_stateless_verification() {
     let mut chain = Chain::new_genesis();
     let prev_header = chain.blocks[0].header.clone();
-    let block = chain.mine_empty_block(3).unwrap();
+    let block = chain.mine_empty_block(3, "miner").unwrap();
 
     // Valid block against its actual parent
     assert!(block.validate_with_prev(&prev_header, 3).is_ok());
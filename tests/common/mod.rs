@@ -0,0 +1,66 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::genesis::{GenesisAllocation, GenesisConfig};
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle, PeerCmd};
+use rusty_chain::core::params::ChainParams;
+use rusty_chain::core::types::Transaction;
+use std::net::SocketAddr;
+
+/// Devnet genesis with default params and a fixed timestamp, funding each `(address, balance)`.
+pub fn genesis(allocations: &[(&str, u64)]) -> GenesisConfig {
+    GenesisConfig {
+        network: "devnet".to_string(),
+        chain_id: None,
+        timestamp_ms: 1_700_000_000_000,
+        difficulty: 1,
+        params: ChainParams::default(),
+        allocations: allocations
+            .iter()
+            .map(|&(address, balance)| GenesisAllocation {
+                address: address.to_string(),
+                balance,
+            })
+            .collect(),
+    }
+}
+
+/// Chain built from `genesis(allocations)`.
+pub fn funded_chain(allocations: &[(&str, u64)]) -> Chain {
+    genesis(allocations).build_chain().unwrap()
+}
+
+/// Mine `txs` into the next block at difficulty 1, paying "miner".
+pub fn mine(chain: &mut Chain, txs: Vec<Transaction>) -> anyhow::Result<()> {
+    chain.mine_block(txs, 1, Some("miner")).map(|_| ())
+}
+
+/// 25 blocks, each with a payment from alice; checkpoints at 10 and 20.
+pub fn long_chain() -> Chain {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    for nonce in 0..25 {
+        let to = format!("user-{}", nonce % 4);
+        chain
+            .mine_block(
+                vec![Transaction::new_with_fee("alice", &to, 10, 1, nonce, 0)],
+                1,
+                Some("miner"),
+            )
+            .unwrap();
+    }
+    chain
+}
+
+/// Send `msg` to `node` from a test peer and return the node's reply to it.
+pub async fn ask(node: &P2PNode, handle: &P2PNodeHandle, msg: Message) -> Message {
+    let peer: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    node.state.lock().await.peer_senders.insert(peer, tx);
+    handle.process_message(msg, peer).await.unwrap();
+    match rx.recv().await {
+        Some(PeerCmd::SendMessage(msg)) => *msg,
+        other => panic!("unexpected: {other:?}"),
+    }
+}
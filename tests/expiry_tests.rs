@@ -5,6 +5,8 @@ use rusty_chain::core::types::Transaction;
 #[test]
 fn test_transaction_expiry_enforcement() {
     let mut chain = Chain::new_genesis();
+    // Spend the reward right away (maturity is covered in state_tests).
+    chain.params.coinbase_maturity = 0;
 
     // Give ALICE some coins so balance check passes
     let alice_addr = "ALICE";
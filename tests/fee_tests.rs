@@ -1,5 +1,11 @@
-use rusty_chain::core::chain::Chain;
-use rusty_chain::core::types::Transaction;
+use rusty_chain::core::chain::{Chain, merkle_root};
+use rusty_chain::core::fee_estimator::{FeeEstimator, bucket_floor, bucket_for};
+use rusty_chain::core::genesis::{GenesisAllocation, GenesisConfig};
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::params::ChainParams;
+use rusty_chain::core::policy::fee_per_kb;
+use rusty_chain::core::types::{Block, BlockHeader, Transaction};
 
 #[test]
 fn test_fee_rate_estimation_with_history() {
@@ -23,7 +29,7 @@ fn test_fee_rate_estimation_with_history() {
         tx.fee = 50;
         txs.push(tx);
 
-        chain.mine_block(txs, 0, None).unwrap();
+        chain.mine_block(txs, 0, Some("miner")).unwrap();
     }
 
     let rate = chain.estimate_fee_rate(10);
@@ -36,3 +42,152 @@ fn test_fee_rate_estimation_empty_chain() {
     let rate = chain.estimate_fee_rate(10);
     assert_eq!(rate, 1.0, "Should return default 1.0 for empty chain");
 }
+
+fn fee_tx(from: String, fee: u64) -> Transaction {
+    Transaction::new_with_fee(from, "bob", 1, fee, 0, 0)
+}
+
+fn block_with(txs: Vec<Transaction>) -> Block {
+    Block {
+        header: BlockHeader {
+            prev_hash: String::new(),
+            timestamp_ms: 0,
+            nonce: 0,
+            merkle_root: merkle_root(&[]),
+            state_root: String::new(),
+        },
+        txs,
+    }
+}
+
+fn rate_bucket_floor(tx: &Transaction) -> u64 {
+    bucket_floor(bucket_for(fee_per_kb(tx.fee, tx.size())))
+}
+
+#[test]
+fn test_fee_buckets_are_powers_of_two() {
+    assert_eq!(bucket_for(0), 0);
+    assert_eq!(bucket_for(1), 1);
+    assert_eq!(bucket_for(3), 2);
+    assert_eq!(bucket_for(4), 3);
+    assert_eq!(bucket_floor(3), 4);
+    assert_eq!(bucket_floor(bucket_for(u64::MAX)), 1 << 63);
+}
+
+#[test]
+fn test_fee_estimator_tracks_confirmation_times() {
+    let mut est = FeeEstimator::new();
+    let mut mp = Mempool::new();
+    let mut lows: Vec<Transaction> = Vec::new();
+
+    // High-fee txs confirm in the next block, low-fee ones wait five blocks.
+    for h in 0..20_u64 {
+        let high = fee_tx(format!("high{h}"), 1_000);
+        let low = fee_tx(format!("low{h}"), 1);
+        for tx in [&high, &low] {
+            mp.add_tx(tx.clone()).unwrap();
+            est.track_tx(tx);
+        }
+        lows.push(low);
+
+        let mut included = vec![high];
+        if h >= 4 {
+            included.push(lows[(h - 4) as usize].clone());
+        }
+        mp.remove_included(&included);
+        est.on_block_connected(h + 1, &block_with(included), &mp);
+    }
+
+    let high_floor = rate_bucket_floor(&fee_tx("x".to_string(), 1_000));
+    let low_floor = rate_bucket_floor(&fee_tx("x".to_string(), 1));
+    assert!(low_floor < high_floor);
+    assert_eq!(est.history_fee_per_kb(1), Some(high_floor));
+    assert_eq!(est.history_fee_per_kb(4), Some(high_floor));
+    assert_eq!(est.history_fee_per_kb(5), Some(low_floor));
+    // Pending lows that are still tracked count against short targets only.
+    assert_eq!(est.tracked_len(), 4);
+
+    let estimate = est.estimate(1, &mp, 1_000_000);
+    assert_eq!(estimate.target_blocks, 1);
+    assert_eq!(estimate.fee_per_kb, high_floor);
+}
+
+#[test]
+fn test_fee_estimator_untracks_dropped_txs() {
+    let mut est = FeeEstimator::new();
+    let mut mp = Mempool::new();
+    let txs: Vec<Transaction> = (0..3).map(|i| fee_tx(format!("s{i}"), 100)).collect();
+    for tx in &txs {
+        mp.add_tx(tx.clone()).unwrap();
+        est.track_tx(tx);
+    }
+    assert_eq!(est.tracked_len(), 3);
+
+    est.untrack(&txs[0].id());
+    assert_eq!(est.tracked_len(), 2);
+
+    mp.remove_tx(&txs[1].id());
+    est.untrack_missing(&mp);
+    assert_eq!(est.tracked_len(), 1);
+}
+
+#[test]
+fn test_fee_estimate_outbids_mempool_backlog() {
+    let est = FeeEstimator::new();
+    let mut mp = Mempool::new();
+    for i in 0..10 {
+        mp.add_tx(fee_tx(format!("s{i}"), 100 + i)).unwrap();
+    }
+    let size = mp.txs[0].size() + 1;
+
+    // Two blocks of three txs: the six best (fees 109..=104) fit, so a new tx must beat
+    // the seventh (fee 103).
+    let tight = est.estimate(2, &mp, size * 3);
+    let seventh = fee_tx("s3".to_string(), 103);
+    assert_eq!(tight.fee_per_kb, fee_per_kb(103, seventh.size()) + 1);
+
+    // Everything fits within the target: no backlog to outbid.
+    let loose = est.estimate(5, &mp, size * 3);
+    assert_eq!(loose.fee_per_kb, 0);
+    assert!(tight.fee_for_size(250) > 0);
+}
+
+#[test]
+fn test_fee_estimator_from_chain_history() {
+    let mut chain = GenesisConfig {
+        network: "devnet".to_string(),
+        chain_id: None,
+        timestamp_ms: 1_700_000_000_000,
+        difficulty: 1,
+        params: ChainParams::default(),
+        allocations: vec![GenesisAllocation {
+            address: "alice".to_string(),
+            balance: 1_000,
+        }],
+    }
+    .build_chain()
+    .unwrap();
+
+    for nonce in 0..3 {
+        let mut tx = Transaction::new_with_fee("alice", "bob", 1, 20, nonce, 0);
+        // Entered the mempool at the tip, even if blocks share a millisecond.
+        tx.timestamp_ms = chain.tip_header().timestamp_ms;
+        chain.mine_block(vec![tx], 1, Some("miner")).unwrap();
+    }
+
+    let est = FeeEstimator::from_chain(&chain);
+    let floor = rate_bucket_floor(&Transaction::new_with_fee("alice", "bob", 1, 20, 0, 0));
+    assert_eq!(est.history_fee_per_kb(1), Some(floor));
+}
+
+#[test]
+fn test_get_fee_estimate_defaults_target() {
+    let msg: Message = serde_json::from_str(r#"{"GetFeeEstimate":{"tx_size":250}}"#).unwrap();
+    assert_eq!(
+        msg,
+        Message::GetFeeEstimate {
+            tx_size: 250,
+            target_blocks: 2,
+        }
+    );
+}
@@ -0,0 +1,171 @@
+use rusty_chain::core::genesis::{GenesisAllocation, GenesisConfig};
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle, PeerCmd};
+use rusty_chain::core::params::ChainParams;
+use rusty_chain::core::types::Transaction;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+fn config() -> GenesisConfig {
+    GenesisConfig {
+        network: "testnet".to_string(),
+        chain_id: None,
+        timestamp_ms: 1_700_000_000_000,
+        difficulty: 1,
+        params: ChainParams::default(),
+        allocations: vec![
+            GenesisAllocation {
+                address: "alice".to_string(),
+                balance: 1_000,
+            },
+            GenesisAllocation {
+                address: "bob".to_string(),
+                balance: 250,
+            },
+        ],
+    }
+}
+
+#[test]
+fn genesis_hash_is_deterministic() {
+    let a = config().build_chain().unwrap();
+    let b = config().build_chain().unwrap();
+    assert_eq!(a.genesis_hash(), b.genesis_hash());
+    assert_eq!(a.network, "testnet");
+    assert_eq!(a.pow_difficulty, 1);
+}
+
+#[test]
+fn genesis_hash_commits_to_network_and_params() {
+    let base = config().build_chain().unwrap().genesis_hash();
+
+    let mut other_net = config();
+    other_net.network = "devnet".to_string();
+    assert_ne!(other_net.build_chain().unwrap().genesis_hash(), base);
+
+    let mut other_params = config();
+    other_params.params.initial_subsidy = 10;
+    assert_ne!(other_params.build_chain().unwrap().genesis_hash(), base);
+}
+
+#[test]
+fn allocations_are_applied_to_state() {
+    let mut chain = config().build_chain().unwrap();
+    chain.validate().unwrap();
+
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_balance("alice"), 1_000);
+    assert_eq!(state.get_balance("bob"), 250);
+    assert_eq!(chain.genesis_allocated(), 1_250);
+
+    // Premined coins are spendable right away.
+    let tx = Transaction::new_with_fee("alice", "carol", 100, 1, 0, 0);
+    chain.mine_block(vec![tx], 1, Some("miner")).unwrap();
+    chain.validate().unwrap();
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_balance("alice"), 899);
+    assert_eq!(state.get_balance("carol"), 100);
+}
+
+#[test]
+fn genesis_config_rejects_duplicate_allocations() {
+    let mut cfg = config();
+    cfg.allocations.push(GenesisAllocation {
+        address: "alice".to_string(),
+        balance: 1,
+    });
+    let err = cfg.build_chain().unwrap_err().to_string();
+    assert!(err.contains("duplicate"), "err={err}");
+}
+
+#[test]
+fn genesis_rejects_non_system_txs() {
+    let mut chain = config().build_chain().unwrap();
+    chain.blocks[0]
+        .txs
+        .push(Transaction::new("alice", "bob", 1, 0));
+    let err = format!("{:?}", chain.compute_state().unwrap_err());
+    assert!(err.contains("SYSTEM allocation"), "err={err}");
+}
+
+#[test]
+fn genesis_config_loads_from_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("genesis.json");
+    std::fs::write(
+        &path,
+        r#"{"network":"testnet","timestamp_ms":1700000000000,"difficulty":1,
+            "allocations":[{"address":"alice","balance":1000},{"address":"bob","balance":250}]}"#,
+    )
+    .unwrap();
+
+    let loaded = GenesisConfig::load(&path).unwrap();
+    assert_eq!(loaded, config());
+}
+
+#[test]
+fn genesis_params_given_in_part_keep_the_other_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("genesis.json");
+    std::fs::write(
+        &path,
+        r#"{"network":"testnet","timestamp_ms":1700000000000,"difficulty":1,
+            "params":{"min_tx_fee":5},"allocations":[]}"#,
+    )
+    .unwrap();
+
+    let loaded = GenesisConfig::load(&path).unwrap();
+    assert_eq!(
+        loaded.params,
+        ChainParams {
+            min_tx_fee: 5,
+            ..ChainParams::default()
+        }
+    );
+    assert!(loaded.params.strict_coinbase);
+}
+
+#[tokio::test]
+async fn handshake_with_other_genesis_is_rejected() {
+    let chain = config().build_chain().unwrap();
+    let node = P2PNode::new(
+        "127.0.0.1:9000".parse().unwrap(),
+        chain,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    node.state.lock().await.peer_senders.insert(peer, tx);
+
+    handle
+        .process_message(
+            Message::Handshake {
+                version: 1,
+                best_height: 0,
+                agent: "test".to_string(),
+                genesis_hash: "00".repeat(32),
+                chain_id: 2,
+                timestamp_ms: 0,
+                pruned_height: 0,
+            },
+            peer,
+        )
+        .await
+        .unwrap();
+
+    match rx.recv().await {
+        Some(PeerCmd::SendMessage(msg)) => match *msg {
+            Message::Reject { reason, .. } => assert!(reason.contains("Genesis mismatch")),
+            other => panic!("expected Reject, got {other:?}"),
+        },
+        other => panic!("expected Reject, got {other:?}"),
+    }
+    assert!(matches!(rx.recv().await, Some(PeerCmd::Disconnect)));
+}
@@ -0,0 +1,409 @@
+mod common;
+
+use common::{ask, mine};
+use ed25519_dalek::SigningKey;
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::crypto::{sign_bytes, verifying_key_to_hex};
+use rusty_chain::core::governance::{
+    GOVERNANCE_ADDRESS, GovernanceOp, MAX_OPEN_PROPOSALS_PER_SENDER, MIN_GOVERNED_BLOCK_SIZE,
+    ParamChange,
+};
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle};
+use rusty_chain::core::params::ChainParams;
+use rusty_chain::core::state_tree::state_root;
+use rusty_chain::core::types::Transaction;
+use std::sync::Arc;
+
+/// A fixed key per name, so accounts can sign governance txs.
+fn key(name: &str) -> SigningKey {
+    SigningKey::from_bytes(&[name.as_bytes()[0]; 32])
+}
+
+fn addr(name: &str) -> String {
+    verifying_key_to_hex(&key(name).verifying_key())
+}
+
+fn sign(mut tx: Transaction, name: &str) -> Transaction {
+    tx.pubkey_hex = Some(addr(name));
+    tx.signature_b64 = Some(sign_bytes(&key(name), &tx.signing_bytes()));
+    tx
+}
+
+fn transfer(chain: &Chain, from: &str, to: &str, amount: u64) -> Transaction {
+    let from = addr(from);
+    let nonce = chain.next_nonce_for(&from);
+    Transaction::new_with_fee(from, addr(to), amount, 1, nonce, 0)
+}
+
+/// Proposals are open for votes in the 3 blocks after their own.
+fn chain_with(params: ChainParams) -> Chain {
+    let funded: Vec<(String, u64)> = ["alice", "bob", "carol"]
+        .iter()
+        .map(|name| addr(name))
+        .zip([6_000, 3_000, 1_000])
+        .collect();
+    let funded: Vec<(&str, u64)> = funded.iter().map(|(a, b)| (a.as_str(), *b)).collect();
+    let mut config = common::genesis(&funded);
+    config.params = ChainParams {
+        governance_voting_period: 3,
+        ..params
+    };
+    config.build_chain().unwrap()
+}
+
+fn mine_to(chain: &mut Chain, height: usize) {
+    while chain.height() < height {
+        mine(chain, vec![]).unwrap();
+    }
+}
+
+fn gov_tx(chain: &Chain, from: &str, op: GovernanceOp) -> Transaction {
+    let address = addr(from);
+    let nonce = chain.next_nonce_for(&address);
+    let mut tx = Transaction::new_with_fee(address, GOVERNANCE_ADDRESS, 0, 1, nonce, 0);
+    tx.chain_id = chain.chain_id;
+    tx.governance = Some(op);
+    sign(tx, from)
+}
+
+fn propose(chain: &Chain, from: &str, change: ParamChange, activation_height: u64) -> Transaction {
+    gov_tx(
+        chain,
+        from,
+        GovernanceOp::Propose {
+            change,
+            activation_height,
+        },
+    )
+}
+
+fn vote(chain: &Chain, from: &str, proposal: &Transaction, approve: bool) -> Transaction {
+    gov_tx(
+        chain,
+        from,
+        GovernanceOp::Vote {
+            proposal_id: proposal.id(),
+            approve,
+        },
+    )
+}
+
+#[test]
+fn passed_proposal_changes_the_block_reward_from_its_activation_height() {
+    let mut chain = chain_with(ChainParams::default());
+    let proposal = propose(&chain, "carol", ParamChange::BlockReward(7), 10);
+    mine(&mut chain, vec![proposal.clone()]).unwrap();
+
+    // Weights are the balances after block 1, the miner's first reward included.
+    let state = chain.compute_state().unwrap();
+    let open = &state.governance.proposals[&proposal.id()];
+    assert_eq!(open.voting_ends, 4);
+    assert_eq!(open.snapshot_height, 1);
+    assert_eq!(open.total_weight, 6_000 + 3_000 + 999 + 51);
+    let root_before = state_root(&state);
+
+    // Alice alone holds a majority; bob's vote against can't stop it.
+    let votes = vec![
+        vote(&chain, "alice", &proposal, true),
+        vote(&chain, "bob", &proposal, false),
+    ];
+    mine(&mut chain, votes).unwrap();
+    let state = chain.compute_state().unwrap();
+    let open = &state.governance.proposals[&proposal.id()];
+    assert_eq!(open.tally(true), 6_000);
+    assert_eq!(open.tally(false), 3_000);
+    assert!(open.passed());
+    assert_ne!(state_root(&state), root_before);
+    state.check_governance().unwrap();
+
+    // Voting closes at the end of block 4 and the change waits for its activation height.
+    mine_to(&mut chain, 4);
+    let state = chain.compute_state().unwrap();
+    assert!(state.governance.proposals.is_empty());
+    assert_eq!(state.governance.scheduled.len(), 1);
+    let late = vote(&chain, "carol", &proposal, true);
+    assert!(chain.validate_transaction(&late).is_err());
+
+    mine_to(&mut chain, 10);
+    assert_eq!(chain.blocks[9].txs[0].amount, 50);
+    assert_eq!(chain.blocks[10].txs[0].amount, 7);
+    assert_eq!(chain.params_at(11).unwrap().block_subsidy(11), 7);
+    assert_eq!(chain.params.initial_subsidy, 50);
+    chain.validate().unwrap();
+}
+
+#[test]
+fn votes_weigh_balances_at_the_snapshot() {
+    let mut chain = chain_with(ChainParams::default());
+    let proposal = propose(&chain, "carol", ParamChange::MinTxFee(2), 10);
+    mine(&mut chain, vec![proposal.clone()]).unwrap();
+
+    // Moving coins after the snapshot moves no voting weight.
+    let txs = vec![transfer(&chain, "alice", "bob", 5_000)];
+    mine(&mut chain, txs).unwrap();
+    let votes = vec![
+        vote(&chain, "alice", &proposal, false),
+        vote(&chain, "bob", &proposal, true),
+    ];
+    mine(&mut chain, votes).unwrap();
+    let state = chain.compute_state().unwrap();
+    let open = &state.governance.proposals[&proposal.id()];
+    assert_eq!(open.tally(false), 6_000);
+    assert_eq!(open.tally(true), 3_000);
+
+    // Only accounts whose balance changed since are remembered, and only while voting runs.
+    let history = &state.governance.balance_history;
+    assert!(history.contains_key(&addr("alice")) && history.contains_key(&addr("bob")));
+    assert!(!history.contains_key(&addr("carol")));
+    mine_to(&mut chain, 4);
+    let state = chain.compute_state().unwrap();
+    assert!(state.governance.balance_history.is_empty());
+    assert!(state.governance.scheduled.is_empty());
+    state.check_governance().unwrap();
+}
+
+#[test]
+fn proposal_without_a_majority_changes_nothing() {
+    let mut chain = chain_with(ChainParams::default());
+    let proposal = propose(&chain, "bob", ParamChange::MaxBlockTxs(2), 6);
+    mine(&mut chain, vec![proposal.clone()]).unwrap();
+    // Bob and carol hold well under half of the weight.
+    let votes = vec![
+        vote(&chain, "bob", &proposal, true),
+        vote(&chain, "carol", &proposal, true),
+    ];
+    mine(&mut chain, votes).unwrap();
+    // A re-vote replaces the earlier one.
+    let revote = vote(&chain, "carol", &proposal, false);
+    mine(&mut chain, vec![revote]).unwrap();
+    let state = chain.compute_state().unwrap();
+    assert_eq!(
+        state.governance.proposals[&proposal.id()].tally(true),
+        2_999
+    );
+
+    mine_to(&mut chain, 6);
+    let state = chain.compute_state().unwrap();
+    assert!(state.governance.is_empty());
+    state.check_governance().unwrap();
+    let transfers = ["alice", "bob", "carol"]
+        .iter()
+        .map(|from| transfer(&chain, from, "dave", 1))
+        .collect();
+    mine(&mut chain, transfers).unwrap();
+    chain.validate().unwrap();
+}
+
+#[test]
+fn governed_min_fee_and_block_limits_are_enforced() {
+    let mut chain = chain_with(ChainParams::default());
+    let fee = propose(&chain, "alice", ParamChange::MinTxFee(5), 6);
+    let txs = propose(&chain, "bob", ParamChange::MaxBlockTxs(2), 6);
+    mine(&mut chain, vec![fee.clone(), txs.clone()]).unwrap();
+    let votes = vec![vote(&chain, "alice", &fee, true)];
+    mine(&mut chain, votes).unwrap();
+    let votes = vec![vote(&chain, "alice", &txs, true)];
+    mine(&mut chain, votes).unwrap();
+    mine_to(&mut chain, 5);
+
+    // Block 6 is the first under the new rules.
+    let cheap = transfer(&chain, "bob", "dave", 10);
+    assert!(format!("{:#}", chain.validate_transaction(&cheap).unwrap_err()).contains("minimum"));
+    assert!(mine(&mut chain, vec![cheap]).is_err());
+    let paid =
+        |from: &str, nonce| Transaction::new_with_fee(addr(from), addr("dave"), 10, 5, nonce, 0);
+    let err = mine(&mut chain, vec![paid("bob", 1), paid("carol", 0)]).unwrap_err();
+    assert!(format!("{err:#}").contains("too many txs"), "err={err:#}");
+    mine(&mut chain, vec![paid("bob", 1)]).unwrap();
+    assert_eq!(chain.params_at(6).unwrap().max_block_txs, 2);
+    chain.validate().unwrap();
+}
+
+#[tokio::test]
+async fn fee_estimates_use_the_governed_block_size() {
+    let mut chain = chain_with(ChainParams::default());
+    let size = MIN_GOVERNED_BLOCK_SIZE;
+    let proposal = propose(&chain, "alice", ParamChange::MaxBlockSize(size), 6);
+    mine(&mut chain, vec![proposal.clone()]).unwrap();
+    let votes = vec![vote(&chain, "alice", &proposal, true)];
+    mine(&mut chain, votes).unwrap();
+    mine_to(&mut chain, 5);
+
+    // Twice the governed block size is waiting, far less than the default size.
+    let mut mempool = Mempool::new();
+    for (nonce, fee) in (0..4).zip([40, 30, 20, 10]) {
+        let mut tx = Transaction::new_with_fee(addr("bob"), addr("dave"), 1, fee, nonce as u64, 0);
+        tx.nonce_id = Some(format!("{nonce}{}", "x".repeat(size / 2)));
+        mempool.add_tx(tx).unwrap();
+    }
+    assert!(mempool.total_size() < ChainParams::default().max_block_size);
+    let node = P2PNode::new(
+        "127.0.0.1:9010".parse().unwrap(),
+        chain,
+        mempool,
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    let reply = ask(
+        &node,
+        &handle,
+        Message::GetFeeEstimate {
+            tx_size: 250,
+            target_blocks: 1,
+        },
+    )
+    .await;
+    let Message::FeeEstimate { fee_per_kb, .. } = reply else {
+        panic!("unexpected reply: {reply:?}");
+    };
+    assert!(fee_per_kb > 0, "a block at the governed size is full");
+}
+
+#[test]
+fn governance_txs_are_checked_before_inclusion() {
+    let mut chain = chain_with(ChainParams::default());
+
+    // Activation must come after voting ends (block 1 + 3).
+    let early = propose(&chain, "alice", ParamChange::MinTxFee(2), 4);
+    let err = chain.validate_transaction(&early).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("after voting ends"),
+        "err={err:#}"
+    );
+    let tiny = propose(&chain, "alice", ParamChange::MaxBlockSize(100), 10);
+    assert!(chain.validate_transaction(&tiny).is_err());
+
+    // Governance txs go to the governance address with no amount, and nothing else does.
+    let mut paying = propose(&chain, "alice", ParamChange::MinTxFee(2), 10);
+    paying.amount = 5;
+    let paying = sign(paying, "alice");
+    assert!(chain.validate_transaction(&paying).is_err());
+    let mut elsewhere = propose(&chain, "alice", ParamChange::MinTxFee(2), 10);
+    elsewhere.to = addr("bob");
+    let elsewhere = sign(elsewhere, "alice");
+    assert!(chain.validate_transaction(&elsewhere).is_err());
+    let plain = Transaction::new_with_fee(addr("alice"), GOVERNANCE_ADDRESS, 5, 1, 0, 0);
+    assert!(chain.validate_transaction(&plain).is_err());
+
+    // Only accounts in the snapshot vote, and only on open proposals.
+    let proposal = propose(&chain, "alice", ParamChange::MinTxFee(2), 10);
+    assert!(
+        chain
+            .validate_transaction(&vote(&chain, "bob", &proposal, true))
+            .is_err()
+    );
+    let txs = vec![proposal.clone(), transfer(&chain, "bob", "dave", 5)];
+    mine(&mut chain, txs).unwrap();
+    chain
+        .validate_transaction(&vote(&chain, "bob", &proposal, true))
+        .unwrap();
+    let txs = vec![transfer(&chain, "bob", "erin", 5)];
+    mine(&mut chain, txs).unwrap();
+    assert!(
+        chain
+            .validate_transaction(&vote(&chain, "erin", &proposal, true))
+            .is_err()
+    );
+
+    // A chain without a voting period takes no proposals, nor reward changes under a cap.
+    let mut disabled = chain_with(ChainParams::default());
+    disabled.params.governance_voting_period = 0;
+    let proposal = propose(&disabled, "alice", ParamChange::MinTxFee(2), 10);
+    assert!(disabled.validate_transaction(&proposal).is_err());
+    let capped = chain_with(ChainParams {
+        max_supply: 1_000_000,
+        ..ChainParams::default()
+    });
+    let proposal = propose(&capped, "alice", ParamChange::BlockReward(1), 10);
+    assert!(capped.validate_transaction(&proposal).is_err());
+}
+
+#[test]
+fn governance_txs_must_be_signed_by_their_sender() {
+    let mut chain = chain_with(ChainParams::default());
+    let proposal = propose(&chain, "carol", ParamChange::MinTxFee(2), 10);
+    mine(&mut chain, vec![proposal.clone()]).unwrap();
+
+    // An unsigned vote "from" alice, or one alice's address signed by someone else.
+    let mut unsigned = vote(&chain, "alice", &proposal, true);
+    unsigned.pubkey_hex = None;
+    unsigned.signature_b64 = None;
+    let err = chain.validate_transaction(&unsigned).unwrap_err();
+    assert!(format!("{err:#}").contains("must be signed"), "err={err:#}");
+    assert!(mine(&mut chain, vec![unsigned]).is_err());
+    let mut forged = vote(&chain, "alice", &proposal, true);
+    forged.signature_b64 = sign(forged.clone(), "bob").signature_b64;
+    assert!(chain.validate_transaction(&forged).is_err());
+    // Turning signature checks off for the tx does not help.
+    forged.is_verifiable = false;
+    let err = chain.validate_transaction(&forged).unwrap_err();
+    assert!(format!("{err:#}").contains("must be signed"), "err={err:#}");
+    assert!(mine(&mut chain, vec![forged]).is_err());
+
+    let votes = vec![vote(&chain, "alice", &proposal, true)];
+    mine(&mut chain, votes).unwrap();
+    chain.validate().unwrap();
+}
+
+#[test]
+fn open_proposals_per_sender_are_capped() {
+    let mut chain = chain_with(ChainParams::default());
+    for value in 0..MAX_OPEN_PROPOSALS_PER_SENDER as u64 {
+        let proposal = propose(&chain, "carol", ParamChange::MinTxFee(value), 10);
+        mine(&mut chain, vec![proposal]).unwrap();
+    }
+    let extra = propose(&chain, "carol", ParamChange::MinTxFee(9), 10);
+    let err = chain.validate_transaction(&extra).unwrap_err();
+    assert!(format!("{err:#}").contains("open proposals"), "err={err:#}");
+    assert!(mine(&mut chain, vec![extra]).is_err());
+
+    // Nor can one block carry more than the cap from a sender.
+    let first = propose(&chain, "bob", ParamChange::MinTxFee(1), 10);
+    let mut txs = vec![first];
+    for nonce in 1..=MAX_OPEN_PROPOSALS_PER_SENDER as u64 {
+        let mut tx = Transaction::new_with_fee(addr("bob"), GOVERNANCE_ADDRESS, 0, 1, nonce, 0);
+        tx.chain_id = chain.chain_id;
+        tx.governance = Some(GovernanceOp::Propose {
+            change: ParamChange::MinTxFee(nonce + 1),
+            activation_height: 10,
+        });
+        txs.push(sign(tx, "bob"));
+    }
+    let err = mine(&mut chain, txs).unwrap_err();
+    assert!(format!("{err:#}").contains("open proposals"), "err={err:#}");
+
+    // Once voting closes, the sender can propose again.
+    mine_to(&mut chain, 6);
+    let again = propose(&chain, "carol", ParamChange::MinTxFee(9), 12);
+    mine(&mut chain, vec![again]).unwrap();
+}
+
+#[test]
+fn param_changes_parse_from_names() {
+    assert_eq!(
+        ParamChange::from_name("min_tx_fee", 3).unwrap(),
+        ParamChange::MinTxFee(3)
+    );
+    assert_eq!(
+        ParamChange::from_name("max_block_size", 20_000)
+            .unwrap()
+            .to_string(),
+        "max_block_size=20000"
+    );
+    assert!(ParamChange::from_name("difficulty", 1).is_err());
+
+    let json = serde_json::to_string(&GovernanceOp::Propose {
+        change: ParamChange::BlockReward(7),
+        activation_height: 10,
+    })
+    .unwrap();
+    assert_eq!(
+        json,
+        r#"{"kind":"propose","change":{"param":"block_reward","value":7},"activation_height":10}"#
+    );
+}
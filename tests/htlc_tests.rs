@@ -0,0 +1,164 @@
+mod common;
+
+use common::mine;
+use ed25519_dalek::SigningKey;
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::crypto::{generate_keypair, sign_bytes, verifying_key_to_hex};
+use rusty_chain::core::htlc::{Htlc, new_secret};
+
+struct Party {
+    key: SigningKey,
+    address: String,
+}
+
+fn party() -> Party {
+    let (key, vk) = generate_keypair();
+    Party {
+        key,
+        address: verifying_key_to_hex(&vk),
+    }
+}
+
+fn chain_with(chain_id: u32, funded: &Party) -> Chain {
+    let mut config = common::genesis(&[(&funded.address, 1_000)]);
+    config.network = format!("swap-{chain_id}");
+    config.chain_id = Some(chain_id);
+    config.build_chain().unwrap()
+}
+
+fn balance(chain: &Chain, address: &str) -> u64 {
+    chain.compute_state().unwrap().get_balance(address)
+}
+
+/// Lock `htlc.amount` from `payer` (who must be `htlc.refund_to`) into the contract.
+fn lock(chain: &mut Chain, htlc: &Htlc, payer: &Party) {
+    let mut tx = htlc.lock_tx(chain.next_nonce_for(&payer.address), 1);
+    tx.pubkey_hex = Some(payer.address.clone());
+    tx.signature_b64 = Some(sign_bytes(&payer.key, &tx.signing_bytes()));
+    mine(chain, vec![tx]).unwrap();
+    assert_eq!(balance(chain, &htlc.address()), htlc.amount);
+}
+
+#[test]
+fn atomic_swap_completes_across_two_chains() {
+    let (alice, bob) = (party(), party());
+    let mut chain_a = chain_with(100, &alice);
+    let mut chain_b = chain_with(200, &bob);
+    let (secret, hash) = new_secret();
+
+    // Alice locks on A for Bob with the long timeout; Bob answers on B with a shorter one.
+    let on_a = Htlc {
+        chain_id: 100,
+        counterparty_chain_id: Some(200),
+        hash: hash.clone(),
+        recipient: bob.address.clone(),
+        refund_to: alice.address.clone(),
+        timeout_height: 20,
+        amount: 300,
+    };
+    let on_b = Htlc {
+        chain_id: 200,
+        counterparty_chain_id: Some(100),
+        hash,
+        recipient: alice.address.clone(),
+        refund_to: bob.address.clone(),
+        timeout_height: 10,
+        amount: 500,
+    };
+    lock(&mut chain_a, &on_a, &alice);
+    lock(&mut chain_b, &on_b, &bob);
+    assert_eq!(
+        chain_a.blocks[1].txs[1].target_chain.as_deref(),
+        Some("200")
+    );
+
+    // Bob can't claim without the secret; nothing is revealed yet.
+    assert!(on_a.find_secret(&chain_b).is_none());
+    assert!(on_b.find_secret(&chain_b).is_none());
+
+    // Alice claims on B, revealing the secret.
+    let claim = on_b.claim_tx(500, 0, 1, &secret, &alice.key).unwrap();
+    // The claim is bound to chain B and can't be replayed on A.
+    assert!(chain_a.validate_transaction(&claim).is_err());
+    mine(&mut chain_b, vec![claim]).unwrap();
+    assert_eq!(balance(&chain_b, &alice.address), 499);
+    assert_eq!(balance(&chain_b, &on_b.address()), 0);
+
+    // Bob reads it off chain B and claims on A.
+    let revealed = on_b.find_secret(&chain_b).unwrap();
+    assert_eq!(revealed, secret);
+    let claim = on_a.claim_tx(300, 0, 1, &revealed, &bob.key).unwrap();
+    mine(&mut chain_a, vec![claim]).unwrap();
+    assert_eq!(balance(&chain_a, &bob.address), 299);
+    assert_eq!(balance(&chain_a, &alice.address), 1_000 - 301);
+
+    chain_a.validate().unwrap();
+    chain_b.validate().unwrap();
+}
+
+#[test]
+fn unclaimed_swap_is_refunded_after_the_timeout() {
+    let (alice, bob) = (party(), party());
+    let mut chain_a = chain_with(100, &alice);
+    let (secret, hash) = new_secret();
+    let htlc = Htlc {
+        chain_id: 100,
+        counterparty_chain_id: Some(200),
+        hash,
+        recipient: bob.address.clone(),
+        refund_to: alice.address.clone(),
+        timeout_height: 6,
+        amount: 300,
+    };
+    lock(&mut chain_a, &htlc, &alice);
+
+    // Only the contract's parties can spend, each on their own branch.
+    assert!(htlc.claim_tx(300, 0, 1, &secret, &alice.key).is_err());
+    let (wrong, _) = new_secret();
+    assert!(htlc.claim_tx(300, 0, 1, &wrong, &bob.key).is_err());
+    let mut forged = htlc.claim_tx(300, 0, 1, &secret, &bob.key).unwrap();
+    forged.to = alice.address.clone();
+    assert!(chain_a.validate_transaction(&forged).is_err());
+
+    // The refund waits for the timeout.
+    let refund = htlc.refund_tx(300, 0, 1, &alice.key).unwrap();
+    while chain_a.height() + 1 < 6 {
+        assert!(chain_a.validate_transaction(&refund).is_err());
+        assert!(mine(&mut chain_a, vec![refund.clone()]).is_err());
+        mine(&mut chain_a, vec![]).unwrap();
+    }
+    chain_a.validate_transaction(&refund).unwrap();
+    mine(&mut chain_a, vec![refund]).unwrap();
+    assert_eq!(balance(&chain_a, &alice.address), 1_000 - 1 - 1);
+    assert_eq!(balance(&chain_a, &htlc.address()), 0);
+
+    // Nothing is left for a late claim.
+    let late = htlc.claim_tx(300, 1, 1, &secret, &bob.key).unwrap();
+    assert!(chain_a.validate_transaction(&late).is_err());
+}
+
+#[test]
+fn contract_round_trips_through_json() {
+    let (alice, bob) = (party(), party());
+    let (_, hash) = new_secret();
+    let htlc = Htlc {
+        chain_id: 100,
+        counterparty_chain_id: None,
+        hash,
+        recipient: bob.address.clone(),
+        refund_to: alice.address.clone(),
+        timeout_height: 6,
+        amount: 300,
+    };
+    htlc.check().unwrap();
+    let json = serde_json::to_string(&htlc).unwrap();
+    assert!(!json.contains("counterparty_chain_id"));
+    let back: Htlc = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.address(), htlc.address());
+
+    let same_party = Htlc {
+        recipient: alice.address.clone(),
+        ..htlc
+    };
+    assert!(same_party.check().is_err());
+}
@@ -0,0 +1,247 @@
+mod common;
+
+use common::funded_chain;
+use rusty_chain::core::asset::{ASSET_REGISTRY_ADDRESS, AssetDefinition};
+use rusty_chain::core::chain::{Chain, MerkleProof, pow_ok};
+use rusty_chain::core::light::LightClient;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle, PeerCmd};
+use rusty_chain::core::types::{BlockHeader, Transaction};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+fn pay(nonce: u64, to: &str, amount: u64) -> Transaction {
+    Transaction::new_with_fee("alice", to, amount, 1, nonce, 0)
+}
+
+fn headers(chain: &Chain) -> Vec<BlockHeader> {
+    chain.blocks[1..].iter().map(|b| b.header.clone()).collect()
+}
+
+#[test]
+fn merkle_proof_verifies_only_the_right_tx() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    let tx = pay(0, "bob", 10);
+    chain
+        .mine_block(vec![tx.clone()], 1, Some("miner"))
+        .unwrap();
+    let block = &chain.blocks[1];
+    let root = &block.header.merkle_root;
+
+    // The coinbase comes first.
+    let proof = MerkleProof::for_block(block, 1).unwrap();
+    proof.verify(&tx.id(), root).unwrap();
+    assert!(proof.verify(&block.txs[0].id(), root).is_err());
+    assert!(proof.verify(&tx.id(), &"00".repeat(32)).is_err());
+    assert!(MerkleProof::for_block(block, 2).is_none());
+
+    let mut forged = proof.clone();
+    forged.tx_ids.push(pay(1, "mallory", 1).id());
+    assert!(forged.verify(&tx.id(), root).is_err());
+
+    // Serving proofs needs the tx index.
+    assert!(chain.tx_proof(&tx.id()).is_err());
+    chain.enable_tx_index();
+    assert_eq!(chain.tx_proof(&tx.id()).unwrap().unwrap().merkle, proof);
+    assert!(chain.tx_proof("nope").unwrap().is_none());
+}
+
+#[test]
+fn headers_are_checked_before_they_are_stored() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    for _ in 0..3 {
+        chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    }
+    let all = headers(&chain);
+    let mut client = LightClient::from_genesis(&chain);
+    assert_eq!(client.height(), 0);
+
+    // Does not connect to genesis.
+    let err = client.apply_headers(1, all[1..].to_vec()).unwrap_err();
+    assert!(err.to_string().contains("does not connect"), "err={err}");
+
+    let mut bad_pow = all[0].clone();
+    while pow_ok(&bad_pow.hash(), 1) {
+        bad_pow.nonce += 1;
+    }
+    let err = client.apply_headers(1, vec![bad_pow]).unwrap_err();
+    assert!(err.to_string().contains("fails PoW"), "err={err}");
+
+    let mut stale = all[0].clone();
+    stale.timestamp_ms = chain.blocks[0].header.timestamp_ms;
+    while !pow_ok(&stale.hash(), 1) {
+        stale.nonce += 1;
+    }
+    let err = client.apply_headers(1, vec![stale]).unwrap_err();
+    assert!(err.to_string().contains("median time past"), "err={err}");
+    assert_eq!(client.height(), 0, "rejected batches change nothing");
+
+    assert_eq!(client.apply_headers(1, all.clone()).unwrap(), 3);
+    assert_eq!(client.tip_hash(), chain.tip_hash());
+    // Already known.
+    assert_eq!(client.apply_headers(2, all[1..].to_vec()).unwrap(), 0);
+}
+
+#[test]
+fn longer_branch_replaces_headers_and_drops_proofs_above_fork() {
+    let mut ours = funded_chain(&[("alice", 100)]);
+    ours.enable_tx_index();
+    let mut theirs = ours.clone();
+
+    let tx = pay(0, "bob", 10);
+    ours.mine_block(vec![tx.clone()], 1, Some("miner")).unwrap();
+    let mut client = LightClient::from_genesis(&ours);
+    client.track("bob");
+    client.apply_headers(1, headers(&ours)).unwrap();
+    assert!(
+        client
+            .add_proof(&ours.tx_proof(&tx.id()).unwrap().unwrap())
+            .unwrap()
+    );
+    assert_eq!(client.balance("bob"), 10);
+
+    // A competing branch of equal length is ignored.
+    theirs
+        .mine_block(vec![pay(0, "carol", 5)], 1, Some("miner"))
+        .unwrap();
+    assert!(client.apply_headers(1, headers(&theirs)).is_err());
+    assert_eq!(client.tip_hash(), ours.tip_hash());
+
+    theirs.mine_block(vec![], 1, Some("miner")).unwrap();
+    assert_eq!(client.apply_headers(1, headers(&theirs)).unwrap(), 2);
+    assert_eq!(client.tip_hash(), theirs.tip_hash());
+    assert_eq!(client.balance("bob"), 0);
+    assert!(client.history("bob").is_empty());
+}
+
+#[test]
+fn proven_txs_give_confirmed_balance() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    chain.enable_tx_index();
+    let to_bob = pay(0, "bob", 30);
+    chain
+        .mine_block(vec![to_bob.clone()], 1, Some("bob"))
+        .unwrap();
+    let from_bob = Transaction::new_with_fee("bob", "carol", 5, 2, 0, 0);
+    chain
+        .mine_block(vec![from_bob.clone()], 1, Some("miner"))
+        .unwrap();
+
+    let mut client = LightClient::from_genesis(&chain);
+    client.track("bob");
+    let coinbase_id = chain.blocks[1].txs[0].id();
+    let proofs: Vec<_> = [&coinbase_id, &to_bob.id(), &from_bob.id()]
+        .iter()
+        .map(|id| chain.tx_proof(id).unwrap().unwrap())
+        .collect();
+
+    // Headers first.
+    assert!(client.add_proof(&proofs[0]).is_err());
+    client.apply_headers(1, headers(&chain)).unwrap();
+    for proof in &proofs {
+        assert!(client.add_proof(proof).unwrap());
+    }
+    assert!(!client.add_proof(&proofs[1]).unwrap(), "deduplicated");
+
+    let state = chain.compute_state().unwrap();
+    assert_eq!(client.balance("bob"), state.get_balance("bob"));
+    assert_eq!(client.history("bob").len(), 3);
+
+    // A proof for an untracked address, or one moved to another height, is refused.
+    let alice_only = chain
+        .tx_proof(&chain.blocks[0].txs[0].id())
+        .unwrap()
+        .unwrap();
+    assert!(client.add_proof(&alice_only).is_err());
+    let mut moved = proofs[1].clone();
+    moved.height = 2;
+    assert!(client.add_proof(&moved).is_err());
+
+    // Survives a save/load round trip.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("light.json");
+    client.save(&path).unwrap();
+    let loaded = LightClient::load(&path).unwrap();
+    assert_eq!(loaded.tip_hash(), client.tip_hash());
+    assert_eq!(loaded.balance("bob"), client.balance("bob"));
+}
+
+#[test]
+fn asset_transfers_only_charge_native_fees() {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    chain.enable_tx_index();
+    let mut create = Transaction::new_with_fee("alice", ASSET_REGISTRY_ADDRESS, 0, 1, 0, 0);
+    create.create_asset = Some(AssetDefinition {
+        asset_id: "PROJ".to_string(),
+        supply: 1_000,
+    });
+    chain.mine_block(vec![create], 1, Some("miner")).unwrap();
+
+    let mut to_bob = Transaction::new_with_fee("alice", "bob", 300, 1, 1, 0);
+    to_bob.asset = Some("PROJ".to_string());
+    let pay_bob = pay(2, "bob", 50);
+    chain
+        .mine_block(vec![to_bob.clone(), pay_bob.clone()], 1, Some("miner"))
+        .unwrap();
+    let mut from_bob = Transaction::new_with_fee("bob", "carol", 100, 2, 0, 0);
+    from_bob.asset = Some("PROJ".to_string());
+    chain
+        .mine_block(vec![from_bob.clone()], 1, Some("miner"))
+        .unwrap();
+
+    let mut client = LightClient::from_genesis(&chain);
+    client.track("bob");
+    client.apply_headers(1, headers(&chain)).unwrap();
+    for tx in [&to_bob, &pay_bob, &from_bob] {
+        assert!(
+            client
+                .add_proof(&chain.tx_proof(&tx.id()).unwrap().unwrap())
+                .unwrap()
+        );
+    }
+
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_balance("bob"), 48);
+    assert_eq!(client.balance("bob"), 48);
+}
+
+#[tokio::test]
+async fn node_serves_tx_proofs() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    chain.enable_tx_index();
+    let tx = pay(0, "bob", 10);
+    chain
+        .mine_block(vec![tx.clone()], 1, Some("miner"))
+        .unwrap();
+    let expected = chain.tx_proof(&tx.id()).unwrap();
+
+    let node = P2PNode::new(
+        "127.0.0.1:9000".parse().unwrap(),
+        chain,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    let peer: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+    let (sender, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    node.state.lock().await.peer_senders.insert(peer, sender);
+
+    handle
+        .process_message(Message::GetTxProof { tx_id: tx.id() }, peer)
+        .await
+        .unwrap();
+    match rx.recv().await {
+        Some(PeerCmd::SendMessage(msg)) => assert_eq!(
+            *msg,
+            Message::TxProof {
+                tx_id: tx.id(),
+                proof: expected,
+            }
+        ),
+        other => panic!("unexpected: {other:?}"),
+    }
+}
@@ -4,14 +4,16 @@ use rusty_chain::core::types::Transaction;
 #[test]
 fn test_locktime_validation() {
     let mut chain = Chain::new_genesis();
+    // Spend the reward right away (maturity is covered in state_tests).
+    chain.params.coinbase_maturity = 0;
     let alice = "alice";
     let bob = "bob";
 
     // 0. Give Alice some coins
     // Mine a block where Alice is the miner to get reward (50 coins)
     chain.mine_block(vec![], 1, Some(alice)).unwrap(); // height 1
-    chain.mine_empty_block(1).unwrap(); // height 2
-    chain.mine_empty_block(1).unwrap(); // height 3
+    chain.mine_empty_block(1, "miner").unwrap(); // height 2
+    chain.mine_empty_block(1, "miner").unwrap(); // height 3
 
     // Alice now has 50 coins. Nonce for Alice is 0 because reward txs don't count towards sender nonces.
     let alice_nonce = chain.compute_state().unwrap().get_nonce(alice);
@@ -31,9 +33,9 @@ fn test_locktime_validation() {
     );
 
     // 2. Mine blocks to reach height 6
-    chain.mine_empty_block(1).unwrap(); // height 4
-    chain.mine_empty_block(1).unwrap(); // height 5
-    chain.mine_empty_block(1).unwrap(); // height 6
+    chain.mine_empty_block(1, "miner").unwrap(); // height 4
+    chain.mine_empty_block(1, "miner").unwrap(); // height 5
+    chain.mine_empty_block(1, "miner").unwrap(); // height 6
 
     // Alice now has balance and we are at height 6 >= locktime 6.
     chain
@@ -53,8 +55,8 @@ fn test_locktime_validation() {
     );
 
     // Mine another block to reach height 8
-    chain.mine_empty_block(1).unwrap(); // height 7
-    chain.mine_empty_block(1).unwrap(); // height 8
+    chain.mine_empty_block(1, "miner").unwrap(); // height 7
+    chain.mine_empty_block(1, "miner").unwrap(); // height 8
     chain
         .validate_transaction(&tx2)
         .expect("Should be valid at height 8");
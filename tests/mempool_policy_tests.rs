@@ -0,0 +1,116 @@
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::policy::{MempoolPolicy, fee_per_kb};
+use rusty_chain::core::types::Transaction;
+
+fn tx(from: &str, nonce: u64, fee: u64) -> Transaction {
+    Transaction::new_with_fee(from, "bob", 1, fee, nonce, 0)
+}
+
+#[test]
+fn per_sender_limit_applies_except_to_replacements() {
+    let mut mp = Mempool::new();
+    mp.policy.max_txs_per_sender = 2;
+
+    mp.add_tx_checked(tx("alice", 0, 1), 0).unwrap();
+    mp.add_tx_checked(tx("alice", 1, 1), 0).unwrap();
+    let err = mp
+        .add_tx_checked(tx("alice", 2, 1), 0)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("too many pending txs"), "err={err}");
+
+    // Other senders are unaffected, and RBF is not a new pending tx.
+    mp.add_tx_checked(tx("carol", 0, 1), 0).unwrap();
+    let mut rbf = tx("alice", 1, 5);
+    rbf.sequence = 1;
+    mp.add_tx_checked(rbf, 0).unwrap();
+    assert_eq!(mp.len(), 3);
+}
+
+#[test]
+fn min_relay_fee_rejects_cheap_txs() {
+    let mut mp = Mempool::new();
+    let sample = tx("alice", 0, 0);
+    // Require slightly more than a fee of 1 pays for this size.
+    mp.policy.min_relay_fee_per_kb = fee_per_kb(1, sample.size()) + 1;
+
+    let err = mp.add_tx_checked(sample, 0).unwrap_err().to_string();
+    assert!(err.contains("below minimum relay fee"), "err={err}");
+    mp.add_tx_checked(tx("alice", 0, 2), 0).unwrap();
+}
+
+#[test]
+fn dynamic_min_fee_rises_as_pool_fills() {
+    let policy = MempoolPolicy {
+        max_bytes: 1_000,
+        min_relay_fee_per_kb: 10,
+        fill_threshold_pct: 50,
+        ..MempoolPolicy::default()
+    };
+    // At or below the threshold: the static minimum.
+    assert_eq!(policy.dynamic_min_fee_per_kb(0, 100), 10);
+    assert_eq!(policy.dynamic_min_fee_per_kb(500, 100), 10);
+    // Halfway to full: halfway to outbidding the cheapest tx (101).
+    assert_eq!(policy.dynamic_min_fee_per_kb(750, 100), 55);
+    // Full: must outbid the cheapest tx.
+    assert_eq!(policy.dynamic_min_fee_per_kb(1_000, 100), 101);
+    assert_eq!(policy.dynamic_min_fee_per_kb(5_000, 100), 101);
+}
+
+#[test]
+fn full_pool_evicts_lowest_fee_rate_package() {
+    let mut mp = Mempool::new();
+    let size = tx("alice", 0, 10).size();
+    mp.policy.max_bytes = size * 2 + size / 2;
+    mp.policy.fill_threshold_pct = 100;
+
+    mp.add_tx_checked(tx("alice", 0, 10), 0).unwrap();
+    mp.add_tx_checked(tx("carol", 0, 1), 0).unwrap();
+
+    // A better-paying tx pushes out carol's cheap one.
+    mp.add_tx_checked(tx("dave", 0, 20), 0).unwrap();
+    let senders: Vec<&str> = mp.txs.iter().map(|t| t.from.as_str()).collect();
+    assert_eq!(senders.len(), 2);
+    assert!(!senders.contains(&"carol"));
+
+    // A tx worse than everything in a full pool is refused.
+    let err = mp
+        .add_tx_checked(tx("erin", 0, 0), 0)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("mempool full"), "err={err}");
+    assert_eq!(mp.len(), 2);
+}
+
+#[test]
+fn replacements_are_held_to_the_pool_size_limit() {
+    let mut mp = Mempool::new();
+    let size = tx("alice", 0, 10).size();
+    mp.policy.max_bytes = size * 2 + size / 2;
+    mp.policy.fill_threshold_pct = 100;
+    mp.add_tx_checked(tx("alice", 0, 10), 0).unwrap();
+    mp.add_tx_checked(tx("carol", 0, 1), 0).unwrap();
+
+    // A bigger replacement overflows the pool and pushes out carol's cheap tx.
+    let mut rbf = tx("alice", 0, 100);
+    rbf.sequence = 1;
+    rbf.nonce_id = Some("x".repeat(size));
+    mp.add_tx_checked(rbf.clone(), 0).unwrap();
+    assert!(mp.total_size() <= mp.policy.max_bytes);
+    let ids: Vec<String> = mp.txs.iter().map(|t| t.id()).collect();
+    assert_eq!(ids, vec![rbf.id()]);
+}
+
+#[test]
+fn min_fee_per_kb_reflects_pool_state() {
+    let mut mp = Mempool::new();
+    let size = tx("alice", 0, 10).size();
+    mp.policy.max_bytes = size * 2;
+    mp.policy.fill_threshold_pct = 0;
+    assert_eq!(mp.min_fee_per_kb(), 0);
+
+    mp.add_tx_checked(tx("alice", 0, 10), 0).unwrap();
+    let lowest = fee_per_kb(10, size);
+    // Half full with a threshold of 0%: halfway to outbidding the cheapest tx.
+    assert_eq!(mp.min_fee_per_kb(), lowest.div_ceil(2));
+}
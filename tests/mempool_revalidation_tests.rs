@@ -0,0 +1,139 @@
+mod common;
+
+use common::funded_chain;
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::mempool::{Mempool, MempoolEvent, RemovalReason};
+use rusty_chain::core::types::Transaction;
+
+const NOW: u64 = 1_800_000_000_000;
+fn tx(nonce: u64, to: &str, amount: u64) -> Transaction {
+    Transaction {
+        from: "alice".to_string(),
+        to: to.to_string(),
+        amount,
+        fee: 1,
+        nonce,
+        ..Default::default()
+    }
+}
+
+fn connect(chain: &mut Chain, mp: &mut Mempool, txs: Vec<Transaction>) -> Vec<MempoolEvent> {
+    let block = chain.mine_block(txs, 1, Some("miner")).unwrap();
+    let state = chain.compute_state().unwrap();
+    mp.on_block_connected(&block, &state, chain.height() as u64 + 1, NOW)
+}
+
+fn removed(events: &[MempoolEvent]) -> Vec<(String, RemovalReason)> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            MempoolEvent::Removed { tx_id, reason } => Some((tx_id.clone(), reason.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn competing_tx_evicts_nonce_conflict() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    let mut mp = Mempool::new();
+    let pending = tx(0, "bob", 10);
+    let child = tx(1, "bob", 10);
+    mp.add_tx(pending.clone()).unwrap();
+    mp.add_tx(child.clone()).unwrap();
+
+    // A different tx with the same nonce confirms first.
+    let events = connect(&mut chain, &mut mp, vec![tx(0, "carol", 10)]);
+
+    assert_eq!(
+        removed(&events),
+        vec![(pending.id(), RemovalReason::NonceConflict)]
+    );
+    // The child now follows the confirmed nonce and stays.
+    assert_eq!(mp.len(), 1);
+    assert_eq!(mp.txs[0].id(), child.id());
+}
+
+#[test]
+fn spent_balance_evicts_pending_tx() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    let mut mp = Mempool::new();
+    let included = tx(0, "bob", 40);
+    let too_big = tx(1, "bob", 80);
+    mp.add_tx(included.clone()).unwrap();
+    mp.add_tx(too_big.clone()).unwrap();
+
+    let events = connect(&mut chain, &mut mp, vec![included.clone()]);
+
+    assert_eq!(
+        removed(&events),
+        vec![
+            (included.id(), RemovalReason::Included),
+            (too_big.id(), RemovalReason::InsufficientBalance),
+        ]
+    );
+    assert!(mp.is_empty());
+}
+
+#[test]
+fn expired_and_orphaned_txs_are_evicted() {
+    let chain = funded_chain(&[("alice", 100)]);
+    let mut mp = Mempool::new();
+    let mut expiring = tx(0, "bob", 10);
+    expiring.expiry = Some(1);
+    let orphan = tx(1, "bob", 10);
+    mp.add_tx(expiring.clone()).unwrap();
+    mp.add_tx(orphan.clone()).unwrap();
+
+    let state = chain.compute_state().unwrap();
+    let events = mp.revalidate(&state, 2, NOW);
+
+    assert_eq!(
+        removed(&events),
+        vec![
+            (expiring.id(), RemovalReason::Expired),
+            (orphan.id(), RemovalReason::NonceGap),
+        ]
+    );
+    assert!(mp.is_empty());
+}
+
+#[test]
+fn disconnected_block_txs_return_to_mempool() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    let mut mp = Mempool::new();
+    let confirmed = tx(0, "bob", 10);
+    mp.add_tx(confirmed.clone()).unwrap();
+    connect(&mut chain, &mut mp, vec![confirmed.clone()]);
+    assert!(mp.is_empty());
+
+    let block = chain.disconnect_tip().unwrap();
+    assert_eq!(chain.height(), 0);
+    let state = chain.compute_state().unwrap();
+    let events = mp.on_block_disconnected(&block, &state, 1, NOW);
+
+    assert_eq!(
+        events,
+        vec![MempoolEvent::Added {
+            tx_id: confirmed.id()
+        }]
+    );
+    // Only the user tx comes back, not the coinbase.
+    assert_eq!(mp.len(), 1);
+
+    // And it can be mined again.
+    let picked = chain.select_block_txs(&mp, Some("miner"));
+    chain.mine_block(picked, 1, Some("miner")).unwrap();
+    chain.validate().unwrap();
+}
+
+#[test]
+fn disconnect_refuses_genesis_and_checkpoints() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    assert!(chain.disconnect_tip().is_err());
+
+    chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    chain.add_checkpoint();
+    let err = chain.disconnect_tip().unwrap_err().to_string();
+    assert!(err.contains("checkpointed"), "err={err}");
+}
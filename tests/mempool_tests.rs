@@ -44,3 +44,47 @@ fn test_mempool_stats() {
     assert_eq!(min_fee, 10);
     assert_eq!(max_fee, 20);
 }
+
+#[test]
+fn test_mempool_rejects_pending_overdraft() {
+    let mut mempool = Mempool::new();
+    let balance = 10;
+
+    // 5 + 1 fee, then 3 + 1 fee: exactly the balance.
+    mempool
+        .add_tx_with_balance(Transaction::new_with_fee("A", "B", 5, 1, 0, 0), 0, balance)
+        .unwrap();
+    mempool
+        .add_tx_with_balance(Transaction::new_with_fee("A", "B", 3, 1, 1, 0), 0, balance)
+        .unwrap();
+    assert_eq!(mempool.pending_spend("A"), 10);
+
+    let err = mempool
+        .add_tx_with_balance(Transaction::new_with_fee("A", "B", 1, 0, 2, 0), 0, balance)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("insufficient balance"), "err={err}");
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn test_mempool_balance_counts_rbf_replacement() {
+    let mut mempool = Mempool::new();
+    let balance = 10;
+    mempool
+        .add_tx_with_balance(Transaction::new_with_fee("A", "B", 5, 1, 0, 0), 0, balance)
+        .unwrap();
+
+    // Replacing nonce 0 frees its 6 coins: 8 + 2 fits, 9 + 2 does not.
+    let mut too_big = Transaction::new_with_fee("A", "B", 9, 2, 0, 0);
+    too_big.sequence = 1;
+    assert!(mempool.add_tx_with_balance(too_big, 0, balance).is_err());
+
+    let mut replacement = Transaction::new_with_fee("A", "B", 8, 2, 0, 0);
+    replacement.sequence = 1;
+    mempool
+        .add_tx_with_balance(replacement, 0, balance)
+        .unwrap();
+    assert_eq!(mempool.len(), 1);
+    assert_eq!(mempool.pending_spend("A"), 10);
+}
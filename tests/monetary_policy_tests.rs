@@ -0,0 +1,98 @@
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::params::ChainParams;
+
+#[test]
+fn default_params_keep_fixed_reward_of_50() {
+    let p = ChainParams::default();
+    assert_eq!(p.block_subsidy(0), 0);
+    assert_eq!(p.block_subsidy(1), 50);
+    assert_eq!(p.block_subsidy(210_000), 50);
+    assert_eq!(p.block_subsidy(210_001), 25);
+}
+
+#[test]
+fn subsidy_halves_each_interval() {
+    let p = ChainParams {
+        initial_subsidy: 40,
+        halving_interval: 2,
+        ..Default::default()
+    };
+    let rewards: Vec<u64> = (1..=8).map(|h| p.block_subsidy(h)).collect();
+    assert_eq!(rewards, vec![40, 40, 20, 20, 10, 10, 5, 5]);
+    assert_eq!(p.issued_through(8), 150);
+}
+
+#[test]
+fn tail_emission_is_a_floor() {
+    let p = ChainParams {
+        initial_subsidy: 40,
+        halving_interval: 1,
+        tail_emission: 7,
+        ..Default::default()
+    };
+    let rewards: Vec<u64> = (1..=6).map(|h| p.block_subsidy(h)).collect();
+    assert_eq!(rewards, vec![40, 20, 10, 7, 7, 7]);
+    assert_eq!(p.issued_through(6), 91);
+}
+
+#[test]
+fn max_supply_caps_issuance() {
+    let p = ChainParams {
+        initial_subsidy: 50,
+        halving_interval: 0,
+        max_supply: 120,
+        ..Default::default()
+    };
+    assert_eq!(p.block_subsidy(1), 50);
+    assert_eq!(p.block_subsidy(2), 50);
+    assert_eq!(p.block_subsidy(3), 20);
+    assert_eq!(p.block_subsidy(4), 0);
+    assert_eq!(p.issued_through(10), 120);
+}
+
+#[test]
+fn mined_coinbase_follows_chain_params() {
+    let mut c = Chain::new_genesis();
+    c.params = ChainParams {
+        initial_subsidy: 8,
+        halving_interval: 1,
+        ..Default::default()
+    };
+
+    c.mine_block(vec![], 1, Some("miner")).unwrap();
+    c.mine_block(vec![], 1, Some("miner")).unwrap();
+    c.validate().unwrap();
+
+    let state = c.compute_state().unwrap();
+    assert_eq!(state.get_balance("miner"), 8 + 4);
+    assert_eq!(
+        state.total_supply(),
+        c.params.issued_through(c.height() as u64)
+    );
+}
+
+#[test]
+fn coinbase_above_subsidy_is_rejected() {
+    let mut c = Chain::new_genesis();
+    c.mine_block(vec![], 1, Some("miner")).unwrap();
+
+    // Tighten the policy after the fact: block 1 now overpays.
+    c.params.initial_subsidy = 10;
+    let err = format!("{:?}", c.validate().unwrap_err());
+    assert!(err.contains("Invalid coinbase reward"), "err={err}");
+}
+
+#[test]
+fn params_default_when_missing_from_chain_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("chain.json");
+    Chain::new_genesis().save(&path).unwrap();
+
+    let mut v: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    v.as_object_mut().unwrap().remove("params");
+    std::fs::write(&path, v.to_string()).unwrap();
+
+    let loaded = Chain::load(&path).unwrap();
+    assert_eq!(loaded.params, ChainParams::default());
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::chain_id::{
+    DEVNET_CHAIN_ID, Network, TESTNET_CHAIN_ID, data_dir_for, known_chain_id,
+};
+use rusty_chain::core::crypto::{generate_keypair, sign_bytes, verifying_key_to_hex};
+use rusty_chain::core::genesis::GenesisConfig;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle, PeerCmd};
+use rusty_chain::core::params::ChainParams;
+use rusty_chain::core::types::Transaction;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn funded_chain(network: &str, chain_id: Option<u32>, address: &str) -> Chain {
+    let mut config = common::genesis(&[(address, 1_000)]);
+    config.network = network.to_string();
+    config.chain_id = chain_id;
+    config.build_chain().unwrap()
+}
+
+fn signed_tx(chain_id: u32) -> Transaction {
+    let (sk, vk) = generate_keypair();
+    let from = verifying_key_to_hex(&vk);
+    let mut tx = Transaction::new(from.clone(), "bob", 10, 0);
+    tx.chain_id = chain_id;
+    tx.signature_b64 = Some(sign_bytes(&sk, &tx.signing_bytes()));
+    tx.pubkey_hex = Some(from);
+    tx
+}
+
+#[test]
+fn well_known_networks_have_fixed_ids_and_dirs() {
+    assert_eq!(known_chain_id("devnet"), Some(DEVNET_CHAIN_ID));
+    assert_eq!(known_chain_id("testnet"), Some(TESTNET_CHAIN_ID));
+    assert_eq!(known_chain_id("staging"), None);
+    assert!("staging".parse::<Network>().is_err());
+
+    assert_eq!(Network::Mainnet.data_dir(), PathBuf::from("data/mainnet"));
+    assert_eq!(
+        Chain::default_path_for("testnet"),
+        PathBuf::from("data/testnet/chain.json")
+    );
+    assert_eq!(
+        Mempool::default_path_for("staging"),
+        data_dir_for("staging").join("mempool.json")
+    );
+}
+
+#[test]
+fn chain_id_is_committed_into_signatures() {
+    let tx = signed_tx(DEVNET_CHAIN_ID);
+    tx.verify_signature_if_present().unwrap();
+
+    // Relabeling the tx for another chain breaks the signature.
+    let mut replayed = tx.clone();
+    replayed.chain_id = TESTNET_CHAIN_ID;
+    assert!(replayed.verify_signature_if_present().is_err());
+    assert_ne!(tx.id(), replayed.id());
+}
+
+#[test]
+fn unset_chain_id_keeps_legacy_tx_id() {
+    let tx = Transaction::new("alice", "bob", 10, 0);
+    let json = serde_json::to_string(&tx).unwrap();
+    assert!(!json.contains("chain_id"));
+}
+
+#[test]
+fn tx_signed_for_other_network_is_rejected() {
+    let tx = signed_tx(DEVNET_CHAIN_ID);
+    let devnet = funded_chain("devnet", None, &tx.from);
+    let staging = funded_chain("staging", Some(42), &tx.from);
+
+    devnet.validate_transaction(&tx).unwrap();
+    let err = staging.validate_transaction(&tx).unwrap_err().to_string();
+    assert!(err.contains("chain_id mismatch"), "err={err}");
+}
+
+#[test]
+fn signed_tx_without_chain_id_is_rejected() {
+    let tx = signed_tx(0);
+    let chain = funded_chain("devnet", None, &tx.from);
+    let err = chain.validate_transaction(&tx).unwrap_err().to_string();
+    assert!(err.contains("must be bound"), "err={err}");
+}
+
+#[test]
+fn custom_network_requires_explicit_chain_id() {
+    let config = GenesisConfig {
+        network: "staging".to_string(),
+        chain_id: None,
+        timestamp_ms: 1_700_000_000_000,
+        difficulty: 1,
+        params: ChainParams::default(),
+        allocations: vec![],
+    };
+    assert!(config.build_chain().is_err());
+
+    let chain = GenesisConfig {
+        chain_id: Some(42),
+        ..config
+    }
+    .build_chain()
+    .unwrap();
+    assert_eq!(chain.chain_id, 42);
+}
+
+#[tokio::test]
+async fn handshake_from_other_network_is_rejected() {
+    let chain = funded_chain("devnet", None, "alice");
+    let genesis_hash = chain.genesis_hash();
+    let node = P2PNode::new(
+        "127.0.0.1:9000".parse().unwrap(),
+        chain,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    node.state.lock().await.peer_senders.insert(peer, tx);
+
+    handle
+        .process_message(
+            Message::Handshake {
+                version: 1,
+                best_height: 0,
+                agent: "test".to_string(),
+                genesis_hash,
+                chain_id: TESTNET_CHAIN_ID,
+                timestamp_ms: 0,
+                pruned_height: 0,
+            },
+            peer,
+        )
+        .await
+        .unwrap();
+
+    match rx.recv().await {
+        Some(PeerCmd::SendMessage(msg)) => match *msg {
+            Message::Reject { reason, .. } => assert!(reason.contains("Chain id mismatch")),
+            other => panic!("expected Reject, got {other:?}"),
+        },
+        other => panic!("expected Reject, got {other:?}"),
+    }
+    assert!(matches!(rx.recv().await, Some(PeerCmd::Disconnect)));
+}
@@ -11,23 +11,26 @@ fn chain_next_nonce_for_starts_at_zero() {
 #[test]
 fn chain_next_nonce_for_is_max_plus_one() {
     let mut c = Chain::new_genesis();
+    c.params.coinbase_maturity = 0;
 
     // Fund alice
-    let mut cb = Transaction::default();
-    cb.from = "SYSTEM".to_string();
-    cb.to = "alice".to_string();
-    cb.amount = 50;
-    cb.nonce = 1;
-    cb.timestamp_ms = 0;
-    cb.priority = 0;
-    cb.is_minable = true;
+    let cb = Transaction {
+        from: "SYSTEM".to_string(),
+        to: "alice".to_string(),
+        amount: 50,
+        nonce: 1,
+        timestamp_ms: 0,
+        priority: 0,
+        is_minable: true,
+        ..Default::default()
+    };
     c.mine_block(vec![cb], 0, None).unwrap();
 
     let tx1 = Transaction::new("alice", "bob", 1, 0);
-    c.mine_block(vec![tx1], 0, None).unwrap();
+    c.mine_block(vec![tx1], 0, Some("miner")).unwrap();
 
     let tx2 = Transaction::new("alice", "bob", 1, 1);
-    c.mine_block(vec![tx2], 0, None).unwrap();
+    c.mine_block(vec![tx2], 0, Some("miner")).unwrap();
 
     assert_eq!(c.next_nonce_for("alice"), 2);
     assert_eq!(c.next_nonce_for("bob"), 0);
@@ -69,3 +72,34 @@ fn mempool_next_nonce_for_includes_pending_count() {
     assert_eq!(mp.next_nonce_for("alice", base), 12);
     assert_eq!(mp.next_nonce_for("bob", base), 10);
 }
+
+#[test]
+fn pending_txs_are_applied_before_validating_their_child() {
+    let mut c = Chain::new_genesis();
+    c.params.coinbase_maturity = 0;
+    let cb = Transaction {
+        from: "SYSTEM".to_string(),
+        to: "alice".to_string(),
+        amount: 50,
+        nonce: 1,
+        is_minable: true,
+        ..Default::default()
+    };
+    c.mine_block(vec![cb], 0, None).unwrap();
+
+    let mut mp = Mempool::new();
+    mp.add_tx_checked(Transaction::new("alice", "bob", 30, 0), 0)
+        .unwrap();
+    let child = Transaction::new("alice", "bob", 20, 1);
+    assert_eq!(mp.ancestors_of(&child).len(), 1);
+
+    // Against the chain alone the nonce is wrong; after its parent it fits exactly.
+    assert!(c.validate_transaction(&child).is_err());
+    c.validate_transaction_after(&mp.ancestors_of(&child), &child)
+        .unwrap();
+    let greedy = Transaction::new("alice", "bob", 21, 1);
+    assert!(
+        c.validate_transaction_after(&mp.ancestors_of(&greedy), &greedy)
+            .is_err()
+    );
+}
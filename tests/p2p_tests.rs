@@ -1,6 +1,13 @@
-use rusty_chain::core::chain::Chain;
+mod common;
+
+use common::funded_chain;
+use rusty_chain::core::chain::{Chain, pow_ok};
+use rusty_chain::core::hash::sha256_hex;
 use rusty_chain::core::mempool::Mempool;
-use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle};
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{MAX_SIDE_BLOCKS, MAX_SIDE_BLOCKS_PER_PEER, P2PNode, P2PNodeHandle};
+use rusty_chain::core::script::{Condition, Witness};
+use rusty_chain::core::types::{Block, BlockHeader, Transaction};
 use std::net::SocketAddr;
 use std::sync::Arc;
 
@@ -43,3 +50,314 @@ async fn test_peer_whitelisting_bypasses_reputation() {
     let state = node.state.lock().await;
     assert!(state.whitelisted_peers.contains(&peer_addr));
 }
+
+#[tokio::test]
+async fn longer_side_branch_replaces_the_tip_and_returns_its_txs() {
+    let genesis = funded_chain(&[("alice", 1_000), ("dave", 1_000)]);
+    let mut ours = genesis.clone();
+    let stranded = Transaction::new_with_fee("dave", "bob", 100, 1, 0, 0);
+    let our_block = ours
+        .mine_block(vec![stranded.clone()], 1, Some("miner"))
+        .unwrap();
+    let mut theirs = genesis;
+    let fork = [
+        theirs
+            .mine_block(
+                vec![Transaction::new_with_fee("alice", "carol", 50, 1, 0, 0)],
+                1,
+                Some("miner"),
+            )
+            .unwrap(),
+        theirs.mine_block(vec![], 1, Some("miner")).unwrap(),
+    ];
+
+    let node = P2PNode::new(
+        "127.0.0.1:9001".parse().unwrap(),
+        ours,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+
+    // Same height as our tip: held, not switched to.
+    handle
+        .process_message(Message::NewBlock(fork[0].clone()), peer)
+        .await
+        .unwrap();
+    {
+        let state = node.state.lock().await;
+        assert_eq!(state.chain.tip_hash(), our_block.header.hash());
+        assert_eq!(state.side_blocks.len(), 1);
+    }
+
+    // One block longer: the node reorganizes, and dave's tx goes back to the mempool.
+    handle
+        .process_message(Message::NewBlock(fork[1].clone()), peer)
+        .await
+        .unwrap();
+    let state = node.state.lock().await;
+    assert_eq!(state.chain.tip_hash(), theirs.tip_hash());
+    assert_eq!(state.chain.next_nonce_for("alice"), 1);
+    assert_eq!(state.chain.next_nonce_for("dave"), 0);
+    assert!(state.mempool.contains_tx(&stranded.id()));
+    assert!(state.side_blocks.contains_key(&our_block.header.hash()));
+    state.chain.validate().unwrap();
+}
+
+#[tokio::test]
+async fn gossiped_txs_are_checked_against_the_cached_tip_state() {
+    let chain = funded_chain(&[("alice", 1_000), ("dave", 1_000)]);
+    let node = P2PNode::new(
+        "127.0.0.1:9002".parse().unwrap(),
+        chain.clone(),
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+
+    // A child is checked after its pending parent, so it may spend what the parent leaves.
+    let parent = Transaction::new_with_fee("alice", "bob", 600, 1, 0, 0);
+    let child = Transaction::new_with_fee("alice", "carol", 390, 9, 1, 0);
+    for tx in [&parent, &child] {
+        handle
+            .process_message(Message::NewTransaction(tx.clone()), peer)
+            .await
+            .unwrap();
+    }
+    {
+        let state = node.state.lock().await;
+        assert_eq!(state.mempool.len(), 2);
+        let (hash, _) = state.tip_state_cache.as_ref().unwrap();
+        assert_eq!(*hash, chain.tip_hash());
+    }
+
+    // Connecting a block moves the cache to the new tip.
+    let mut next = chain;
+    let block = next
+        .mine_block(vec![parent, child], 1, Some("miner"))
+        .unwrap();
+    handle
+        .process_message(Message::NewBlock(block), peer)
+        .await
+        .unwrap();
+    let mut state = node.state.lock().await;
+    assert!(state.mempool.is_empty());
+    let (hash, cached) = state.tip_state_cache.clone().unwrap();
+    assert_eq!(hash, next.tip_hash());
+    assert_eq!(cached.get_nonce("alice"), 2);
+    assert!(Arc::ptr_eq(&cached, &state.tip_state().unwrap()));
+}
+
+fn node_on(chain: Chain) -> (P2PNode, P2PNodeHandle) {
+    let node = P2PNode::new(
+        "127.0.0.1:9003".parse().unwrap(),
+        chain,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    (node, handle)
+}
+
+/// A coinbase-only block on `chain`'s tip; `miner` makes blocks on the same parent distinct.
+fn side_block(chain: &Chain, miner: &str) -> Block {
+    chain.clone().mine_block(vec![], 1, Some(miner)).unwrap()
+}
+
+fn regrind(header: &mut BlockHeader, difficulty: usize) {
+    while !pow_ok(&header.hash(), difficulty) {
+        header.nonce += 1;
+    }
+}
+
+#[tokio::test]
+async fn side_blocks_at_or_below_the_last_checkpoint_are_refused() {
+    let genesis = funded_chain(&[("alice", 1_000)]);
+    let mut ours = genesis.clone();
+    for _ in 0..10 {
+        ours.mine_block(vec![], 1, Some("miner")).unwrap();
+    }
+    assert!(ours.get_checkpoint_at(10).is_some());
+    let (node, handle) = node_on(ours.clone());
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+
+    for block in [side_block(&genesis, "other"), side_block(&ours, "other")] {
+        handle
+            .process_message(Message::NewBlock(block), peer)
+            .await
+            .unwrap();
+    }
+    // Only the fork above the checkpoint reaches the node, and it extends the tip.
+    let state = node.state.lock().await;
+    assert!(state.side_blocks.is_empty());
+    assert_eq!(state.chain.height(), 11);
+}
+
+#[tokio::test]
+async fn side_blocks_are_capped_per_peer_and_evict_the_lowest() {
+    let genesis = funded_chain(&[("alice", 1_000)]);
+    let mut ours = genesis.clone();
+    ours.mine_block(vec![], 1, Some("miner")).unwrap();
+    ours.mine_block(vec![], 1, Some("miner")).unwrap();
+    let (node, handle) = node_on(ours.clone());
+    let parent = {
+        let mut c = ours.clone();
+        c.disconnect_tip().unwrap();
+        c
+    };
+
+    // One low fork from its own peer, then a flood at the tip height.
+    let low = side_block(&genesis, "low");
+    handle
+        .process_message(Message::NewBlock(low.clone()), "9.9.9.9:1".parse().unwrap())
+        .await
+        .unwrap();
+    let flooder: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+    for i in 0..=MAX_SIDE_BLOCKS_PER_PEER {
+        handle
+            .process_message(
+                Message::NewBlock(side_block(&parent, &format!("f{i}"))),
+                flooder,
+            )
+            .await
+            .unwrap();
+    }
+    {
+        let state = node.state.lock().await;
+        assert_eq!(state.side_blocks.len(), MAX_SIDE_BLOCKS_PER_PEER + 1);
+        assert!(state.side_blocks.contains_key(&low.header.hash()));
+    }
+
+    // Filling the store from other peers pushes out the lowest block, never the new one.
+    for i in 0..MAX_SIDE_BLOCKS - MAX_SIDE_BLOCKS_PER_PEER {
+        let peer: SocketAddr = format!("10.0.{}.1:9000", i / MAX_SIDE_BLOCKS_PER_PEER)
+            .parse()
+            .unwrap();
+        handle
+            .process_message(
+                Message::NewBlock(side_block(&parent, &format!("p{i}"))),
+                peer,
+            )
+            .await
+            .unwrap();
+    }
+    let state = node.state.lock().await;
+    assert_eq!(state.side_blocks.len(), MAX_SIDE_BLOCKS);
+    assert!(!state.side_blocks.contains_key(&low.header.hash()));
+    assert_eq!(state.chain.tip_hash(), ours.tip_hash());
+}
+
+#[tokio::test]
+async fn failed_reorg_drops_the_bad_block_and_its_descendants() {
+    let genesis = funded_chain(&[("alice", 1_000)]);
+    let mut ours = genesis.clone();
+    ours.mine_block(vec![], 1, Some("miner")).unwrap();
+    let (node, handle) = node_on(ours.clone());
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+
+    let difficulty = genesis.pow_difficulty;
+    let mut bad = side_block(&genesis, "other");
+    bad.header.state_root = "11".repeat(32);
+    regrind(&mut bad.header, difficulty);
+    let mut child = side_block(&genesis, "child");
+    child.header.prev_hash = bad.header.hash();
+    regrind(&mut child.header, difficulty);
+
+    for block in [bad, child] {
+        handle
+            .process_message(Message::NewBlock(block), peer)
+            .await
+            .unwrap();
+    }
+    let state = node.state.lock().await;
+    assert!(state.side_blocks.is_empty());
+    assert_eq!(state.chain.tip_hash(), ours.tip_hash());
+}
+
+#[tokio::test]
+async fn node_keeps_responding_after_rejecting_an_overdrawing_tx() {
+    let (node, handle) = node_on(funded_chain(&[("alice", 1_000)]));
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+
+    // The replacement alone fits the tip balance, but not with the pending child after it.
+    let parent = Transaction::new_with_fee("alice", "bob", 600, 1, 0, 0);
+    let child = Transaction::new_with_fee("alice", "carol", 390, 9, 1, 0);
+    let mut overdraw = Transaction::new_with_fee("alice", "bob", 700, 2, 0, 0);
+    overdraw.sequence = 1;
+    for tx in [parent, child, overdraw] {
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            handle.process_message(Message::NewTransaction(tx), peer),
+        )
+        .await
+        .expect("node deadlocked handling a rejected tx")
+        .unwrap();
+    }
+
+    let state = tokio::time::timeout(std::time::Duration::from_secs(5), node.state.lock())
+        .await
+        .expect("node state still locked");
+    assert_eq!(state.mempool.len(), 2);
+    assert_eq!(state.mempool.pending_spend("alice"), 1_000);
+}
+
+#[tokio::test]
+async fn copies_with_a_mangled_witness_do_not_shadow_the_valid_one() {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    let condition = Condition::HashLock {
+        sha256: sha256_hex(b"secret"),
+    };
+    let address = condition.address();
+    chain
+        .mine_block(
+            vec![Transaction::new_with_fee("alice", &address, 100, 0, 0, 0)],
+            1,
+            Some("miner"),
+        )
+        .unwrap();
+    let mut spend = Transaction::new_with_fee(address, "bob", 50, 1, 0, 0);
+    spend.chain_id = chain.chain_id;
+    spend.script = Some(condition.to_script());
+    spend.witness = Some(Witness {
+        preimages: vec![hex::encode(b"secret")],
+        ..Default::default()
+    });
+    // Tx ids, and so block hashes, don't cover the witness.
+    let mut mangled = spend.clone();
+    mangled.witness.as_mut().unwrap().preimages = vec![hex::encode(b"guess")];
+    assert_eq!(mangled.id(), spend.id());
+    let peer: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+
+    let (node, handle) = node_on(chain.clone());
+    for tx in [&mangled, &spend] {
+        handle
+            .process_message(Message::NewTransaction(tx.clone()), peer)
+            .await
+            .unwrap();
+    }
+    assert!(node.state.lock().await.mempool.contains_tx(&spend.id()));
+
+    let (node, handle) = node_on(chain.clone());
+    let block = chain.mine_block(vec![spend], 1, Some("miner")).unwrap();
+    let mut bad = block.clone();
+    bad.txs[1] = mangled;
+    assert_eq!(bad.header.hash(), block.header.hash());
+    for b in [bad, block] {
+        handle
+            .process_message(Message::NewBlock(b), peer)
+            .await
+            .unwrap();
+    }
+    assert_eq!(node.state.lock().await.chain.tip_hash(), chain.tip_hash());
+}
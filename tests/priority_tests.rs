@@ -1,49 +1,49 @@
+use rusty_chain::core::genesis::{GenesisAllocation, GenesisConfig};
 use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::params::ChainParams;
 use rusty_chain::core::types::Transaction;
 
 #[test]
-fn test_mempool_priority_sorting() {
+fn test_mempool_priority_does_not_reorder() {
     let mut mempool = Mempool::new();
 
-    // Same fee, different priority
+    // Same sender: nonce order wins over fee and priority.
     let mut tx1 = Transaction::new("A", "B", 10, 0);
     tx1.fee = 10;
     tx1.priority = 10;
 
     let mut tx2 = Transaction::new("A", "C", 10, 1);
     tx2.fee = 10;
-    tx2.priority = 20; // Higher priority
+    tx2.priority = 20;
 
-    // Higher fee, lower priority
     let mut tx3 = Transaction::new("A", "D", 10, 2);
     tx3.fee = 20;
     tx3.priority = 0;
 
-    mempool.add_tx(tx1).unwrap();
-    mempool.add_tx(tx2).unwrap();
+    // Another sender with a max priority but a low fee rate.
+    let mut tx4 = Transaction::new("E", "F", 10, 0);
+    tx4.fee = 1;
+    tx4.priority = 255;
+
+    mempool.add_tx(tx4).unwrap();
     mempool.add_tx(tx3).unwrap();
+    mempool.add_tx(tx2).unwrap();
+    mempool.add_tx(tx1).unwrap();
 
     let sorted = mempool.drain_sorted();
-
-    // 1. tx3 (fee 20)
-    assert_eq!(sorted[0].from, "A");
-    assert_eq!(sorted[0].to, "D");
-
-    // 2. tx2 (fee 10, priority 20)
-    assert_eq!(sorted[1].to, "C");
-
-    // 3. tx1 (fee 10, priority 10)
-    assert_eq!(sorted[2].to, "B");
+    let to: Vec<&str> = sorted.iter().map(|t| t.to.as_str()).collect();
+    assert_eq!(to, vec!["B", "C", "D", "F"]);
 }
 
 #[test]
-fn test_mempool_priority_limit_size() {
+fn test_mempool_limit_size_evicts_descendants_first() {
     let mut mempool = Mempool::new();
 
     let mut tx1 = Transaction::new("A", "B", 10, 0);
     tx1.fee = 10;
     tx1.priority = 50;
 
+    // Higher priority, but it depends on tx1.
     let mut tx2 = Transaction::new("A", "C", 10, 1);
     tx2.fee = 10;
     tx2.priority = 100;
@@ -52,9 +52,54 @@ fn test_mempool_priority_limit_size() {
     mempool.add_tx(tx1).unwrap();
     mempool.add_tx(tx2).unwrap();
 
-    // Limit to size of 1 tx. Higher priority should stay.
+    // Limit to size of 1 tx. The parent must stay.
     mempool.limit_size(size + 1);
 
     assert_eq!(mempool.len(), 1);
-    assert_eq!(mempool.txs[0].priority, 100);
+    assert_eq!(mempool.txs[0].nonce, 0);
+}
+
+#[test]
+fn parent_and_child_from_one_sender_are_mined_together() {
+    let mut chain = GenesisConfig {
+        network: "devnet".to_string(),
+        chain_id: None,
+        timestamp_ms: 1_700_000_000_000,
+        difficulty: 1,
+        params: ChainParams::default(),
+        allocations: vec![GenesisAllocation {
+            address: "alice".to_string(),
+            balance: 1_000,
+        }],
+    }
+    .build_chain()
+    .unwrap();
+
+    // The child spends what's left after the parent: only valid once the parent applied.
+    let mut mempool = Mempool::new();
+    mempool
+        .add_tx(Transaction::new_with_fee("alice", "bob", 600, 1, 0, 0))
+        .unwrap();
+    mempool
+        .add_tx(Transaction::new_with_fee("alice", "carol", 390, 9, 1, 0))
+        .unwrap();
+
+    let txs = chain.select_block_txs(&mempool, Some("miner"));
+    assert_eq!(txs.len(), 2);
+    chain.mine_block(txs, 1, Some("miner")).unwrap();
+
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_balance("alice"), 0);
+    assert_eq!(state.get_balance("bob"), 600);
+    assert_eq!(state.get_balance("carol"), 390);
+    assert_eq!(chain.next_nonce_for("alice"), 2);
+    chain.validate().unwrap();
+
+    // A child that overspends still fails the block, even with its parent in front.
+    let txs = vec![
+        Transaction::new_with_fee("bob", "dave", 500, 1, 0, 0),
+        Transaction::new_with_fee("bob", "dave", 100, 1, 1, 0),
+    ];
+    assert!(chain.mine_block(txs, 1, Some("miner")).is_err());
+    assert_eq!(chain.height(), 1);
 }
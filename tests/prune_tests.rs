@@ -0,0 +1,209 @@
+mod common;
+
+use common::{funded_chain, long_chain};
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle, PeerCmd};
+use rusty_chain::core::types::Transaction;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[test]
+fn prune_drops_old_bodies_but_keeps_headers_and_state() {
+    let full = long_chain();
+    let mut chain = full.clone();
+    assert_eq!(chain.set_prune_keep_blocks(10).unwrap(), 15);
+    assert_eq!(chain.pruned_height(), 15);
+    assert!(chain.blocks[1..=15].iter().all(|b| b.txs.is_empty()));
+    assert!(chain.blocks[16..].iter().all(|b| !b.txs.is_empty()));
+    assert!(!chain.blocks[0].txs.is_empty(), "genesis is kept");
+    for (ours, theirs) in chain.blocks.iter().zip(&full.blocks) {
+        assert_eq!(ours.header, theirs.header);
+    }
+    assert_eq!(chain.checkpoints, full.checkpoints);
+    assert_eq!(
+        chain.compute_state().unwrap().accounts,
+        full.compute_state().unwrap().accounts
+    );
+    assert_eq!(chain.next_nonce_for("alice"), 25);
+    chain.validate().unwrap();
+    // Already pruned that far.
+    assert_eq!(chain.prune(10).unwrap(), 0);
+
+    // Pruning mode survives a reload and keeps pruning as blocks arrive.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("chain.json");
+    chain.save(&path).unwrap();
+    let mut chain = Chain::load(&path).unwrap();
+    assert_eq!(chain.prune_keep_blocks, Some(10));
+    for nonce in 25..28 {
+        chain
+            .mine_block(
+                vec![Transaction::new_with_fee("alice", "bob", 10, 1, nonce, 0)],
+                1,
+                Some("miner"),
+            )
+            .unwrap();
+    }
+    assert_eq!(chain.pruned_height(), 18);
+    assert!(chain.compute_state_at(17).is_err());
+    chain.validate().unwrap();
+}
+
+#[test]
+fn prune_refuses_short_windows_and_the_tx_index() {
+    let mut chain = long_chain();
+    let err = chain.prune(5).unwrap_err();
+    assert!(err.to_string().contains("at least 10"), "err={err}");
+
+    chain.enable_tx_index();
+    let err = chain.set_prune_keep_blocks(10).unwrap_err();
+    assert!(err.to_string().contains("tx index"), "err={err}");
+    assert_eq!(chain.prune_keep_blocks, None);
+    assert_eq!(chain.pruned_height(), 0);
+}
+
+#[test]
+fn pruned_chain_can_still_reorg_within_the_window() {
+    let full = long_chain();
+    let mut pruned = full.clone();
+    pruned.set_prune_keep_blocks(10).unwrap();
+
+    // A heavier branch forking below the tip (but above the last checkpoint).
+    let mut other = full.clone();
+    other.disconnect_tip().unwrap();
+    other.disconnect_tip().unwrap();
+    for _ in 0..3 {
+        other.mine_block(vec![], 1, Some("rival")).unwrap();
+    }
+
+    pruned.disconnect_tip().unwrap();
+    pruned.disconnect_tip().unwrap();
+    for block in &other.blocks[24..] {
+        pruned.append_block(block.clone()).unwrap();
+    }
+    assert_eq!(pruned.tip_hash(), other.tip_hash());
+    assert_eq!(
+        pruned.compute_state().unwrap().accounts,
+        other.compute_state().unwrap().accounts
+    );
+
+    // Blocks below the pruned height can't be unwound.
+    while pruned.height() > 20 {
+        pruned.disconnect_tip().unwrap();
+    }
+    assert!(pruned.disconnect_tip().is_err(), "checkpoint at 20");
+    assert!(pruned.is_pruned(16));
+}
+
+fn node_with(chain: Chain) -> (P2PNode, P2PNodeHandle) {
+    let node = P2PNode::new(
+        "127.0.0.1:9000".parse().unwrap(),
+        chain,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    (node, handle)
+}
+
+#[tokio::test]
+async fn pruned_node_rejects_getdata_for_pruned_blocks() {
+    let mut chain = long_chain();
+    chain.set_prune_keep_blocks(10).unwrap();
+    let old = chain.blocks[5].header.hash();
+    let recent = chain.blocks[20].header.hash();
+    let (node, handle) = node_with(chain);
+
+    let peer: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    node.state.lock().await.peer_senders.insert(peer, tx);
+
+    handle
+        .process_message(
+            Message::GetData {
+                block_hashes: vec![old, recent.clone()],
+            },
+            peer,
+        )
+        .await
+        .unwrap();
+    match rx.recv().await {
+        Some(PeerCmd::SendMessage(msg)) => match *msg {
+            Message::Reject {
+                code,
+                reason,
+                message_type,
+            } => {
+                assert_eq!(code, 404);
+                assert_eq!(message_type, "GetData");
+                assert!(reason.contains("height 5"), "reason={reason}");
+            }
+            other => panic!("expected Reject, got {other:?}"),
+        },
+        other => panic!("expected Reject, got {other:?}"),
+    }
+    // Blocks it still has are served.
+    match rx.recv().await {
+        Some(PeerCmd::SendMessage(msg)) => match *msg {
+            Message::Blocks(blocks) => {
+                assert_eq!(blocks.len(), 1);
+                assert_eq!(blocks[0].header.hash(), recent);
+            }
+            other => panic!("expected Blocks, got {other:?}"),
+        },
+        other => panic!("expected Blocks, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn pruned_height_is_advertised_and_respected() {
+    // Our handshake carries the pruned height.
+    let mut chain = long_chain();
+    chain.set_prune_keep_blocks(10).unwrap();
+    let (node, _) = node_with(chain);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target = listener.local_addr().unwrap();
+    node.connect(target, 25, "test".to_string()).await.unwrap();
+    let (mut stream, _) = listener.accept().await.unwrap();
+    match Message::decode_async(&mut stream).await.unwrap() {
+        Message::Handshake { pruned_height, .. } => assert_eq!(pruned_height, 15),
+        other => panic!("expected Handshake, got {other:?}"),
+    }
+
+    // A fresh node doesn't try to sync blocks from a pruned peer.
+    let fresh = funded_chain(&[("alice", 1_000)]);
+    let genesis_hash = fresh.genesis_hash();
+    let chain_id = fresh.chain_id;
+    let (node, handle) = node_with(fresh);
+    let peer: SocketAddr = "10.0.0.2:9000".parse().unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    node.state.lock().await.peer_senders.insert(peer, tx);
+    handle
+        .process_message(
+            Message::Handshake {
+                version: 1,
+                best_height: 25,
+                agent: "pruned".to_string(),
+                genesis_hash,
+                chain_id,
+                timestamp_ms: 0,
+                pruned_height: 15,
+            },
+            peer,
+        )
+        .await
+        .unwrap();
+    drop(node.state.lock().await.peer_senders.remove(&peer));
+    let mut sent = Vec::new();
+    while let Some(PeerCmd::SendMessage(msg)) = rx.recv().await {
+        sent.push(msg.get_type_name());
+    }
+    assert!(!sent.contains(&"GetCheckpoints"), "sent={sent:?}");
+    assert!(!node.state.lock().await.peer_has_block(&peer, 15));
+    assert!(node.state.lock().await.peer_has_block(&peer, 16));
+}
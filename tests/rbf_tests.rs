@@ -1,3 +1,6 @@
+mod common;
+
+use common::funded_chain;
 use rusty_chain::core::mempool::Mempool;
 use rusty_chain::core::types::Transaction;
 
@@ -68,24 +71,151 @@ fn rbf_rejects_lower_fee_even_with_higher_sequence() {
 }
 
 #[test]
-fn rbf_replaces_correct_transaction() {
+fn rbf_replaces_correct_transaction_and_evicts_descendants() {
     let mut mp = Mempool::default();
     let base = 0;
 
-    // Add two txs from alice
+    // Add three txs from alice and one from carol
     mp.add_tx_checked(Transaction::new("alice", "bob", 1, 0), base)
         .unwrap();
     mp.add_tx_checked(Transaction::new("alice", "bob", 1, 1), base)
         .unwrap();
+    mp.add_tx_checked(Transaction::new("alice", "bob", 1, 2), base)
+        .unwrap();
+    mp.add_tx_checked(Transaction::new("carol", "bob", 1, 0), base)
+        .unwrap();
 
-    // Replace the first one (nonce=0)
-    let mut rbf = Transaction::new_with_fee("alice", "bob", 5, 10, 0, 0);
+    // Replace the middle one (nonce=1): nonce 2 depended on it and goes too.
+    let mut rbf = Transaction::new_with_fee("alice", "bob", 5, 10, 1, 0);
     rbf.sequence = 1;
     mp.add_tx_checked(rbf, base).unwrap();
 
-    assert_eq!(mp.txs.len(), 2);
-    assert_eq!(mp.txs[0].nonce, 0);
-    assert_eq!(mp.txs[0].fee, 10, "First tx should be replaced");
-    assert_eq!(mp.txs[1].nonce, 1);
-    assert_eq!(mp.txs[1].fee, 0, "Second tx should be untouched");
+    let mut alice: Vec<(u64, u64)> = mp
+        .txs
+        .iter()
+        .filter(|t| t.from == "alice")
+        .map(|t| (t.nonce, t.fee))
+        .collect();
+    alice.sort();
+    assert_eq!(alice, vec![(0, 0), (1, 10)]);
+    assert_eq!(mp.len(), 3, "carol's tx is untouched");
+    assert_eq!(mp.next_nonce_for("alice", base), 2);
+    for tx in &mp.txs {
+        assert!(mp.contains_tx(&tx.id()), "index must follow the evictions");
+    }
+}
+
+fn fee_tx(from: &str, nonce: u64, fee: u64, sequence: u32) -> Transaction {
+    let mut tx = Transaction::new_with_fee(from, "bob", 1, fee, nonce, 0);
+    tx.sequence = sequence;
+    tx
+}
+
+#[test]
+fn rbf_requires_minimum_fee_rate_bump() {
+    let mut mp = Mempool::new();
+    mp.policy.min_rbf_bump_pct = 50;
+    mp.policy.incremental_relay_fee_per_kb = 0;
+    mp.add_tx_checked(fee_tx("alice", 0, 100, 0), 0).unwrap();
+
+    // Higher fee, but not 50% higher.
+    let err = mp
+        .add_tx_checked(fee_tx("alice", 0, 120, 1), 0)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("bump"), "err={err}");
+
+    mp.add_tx_checked(fee_tx("alice", 0, 160, 1), 0).unwrap();
+    assert_eq!(mp.txs[0].fee, 160);
+}
+
+#[test]
+fn rbf_must_pay_for_evicted_descendants_and_its_own_relay() {
+    let mut mp = Mempool::new();
+    mp.policy.min_rbf_bump_pct = 0;
+    mp.policy.incremental_relay_fee_per_kb = 0;
+    mp.add_tx_checked(fee_tx("alice", 0, 10, 0), 0).unwrap();
+    mp.add_tx_checked(fee_tx("alice", 1, 50, 0), 0).unwrap();
+
+    // Beats the replaced tx, but not the 60 it would evict in total.
+    let err = mp
+        .add_tx_checked(fee_tx("alice", 0, 59, 1), 0)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("evicted fees 60"), "err={err}");
+    assert_eq!(mp.len(), 2);
+
+    // With a relay fee, covering the evicted fees exactly is not enough either.
+    mp.policy.incremental_relay_fee_per_kb = 1_000;
+    let exact = fee_tx("alice", 0, 60, 1);
+    let relay = exact.size() as u64;
+    let err = mp.add_tx_checked(exact, 0).unwrap_err().to_string();
+    assert!(err.contains("relay fee"), "err={err}");
+
+    mp.add_tx_checked(fee_tx("alice", 0, 60 + relay + 3, 1), 0)
+        .unwrap();
+    assert_eq!(mp.len(), 1);
+}
+
+#[test]
+fn rbf_balance_check_counts_evicted_descendants() {
+    let mut mp = Mempool::new();
+    mp.add_tx_with_balance(
+        Transaction::new_with_fee("alice", "bob", 40, 1, 0, 0),
+        0,
+        100,
+    )
+    .unwrap();
+    mp.add_tx_with_balance(
+        Transaction::new_with_fee("alice", "bob", 50, 1, 1, 0),
+        0,
+        100,
+    )
+    .unwrap();
+
+    // Replacing nonce 0 frees both pending txs, so 90 + 5 fits a balance of 100.
+    let mut rbf = Transaction::new_with_fee("alice", "bob", 90, 5, 0, 0);
+    rbf.sequence = 1;
+    mp.add_tx_with_balance(rbf, 0, 100).unwrap();
+    assert_eq!(mp.len(), 1);
+    assert_eq!(mp.pending_spend("alice"), 95);
+}
+
+#[test]
+fn cpfp_child_gets_stuck_parent_mined() {
+    let mut mp = Mempool::new();
+    // A stuck, cheap parent and a competing tx that pays more than it.
+    mp.add_tx_checked(fee_tx("alice", 0, 1, 0), 0).unwrap();
+    mp.add_tx_checked(fee_tx("carol", 0, 20, 0), 0).unwrap();
+    let cost = |tx: &Transaction| tx.size() + 1;
+    let (parent, carol) = (mp.txs[0].clone(), mp.txs[1].clone());
+
+    // Room for one tx: carol wins.
+    let picked = mp.select_for_block(cost(&carol), 10, |_| 0);
+    let senders: Vec<&str> = picked.iter().map(|t| t.from.as_str()).collect();
+    assert_eq!(senders, vec!["carol"]);
+
+    // The child pays enough for both: the package (1 + 100) / 2 beats carol's 20.
+    let child = fee_tx("alice", 1, 100, 0);
+    mp.add_tx_checked(child.clone(), 0).unwrap();
+    let package = cost(&parent) + cost(&child);
+    let picked = mp.select_for_block(package, 10, |_| 0);
+    let order: Vec<(&str, u64)> = picked.iter().map(|t| (t.from.as_str(), t.nonce)).collect();
+    assert_eq!(order, vec![("alice", 0), ("alice", 1)]);
+
+    // Under eviction the parent survives with its child; carol's tx goes instead.
+    mp.limit_size(parent.size() + child.size());
+    let left: Vec<(&str, u64)> = mp.txs.iter().map(|t| (t.from.as_str(), t.nonce)).collect();
+    assert_eq!(left, vec![("alice", 0), ("alice", 1)]);
+
+    // The package is a valid block: both txs confirm, parent first.
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let mined = chain.mine_block(picked, 1, Some("miner")).unwrap();
+    mp.remove_included(&mined.txs);
+    assert!(mp.is_empty());
+    assert_eq!(chain.next_nonce_for("alice"), 2);
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_balance("alice"), 10_000 - 2 - 101);
+    assert_eq!(state.get_balance("bob"), 2);
+    chain.validate().unwrap();
 }
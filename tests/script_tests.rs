@@ -0,0 +1,276 @@
+mod common;
+
+use common::funded_chain;
+use ed25519_dalek::SigningKey;
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::crypto::{generate_keypair, sign_bytes, verifying_key_to_hex};
+use rusty_chain::core::hash::sha256_hex;
+use rusty_chain::core::script::{Condition, MAX_CONDITION_DEPTH, Witness, WitnessSignature};
+use rusty_chain::core::types::Transaction;
+
+fn key() -> (SigningKey, String) {
+    let (sk, vk) = generate_keypair();
+    (sk, verifying_key_to_hex(&vk))
+}
+
+/// Lock 100 coins from alice to `condition`; returns its address.
+fn lock(chain: &mut Chain, condition: &Condition) -> String {
+    let address = condition.address();
+    let nonce = chain.next_nonce_for("alice");
+    chain
+        .mine_block(
+            vec![Transaction::new_with_fee(
+                "alice", &address, 100, 0, nonce, 0,
+            )],
+            1,
+            Some("miner"),
+        )
+        .unwrap();
+    address
+}
+
+fn spend(condition: &Condition, to: &str, amount: u64, nonce: u64) -> Transaction {
+    let mut tx = Transaction::new_with_fee(condition.address(), to, amount, 1, nonce, 0);
+    tx.chain_id = funded_chain(&[("alice", 1_000)]).chain_id;
+    tx.script = Some(condition.to_script());
+    tx.witness = Some(Witness::default());
+    tx
+}
+
+fn sign_witness(tx: &mut Transaction, keys: &[&(SigningKey, String)]) {
+    let msg = tx.signing_bytes();
+    let witness = tx.witness.get_or_insert_with(Witness::default);
+    for (sk, pk) in keys {
+        witness.signatures.push(WitnessSignature {
+            pubkey_hex: pk.clone(),
+            signature_b64: sign_bytes(sk, &msg),
+        });
+    }
+}
+
+#[test]
+fn hashlock_and_multisig_gate_spending() {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    let (a, b, c) = (key(), key(), key());
+    let secret = b"swap secret";
+    let condition = Condition::All {
+        conditions: vec![
+            Condition::HashLock {
+                sha256: sha256_hex(secret),
+            },
+            Condition::Multisig {
+                threshold: 2,
+                pubkeys: vec![a.1.clone(), b.1.clone(), c.1.clone()],
+            },
+        ],
+    };
+    let address = lock(&mut chain, &condition);
+    assert_eq!(chain.compute_state().unwrap().get_balance(&address), 100);
+
+    // Preimage but only one signature.
+    let mut tx = spend(&condition, "bob", 50, 0);
+    tx.witness
+        .as_mut()
+        .unwrap()
+        .preimages
+        .push(hex::encode(secret));
+    sign_witness(&mut tx, &[&a]);
+    let err = chain.validate_transaction(&tx).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("does not satisfy"),
+        "err={err:#}"
+    );
+
+    // Two signatures, no preimage.
+    let mut tx = spend(&condition, "bob", 50, 0);
+    sign_witness(&mut tx, &[&a, &c]);
+    assert!(chain.validate_transaction(&tx).is_err());
+
+    // Both: the spend is mined.
+    let mut tx = spend(&condition, "bob", 50, 0);
+    tx.witness
+        .as_mut()
+        .unwrap()
+        .preimages
+        .push(hex::encode(secret));
+    sign_witness(&mut tx, &[&a, &c]);
+    chain.validate_transaction(&tx).unwrap();
+    chain.mine_block(vec![tx], 1, Some("miner")).unwrap();
+    let state = chain.compute_state().unwrap();
+    assert_eq!(state.get_balance(&address), 49);
+    assert_eq!(state.get_balance("bob"), 50);
+    chain.validate().unwrap();
+}
+
+#[test]
+fn timelock_spend_waits_for_its_height() {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    let owner = key();
+    let condition = Condition::All {
+        conditions: vec![
+            Condition::After { height: 5 },
+            Condition::Multisig {
+                threshold: 1,
+                pubkeys: vec![owner.1.clone()],
+            },
+        ],
+    };
+    lock(&mut chain, &condition);
+
+    let mut tx = spend(&condition, "bob", 10, 0);
+    sign_witness(&mut tx, &[&owner]);
+    while chain.height() < 4 {
+        assert!(chain.validate_transaction(&tx).is_err());
+        assert!(
+            chain
+                .mine_block(vec![tx.clone()], 1, Some("miner"))
+                .is_err()
+        );
+        chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    }
+    // The next block is at height 5.
+    chain.mine_block(vec![tx], 1, Some("miner")).unwrap();
+    assert_eq!(chain.compute_state().unwrap().get_balance("bob"), 10);
+}
+
+#[test]
+fn spends_must_reveal_the_matching_script_and_valid_signatures() {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    let (owner, other) = (key(), key());
+    let condition = Condition::Multisig {
+        threshold: 1,
+        pubkeys: vec![owner.1.clone()],
+    };
+    lock(&mut chain, &condition);
+
+    // No script.
+    let mut tx = spend(&condition, "bob", 10, 0);
+    tx.script = None;
+    sign_witness(&mut tx, &[&owner]);
+    let err = chain.validate_transaction(&tx).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("needs the condition"),
+        "err={err:#}"
+    );
+
+    // A script that hashes elsewhere.
+    let mut tx = spend(&condition, "bob", 10, 0);
+    tx.script = Some(
+        Condition::Multisig {
+            threshold: 1,
+            pubkeys: vec![other.1.clone()],
+        }
+        .to_script(),
+    );
+    sign_witness(&mut tx, &[&other]);
+    let err = chain.validate_transaction(&tx).unwrap_err();
+    assert!(format!("{err:#}").contains("not the sender"), "err={err:#}");
+
+    // A signature made before the tx was changed.
+    let mut tx = spend(&condition, "bob", 10, 0);
+    sign_witness(&mut tx, &[&owner]);
+    tx.amount = 90;
+    let err = chain.validate_transaction(&tx).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("witness signature"),
+        "err={err:#}"
+    );
+
+    // The witness isn't part of the tx id.
+    let mut tx = spend(&condition, "bob", 10, 0);
+    let id = tx.id();
+    sign_witness(&mut tx, &[&owner]);
+    assert_eq!(tx.id(), id);
+    chain.validate_transaction(&tx).unwrap();
+}
+
+#[test]
+fn any_lets_a_claim_through_before_the_refund_opens() {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    let (buyer, seller) = (key(), key());
+    let secret = b"preimage";
+    let condition = Condition::Any {
+        conditions: vec![
+            Condition::All {
+                conditions: vec![
+                    Condition::HashLock {
+                        sha256: sha256_hex(secret),
+                    },
+                    Condition::Multisig {
+                        threshold: 1,
+                        pubkeys: vec![buyer.1.clone()],
+                    },
+                ],
+            },
+            Condition::All {
+                conditions: vec![
+                    Condition::After { height: 100 },
+                    Condition::Multisig {
+                        threshold: 1,
+                        pubkeys: vec![seller.1.clone()],
+                    },
+                ],
+            },
+        ],
+    };
+    lock(&mut chain, &condition);
+
+    // The refund branch isn't open yet.
+    let mut refund = spend(&condition, "seller", 99, 0);
+    sign_witness(&mut refund, &[&seller]);
+    assert!(chain.validate_transaction(&refund).is_err());
+
+    let mut claim = spend(&condition, "buyer", 99, 0);
+    claim
+        .witness
+        .as_mut()
+        .unwrap()
+        .preimages
+        .push(hex::encode(secret));
+    sign_witness(&mut claim, &[&buyer]);
+    chain.mine_block(vec![claim], 1, Some("miner")).unwrap();
+    assert_eq!(chain.compute_state().unwrap().get_balance("buyer"), 99);
+}
+
+#[test]
+fn malformed_conditions_are_rejected() {
+    let (_, pk) = key();
+    let bad = [
+        Condition::Multisig {
+            threshold: 0,
+            pubkeys: vec![pk.clone()],
+        },
+        Condition::Multisig {
+            threshold: 2,
+            pubkeys: vec![pk.clone(), pk.clone()],
+        },
+        Condition::Multisig {
+            threshold: 1,
+            pubkeys: vec!["not-a-key".to_string()],
+        },
+        Condition::HashLock {
+            sha256: "abcd".to_string(),
+        },
+        Condition::Any { conditions: vec![] },
+    ];
+    for condition in bad {
+        assert!(condition.check().is_err(), "{condition:?}");
+        assert!(Condition::from_script(&condition.to_script()).is_err());
+    }
+
+    let mut deep = Condition::After { height: 1 };
+    for _ in 0..MAX_CONDITION_DEPTH {
+        deep = Condition::All {
+            conditions: vec![deep],
+        };
+    }
+    let err = deep.check().unwrap_err();
+    assert!(err.to_string().contains("deeper"), "err={err}");
+
+    assert!(Condition::from_script("{\"type\":\"eval\"}").is_err());
+
+    // Formatting of the revealed script doesn't change the address.
+    let condition = Condition::After { height: 7 };
+    let spaced = Condition::from_script("{ \"type\": \"after\", \"height\": 7 }").unwrap();
+    assert_eq!(spaced.address(), condition.address());
+}
@@ -1,5 +1,15 @@
-use rusty_chain::core::crypto::{generate_keypair, sign_bytes, verifying_key_to_hex};
+use base64::{Engine as _, engine::general_purpose::STANDARD as B64};
+use curve25519_dalek::constants::EIGHT_TORSION;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::Signature;
+use rusty_chain::core::crypto::{
+    generate_keypair, sign_bytes, verify_signature, verifying_key_to_hex,
+};
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::sigcache::{SignatureCache, verify_signatures_batch};
 use rusty_chain::core::types::Transaction;
+use sha2::{Digest, Sha512};
 
 #[test]
 fn signed_tx_verifies() {
@@ -65,3 +75,208 @@ fn signed_tx_requires_from_to_match_pubkey_hex() {
     let err = tx.verify_signature_if_present().unwrap_err().to_string();
     assert!(err.contains("from=<pubkey_hex>"), "err={err}");
 }
+
+fn signed_tx(nonce: u64) -> Transaction {
+    let (sk, vk) = generate_keypair();
+    let from = verifying_key_to_hex(&vk);
+    let mut tx = Transaction::new(from.clone(), "bob", 10, nonce);
+    tx.signature_b64 = Some(sign_bytes(&sk, &tx.signing_bytes()));
+    tx.pubkey_hex = Some(from);
+    tx
+}
+
+#[test]
+fn batch_verification_accepts_valid_signatures() {
+    let txs: Vec<Transaction> = (0..16).map(signed_tx).collect();
+    verify_signatures_batch(&txs, None).unwrap();
+}
+
+#[test]
+fn batch_verification_names_the_bad_tx() {
+    let mut txs: Vec<Transaction> = (0..16).map(signed_tx).collect();
+    txs[7].amount = 999;
+
+    let err = verify_signatures_batch(&txs, None).unwrap_err().to_string();
+    assert!(err.contains("tx index=7"), "err={err}");
+}
+
+/// A tx signed by a real key whose `R` carries an order-8 torsion point: a cofactorless
+/// batch equation would hold for 1 in 8 random coefficients, a cofactorless check never.
+fn torsioned_tx(nonce: u64) -> Transaction {
+    let (sk, vk) = generate_keypair();
+    let from = verifying_key_to_hex(&vk);
+    let mut tx = Transaction::new(from.clone(), "bob", 10, nonce);
+    let msg = tx.signing_bytes();
+
+    let r = Scalar::from_bytes_mod_order_wide(&Sha512::digest(&msg).into());
+    let big_r = EdwardsPoint::mul_base(&r) + EIGHT_TORSION[1];
+    let r_bytes = big_r.compress().to_bytes();
+    let k = Scalar::from_bytes_mod_order_wide(
+        &Sha512::new()
+            .chain_update(r_bytes)
+            .chain_update(vk.as_bytes())
+            .chain_update(&msg)
+            .finalize()
+            .into(),
+    );
+    let s = r + k * sk.to_scalar();
+    let sig = Signature::from_components(r_bytes, s.to_bytes());
+
+    tx.signature_b64 = Some(B64.encode(sig.to_bytes()));
+    tx.pubkey_hex = Some(from);
+    tx
+}
+
+#[test]
+fn torsioned_r_gets_the_same_verdict_alone_and_in_batches() {
+    // The cofactored equation ignores the torsion component, on its own and in any batch.
+    let tx = torsioned_tx(0);
+    tx.verify_signature_if_present().unwrap();
+    for _ in 0..64 {
+        let mut txs: Vec<Transaction> = (0..3).map(signed_tx).collect();
+        txs.push(torsioned_tx(3));
+        verify_signatures_batch(&txs, None).unwrap();
+    }
+}
+
+#[test]
+fn small_order_r_is_rejected_alone_and_in_batches() {
+    let mut tx = signed_tx(0);
+    let (_, sig) = tx.signature_parts().unwrap().unwrap();
+    let forged = Signature::from_components(
+        EIGHT_TORSION[1].compress().to_bytes(),
+        sig.s_bytes().to_owned(),
+    );
+    tx.signature_b64 = Some(B64.encode(forged.to_bytes()));
+
+    let err = tx.verify_signature_if_present().unwrap_err().to_string();
+    assert!(err.contains("small order"), "err={err}");
+    let mut txs: Vec<Transaction> = (1..4).map(signed_tx).collect();
+    txs.insert(1, tx);
+    let err = verify_signatures_batch(&txs, None).unwrap_err().to_string();
+    assert!(
+        err.contains("tx index=1") && err.contains("small order"),
+        "err={err}"
+    );
+}
+
+#[test]
+fn cofactored_rule_only_changes_the_verdict_on_torsioned_r() {
+    // `verify_strict` was the rule before; both agree on everything but a torsioned `R`.
+    let verdicts = |tx: &Transaction| {
+        let (vk, sig) = tx.signature_parts().unwrap().unwrap();
+        let msg = tx.signing_bytes();
+        (
+            verify_signature(&vk, &msg, &sig).is_ok(),
+            vk.verify_strict(&msg, &sig).is_ok(),
+        )
+    };
+
+    let valid = signed_tx(0);
+    assert_eq!(verdicts(&valid), (true, true));
+    let mut tampered = signed_tx(1);
+    tampered.amount = 999;
+    assert_eq!(verdicts(&tampered), (false, false));
+    let mut small_order = signed_tx(2);
+    let (_, sig) = small_order.signature_parts().unwrap().unwrap();
+    let forged = Signature::from_components(
+        EIGHT_TORSION[1].compress().to_bytes(),
+        sig.s_bytes().to_owned(),
+    );
+    small_order.signature_b64 = Some(B64.encode(forged.to_bytes()));
+    assert_eq!(verdicts(&small_order), (false, false));
+
+    assert_eq!(verdicts(&torsioned_tx(3)), (true, false));
+}
+
+#[test]
+fn batch_verification_skips_cached_txs() {
+    let mut txs: Vec<Transaction> = (0..4).map(signed_tx).collect();
+    txs[2].amount = 999;
+
+    // A cache entry for the exact (id, key, signature) triple short-circuits verification.
+    let mut cache = SignatureCache::new();
+    cache.insert(&txs[2]);
+    verify_signatures_batch(&txs, Some(&cache)).unwrap();
+
+    // A different signature for the same id is not a cache hit.
+    let mut other = txs[2].clone();
+    other.signature_b64 = txs[0].signature_b64.clone();
+    assert!(!cache.contains(&other));
+
+    // Nor is a different key: `pubkey_hex` is not part of the id.
+    let mut other = txs[2].clone();
+    other.pubkey_hex = txs[0].pubkey_hex.clone();
+    assert_eq!(other.id(), txs[2].id());
+    assert!(!cache.contains(&other));
+    txs[2] = other;
+    let err = verify_signatures_batch(&txs, Some(&cache))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("tx index=2"), "err={err}");
+}
+
+#[test]
+fn mempool_admission_populates_signature_cache() {
+    let mut mp = Mempool::new();
+    let tx = signed_tx(0);
+    mp.add_tx_checked(tx.clone(), 0).unwrap();
+    assert!(mp.sig_cache.contains(&tx));
+
+    mp.remove_included(std::slice::from_ref(&tx));
+    assert!(!mp.sig_cache.contains(&tx));
+
+    // A signature checked ahead of admission stays cached only if the tx gets in.
+    let gap = signed_tx(5);
+    mp.check_signature(&gap).unwrap();
+    assert!(mp.sig_cache.contains(&gap));
+    assert!(mp.add_tx_checked(gap.clone(), 0).is_err());
+    assert!(!mp.sig_cache.contains(&gap));
+    let broke = signed_tx(0);
+    mp.check_signature(&broke).unwrap();
+    assert!(mp.add_tx_with_balance(broke.clone(), 0, 1).is_err());
+    assert!(!mp.sig_cache.contains(&broke));
+    mp.check_signature(&broke).unwrap();
+    mp.add_tx_with_balance(broke.clone(), 0, 1_000).unwrap();
+    assert!(mp.sig_cache.contains(&broke));
+}
+
+#[test]
+fn evicted_txs_leave_the_signature_cache() {
+    let mut mp = Mempool::new();
+    let removed = signed_tx(0);
+    let expiring = {
+        let (sk, vk) = generate_keypair();
+        let from = verifying_key_to_hex(&vk);
+        let mut tx = Transaction::new(from.clone(), "bob", 10, 0);
+        tx.ttl_ms = 1;
+        tx.signature_b64 = Some(sign_bytes(&sk, &tx.signing_bytes()));
+        tx.pubkey_hex = Some(from);
+        tx
+    };
+    let kept = signed_tx(0);
+    for tx in [&removed, &expiring, &kept] {
+        mp.add_tx_checked(tx.clone(), 0).unwrap();
+    }
+    assert_eq!(mp.sig_cache.len(), 3);
+
+    mp.remove_tx(&removed.id());
+    assert!(!mp.sig_cache.contains(&removed));
+    assert_eq!(mp.evict_expired(expiring.timestamp_ms + 1), 1);
+    assert!(!mp.sig_cache.contains(&expiring));
+    assert!(mp.sig_cache.contains(&kept));
+    mp.clear();
+    assert!(mp.sig_cache.is_empty());
+}
+
+#[test]
+fn signature_cache_evicts_oldest_when_full() {
+    let mut cache = SignatureCache::with_capacity(2);
+    let txs: Vec<Transaction> = (0..3).map(signed_tx).collect();
+    for tx in &txs {
+        cache.insert(tx);
+    }
+    assert_eq!(cache.len(), 2);
+    assert!(!cache.contains(&txs[0]));
+    assert!(cache.contains(&txs[2]));
+}
@@ -16,6 +16,7 @@ fn test_block_size() {
             merkle_root: "def".to_string(),
             timestamp_ms: 1000,
             nonce: 1,
+            state_root: String::new(),
         },
         txs: vec![tx],
     };
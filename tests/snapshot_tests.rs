@@ -0,0 +1,129 @@
+mod common;
+
+use common::{funded_chain, long_chain};
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::snapshot::Snapshot;
+use rusty_chain::core::state::Account;
+
+#[test]
+fn import_resumes_from_checkpoint_and_syncs_later_blocks() {
+    let full = long_chain();
+    let snapshot = Snapshot::from_chain(&full, 20).unwrap();
+    assert_eq!(snapshot.height(), 20);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot.json.gz");
+    let hash = snapshot.save(&path).unwrap();
+    let loaded = Snapshot::load(&path).unwrap();
+    assert_eq!(loaded.hash(), hash);
+
+    let chain = loaded.into_chain(&full.blocks[20].header.hash()).unwrap();
+    assert_eq!(chain.height(), 20);
+    assert!(chain.is_pruned(1) && chain.is_pruned(20) && !chain.is_pruned(0));
+    assert_eq!(
+        chain.compute_state().unwrap().accounts,
+        full.compute_state_at(20).unwrap().accounts
+    );
+    assert_eq!(chain.next_nonce_for("alice"), 20);
+    chain.validate().unwrap();
+
+    // Survives a save/load of the chain file, then follows the full chain.
+    let chain_path = dir.path().join("chain.json");
+    chain.save(&chain_path).unwrap();
+    let mut chain = Chain::load(&chain_path).unwrap();
+    for block in &full.blocks[21..] {
+        chain.append_block(block.clone()).unwrap();
+    }
+    assert_eq!(chain.tip_hash(), full.tip_hash());
+    assert_eq!(
+        chain.compute_state().unwrap().accounts,
+        full.compute_state().unwrap().accounts
+    );
+    chain.validate().unwrap();
+
+    // Pruned history can't be unwound or replayed.
+    assert!(chain.compute_state_at(19).is_err());
+    for _ in 21..=25 {
+        chain.disconnect_tip().unwrap();
+    }
+    assert!(chain.disconnect_tip().is_err());
+}
+
+#[test]
+fn export_requires_a_checkpointed_height() {
+    let full = long_chain();
+    let err = Snapshot::from_chain(&full, 15).unwrap_err();
+    assert!(err.to_string().contains("not a checkpoint"), "err={err}");
+    assert!(Snapshot::from_chain(&full, 30).is_err());
+}
+
+#[test]
+fn import_rejects_untrusted_or_tampered_snapshots() {
+    let full = long_chain();
+    let snapshot = Snapshot::from_chain(&full, 10).unwrap();
+
+    // Not the checkpointed block.
+    let err = snapshot
+        .clone()
+        .into_chain(&full.blocks[20].header.hash())
+        .unwrap_err();
+    assert!(err.to_string().contains("trusted checkpoint"), "err={err}");
+
+    // State that doesn't match the header's state root.
+    let mut richer = snapshot.clone();
+    richer.state.accounts.insert(
+        "mallory".to_string(),
+        Account {
+            balance: 1_000_000,
+            nonce: 0,
+            ..Default::default()
+        },
+    );
+    assert_ne!(richer.hash(), snapshot.hash());
+    let err = richer
+        .into_chain(&full.blocks[10].header.hash())
+        .unwrap_err();
+    assert!(
+        format!("{err:#}").contains("state root mismatch"),
+        "err={err:#}"
+    );
+
+    // Parameters and difficulty are hashed, and must match a local chain.
+    let mut looser = snapshot.clone();
+    looser.params.max_block_size *= 2;
+    assert_ne!(looser.hash(), snapshot.hash());
+    let err = looser.check_local(&full).unwrap_err();
+    assert!(err.to_string().contains("parameters"), "err={err}");
+    let mut easier = snapshot.clone();
+    easier.pow_difficulty = 0;
+    assert_ne!(easier.hash(), snapshot.hash());
+    assert!(easier.check_local(&full).is_err());
+    snapshot.check_local(&full).unwrap();
+    assert!(snapshot.check_local(&Chain::new_genesis()).is_err());
+
+    // Broken header chain.
+    let mut broken = snapshot.clone();
+    broken.headers.remove(4);
+    assert!(broken.into_chain(&full.blocks[10].header.hash()).is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot.json.gz");
+    std::fs::write(&path, b"{}").unwrap();
+    assert!(Snapshot::load(&path).is_err());
+}
+
+#[test]
+fn chains_without_state_roots_cannot_be_imported() {
+    let mut chain = funded_chain(&[("alice", 1_000)]);
+    chain.blocks[0].header.state_root.clear();
+    chain.rebuild_block_index();
+    chain.checkpoints.insert(0, chain.genesis_hash());
+    for _ in 0..10 {
+        chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    }
+    let snapshot = Snapshot::from_chain(&chain, 10).unwrap();
+    let err = snapshot
+        .into_chain(&chain.blocks[10].header.hash())
+        .unwrap_err();
+    assert!(err.to_string().contains("state roots"), "err={err}");
+}
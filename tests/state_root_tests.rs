@@ -0,0 +1,282 @@
+mod common;
+
+use common::{ask, funded_chain};
+use rusty_chain::core::chain::pow_ok;
+use rusty_chain::core::light::LightClient;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle};
+use rusty_chain::core::state::{Account, ImmatureReward, State};
+use rusty_chain::core::state_tree::{AccountProof, state_root};
+use rusty_chain::core::types::Transaction;
+use std::sync::Arc;
+
+fn state_with(n: u64) -> State {
+    let mut state = State::new();
+    for i in 0..n {
+        state.accounts.insert(
+            format!("addr-{i}"),
+            Account {
+                balance: i * 10,
+                nonce: i % 3,
+                ..Default::default()
+            },
+        );
+    }
+    state
+}
+
+#[test]
+fn root_commits_to_every_account() {
+    assert_eq!(state_root(&State::new()), "00".repeat(32));
+
+    let state = state_with(20);
+    let root = state_root(&state);
+    // Independent of insertion order.
+    let mut reversed = State::new();
+    for (address, account) in state.accounts.iter().collect::<Vec<_>>().into_iter().rev() {
+        reversed.accounts.insert(address.clone(), account.clone());
+    }
+    assert_eq!(state_root(&reversed), root);
+
+    let mut changed = state.clone();
+    changed.accounts.get_mut("addr-7").unwrap().balance += 1;
+    assert_ne!(state_root(&changed), root);
+    let mut changed = state.clone();
+    changed.accounts.get_mut("addr-7").unwrap().nonce += 1;
+    assert_ne!(state_root(&changed), root);
+    let mut changed = state.clone();
+    changed
+        .accounts
+        .insert("addr-99".to_string(), Account::default());
+    assert_ne!(state_root(&changed), root);
+}
+
+#[test]
+fn leaf_encoding_separates_account_sections() {
+    let root_of = |account: Account| {
+        let mut state = State::new();
+        state.accounts.insert("addr".to_string(), account);
+        state_root(&state)
+    };
+    let contract = |code: &str, storage: Option<&str>| Account {
+        code_hash: Some(code.to_string()),
+        storage_root: storage.map(str::to_string),
+        ..Default::default()
+    };
+
+    // Field boundaries inside the code section.
+    assert_ne!(
+        root_of(contract("ab", Some(""))),
+        root_of(contract("a", Some("b")))
+    );
+    assert_ne!(root_of(contract("", None)), root_of(contract("", Some(""))));
+    assert_ne!(root_of(contract("", None)), root_of(Account::default()));
+
+    // An immature reward and an asset balance with the same bytes.
+    let immature = Account {
+        immature: vec![ImmatureReward {
+            spendable_at: 5,
+            amount: 7,
+        }],
+        ..Default::default()
+    };
+    let mut asset = Account::default();
+    asset.assets.insert(String::new(), 7);
+    assert_ne!(root_of(immature), root_of(asset));
+}
+
+#[test]
+fn inclusion_and_non_inclusion_proofs_verify() {
+    for n in [0, 1, 2, 25] {
+        let state = state_with(n);
+        let root = state_root(&state);
+        for i in 0..n {
+            let proof = AccountProof::build(&state, &format!("addr-{i}"));
+            assert_eq!(
+                proof.account,
+                state.accounts.get(&format!("addr-{i}")).cloned()
+            );
+            proof.verify(&root).unwrap();
+        }
+        // Absent addresses end either at an empty subtree or at another account's leaf.
+        for i in 0..40 {
+            let proof = AccountProof::build(&state, &format!("missing-{i}"));
+            assert!(proof.account.is_none());
+            proof.verify(&root).unwrap();
+        }
+    }
+}
+
+#[test]
+fn forged_account_proofs_fail() {
+    let state = state_with(25);
+    let root = state_root(&state);
+    let proof = AccountProof::build(&state, "addr-3");
+
+    let mut richer = proof.clone();
+    richer.account.as_mut().unwrap().balance += 1;
+    assert!(richer.verify(&root).is_err());
+
+    // Hiding an existing account.
+    let mut hidden = proof.clone();
+    hidden.account = None;
+    assert!(hidden.verify(&root).is_err());
+
+    let mut renamed = proof.clone();
+    renamed.address = "addr-4".to_string();
+    assert!(renamed.verify(&root).is_err());
+
+    let mut short = proof.clone();
+    short.siblings.pop();
+    assert!(short.verify(&root).is_err());
+
+    assert!(proof.verify(&state_root(&state_with(24))).is_err());
+
+    // Claiming an absent address exists.
+    let mut invented = AccountProof::build(&state, "nobody");
+    invented.account = Some(Account::default());
+    invented.other_leaf = None;
+    assert!(invented.verify(&root).is_err());
+}
+
+#[test]
+fn headers_commit_to_post_block_state() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    assert!(chain.commits_state());
+    assert_eq!(
+        chain.blocks[0].header.state_root,
+        state_root(&chain.compute_state().unwrap())
+    );
+
+    chain
+        .mine_block(
+            vec![Transaction::new_with_fee("alice", "bob", 10, 1, 0, 0)],
+            1,
+            Some("miner"),
+        )
+        .unwrap();
+    assert_eq!(
+        chain.tip_header().state_root,
+        state_root(&chain.compute_state().unwrap())
+    );
+    chain.validate().unwrap();
+
+    // A block claiming another post-state is rejected, even with valid PoW.
+    let mut other = chain.clone();
+    let mut block = other
+        .mine_block(
+            vec![Transaction::new_with_fee("alice", "carol", 5, 1, 1, 0)],
+            1,
+            Some("miner"),
+        )
+        .unwrap();
+    chain.validate_block(&block).unwrap();
+    block.header.state_root = chain.tip_header().state_root.clone();
+    while !pow_ok(&block.header.hash(), 1) {
+        block.header.nonce += 1;
+    }
+    let err = chain.validate_block(&block).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("state root mismatch"),
+        "err={err:#}"
+    );
+
+    // The same tampering in a stored chain fails full validation.
+    let mut tampered = chain.clone();
+    tampered.blocks.push(block);
+    let err = tampered.validate().unwrap_err();
+    assert!(
+        format!("{err:#}").contains("state root mismatch"),
+        "err={err:#}"
+    );
+}
+
+#[test]
+fn chains_without_state_roots_stay_valid() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    // As loaded from a chain file written before state roots existed.
+    chain.blocks[0].header.state_root.clear();
+    chain.rebuild_block_index();
+    chain.checkpoints.insert(0, chain.genesis_hash());
+    assert!(!chain.commits_state());
+
+    let json = serde_json::to_string(&chain.blocks[0].header).unwrap();
+    assert!(!json.contains("state_root"), "legacy header hash unchanged");
+
+    chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    assert!(chain.tip_header().state_root.is_empty());
+    chain.validate().unwrap();
+    assert!(chain.account_proof("alice").is_err());
+}
+
+#[tokio::test]
+async fn light_client_verifies_served_account_proofs() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    let stale = chain.account_proof("alice").unwrap();
+    chain
+        .mine_block(
+            vec![Transaction::new_with_fee("alice", "bob", 10, 1, 0, 0)],
+            1,
+            Some("miner"),
+        )
+        .unwrap();
+    let mut client = LightClient::from_genesis(&chain);
+    client
+        .apply_headers(
+            1,
+            chain.blocks[1..].iter().map(|b| b.header.clone()).collect(),
+        )
+        .unwrap();
+    client.track("alice");
+    client.track("nobody");
+    let tip = chain.tip_hash();
+
+    // A genuine proof from before alice paid bob no longer counts.
+    let err = client.add_account_proof(0, &stale).unwrap_err();
+    assert!(err.to_string().contains("synced tip"), "err={err}");
+
+    let node = P2PNode::new(
+        "127.0.0.1:9000".parse().unwrap(),
+        chain,
+        Mempool::new(),
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+
+    for address in ["alice", "nobody"] {
+        let reply = ask(
+            &node,
+            &handle,
+            Message::GetAccountProof {
+                address: address.to_string(),
+            },
+        )
+        .await;
+        let Message::AccountProof { height, proof } = reply else {
+            panic!("unexpected: {reply:?}");
+        };
+        assert_eq!(height, 1);
+        client.add_account_proof(height, &proof).unwrap();
+
+        // Checked against the header, not taken on trust.
+        let mut forged = proof.clone();
+        forged.account = Some(Account {
+            balance: 1_000,
+            nonce: 0,
+            ..Default::default()
+        });
+        assert!(client.add_account_proof(height, &forged).is_err());
+    }
+    assert_eq!(client.balance("alice"), 89);
+    assert_eq!(client.accounts["alice"].account.as_ref().unwrap().nonce, 1);
+    assert_eq!(client.balance("nobody"), 0);
+    assert!(client.accounts["nobody"].account.is_none());
+
+    // Proofs come from the node's cached tip state.
+    let state = node.state.lock().await;
+    assert_eq!(state.tip_state_cache.as_ref().unwrap().0, tip);
+}
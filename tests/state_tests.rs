@@ -1,6 +1,25 @@
 use rusty_chain::core::chain::Chain;
+use rusty_chain::core::params::ChainParams;
+use rusty_chain::core::state::State;
 use rusty_chain::core::types::Transaction;
 
+/// Mine empty blocks until the rewards mined so far can be spent.
+fn mature(c: &mut Chain) {
+    for _ in 0..c.params.coinbase_maturity {
+        c.mine_empty_block(1, "miner").unwrap();
+    }
+}
+
+fn coinbase_to(to: &str, height: u64, amount: u64) -> Transaction {
+    Transaction {
+        from: "SYSTEM".to_string(),
+        to: to.to_string(),
+        amount,
+        nonce: height,
+        ..Default::default()
+    }
+}
+
 #[test]
 fn genesis_state_is_empty() {
     let c = Chain::new_genesis();
@@ -13,14 +32,16 @@ fn coinbase_tx_increases_balance() {
     let mut c = Chain::new_genesis();
 
     // Construct a coinbase tx
-    let mut coinbase = Transaction::default();
-    coinbase.from = "SYSTEM".to_string();
-    coinbase.to = "alice".to_string();
-    coinbase.amount = 50;
-    coinbase.nonce = 1;
-    coinbase.timestamp_ms = 0;
-    coinbase.priority = 0;
-    coinbase.is_minable = true;
+    let coinbase = Transaction {
+        from: "SYSTEM".to_string(),
+        to: "alice".to_string(),
+        amount: 50,
+        nonce: 1,
+        timestamp_ms: 0,
+        priority: 0,
+        is_minable: true,
+        ..Default::default()
+    };
 
     c.mine_block(vec![coinbase], 1, None).unwrap();
 
@@ -33,19 +54,22 @@ fn transfer_tx_updates_balances() {
     let mut c = Chain::new_genesis();
 
     // 1. Mine coinbase to Alice
-    let mut coinbase = Transaction::default();
-    coinbase.from = "SYSTEM".to_string();
-    coinbase.to = "alice".to_string();
-    coinbase.amount = 50;
-    coinbase.nonce = 1;
-    coinbase.timestamp_ms = 0;
-    coinbase.priority = 0;
-    coinbase.is_minable = true;
+    let coinbase = Transaction {
+        from: "SYSTEM".to_string(),
+        to: "alice".to_string(),
+        amount: 50,
+        nonce: 1,
+        timestamp_ms: 0,
+        priority: 0,
+        is_minable: true,
+        ..Default::default()
+    };
     c.mine_block(vec![coinbase], 1, None).unwrap();
+    mature(&mut c);
 
     // 2. Mine transfer Alice -> Bob
     let tx = Transaction::new("alice", "bob", 10, 0);
-    c.mine_block(vec![tx], 1, None).unwrap();
+    c.mine_block(vec![tx], 1, Some("miner")).unwrap();
 
     let state = c.compute_state().unwrap();
     assert_eq!(state.get_balance("alice"), 40);
@@ -59,7 +83,7 @@ fn insufficient_balance_makes_chain_invalid() {
 
     // Alice has 0. Tries to send 10.
     let tx = Transaction::new("alice", "bob", 10, 0);
-    let err = c.mine_block(vec![tx], 1, None).unwrap_err();
+    let err = c.mine_block(vec![tx], 1, Some("miner")).unwrap_err();
 
     // mine_block should fail
     assert!(
@@ -74,19 +98,22 @@ fn invalid_nonce_makes_chain_invalid() {
     let mut c = Chain::new_genesis();
 
     // Fund Alice
-    let mut coinbase = Transaction::default();
-    coinbase.from = "SYSTEM".to_string();
-    coinbase.to = "alice".to_string();
-    coinbase.amount = 50;
-    coinbase.nonce = 1;
-    coinbase.timestamp_ms = 0;
-    coinbase.priority = 0;
-    coinbase.is_minable = true;
+    let coinbase = Transaction {
+        from: "SYSTEM".to_string(),
+        to: "alice".to_string(),
+        amount: 50,
+        nonce: 1,
+        timestamp_ms: 0,
+        priority: 0,
+        is_minable: true,
+        ..Default::default()
+    };
     c.mine_block(vec![coinbase], 1, None).unwrap();
+    mature(&mut c);
 
     // Alice sends with nonce 5 (expected 0)
     let tx = Transaction::new("alice", "bob", 10, 5);
-    let err = c.mine_block(vec![tx], 1, None).unwrap_err();
+    let err = c.mine_block(vec![tx], 1, Some("miner")).unwrap_err();
 
     assert!(
         format!("{:?}", err).contains("Invalid nonce"),
@@ -100,15 +127,18 @@ fn fees_are_collected_by_miner() {
     let mut c = Chain::new_genesis();
 
     // 1. Give Alice some starting funds (100)
-    let mut cb = Transaction::default();
-    cb.from = "SYSTEM".to_string();
-    cb.to = "alice".to_string();
-    cb.amount = 50;
-    cb.nonce = 1;
-    cb.timestamp_ms = 0;
-    cb.priority = 0;
-    cb.is_minable = true;
+    let cb = Transaction {
+        from: "SYSTEM".to_string(),
+        to: "alice".to_string(),
+        amount: 50,
+        nonce: 1,
+        timestamp_ms: 0,
+        priority: 0,
+        is_minable: true,
+        ..Default::default()
+    };
     c.mine_block(vec![cb], 1, None).unwrap();
+    mature(&mut c);
 
     // 2. Alice sends 10 to Bob with 5 fee. Miner is 'charlie'.
     let tx = Transaction::new_with_fee("alice", "bob", 10, 5, 0, 0);
@@ -129,18 +159,21 @@ fn insufficient_balance_for_fee_fails() {
     let mut c = Chain::new_genesis();
 
     // Alice has 50. Tries to send 50 with 1 fee (needs 51).
-    let mut cb = Transaction::default();
-    cb.from = "SYSTEM".to_string();
-    cb.to = "alice".to_string();
-    cb.amount = 50;
-    cb.nonce = 1;
-    cb.timestamp_ms = 0;
-    cb.priority = 0;
-    cb.is_minable = true;
+    let cb = Transaction {
+        from: "SYSTEM".to_string(),
+        to: "alice".to_string(),
+        amount: 50,
+        nonce: 1,
+        timestamp_ms: 0,
+        priority: 0,
+        is_minable: true,
+        ..Default::default()
+    };
     c.mine_block(vec![cb], 1, None).unwrap();
+    mature(&mut c);
 
     let tx = Transaction::new_with_fee("alice", "bob", 50, 1, 0, 0);
-    let err = c.mine_block(vec![tx], 1, None).unwrap_err();
+    let err = c.mine_block(vec![tx], 1, Some("miner")).unwrap_err();
 
     assert!(
         format!("{:?}", err).contains("Insufficient balance"),
@@ -150,14 +183,82 @@ fn insufficient_balance_for_fee_fails() {
 }
 
 #[test]
-fn saturating_math_prevents_underflow_panic() {
+fn checked_math_rejects_underflow_without_panic() {
     let mut c = Chain::new_genesis();
 
-    // Construct a tx that would normally underflow if not for saturating math
-    // (Though validate_tx usually catches this, apply_tx should be robust)
+    // A tx that would underflow the sender's balance: validate_tx catches it, and apply_tx
+    // uses checked math as a backstop, so the block is rejected instead of panicking.
     let tx = Transaction::new("alice", "bob", 100, 0);
-    let _ = c.mine_block(vec![tx], 1, None);
+    assert!(c.mine_block(vec![tx], 1, Some("miner")).is_err());
 
-    // We expect validation to catch it, but we want to ensure compute_state doesn't panic
     let _ = c.compute_state();
 }
+
+#[test]
+fn blocks_need_exactly_one_coinbase_paying_a_valid_address() {
+    let params = ChainParams::default();
+    let mut state = State::new();
+    let transfer = Transaction::new("alice", "bob", 0, 0);
+
+    let err = state
+        .apply_block_txs(std::slice::from_ref(&transfer), 1, &params)
+        .unwrap_err();
+    assert!(err.to_string().contains("no coinbase"), "err={err}");
+    assert!(state.apply_block_txs(&[], 1, &params).is_err());
+
+    let twice = vec![coinbase_to("miner", 1, 50), coinbase_to("miner", 1, 0)];
+    let err = state.apply_block_txs(&twice, 1, &params).unwrap_err();
+    assert!(err.to_string().contains("only index 0"), "err={err}");
+
+    for bad in ["", "SYSTEM", "not an address", &"a".repeat(129)] {
+        let err = state
+            .apply_block_txs(&[coinbase_to(bad, 1, 50)], 1, &params)
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid address"), "err={err}");
+    }
+    assert!(state.accounts.is_empty());
+
+    state
+        .apply_block_txs(&[coinbase_to("miner", 1, 50), transfer], 1, &params)
+        .unwrap();
+
+    // Chain files from before the rule may hold coinbase-less blocks.
+    let legacy = ChainParams {
+        strict_coinbase: false,
+        ..ChainParams::default()
+    };
+    state.apply_block_txs(&[], 2, &legacy).unwrap();
+}
+
+#[test]
+fn coinbase_rewards_mature_before_they_can_be_spent() {
+    let mut c = Chain::new_genesis();
+    let maturity = c.params.coinbase_maturity;
+    assert!(maturity > 0);
+    c.mine_empty_block(1, "alice").unwrap();
+
+    let state = c.compute_state().unwrap();
+    assert_eq!(state.get_balance("alice"), 50);
+    assert_eq!(state.get_spendable("alice", 2), 0);
+    assert_eq!(state.get_spendable("alice", 1 + maturity), 50);
+
+    let spend = Transaction::new("alice", "bob", 10, 0);
+    let err = c
+        .mine_block(vec![spend.clone()], 1, Some("miner"))
+        .unwrap_err();
+    assert!(
+        format!("{err:?}").contains("Insufficient balance"),
+        "err={err:?}"
+    );
+
+    // The reward of block 1 is spendable from block 1 + maturity.
+    while c.height() + 1 < 1 + maturity as usize {
+        c.mine_empty_block(1, "miner").unwrap();
+    }
+    c.mine_block(vec![spend], 1, Some("miner")).unwrap();
+    let state = c.compute_state().unwrap();
+    assert_eq!(state.get_balance("alice"), 40);
+    assert!(state.accounts["alice"].immature.is_empty(), "unlocked");
+    assert_eq!(state.accounts["miner"].immature.len() as u64, maturity);
+    c.validate().unwrap();
+}
@@ -0,0 +1,206 @@
+use proptest::prelude::*;
+use rusty_chain::core::genesis::{GenesisAllocation, GenesisConfig};
+use rusty_chain::core::params::ChainParams;
+use rusty_chain::core::state::{Account, State, SupplyChange};
+use rusty_chain::core::types::Transaction;
+
+const USERS: [&str; 4] = ["alice", "bob", "carol", "dave"];
+
+/// Coinbase-less blocks allowed, so fees can be burned for good.
+fn lax_params() -> ChainParams {
+    ChainParams {
+        strict_coinbase: false,
+        ..ChainParams::default()
+    }
+}
+
+fn coinbase(height: usize, amount: u64) -> Transaction {
+    Transaction {
+        from: "SYSTEM".to_string(),
+        to: "miner".to_string(),
+        amount,
+        nonce: height as u64,
+        ..Default::default()
+    }
+}
+
+fn allocations(balances: &[u64]) -> State {
+    let txs: Vec<_> = USERS
+        .iter()
+        .zip(balances)
+        .map(|(user, &balance)| Transaction::new_with_fee("SYSTEM", *user, balance, 0, 0, 0))
+        .collect();
+    let mut state = State::new();
+    state
+        .apply_block_txs(&txs, 0, &ChainParams::default())
+        .unwrap();
+    state
+}
+
+/// One block: optionally a coinbase, then transfers; a sender's take consecutive nonces.
+#[derive(Debug, Clone)]
+struct BlockPlan {
+    with_coinbase: bool,
+    transfers: Vec<(usize, usize, u64, u64)>,
+}
+
+fn block_plan() -> impl Strategy<Value = BlockPlan> {
+    (
+        any::<bool>(),
+        prop::collection::vec((0..4_usize, 0..4_usize, 0..400_u64, 0..20_u64), 0..6),
+    )
+        .prop_map(|(with_coinbase, transfers)| BlockPlan {
+            with_coinbase,
+            transfers,
+        })
+}
+
+proptest! {
+    #[test]
+    fn random_transfers_conserve_supply(
+        balances in prop::collection::vec(0..1_000_u64, 4),
+        plans in prop::collection::vec(block_plan(), 1..20),
+    ) {
+        let params = lax_params();
+        let mut state = allocations(&balances);
+        let mut total = SupplyChange {
+            minted: balances.iter().sum(),
+            burned: 0,
+        };
+
+        for (i, plan) in plans.iter().enumerate() {
+            let height = i + 1;
+            let mut sent = [0_u64; 4];
+            let mut txs: Vec<Transaction> = plan
+                .transfers
+                .iter()
+                .map(|&(from, to, amount, fee)| {
+                    let nonce = state.get_nonce(USERS[from]) + sent[from];
+                    sent[from] += 1;
+                    Transaction::new_with_fee(USERS[from], USERS[to], amount, fee, nonce, 0)
+                })
+                .collect();
+            if plan.with_coinbase {
+                let fees: u64 = txs.iter().map(|tx| tx.fee).sum();
+                txs.insert(0, coinbase(height, params.block_subsidy(height as u64) + fees));
+            }
+
+            let before = state.clone();
+            match state.apply_block_txs(&txs, height, &params) {
+                Ok(()) => {
+                    let change = SupplyChange::of_txs(&txs).unwrap();
+                    prop_assert_eq!(
+                        state.total_supply(),
+                        before.total_supply() + change.minted - change.burned
+                    );
+                    total = total.checked_add(change).unwrap();
+                    for (user, count) in USERS.iter().zip(sent) {
+                        prop_assert_eq!(state.get_nonce(user), before.get_nonce(user) + count);
+                    }
+                }
+                Err(_) => prop_assert_eq!(&state.accounts, &before.accounts),
+            }
+            prop_assert_eq!(total.net(), Some(state.total_supply()));
+        }
+    }
+
+    #[test]
+    fn overflowing_blocks_are_rejected_and_leave_state_untouched(
+        headroom in 0..1_000_u64,
+        amount in 1..2_000_u64,
+        fee in 0..10_u64,
+    ) {
+        let mut state = State::new();
+        state.accounts.insert(
+            "alice".to_string(),
+            Account { balance: 5_000, nonce: 0, ..Default::default() },
+        );
+        state.accounts.insert(
+            "whale".to_string(),
+            Account { balance: u64::MAX - 5_000 - headroom, nonce: 0, ..Default::default() },
+        );
+        let before = state.accounts.clone();
+
+        let txs = vec![Transaction::new_with_fee("alice", "whale", amount, fee, 0, 0)];
+        let result = state.apply_block_txs(&txs, 1, &lax_params());
+        if amount > headroom + 5_000 {
+            prop_assert!(result.is_err());
+            prop_assert_eq!(&state.accounts, &before);
+        } else {
+            prop_assert!(result.is_ok());
+            prop_assert_eq!(state.get_balance("whale"), before["whale"].balance + amount);
+        }
+    }
+}
+
+#[test]
+fn checked_math_rejects_amount_plus_fee_overflow() {
+    let mut state = allocations(&[100, 0, 0, 0]);
+    let before = state.accounts.clone();
+    let txs = vec![Transaction::new_with_fee("alice", "bob", u64::MAX, 1, 0, 0)];
+    let err = state.apply_block_txs(&txs, 1, &lax_params()).unwrap_err();
+    assert!(format!("{err:#}").contains("overflow"), "err={err:#}");
+    assert_eq!(state.accounts, before);
+
+    // A coinbase whose reward would overflow once fees are added.
+    let txs = vec![
+        coinbase(1, u64::MAX),
+        Transaction::new_with_fee("alice", "bob", 1, u64::MAX, 0, 0),
+    ];
+    assert!(
+        state
+            .apply_block_txs(&txs, 1, &ChainParams::default())
+            .is_err()
+    );
+    assert_eq!(state.accounts, before);
+}
+
+#[test]
+fn chain_supply_is_minted_minus_burned() {
+    let mut chain = GenesisConfig {
+        network: "devnet".to_string(),
+        chain_id: None,
+        timestamp_ms: 1_700_000_000_000,
+        difficulty: 1,
+        params: lax_params(),
+        allocations: vec![GenesisAllocation {
+            address: "alice".to_string(),
+            balance: 1_000,
+        }],
+    }
+    .build_chain()
+    .unwrap();
+    let subsidy = chain.params.block_subsidy(1);
+
+    // With a miner the fee is re-minted; without one it stays burned.
+    chain
+        .mine_block(
+            vec![Transaction::new_with_fee("alice", "bob", 10, 3, 0, 0)],
+            1,
+            Some("miner"),
+        )
+        .unwrap();
+    chain
+        .mine_block(
+            vec![Transaction::new_with_fee("alice", "bob", 10, 4, 1, 0)],
+            1,
+            None,
+        )
+        .unwrap();
+
+    let audit = chain.supply_audit().unwrap();
+    assert_eq!(audit.minted, 1_000 + subsidy + 3);
+    assert_eq!(audit.burned, 7);
+    assert_eq!(
+        audit.net(),
+        Some(chain.compute_state().unwrap().total_supply())
+    );
+
+    // Pruning folds the old blocks into the base state's supply.
+    for _ in 0..12 {
+        chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    }
+    let full = chain.supply_audit().unwrap();
+    chain.set_prune_keep_blocks(10).unwrap();
+    assert_eq!(chain.supply_audit().unwrap().net(), full.net());
+}
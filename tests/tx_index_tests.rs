@@ -0,0 +1,217 @@
+mod common;
+
+use common::{ask, funded_chain, long_chain};
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::network::Message;
+use rusty_chain::core::p2p::{P2PNode, P2PNodeHandle};
+use rusty_chain::core::tx_index::{TxIndex, TxLocation};
+use rusty_chain::core::types::Transaction;
+use std::sync::Arc;
+
+fn pay(nonce: u64, to: &str) -> Transaction {
+    Transaction::new_with_fee("alice", to, 10, 1, nonce, 0)
+}
+
+#[test]
+fn index_follows_appended_and_disconnected_blocks() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    assert!(chain.find_tx("nope").is_err(), "index is off by default");
+    chain.enable_tx_index();
+
+    let first = pay(0, "bob");
+    let second = pay(1, "carol");
+    chain
+        .mine_block(vec![first.clone()], 1, Some("miner"))
+        .unwrap();
+    chain
+        .mine_block(vec![second.clone()], 1, Some("miner"))
+        .unwrap();
+
+    let (loc, tx) = chain.find_tx(&first.id()).unwrap().unwrap();
+    assert_eq!(
+        loc,
+        TxLocation {
+            height: 1,
+            index: 1
+        }
+    );
+    assert_eq!(tx.id(), first.id());
+
+    // Oldest first: genesis allocation, then both payments.
+    let alice: Vec<usize> = chain
+        .address_history("alice")
+        .unwrap()
+        .iter()
+        .map(|(loc, _)| loc.height)
+        .collect();
+    assert_eq!(alice, vec![0, 1, 2]);
+    // Coinbases are indexed under the miner.
+    assert_eq!(chain.address_history("miner").unwrap().len(), 2);
+    assert!(chain.address_history("SYSTEM").unwrap().is_empty());
+
+    chain.disconnect_tip().unwrap();
+    assert!(chain.find_tx(&second.id()).unwrap().is_none());
+    assert!(chain.address_history("carol").unwrap().is_empty());
+    assert_eq!(chain.address_history("alice").unwrap().len(), 2);
+
+    // Same as a fresh build.
+    let rebuilt = TxIndex::build(&chain);
+    let live = chain.tx_index.as_ref().unwrap();
+    assert_eq!(live.txs, rebuilt.txs);
+    assert_eq!(live.addresses, rebuilt.addresses);
+    assert_eq!(live.tip_hash, chain.tip_hash());
+}
+
+#[test]
+fn pruned_chains_refuse_partial_history() {
+    let mut chain = long_chain();
+    chain.set_prune_keep_blocks(10).unwrap();
+    chain.enable_tx_index();
+    let err = chain.address_history("alice").unwrap_err();
+    assert!(err.to_string().contains("pruned"), "err={err}");
+}
+
+#[test]
+fn sync_catches_up_or_rebuilds_stale_index() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    chain
+        .mine_block(vec![pay(0, "bob")], 1, Some("miner"))
+        .unwrap();
+    let mut index = TxIndex::build(&chain);
+    let dir = tempfile::tempdir().unwrap();
+    let path = TxIndex::path_for_chain(&dir.path().join("chain.json"));
+    index.save(&path).unwrap();
+
+    // Catch up on newer blocks only.
+    chain
+        .mine_block(vec![pay(1, "carol")], 1, Some("miner"))
+        .unwrap();
+    chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    let mut loaded = TxIndex::load(&path).unwrap();
+    assert_eq!(loaded.sync(&chain), 2);
+    assert_eq!(loaded.height, 3);
+    assert_eq!(loaded.history("carol").len(), 1);
+    assert_eq!(loaded.sync(&chain), 0);
+
+    // The indexed tip is not on this chain: start over.
+    let mut other = funded_chain(&[("alice", 100)]);
+    other
+        .mine_block(vec![pay(0, "dave")], 1, Some("miner"))
+        .unwrap();
+    index.sync(&other);
+    assert!(index.history("bob").is_empty());
+    assert_eq!(index.history("dave").len(), 1);
+    assert_eq!(index.tip_hash, other.tip_hash());
+}
+
+fn node(chain: Chain, mempool: Mempool) -> (P2PNode, P2PNodeHandle) {
+    let node = P2PNode::new(
+        "127.0.0.1:9000".parse().unwrap(),
+        chain,
+        mempool,
+        None,
+        None,
+    );
+    let handle = P2PNodeHandle {
+        state: Arc::clone(&node.state),
+    };
+    (node, handle)
+}
+
+#[tokio::test]
+async fn p2p_tx_and_history_queries() {
+    let mut chain = funded_chain(&[("alice", 100)]);
+    chain.enable_tx_index();
+    let confirmed = pay(0, "bob");
+    chain
+        .mine_block(vec![confirmed.clone()], 1, Some("miner"))
+        .unwrap();
+    let pending = pay(1, "bob");
+    let mut mp = Mempool::new();
+    mp.add_tx(pending.clone()).unwrap();
+    chain.mine_block(vec![], 1, Some("miner")).unwrap();
+    let (node, handle) = node(chain, mp);
+
+    let reply = ask(
+        &node,
+        &handle,
+        Message::GetTx {
+            tx_id: confirmed.id(),
+        },
+    )
+    .await;
+    assert_eq!(
+        reply,
+        Message::TxInfo {
+            tx_id: confirmed.id(),
+            tx: Some(confirmed.clone()),
+            location: Some(TxLocation {
+                height: 1,
+                index: 1
+            }),
+            confirmations: 2,
+        }
+    );
+
+    let reply = ask(
+        &node,
+        &handle,
+        Message::GetTx {
+            tx_id: pending.id(),
+        },
+    )
+    .await;
+    assert!(matches!(
+        reply,
+        Message::TxInfo {
+            location: None,
+            confirmations: 0,
+            tx: Some(_),
+            ..
+        }
+    ));
+
+    let reply = ask(
+        &node,
+        &handle,
+        Message::GetHistory {
+            address: "bob".to_string(),
+        },
+    )
+    .await;
+    assert_eq!(
+        reply,
+        Message::History {
+            address: "bob".to_string(),
+            confirmed: vec![(
+                confirmed.id(),
+                TxLocation {
+                    height: 1,
+                    index: 1
+                }
+            )],
+            pending: vec![pending.id()],
+        }
+    );
+}
+
+#[tokio::test]
+async fn p2p_history_rejected_without_index() {
+    let (node, handle) = node(funded_chain(&[("alice", 100)]), Mempool::new());
+    let reply = ask(
+        &node,
+        &handle,
+        Message::GetHistory {
+            address: "bob".to_string(),
+        },
+    )
+    .await;
+    match reply {
+        Message::Reject { code, reason, .. } => {
+            assert_eq!(code, 501);
+            assert!(reason.contains("tx index is disabled"), "reason={reason}");
+        }
+        other => panic!("unexpected: {other:?}"),
+    }
+}
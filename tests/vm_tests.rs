@@ -0,0 +1,272 @@
+mod common;
+
+use common::funded_chain;
+use rusty_chain::core::chain::Chain;
+use rusty_chain::core::mempool::Mempool;
+use rusty_chain::core::state_tree::state_root;
+use rusty_chain::core::types::Transaction;
+use rusty_chain::core::vm::{
+    CallContext, ContractOp, DEPLOY_GAS_PER_OP, Op, contract_address, execute, parse,
+};
+use std::collections::BTreeMap;
+
+/// storage[0] += arg 0
+const COUNTER: &str = "PUSH 0  PUSH 0 SLOAD  PUSH 0 ARG  ADD  SSTORE";
+
+/// storage[1] = arg 0, then revert if arg 0 > 10.
+const GUARDED: &str = "
+    PUSH 1 PUSH 0 ARG SSTORE   # 0..3
+    PUSH 0 ARG PUSH 10 GT      # 4..7
+    PUSH 11 JUMPI              # 8..9
+    STOP                       # 10
+    REVERT                     # 11
+";
+fn contract_tx(chain: &Chain, to: &str, op: ContractOp, amount: u64, gas: u64) -> Transaction {
+    let nonce = chain.next_nonce_for("alice");
+    let mut tx = Transaction::new_with_fee("alice", to, amount, gas, nonce, 0);
+    tx.gas_limit = gas;
+    tx.contract = Some(op);
+    tx
+}
+
+fn deploy(chain: &mut Chain, code: &str) -> String {
+    let address = contract_address("alice", chain.next_nonce_for("alice"));
+    let tx = contract_tx(
+        chain,
+        &address,
+        ContractOp::Deploy {
+            code: code.to_string(),
+        },
+        0,
+        500,
+    );
+    chain.mine_block(vec![tx], 1, Some("miner")).unwrap();
+    address
+}
+
+fn call(chain: &mut Chain, address: &str, args: Vec<u64>, amount: u64, gas: u64) {
+    let tx = contract_tx(chain, address, ContractOp::Call { args }, amount, gas);
+    chain.mine_block(vec![tx], 1, Some("miner")).unwrap();
+}
+
+#[test]
+fn deployed_counter_keeps_storage_across_calls() {
+    let mut chain = funded_chain(&[("alice", 10_000)]);
+    let address = deploy(&mut chain, COUNTER);
+    let state = chain.compute_state().unwrap();
+    let account = &state.accounts[&address];
+    assert_eq!(
+        account.code_hash.as_deref(),
+        Some(state.contracts[&address].code_hash().as_str())
+    );
+    let root_before = state_root(&state);
+
+    call(&mut chain, &address, vec![5], 0, 300);
+    call(&mut chain, &address, vec![7], 25, 300);
+    let state = chain.compute_state().unwrap();
+    let contract = &state.contracts[&address];
+    assert_eq!(contract.storage.get(&0), Some(&12));
+    assert_eq!(
+        state.accounts[&address].storage_root.as_deref(),
+        Some(contract.storage_root().as_str())
+    );
+    assert_eq!(state.get_balance(&address), 25);
+    assert_ne!(state_root(&state), root_before);
+    state.check_contracts().unwrap();
+    chain.validate().unwrap();
+}
+
+#[test]
+fn failed_calls_revert_state_but_still_charge_gas() {
+    let mut chain = funded_chain(&[("alice", 10_000)]);
+    let address = deploy(&mut chain, GUARDED);
+    call(&mut chain, &address, vec![3], 0, 300);
+    let before = chain.compute_state().unwrap();
+
+    // Reverts after writing storage, with coins attached.
+    call(&mut chain, &address, vec![50], 40, 300);
+    let after = chain.compute_state().unwrap();
+    assert_eq!(after.contracts[&address].storage.get(&1), Some(&3));
+    assert_eq!(
+        after.accounts[&address].storage_root,
+        before.accounts[&address].storage_root
+    );
+    assert_eq!(after.get_balance(&address), 0);
+    assert_eq!(
+        after.get_balance("alice"),
+        before.get_balance("alice") - 300
+    );
+    assert_eq!(after.get_nonce("alice"), before.get_nonce("alice") + 1);
+
+    // Out of gas the same way.
+    call(&mut chain, &address, vec![4], 0, 5);
+    let oog = chain.compute_state().unwrap();
+    assert_eq!(oog.contracts[&address].storage.get(&1), Some(&3));
+    assert_eq!(oog.get_balance("alice"), after.get_balance("alice") - 5);
+    chain.validate().unwrap();
+}
+
+#[test]
+fn deploy_without_enough_gas_creates_nothing() {
+    let mut chain = funded_chain(&[("alice", 10_000)]);
+    let ops = parse(COUNTER).unwrap().len() as u64;
+    let address = contract_address("alice", 0);
+    let tx = contract_tx(
+        &chain,
+        &address,
+        ContractOp::Deploy {
+            code: COUNTER.to_string(),
+        },
+        10,
+        ops * DEPLOY_GAS_PER_OP - 1,
+    );
+    chain.mine_block(vec![tx], 1, Some("miner")).unwrap();
+    let state = chain.compute_state().unwrap();
+    assert!(state.contracts.is_empty());
+    assert_eq!(state.get_balance(&address), 0);
+    assert_eq!(
+        state.get_balance("alice"),
+        10_000 - (ops * DEPLOY_GAS_PER_OP - 1)
+    );
+    // The address was tied to that nonce; it can't be deployed to again.
+    let retry = contract_tx(
+        &chain,
+        &address,
+        ContractOp::Deploy {
+            code: COUNTER.to_string(),
+        },
+        0,
+        500,
+    );
+    assert!(chain.validate_transaction(&retry).is_err());
+}
+
+#[test]
+fn contract_txs_are_checked_before_inclusion() {
+    let mut chain = funded_chain(&[("alice", 10_000)]);
+    let address = deploy(&mut chain, COUNTER);
+
+    // The fee must cover the gas limit.
+    let mut tx = contract_tx(&chain, &address, ContractOp::Call { args: vec![1] }, 0, 100);
+    tx.fee = 99;
+    let err = chain.validate_transaction(&tx).unwrap_err();
+    assert!(format!("{err:#}").contains("gas_limit"), "err={err:#}");
+
+    // Calls need a contract, deploys their own address, and code must parse.
+    let tx = contract_tx(&chain, "bob", ContractOp::Call { args: vec![] }, 0, 100);
+    assert!(chain.validate_transaction(&tx).is_err());
+    let tx = contract_tx(
+        &chain,
+        "bob",
+        ContractOp::Deploy {
+            code: COUNTER.to_string(),
+        },
+        0,
+        500,
+    );
+    let err = chain.validate_transaction(&tx).unwrap_err();
+    assert!(format!("{err:#}").contains("must target"), "err={err:#}");
+    let nonce = chain.next_nonce_for("alice");
+    let tx = contract_tx(
+        &chain,
+        &contract_address("alice", nonce),
+        ContractOp::Deploy {
+            code: "PUSH".to_string(),
+        },
+        0,
+        500,
+    );
+    assert!(chain.validate_transaction(&tx).is_err());
+
+    // Plain transfers still need an amount.
+    let tx = Transaction::new_with_fee("alice", "bob", 0, 1, nonce, 0);
+    assert!(chain.validate_transaction(&tx).is_err());
+
+    // Nothing can spend from a contract's balance: not a transfer, nor a call of its own.
+    call(&mut chain, &address, vec![1], 40, 300);
+    let drain = Transaction::new_with_fee(address.clone(), "bob", 40, 0, 0, 0);
+    let err = chain.validate_transaction(&drain).unwrap_err();
+    assert!(format!("{err:#}").contains("is a contract"), "err={err:#}");
+    assert!(chain.mine_block(vec![drain], 1, Some("miner")).is_err());
+    assert_eq!(chain.compute_state().unwrap().get_balance(&address), 40);
+}
+
+#[test]
+fn blocks_are_capped_by_total_gas() {
+    let mut chain = funded_chain(&[("alice", 10_000), ("carol", 10_000)]);
+    let address = deploy(&mut chain, COUNTER);
+    let mut strict = chain.clone();
+    strict.params.max_block_gas = 500;
+
+    let mut first = contract_tx(&chain, &address, ContractOp::Call { args: vec![1] }, 0, 300);
+    let mut second = first.clone();
+    second.nonce += 1;
+    let block = chain
+        .mine_block(vec![first.clone(), second.clone()], 1, Some("miner"))
+        .unwrap();
+    assert_eq!(block.gas_limit(), 600);
+    let err = strict.validate_block(&block).unwrap_err().to_string();
+    assert!(err.contains("too much gas"), "err={err}");
+
+    // The template stops at the budget; plain transfers don't count against it.
+    let mut mp = Mempool::new();
+    first.nonce = strict.next_nonce_for("alice");
+    second.nonce = first.nonce + 1;
+    mp.add_tx(first).unwrap();
+    mp.add_tx(second).unwrap();
+    mp.add_tx(Transaction::new_with_fee("carol", "bob", 1, 1, 0, 0))
+        .unwrap();
+    let picked = strict.select_block_txs(&mp, Some("miner"));
+    let order: Vec<(&str, u64)> = picked.iter().map(|t| (t.from.as_str(), t.nonce)).collect();
+    assert_eq!(order, vec![("alice", 1), ("carol", 0)]);
+}
+
+#[test]
+fn vm_faults_and_limits() {
+    let run = |code: &str, args: &[u64], gas_limit: u64| {
+        let mut storage = BTreeMap::new();
+        let ctx = CallContext {
+            args,
+            value: 0,
+            height: 1,
+            gas_limit,
+        };
+        let exec = execute(&parse(code).unwrap(), &mut storage, ctx);
+        (exec, storage)
+    };
+
+    let (exec, storage) = run("PUSH 9 PUSH 2 SUB PUSH 3 MUL PUSH 4 SWAP SSTORE", &[], 300);
+    exec.result.unwrap();
+    assert_eq!(storage.get(&4), Some(&21));
+    assert_eq!(exec.gas_used, 1 + 1 + 3 + 1 + 5 + 1 + 1 + 200);
+
+    let (exec, _) = run("PUSH 0 PUSH 1 SUB", &[], 100);
+    assert!(exec.result.unwrap_err().to_string().contains("arithmetic"));
+    let (exec, _) = run("PUSH 1 PUSH 0 DIV", &[], 100);
+    assert!(exec.result.is_err());
+    let (exec, _) = run("ADD", &[], 100);
+    assert!(exec.result.unwrap_err().to_string().contains("underflow"));
+    let (exec, _) = run("PUSH 2 ARG", &[1, 2], 100);
+    assert!(exec.result.is_err());
+    let (exec, _) = run("PUSH 99 JUMP", &[], 100);
+    assert!(
+        exec.result
+            .unwrap_err()
+            .to_string()
+            .contains("out of range")
+    );
+
+    // An endless loop stops at the gas limit, having used all of it.
+    let (exec, storage) = run("PUSH 1 PUSH 1 SSTORE PUSH 0 JUMP", &[], 1_000);
+    assert!(exec.result.unwrap_err().to_string().contains("out of gas"));
+    assert_eq!(exec.gas_used, 1_000);
+    assert!(storage.is_empty(), "storage is only written on success");
+
+    assert_eq!(
+        parse("push 1 # comment\nstop").unwrap(),
+        vec![Op::Push(1), Op::Stop]
+    );
+    assert!(parse("").is_err());
+    assert!(parse("JUMPX").is_err());
+    assert!(parse("PUSH -1").is_err());
+}